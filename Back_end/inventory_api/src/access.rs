@@ -0,0 +1,170 @@
+use mongodb::bson::oid::ObjectId;
+
+use crate::entities::user_group::GroupRole;
+use crate::error::ApiError;
+use crate::log::write_log;
+use crate::middleware::auth::AuthUser;
+use crate::repository::{RepoResult, Repositories};
+
+/// Recurso sobre el que se comprueba el acceso.
+#[derive(Debug, Clone, Copy)]
+pub enum Resource {
+    Group(ObjectId),
+    Property(ObjectId),
+    Zone(ObjectId),
+    Item(ObjectId),
+}
+
+impl Resource {
+    fn not_found(&self) -> &'static str {
+        match self {
+            Resource::Group(_) => "Grupo no encontrado",
+            Resource::Property(_) => "Propiedad no encontrada",
+            Resource::Zone(_) => "Zona no encontrada",
+            Resource::Item(_) => "Objeto no encontrado",
+        }
+    }
+}
+
+/// Las zonas cuelgan de una propiedad o de otra zona a través del mismo `parentZoneId`;
+/// devuelve el recurso que corresponde a ese identificador.
+pub async fn parent_resource(repos: &Repositories, parent_id: ObjectId) -> RepoResult<Resource> {
+    let is_property = repos.properties.find_by_id(parent_id).await?.is_some();
+    Ok(if is_property {
+        Resource::Property(parent_id)
+    } else {
+        Resource::Zone(parent_id)
+    })
+}
+
+/// Grupo al que pertenece un recurso y dueños de los elementos privados de su rama.
+struct Scope {
+    group_id: ObjectId,
+    private_owners: Vec<ObjectId>,
+}
+
+/// Recorre item → zona (y zonas padre) → propiedad → grupo. Devuelve `None` si algún
+/// eslabón no existe.
+async fn resolve(repos: &Repositories, resource: Resource) -> RepoResult<Option<Scope>> {
+    let mut private_owners = Vec::new();
+    let zone_id = match resource {
+        Resource::Group(group_id) => {
+            let exists = repos.groups.find_by_id(group_id).await?.is_some();
+            return Ok(exists.then_some(Scope {
+                group_id,
+                private_owners,
+            }));
+        }
+        Resource::Property(property_id) => {
+            return resolve_property(repos, property_id, private_owners).await;
+        }
+        Resource::Zone(zone_id) => zone_id,
+        // Algunos items antiguos cuelgan directamente de la propiedad
+        Resource::Item(item_id) => match repos.items.find_by_id(item_id).await? {
+            Some(item) => match parent_resource(repos, item.zone_id).await? {
+                Resource::Property(property_id) => {
+                    return resolve_property(repos, property_id, private_owners).await;
+                }
+                _ => item.zone_id,
+            },
+            None => return Ok(None),
+        },
+    };
+    let mut current = zone_id;
+    let mut visited = Vec::new();
+    // Las zonas privadas ocultan toda su rama, así que se comprueban también los ancestros
+    loop {
+        visited.push(current);
+        let zone = match repos.zones.find_by_id(current).await? {
+            Some(zone) => zone,
+            None => return Ok(None),
+        };
+        if let Some(owner) = zone.user_id {
+            private_owners.push(owner);
+        }
+        match zone.parent_zone_id {
+            Some(parent) if parent != zone.property_id && !visited.contains(&parent) => {
+                current = parent;
+            }
+            _ => return resolve_property(repos, zone.property_id, private_owners).await,
+        }
+    }
+}
+
+async fn resolve_property(
+    repos: &Repositories,
+    property_id: ObjectId,
+    mut private_owners: Vec<ObjectId>,
+) -> RepoResult<Option<Scope>> {
+    let property = match repos.properties.find_by_id(property_id).await? {
+        Some(property) => property,
+        None => return Ok(None),
+    };
+    if let Some(owner) = property.user_id {
+        private_owners.push(owner);
+    }
+    Ok(Some(Scope {
+        group_id: property.group_id,
+        private_owners,
+    }))
+}
+
+/// Comprueba que el usuario pueda acceder al recurso con al menos el rol indicado en su
+/// grupo y que ningún elemento privado de otro usuario lo contenga. Devuelve el grupo del
+/// recurso o el error a devolver. Los administradores tienen acceso
+/// a todo.
+pub async fn authorize(
+    repos: &Repositories,
+    user: &AuthUser,
+    resource: Resource,
+    required: GroupRole,
+    route: &str,
+) -> Result<ObjectId, ApiError> {
+    let scope = match resolve(repos, resource).await {
+        Ok(Some(scope)) => scope,
+        Ok(None) => {
+            write_log(&format!("{} - {:?} no encontrado", route, resource)).ok();
+            return Err(ApiError::not_found(resource.not_found()));
+        }
+        Err(_) => {
+            write_log(&format!("{} - Error resolviendo {:?}", route, resource)).ok();
+            return Err(ApiError::unexpected());
+        }
+    };
+    if user.is_admin() {
+        return Ok(scope.group_id);
+    }
+    if scope.private_owners.iter().any(|owner| *owner != user.id) {
+        write_log(&format!(
+            "{} - {:?} privado de otro usuario, acceso denegado a {}",
+            route, resource, user.id
+        ))
+        .ok();
+        return Err(ApiError::forbidden("No tienes acceso a este recurso"));
+    }
+    match repos.user_groups.find(user.id, scope.group_id).await {
+        Ok(Some(ug)) if ug.role >= required => Ok(scope.group_id),
+        Ok(Some(_)) => {
+            write_log(&format!(
+                "{} - Usuario {} sin permisos suficientes en el grupo {}",
+                route, user.id, scope.group_id
+            ))
+            .ok();
+            Err(ApiError::forbidden(
+                "No tienes permisos suficientes en este grupo",
+            ))
+        }
+        Ok(None) => {
+            write_log(&format!(
+                "{} - Usuario {} no pertenece al grupo {}",
+                route, user.id, scope.group_id
+            ))
+            .ok();
+            Err(ApiError::forbidden("El Usuario no pertenece a este grupo"))
+        }
+        Err(_) => {
+            write_log(&format!("{} - Error comprobando permisos", route)).ok();
+            Err(ApiError::unexpected())
+        }
+    }
+}
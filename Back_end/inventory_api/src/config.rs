@@ -0,0 +1,557 @@
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use once_cell::sync::OnceCell;
+use serde::Deserialize;
+
+/// Secreto de desarrollo; solo se acepta con el perfil `dev`.
+pub const DEFAULT_API_KEY: &str = "clave_secreta";
+
+/// Fichero que se lee si no se indica otro en `CONFIG_FILE`.
+const DEFAULT_CONFIG_FILE: &str = "config.toml";
+
+static CONFIG: OnceCell<Config> = OnceCell::new();
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Profile {
+    Dev,
+    Prod,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageBackend {
+    Mongo,
+    Memory,
+}
+
+/// Configuración de la API. Se lee de un fichero TOML y cada valor se puede
+/// sobrescribir con su variable de entorno.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub profile: Profile,
+    pub server: ServerConfig,
+    pub cors: CorsConfig,
+    pub database: DatabaseConfig,
+    pub auth: AuthConfig,
+    pub mail: MailConfig,
+    pub trash: TrashConfig,
+    pub rate_limit: RateLimitSettings,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub host: String,
+    pub port: u16,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CorsConfig {
+    pub allowed_origins: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DatabaseConfig {
+    pub backend: StorageBackend,
+    pub uri: String,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthConfig {
+    /// Secreto con el que se firman los tokens.
+    pub api_key: String,
+    /// Duración del token de acceso en segundos.
+    pub access_token_ttl_secs: u64,
+    /// Duración del refresh token en días.
+    pub refresh_token_ttl_days: u32,
+    pub argon2: Argon2Config,
+}
+
+/// Coste de Argon2id para las contraseñas nuevas. Las guardadas con otros parámetros se
+/// vuelven a calcular en el siguiente login.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Argon2Config {
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MailTransport {
+    Smtp,
+    File,
+    Stdout,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MailConfig {
+    pub transport: MailTransport,
+    /// URL del frontend usada en los enlaces de los correos.
+    pub app_url: String,
+    pub from: String,
+    pub smtp_host: String,
+    pub smtp_port: u16,
+    pub smtp_username: Option<String>,
+    pub smtp_password: Option<String>,
+    /// Directorio de los correos con el transporte `file`.
+    pub dir: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TrashConfig {
+    /// Días que se guardan los elementos borrados antes de eliminarlos definitivamente.
+    pub retention_days: u32,
+}
+
+/// Límites de las rutas de login y registro.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RateLimitSettings {
+    /// Ventana en segundos en la que se cuentan las peticiones.
+    pub window_secs: u64,
+    /// Peticiones permitidas por ventana y clave (IP o correo).
+    pub max_requests: u32,
+    /// Fallos consecutivos que provocan un bloqueo.
+    pub max_failures: u32,
+    /// Duración en segundos del primer bloqueo; cada bloqueo posterior dura el doble.
+    pub lockout_secs: u64,
+    pub max_lockout_secs: u64,
+    /// Segundos sin bloqueos tras los que se olvidan los anteriores.
+    pub lockout_decay_secs: u64,
+    /// Cabecera con la IP del cliente detrás de un proxy. Sin ella se usa la conexión.
+    pub ip_header: Option<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            profile: Profile::Prod,
+            server: ServerConfig::default(),
+            cors: CorsConfig::default(),
+            database: DatabaseConfig::default(),
+            auth: AuthConfig::default(),
+            mail: MailConfig::default(),
+            trash: TrashConfig::default(),
+            rate_limit: RateLimitSettings::default(),
+        }
+    }
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            host: "0.0.0.0".to_string(),
+            port: 8000,
+        }
+    }
+}
+
+impl Default for CorsConfig {
+    fn default() -> Self {
+        Self {
+            allowed_origins: vec!["http://localhost:8081".to_string()],
+        }
+    }
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        Self {
+            backend: StorageBackend::Mongo,
+            uri: "mongodb://localhost:27017".to_string(),
+            name: "TFG".to_string(),
+        }
+    }
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        Self {
+            api_key: DEFAULT_API_KEY.to_string(),
+            access_token_ttl_secs: 15 * 60,
+            refresh_token_ttl_days: 30,
+            argon2: Argon2Config::default(),
+        }
+    }
+}
+
+impl Default for Argon2Config {
+    fn default() -> Self {
+        Self {
+            memory_kib: 19 * 1024,
+            iterations: 2,
+            parallelism: 1,
+        }
+    }
+}
+
+impl Argon2Config {
+    pub fn params(&self) -> Result<argon2::Params, argon2::Error> {
+        argon2::Params::new(self.memory_kib, self.iterations, self.parallelism, None)
+    }
+}
+
+impl Default for MailConfig {
+    fn default() -> Self {
+        Self {
+            transport: MailTransport::Stdout,
+            app_url: "http://localhost:8081".to_string(),
+            from: "no-reply@localhost".to_string(),
+            smtp_host: "localhost".to_string(),
+            smtp_port: 587,
+            smtp_username: None,
+            smtp_password: None,
+            dir: "mails".to_string(),
+        }
+    }
+}
+
+impl Default for TrashConfig {
+    fn default() -> Self {
+        Self { retention_days: 30 }
+    }
+}
+
+impl Default for RateLimitSettings {
+    fn default() -> Self {
+        Self {
+            window_secs: 60,
+            max_requests: 10,
+            max_failures: 5,
+            lockout_secs: 30,
+            max_lockout_secs: 3600,
+            lockout_decay_secs: 24 * 3600,
+            ip_header: None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Read(PathBuf, std::io::Error),
+    Parse(toml::de::Error),
+    Env { var: &'static str, value: String },
+    Invalid(Vec<String>),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Read(path, e) => {
+                write!(f, "No se pudo leer {}: {}", path.display(), e)
+            }
+            ConfigError::Parse(e) => write!(f, "Fichero de configuración inválido: {}", e),
+            ConfigError::Env { var, value } => {
+                write!(f, "Valor inválido en {}: {:?}", var, value)
+            }
+            ConfigError::Invalid(errors) => {
+                write!(f, "Configuración inválida: {}", errors.join("; "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl Config {
+    /// Configuración por defecto con el perfil de desarrollo.
+    pub fn development() -> Self {
+        Self {
+            profile: Profile::Dev,
+            ..Self::default()
+        }
+    }
+
+    /// Carga `.env`, el fichero de `CONFIG_FILE` (o `config.toml` si existe) y las
+    /// variables de entorno, y valida el resultado.
+    pub fn load() -> Result<Self, ConfigError> {
+        dotenv::dotenv().ok();
+        let file = match env::var("CONFIG_FILE") {
+            Ok(path) => Some(read_file(Path::new(&path))?),
+            Err(_) if Path::new(DEFAULT_CONFIG_FILE).exists() => {
+                Some(read_file(Path::new(DEFAULT_CONFIG_FILE))?)
+            }
+            Err(_) => None,
+        };
+        Self::from_sources(file.as_deref(), |name| env::var(name).ok())
+    }
+
+    /// Construye la configuración a partir del contenido TOML (si lo hay) y de una
+    /// función que devuelve el valor de cada variable de entorno.
+    pub fn from_sources(
+        toml_str: Option<&str>,
+        env: impl Fn(&str) -> Option<String>,
+    ) -> Result<Self, ConfigError> {
+        let mut config: Config = match toml_str {
+            Some(s) => toml::from_str(s).map_err(ConfigError::Parse)?,
+            None => Config::default(),
+        };
+        config.apply_env(env)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn apply_env(&mut self, env: impl Fn(&str) -> Option<String>) -> Result<(), ConfigError> {
+        if let Some(value) = env("APP_PROFILE") {
+            self.profile = match value.as_str() {
+                "dev" => Profile::Dev,
+                "prod" => Profile::Prod,
+                _ => {
+                    return Err(ConfigError::Env {
+                        var: "APP_PROFILE",
+                        value,
+                    })
+                }
+            };
+        }
+        if let Some(value) = env("SERVER_HOST") {
+            self.server.host = value;
+        }
+        if let Some(value) = env("SERVER_PORT") {
+            self.server.port = parse_env("SERVER_PORT", value)?;
+        }
+        if let Some(value) = env("CORS_ALLOWED_ORIGINS") {
+            self.cors.allowed_origins = value
+                .split(',')
+                .map(|origin| origin.trim().to_string())
+                .filter(|origin| !origin.is_empty())
+                .collect();
+        }
+        if let Some(value) = env("STORAGE_BACKEND") {
+            self.database.backend = match value.as_str() {
+                "mongo" => StorageBackend::Mongo,
+                "memory" => StorageBackend::Memory,
+                _ => {
+                    return Err(ConfigError::Env {
+                        var: "STORAGE_BACKEND",
+                        value,
+                    })
+                }
+            };
+        }
+        if let Some(value) = env("MONGODB_URI") {
+            self.database.uri = value;
+        }
+        if let Some(value) = env("MONGODB_DATABASE") {
+            self.database.name = value;
+        }
+        if let Some(value) = env("API_KEY") {
+            self.auth.api_key = value;
+        }
+        if let Some(value) = env("ACCESS_TOKEN_TTL_SECS") {
+            self.auth.access_token_ttl_secs = parse_env("ACCESS_TOKEN_TTL_SECS", value)?;
+        }
+        if let Some(value) = env("REFRESH_TOKEN_TTL_DAYS") {
+            self.auth.refresh_token_ttl_days = parse_env("REFRESH_TOKEN_TTL_DAYS", value)?;
+        }
+        if let Some(value) = env("ARGON2_MEMORY_KIB") {
+            self.auth.argon2.memory_kib = parse_env("ARGON2_MEMORY_KIB", value)?;
+        }
+        if let Some(value) = env("ARGON2_ITERATIONS") {
+            self.auth.argon2.iterations = parse_env("ARGON2_ITERATIONS", value)?;
+        }
+        if let Some(value) = env("ARGON2_PARALLELISM") {
+            self.auth.argon2.parallelism = parse_env("ARGON2_PARALLELISM", value)?;
+        }
+        if let Some(value) = env("MAIL_TRANSPORT") {
+            self.mail.transport = match value.as_str() {
+                "smtp" => MailTransport::Smtp,
+                "file" => MailTransport::File,
+                "stdout" => MailTransport::Stdout,
+                _ => {
+                    return Err(ConfigError::Env {
+                        var: "MAIL_TRANSPORT",
+                        value,
+                    })
+                }
+            };
+        }
+        if let Some(value) = env("APP_URL") {
+            self.mail.app_url = value;
+        }
+        if let Some(value) = env("MAIL_FROM") {
+            self.mail.from = value;
+        }
+        if let Some(value) = env("SMTP_HOST") {
+            self.mail.smtp_host = value;
+        }
+        if let Some(value) = env("SMTP_PORT") {
+            self.mail.smtp_port = parse_env("SMTP_PORT", value)?;
+        }
+        if let Some(value) = env("SMTP_USERNAME") {
+            self.mail.smtp_username = Some(value);
+        }
+        if let Some(value) = env("SMTP_PASSWORD") {
+            self.mail.smtp_password = Some(value);
+        }
+        if let Some(value) = env("MAIL_DIR") {
+            self.mail.dir = value;
+        }
+        if let Some(value) = env("TRASH_RETENTION_DAYS") {
+            self.trash.retention_days = parse_env("TRASH_RETENTION_DAYS", value)?;
+        }
+        if let Some(value) = env("RATE_LIMIT_WINDOW_SECS") {
+            self.rate_limit.window_secs = parse_env("RATE_LIMIT_WINDOW_SECS", value)?;
+        }
+        if let Some(value) = env("RATE_LIMIT_MAX_REQUESTS") {
+            self.rate_limit.max_requests = parse_env("RATE_LIMIT_MAX_REQUESTS", value)?;
+        }
+        if let Some(value) = env("RATE_LIMIT_MAX_FAILURES") {
+            self.rate_limit.max_failures = parse_env("RATE_LIMIT_MAX_FAILURES", value)?;
+        }
+        if let Some(value) = env("RATE_LIMIT_LOCKOUT_SECS") {
+            self.rate_limit.lockout_secs = parse_env("RATE_LIMIT_LOCKOUT_SECS", value)?;
+        }
+        if let Some(value) = env("RATE_LIMIT_MAX_LOCKOUT_SECS") {
+            self.rate_limit.max_lockout_secs = parse_env("RATE_LIMIT_MAX_LOCKOUT_SECS", value)?;
+        }
+        if let Some(value) = env("RATE_LIMIT_LOCKOUT_DECAY_SECS") {
+            self.rate_limit.lockout_decay_secs = parse_env("RATE_LIMIT_LOCKOUT_DECAY_SECS", value)?;
+        }
+        if let Some(value) = env("RATE_LIMIT_IP_HEADER") {
+            // Vacía vuelve a la IP de la conexión
+            self.rate_limit.ip_header = Some(value).filter(|header| !header.trim().is_empty());
+        }
+        Ok(())
+    }
+
+    /// Comprueba todos los valores y devuelve la lista completa de problemas.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let mut errors = Vec::new();
+        if self.server.host.trim().is_empty() {
+            errors.push("server.host no puede estar vacío".to_string());
+        }
+        if self.server.port == 0 {
+            errors.push("server.port debe ser mayor que 0".to_string());
+        }
+        if self.cors.allowed_origins.is_empty() {
+            errors.push("cors.allowed_origins debe tener al menos un origen".to_string());
+        }
+        for origin in &self.cors.allowed_origins {
+            if !origin.starts_with("http://") && !origin.starts_with("https://") {
+                errors.push(format!("Origen CORS inválido: {}", origin));
+            }
+        }
+        if self.database.backend == StorageBackend::Mongo {
+            let uri = &self.database.uri;
+            if !uri.starts_with("mongodb://") && !uri.starts_with("mongodb+srv://") {
+                errors.push("database.uri debe empezar por mongodb://".to_string());
+            }
+            if self.database.name.trim().is_empty() {
+                errors.push("database.name no puede estar vacío".to_string());
+            }
+        }
+        if self.auth.api_key.is_empty() {
+            errors.push("auth.api_key no puede estar vacío".to_string());
+        }
+        // Fuera de desarrollo no se arranca con el secreto por defecto ni con uno débil
+        if self.profile != Profile::Dev {
+            if self.auth.api_key == DEFAULT_API_KEY {
+                errors.push(
+                    "auth.api_key tiene el valor por defecto; define API_KEY para este perfil"
+                        .to_string(),
+                );
+            } else if self.auth.api_key.len() < 32 {
+                errors.push("auth.api_key debe tener al menos 32 caracteres".to_string());
+            }
+        }
+        if self.auth.access_token_ttl_secs == 0 {
+            errors.push("auth.access_token_ttl_secs debe ser mayor que 0".to_string());
+        }
+        if self.auth.refresh_token_ttl_days == 0 {
+            errors.push("auth.refresh_token_ttl_days debe ser mayor que 0".to_string());
+        }
+        if let Err(e) = self.auth.argon2.params() {
+            errors.push(format!("Parámetros de auth.argon2 inválidos: {}", e));
+        }
+        let app_url = &self.mail.app_url;
+        if !app_url.starts_with("http://") && !app_url.starts_with("https://") {
+            errors.push("mail.app_url debe empezar por http:// o https://".to_string());
+        }
+        if !self.mail.from.contains('@') {
+            errors.push(format!("Remitente de correo inválido: {}", self.mail.from));
+        }
+        if self.mail.transport == MailTransport::Smtp {
+            if self.mail.smtp_host.trim().is_empty() {
+                errors.push("mail.smtp_host no puede estar vacío".to_string());
+            }
+            if self.mail.smtp_port == 0 {
+                errors.push("mail.smtp_port debe ser mayor que 0".to_string());
+            }
+            if self.mail.smtp_username.is_some() != self.mail.smtp_password.is_some() {
+                errors
+                    .push("mail.smtp_username y mail.smtp_password se definen juntos".to_string());
+            }
+        }
+        if self.mail.transport == MailTransport::File && self.mail.dir.trim().is_empty() {
+            errors.push("mail.dir no puede estar vacío".to_string());
+        }
+        if self.trash.retention_days == 0 {
+            errors.push("trash.retention_days debe ser mayor que 0".to_string());
+        }
+        let limits = &self.rate_limit;
+        if limits.window_secs == 0 {
+            errors.push("rate_limit.window_secs debe ser mayor que 0".to_string());
+        }
+        if limits.max_requests == 0 {
+            errors.push("rate_limit.max_requests debe ser mayor que 0".to_string());
+        }
+        if limits.max_failures == 0 {
+            errors.push("rate_limit.max_failures debe ser mayor que 0".to_string());
+        }
+        if limits.lockout_secs == 0 {
+            errors.push("rate_limit.lockout_secs debe ser mayor que 0".to_string());
+        }
+        if limits.max_lockout_secs < limits.lockout_secs {
+            errors.push(
+                "rate_limit.max_lockout_secs no puede ser menor que rate_limit.lockout_secs"
+                    .to_string(),
+            );
+        }
+        if limits
+            .ip_header
+            .as_ref()
+            .is_some_and(|header| header.trim().is_empty())
+        {
+            errors.push("rate_limit.ip_header no puede estar vacío".to_string());
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError::Invalid(errors))
+        }
+    }
+}
+
+fn parse_env<T: FromStr>(var: &'static str, value: String) -> Result<T, ConfigError> {
+    value.parse().map_err(|_| ConfigError::Env { var, value })
+}
+
+fn read_file(path: &Path) -> Result<String, ConfigError> {
+    fs::read_to_string(path).map_err(|e| ConfigError::Read(path.to_path_buf(), e))
+}
+
+/// Guarda la configuración global. Solo tiene efecto la primera vez.
+pub fn init(config: Config) {
+    CONFIG.set(config).ok();
+}
+
+/// Configuración global. Si no se ha inicializado (por ejemplo, en los tests) se usa la
+/// de desarrollo por defecto.
+pub fn get() -> &'static Config {
+    CONFIG.get_or_init(Config::development)
+}
@@ -0,0 +1,51 @@
+use chrono::{NaiveDate, TimeZone, Utc};
+use mongodb::bson::DateTime;
+
+/// Lee una fecha en RFC 3339 (`2025-03-01T10:00:00Z`) o solo el día (`2025-03-01`,
+/// a las 00:00 UTC).
+pub fn parse_date(value: &str) -> Option<DateTime> {
+    if let Ok(date) = chrono::DateTime::parse_from_rfc3339(value) {
+        return Some(DateTime::from_millis(date.timestamp_millis()));
+    }
+    let day = NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()?;
+    let midnight = day.and_hms_opt(0, 0, 0)?;
+    Some(DateTime::from_millis(
+        Utc.from_utc_datetime(&midnight).timestamp_millis(),
+    ))
+}
+
+/// Serde para fechas opcionales: en MongoDB se guardan como fecha BSON y en JSON se
+/// leen y escriben como texto (ver [`parse_date`]).
+pub mod optional_date {
+    use mongodb::bson::{Bson, DateTime};
+    use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        date: &Option<DateTime>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match date {
+            Some(date) if serializer.is_human_readable() => date
+                .try_to_rfc3339_string()
+                .map_err(serde::ser::Error::custom)?
+                .serialize(serializer),
+            Some(date) => date.serialize(serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    /// Acepta tanto una fecha BSON como su forma en texto, porque no todos los
+    /// (de)serializadores de `bson` indican lo mismo en `is_human_readable`.
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<DateTime>, D::Error> {
+        match Option::<Bson>::deserialize(deserializer)? {
+            None | Some(Bson::Null) => Ok(None),
+            Some(Bson::DateTime(date)) => Ok(Some(date)),
+            Some(Bson::String(value)) => super::parse_date(&value)
+                .map(Some)
+                .ok_or_else(|| D::Error::custom(format!("Fecha inválida: {}", value))),
+            Some(other) => Err(D::Error::custom(format!("Fecha inválida: {}", other))),
+        }
+    }
+}
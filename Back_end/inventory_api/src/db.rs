@@ -1,18 +1,18 @@
 use mongodb::{Client, Database};
 use once_cell::sync::OnceCell;
 
+use crate::config::DatabaseConfig;
+
 static DATABASE: OnceCell<Database> = OnceCell::new();
 
 /// Inicializa la base de datos y la almacena en un singleton.
 /// Si ya se creó, se devuelve la instancia existente.
-pub async fn init_db() -> mongodb::error::Result<Database> {
+pub async fn init_db(config: &DatabaseConfig) -> mongodb::error::Result<Database> {
     if let Some(db) = DATABASE.get() {
         return Ok(db.clone());
     }
-    // En este ejemplo se usa la URI por defecto; modifícala según tus necesidades.
-    let client = Client::with_uri_str("mongodb://localhost:27017").await?;
-    // Se usa el nombre "mydatabase", pero puedes cambiarlo.
-    let db = client.database("TFG");
+    let client = Client::with_uri_str(&config.uri).await?;
+    let db = client.database(&config.name);
     DATABASE
         .set(db.clone())
         .expect("Error al establecer la base de datos");
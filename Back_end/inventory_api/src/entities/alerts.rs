@@ -0,0 +1,81 @@
+use actix_web::{get, web, HttpResponse};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::error::ApiError;
+use crate::log::write_log;
+use crate::middleware::auth::AuthUser;
+use crate::repository::Repositories;
+use crate::services::alerts as alerts_service;
+
+#[get("/alerts/low-stock")]
+async fn get_low_stock_handler(
+    repos: web::Data<Repositories>,
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
+    match alerts_service::low_stock(&repos, user.id, user.is_admin()).await {
+        Ok(alerts) => {
+            write_log(&format!(
+                "GET /alerts/low-stock - {} propiedades con stock bajo para el usuario {}",
+                alerts.len(),
+                user.id
+            ))
+            .ok();
+            Ok(HttpResponse::Ok().json(alerts))
+        }
+        Err(e) => {
+            write_log(&format!("GET /alerts/low-stock - {}", e)).ok();
+            Err(e)
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ExpiringQuery {
+    days: Option<String>,
+}
+
+/// Días que se miran hacia delante si no se indica `days`.
+const DEFAULT_EXPIRING_DAYS: u32 = 7;
+
+#[get("/alerts/expiring")]
+async fn get_expiring_handler(
+    repos: web::Data<Repositories>,
+    query: web::Query<ExpiringQuery>,
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
+    let days = match query.days.as_deref() {
+        None => DEFAULT_EXPIRING_DAYS,
+        Some(days) => match days.parse::<u32>() {
+            Ok(days) if days <= 3650 => days,
+            _ => {
+                write_log(&format!("GET /alerts/expiring - days inválido: {}", days)).ok();
+                return Err(
+                    ApiError::validation("'days' debe ser un número entre 0 y 3650")
+                        .with_details(json!({"field": "days"})),
+                );
+            }
+        },
+    };
+    match alerts_service::expiring(&repos, user.id, user.is_admin(), days).await {
+        Ok(items) => {
+            write_log(&format!(
+                "GET /alerts/expiring - {} items caducan en {} días para el usuario {}",
+                items.len(),
+                days,
+                user.id
+            ))
+            .ok();
+            Ok(HttpResponse::Ok().json(items))
+        }
+        Err(e) => {
+            write_log(&format!("GET /alerts/expiring - {}", e)).ok();
+            Err(e)
+        }
+    }
+}
+
+pub fn configure_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_low_stock_handler)
+        .service(get_expiring_handler);
+}
@@ -0,0 +1,134 @@
+use actix_web::{get, put, web, HttpResponse};
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+
+use crate::access::{authorize, Resource};
+use crate::entities::audit::AuditEntity;
+use crate::entities::user_group::GroupRole;
+use crate::error::ApiError;
+use crate::log::write_log;
+use crate::middleware::auth::AuthUser;
+use crate::repository::Repositories;
+use crate::services::{attribute as attribute_service, audit as audit_service};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AttributeType {
+    Text,
+    Number,
+    /// Se guarda como texto RFC 3339 en UTC, así que se puede comparar como texto.
+    Date,
+    Bool,
+    Enum,
+}
+
+/// Atributo que pueden llevar los items de un grupo.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttributeDef {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub kind: AttributeType,
+    #[serde(default)]
+    pub required: bool,
+    /// Valores admitidos; solo para el tipo `enum`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub options: Option<Vec<String>>,
+}
+
+#[get("/groups/{id}/attributes")]
+async fn get_attributes_handler(
+    repos: web::Data<Repositories>,
+    path: web::Path<String>,
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
+    let group_id = match ObjectId::parse_str(path.into_inner()) {
+        Ok(id) => id,
+        Err(_) => {
+            write_log("GET /groups/{id}/attributes - ID inválido").ok();
+            return Err(ApiError::validation("ID inválido"));
+        }
+    };
+    authorize(
+        &repos,
+        &user,
+        Resource::Group(group_id),
+        GroupRole::Viewer,
+        "GET /groups/{id}/attributes",
+    )
+    .await?;
+    let schema = attribute_service::group_schema(&repos, group_id).await?;
+    write_log(&format!(
+        "GET /groups/{{id}}/attributes - {} atributos en el grupo {}",
+        schema.len(),
+        group_id
+    ))
+    .ok();
+    Ok(HttpResponse::Ok().json(schema))
+}
+
+/// Sustituye los atributos del grupo. Los items ya guardados no se vuelven a validar.
+#[put("/groups/{id}/attributes")]
+async fn put_attributes_handler(
+    repos: web::Data<Repositories>,
+    path: web::Path<String>,
+    body: web::Json<serde_json::Value>,
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
+    let group_id = match ObjectId::parse_str(path.into_inner()) {
+        Ok(id) => id,
+        Err(_) => {
+            write_log("PUT /groups/{id}/attributes - ID inválido").ok();
+            return Err(ApiError::validation("ID inválido"));
+        }
+    };
+    authorize(
+        &repos,
+        &user,
+        Resource::Group(group_id),
+        GroupRole::Owner,
+        "PUT /groups/{id}/attributes",
+    )
+    .await?;
+    let before = repos.groups.find_by_id(group_id).await?;
+    let result = match attribute_service::parse_schema(&body) {
+        Ok(schema) => attribute_service::set_schema(&repos, group_id, &schema)
+            .await
+            .map(|_| schema),
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok(schema) => {
+            write_log(&format!(
+                "PUT /groups/{{id}}/attributes - Grupo {} con {} atributos",
+                group_id,
+                schema.len()
+            ))
+            .ok();
+            let after = repos.groups.find_by_id(group_id).await.ok().flatten();
+            audit_service::record(
+                &repos,
+                user.id,
+                group_id,
+                AuditEntity::Group,
+                group_id,
+                before.as_ref(),
+                after.as_ref(),
+            )
+            .await;
+            Ok(HttpResponse::Ok().json(schema))
+        }
+        Err(e) => {
+            write_log(&format!(
+                "PUT /groups/{{id}}/attributes - Error en el grupo {}: {}",
+                group_id, e
+            ))
+            .ok();
+            Err(e)
+        }
+    }
+}
+
+pub fn configure_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_attributes_handler)
+        .service(put_attributes_handler);
+}
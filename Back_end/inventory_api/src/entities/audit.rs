@@ -0,0 +1,249 @@
+use actix_web::{get, web, HttpResponse};
+use mongodb::bson::{oid::ObjectId, Bson, DateTime, Document};
+use serde::{Deserialize, Serialize};
+
+use crate::access::{authorize, Resource};
+use crate::entities::session::to_rfc3339;
+use crate::entities::user_group::GroupRole;
+use crate::error::ApiError;
+use crate::log::write_log;
+use crate::middleware::auth::AuthUser;
+use crate::repository::Repositories;
+use crate::services::audit as audit_service;
+
+/// Tipo de elemento al que se refiere un evento de auditoría.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditEntity {
+    Group,
+    Property,
+    Zone,
+    Item,
+}
+
+impl AuditEntity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditEntity::Group => "group",
+            AuditEntity::Property => "property",
+            AuditEntity::Zone => "zone",
+            AuditEntity::Item => "item",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "group" => Some(AuditEntity::Group),
+            "property" => Some(AuditEntity::Property),
+            "zone" => Some(AuditEntity::Zone),
+            "item" => Some(AuditEntity::Item),
+            _ => None,
+        }
+    }
+
+    pub fn resource(&self, id: ObjectId) -> Resource {
+        match self {
+            AuditEntity::Group => Resource::Group(id),
+            AuditEntity::Property => Resource::Property(id),
+            AuditEntity::Zone => Resource::Zone(id),
+            AuditEntity::Item => Resource::Item(id),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditAction {
+    Create,
+    Update,
+    Delete,
+    /// Salida de la papelera; lleva en `after` el elemento restaurado.
+    Restore,
+}
+
+/// Cambio registrado en la colección `auditLog`. En las modificaciones, `before` y `after`
+/// solo tienen los campos que cambiaron; en las altas solo hay `after` y en los borrados
+/// solo `before`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEvent {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    #[serde(rename = "groupId")]
+    pub group_id: ObjectId,
+    #[serde(rename = "entityType")]
+    pub entity_type: AuditEntity,
+    #[serde(rename = "entityId")]
+    pub entity_id: ObjectId,
+    pub action: AuditAction,
+    /// Usuario que hizo el cambio.
+    #[serde(rename = "userId")]
+    pub user_id: ObjectId,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub before: Option<Document>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub after: Option<Document>,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime,
+}
+
+/// Evento tal y como se devuelve en la API.
+#[derive(Debug, Serialize)]
+struct AuditEventInfo {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    id: Option<ObjectId>,
+    #[serde(rename = "groupId")]
+    group_id: ObjectId,
+    #[serde(rename = "entityType")]
+    entity_type: AuditEntity,
+    #[serde(rename = "entityId")]
+    entity_id: ObjectId,
+    action: AuditAction,
+    #[serde(rename = "userId")]
+    user_id: ObjectId,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    before: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    after: Option<serde_json::Value>,
+    #[serde(rename = "createdAt")]
+    created_at: String,
+}
+
+impl From<AuditEvent> for AuditEventInfo {
+    fn from(e: AuditEvent) -> Self {
+        let to_json = |document: Document| Bson::Document(document).into_relaxed_extjson();
+        Self {
+            id: e.id,
+            group_id: e.group_id,
+            entity_type: e.entity_type,
+            entity_id: e.entity_id,
+            action: e.action,
+            user_id: e.user_id,
+            before: e.before.map(to_json),
+            after: e.after.map(to_json),
+            created_at: to_rfc3339(e.created_at),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct HistoryQuery {
+    limit: Option<String>,
+}
+
+fn history_limit(query: &HistoryQuery) -> Result<i64, ApiError> {
+    match &query.limit {
+        None => Ok(audit_service::DEFAULT_LIMIT),
+        Some(limit) => match limit.parse::<i64>() {
+            Ok(limit) if (1..=audit_service::MAX_LIMIT).contains(&limit) => Ok(limit),
+            _ => Err(ApiError::validation(format!(
+                "'limit' debe ser un número entre 1 y {}",
+                audit_service::MAX_LIMIT
+            ))
+            .with_details(serde_json::json!({"field": "limit"}))),
+        },
+    }
+}
+
+#[get("/groups/{id}/history")]
+async fn get_group_history_handler(
+    repos: web::Data<Repositories>,
+    path: web::Path<String>,
+    query: web::Query<HistoryQuery>,
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
+    let group_id = match ObjectId::parse_str(path.into_inner()) {
+        Ok(id) => id,
+        Err(_) => {
+            write_log("GET /groups/{id}/history - ID inválido").ok();
+            return Err(ApiError::validation("ID inválido"));
+        }
+    };
+    let limit = match history_limit(&query) {
+        Ok(limit) => limit,
+        Err(e) => {
+            write_log(&format!("GET /groups/{{id}}/history - {}", e)).ok();
+            return Err(e);
+        }
+    };
+    // El historial del grupo incluye los elementos privados de todos sus miembros
+    authorize(
+        &repos,
+        &user,
+        Resource::Group(group_id),
+        GroupRole::Owner,
+        "GET /groups/{id}/history",
+    )
+    .await?;
+    match repos.audit.find_by_group(group_id, limit).await {
+        Ok(events) => {
+            write_log(&format!(
+                "GET /groups/{{id}}/history - {} eventos del grupo {}",
+                events.len(),
+                group_id
+            ))
+            .ok();
+            let events: Vec<AuditEventInfo> = events.into_iter().map(Into::into).collect();
+            Ok(HttpResponse::Ok().json(events))
+        }
+        Err(e) => {
+            write_log(&format!("GET /groups/{{id}}/history - Error: {}", e)).ok();
+            Err(e.into())
+        }
+    }
+}
+
+#[get("/history/{entityType}/{id}")]
+async fn get_entity_history_handler(
+    repos: web::Data<Repositories>,
+    path: web::Path<(String, String)>,
+    query: web::Query<HistoryQuery>,
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
+    let (entity_type, id) = path.into_inner();
+    let entity_type = match AuditEntity::parse(&entity_type) {
+        Some(entity_type) => entity_type,
+        None => {
+            write_log("GET /history/{entityType}/{id} - Tipo inválido").ok();
+            return Err(ApiError::validation(
+                "El tipo debe ser 'group', 'property', 'zone' o 'item'",
+            )
+            .with_details(serde_json::json!({"field": "entityType"})));
+        }
+    };
+    let entity_id = match ObjectId::parse_str(id) {
+        Ok(id) => id,
+        Err(_) => {
+            write_log("GET /history/{entityType}/{id} - ID inválido").ok();
+            return Err(ApiError::validation("ID inválido"));
+        }
+    };
+    let limit = match history_limit(&query) {
+        Ok(limit) => limit,
+        Err(e) => {
+            write_log(&format!("GET /history/{{entityType}}/{{id}} - {}", e)).ok();
+            return Err(e);
+        }
+    };
+    match audit_service::entity_history(&repos, &user, entity_type, entity_id, limit).await {
+        Ok(events) => {
+            write_log(&format!(
+                "GET /history/{{entityType}}/{{id}} - {} eventos de {:?} {}",
+                events.len(),
+                entity_type,
+                entity_id
+            ))
+            .ok();
+            let events: Vec<AuditEventInfo> = events.into_iter().map(Into::into).collect();
+            Ok(HttpResponse::Ok().json(events))
+        }
+        Err(e) => {
+            write_log(&format!("GET /history/{{entityType}}/{{id}} - {}", e)).ok();
+            Err(e)
+        }
+    }
+}
+
+pub fn configure_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_group_history_handler)
+        .service(get_entity_history_handler);
+}
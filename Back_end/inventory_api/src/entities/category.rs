@@ -0,0 +1,204 @@
+use actix_web::{delete, get, patch, post, web, HttpResponse};
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+
+use crate::access::{authorize, Resource};
+use crate::entities::user_group::GroupRole;
+use crate::error::ApiError;
+use crate::log::write_log;
+use crate::middleware::auth::AuthUser;
+use crate::repository::Repositories;
+use crate::services::category as category_service;
+
+/// Categoría de items de un grupo, guardada en la colección `categories`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Category {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    #[serde(rename = "groupId")]
+    pub group_id: ObjectId,
+    pub name: String,
+}
+
+/// Categoría del path y grupo al que pertenece, con el acceso ya comprobado.
+async fn authorized_category(
+    repos: &Repositories,
+    user: &AuthUser,
+    id_str: &str,
+    role: GroupRole,
+    route: &str,
+) -> Result<Category, ApiError> {
+    let category_id = match ObjectId::parse_str(id_str) {
+        Ok(id) => id,
+        Err(_) => {
+            write_log(&format!("{} - ID inválido: {}", route, id_str)).ok();
+            return Err(ApiError::validation("ID inválido"));
+        }
+    };
+    let category = match repos.categories.find_by_id(category_id).await? {
+        Some(category) => category,
+        None => {
+            write_log(&format!(
+                "{} - Categoría no encontrada: {}",
+                route, category_id
+            ))
+            .ok();
+            return Err(ApiError::not_found("Categoría no encontrada"));
+        }
+    };
+    authorize(repos, user, Resource::Group(category.group_id), role, route).await?;
+    Ok(category)
+}
+
+#[get("/groups/{id}/categories")]
+async fn get_categories_handler(
+    repos: web::Data<Repositories>,
+    path: web::Path<String>,
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
+    let group_id = match ObjectId::parse_str(path.into_inner()) {
+        Ok(id) => id,
+        Err(_) => {
+            write_log("GET /groups/{id}/categories - ID inválido").ok();
+            return Err(ApiError::validation("ID inválido"));
+        }
+    };
+    authorize(
+        &repos,
+        &user,
+        Resource::Group(group_id),
+        GroupRole::Viewer,
+        "GET /groups/{id}/categories",
+    )
+    .await?;
+    match repos.categories.find_by_group(group_id).await {
+        Ok(categories) => {
+            write_log(&format!(
+                "GET /groups/{{id}}/categories - {} categorías en el grupo {}",
+                categories.len(),
+                group_id
+            ))
+            .ok();
+            Ok(HttpResponse::Ok().json(categories))
+        }
+        Err(e) => {
+            write_log(&format!("GET /groups/{{id}}/categories - Error: {}", e)).ok();
+            Err(e.into())
+        }
+    }
+}
+
+#[post("/groups/{id}/categories")]
+async fn create_category_handler(
+    repos: web::Data<Repositories>,
+    path: web::Path<String>,
+    body: web::Json<serde_json::Value>,
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
+    let group_id = match ObjectId::parse_str(path.into_inner()) {
+        Ok(id) => id,
+        Err(_) => {
+            write_log("POST /groups/{id}/categories - ID inválido").ok();
+            return Err(ApiError::validation("ID inválido"));
+        }
+    };
+    authorize(
+        &repos,
+        &user,
+        Resource::Group(group_id),
+        GroupRole::Editor,
+        "POST /groups/{id}/categories",
+    )
+    .await?;
+    let result = match category_service::category_name(&body) {
+        Ok(name) => category_service::create_category(&repos, group_id, name).await,
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok(category_id) => {
+            write_log(&format!(
+                "POST /groups/{{id}}/categories - Categoría {} creada en el grupo {}",
+                category_id, group_id
+            ))
+            .ok();
+            Ok(HttpResponse::Ok().json(category_id))
+        }
+        Err(e) => {
+            write_log(&format!("POST /groups/{{id}}/categories - {}", e)).ok();
+            Err(e)
+        }
+    }
+}
+
+#[patch("/categories/{id}")]
+async fn patch_category_handler(
+    repos: web::Data<Repositories>,
+    path: web::Path<String>,
+    body: web::Json<serde_json::Value>,
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
+    let category = authorized_category(
+        &repos,
+        &user,
+        &path.into_inner(),
+        GroupRole::Editor,
+        "PATCH /categories/{id}",
+    )
+    .await?;
+    let result = match category_service::category_name(&body) {
+        Ok(name) => category_service::rename_category(&repos, &category, name).await,
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok(()) => {
+            write_log(&format!(
+                "PATCH /categories/{{id}} - Categoría actualizada: {:?}",
+                category.id
+            ))
+            .ok();
+            Ok(HttpResponse::Ok().body("Categoría actualizada"))
+        }
+        Err(e) => {
+            write_log(&format!("PATCH /categories/{{id}} - {}", e)).ok();
+            Err(e)
+        }
+    }
+}
+
+/// Los items y plantillas de la categoría se quedan sin categoría.
+#[delete("/categories/{id}")]
+async fn delete_category_handler(
+    repos: web::Data<Repositories>,
+    path: web::Path<String>,
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
+    let category = authorized_category(
+        &repos,
+        &user,
+        &path.into_inner(),
+        GroupRole::Editor,
+        "DELETE /categories/{id}",
+    )
+    .await?;
+    match category_service::delete_category(&repos, &category).await {
+        Ok(()) => {
+            write_log(&format!(
+                "DELETE /categories/{{id}} - Categoría eliminada: {:?}",
+                category.id
+            ))
+            .ok();
+            Ok(HttpResponse::Ok().body("Categoría eliminada"))
+        }
+        Err(e) => {
+            write_log(&format!("DELETE /categories/{{id}} - {}", e)).ok();
+            Err(e)
+        }
+    }
+}
+
+pub fn configure_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_categories_handler)
+        .service(create_category_handler)
+        .service(patch_category_handler)
+        .service(delete_category_handler);
+}
@@ -1,15 +1,21 @@
 use actix_multipart::Multipart;
-use actix_web::{delete, get, patch, post, web, HttpResponse, Responder};
+use actix_web::{get, post, web, HttpResponse};
 use futures_util::StreamExt;
 use mongodb::bson::{doc, oid::ObjectId};
-use mongodb::Database;
 use std::fs;
 use std::path::Path;
 
+use crate::access::{authorize, Resource};
+use crate::entities::audit::AuditEntity;
+use crate::entities::user_group::GroupRole;
+use crate::error::ApiError;
 use crate::log::write_log;
+use crate::middleware::auth::AuthUser;
+use crate::repository::{Changes, Repositories};
+use crate::services::audit as audit_service;
 
 #[get("/image/{filename}")]
-pub async fn get_image_by_name_handler(path: web::Path<String>) -> impl Responder {
+pub async fn get_image_by_name_handler(path: web::Path<String>) -> Result<HttpResponse, ApiError> {
     let filename = path.into_inner();
     let file_path = Path::new("images").join(&filename);
     if !file_path.exists() {
@@ -18,7 +24,7 @@ pub async fn get_image_by_name_handler(path: web::Path<String>) -> impl Responde
             filename
         ))
         .ok();
-        return HttpResponse::NotFound().body("Imagen no encontrada");
+        return Err(ApiError::not_found("Imagen no encontrada"));
     }
     let file_data = match fs::read(&file_path) {
         Ok(data) => data,
@@ -28,7 +34,7 @@ pub async fn get_image_by_name_handler(path: web::Path<String>) -> impl Responde
                 filename
             ))
             .ok();
-            return HttpResponse::InternalServerError().body("Error al leer la imagen");
+            return Err(ApiError::internal("Error al leer la imagen"));
         }
     };
     write_log(&format!(
@@ -37,17 +43,21 @@ pub async fn get_image_by_name_handler(path: web::Path<String>) -> impl Responde
         file_data.len()
     ))
     .ok();
-    HttpResponse::Ok()
+    Ok(HttpResponse::Ok()
         .content_type("image/png")
         .append_header((
             "Content-Disposition",
             format!("inline; filename=\"{}\"", filename),
         ))
-        .body(file_data)
+        .body(file_data))
 }
 
 #[post("/image")]
-pub async fn post_image_handler(mut payload: Multipart, db: web::Data<Database>) -> impl Responder {
+pub async fn post_image_handler(
+    mut payload: Multipart,
+    repos: web::Data<Repositories>,
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
     let mut object_id: Option<String> = None;
     let mut file_bytes: Option<bytes::BytesMut> = None;
     while let Some(item) = payload.next().await {
@@ -55,7 +65,7 @@ pub async fn post_image_handler(mut payload: Multipart, db: web::Data<Database>)
             Ok(f) => f,
             Err(_) => {
                 write_log("POST /image - Error procesando multipart").ok();
-                return HttpResponse::BadRequest().body("Error procesando multipart");
+                return Err(ApiError::internal("Error procesando multipart"));
             }
         };
         match field.name() {
@@ -66,7 +76,7 @@ pub async fn post_image_handler(mut payload: Multipart, db: web::Data<Database>)
                         Ok(d) => d,
                         Err(_) => {
                             write_log("POST /image - Error leyendo objectID").ok();
-                            return HttpResponse::BadRequest().body("Error leyendo objectID");
+                            return Err(ApiError::internal("Error leyendo objectID"));
                         }
                     };
                     data.extend_from_slice(&bytes);
@@ -80,7 +90,7 @@ pub async fn post_image_handler(mut payload: Multipart, db: web::Data<Database>)
                         Ok(d) => d,
                         Err(_) => {
                             write_log("POST /image - Error leyendo archivo").ok();
-                            return HttpResponse::BadRequest().body("Error leyendo archivo");
+                            return Err(ApiError::internal("Error leyendo archivo"));
                         }
                     };
                     bytes_mut.extend_from_slice(&data);
@@ -95,71 +105,69 @@ pub async fn post_image_handler(mut payload: Multipart, db: web::Data<Database>)
         Some(data) => data,
         None => {
             write_log("POST /image - No se recibió archivo").ok();
-            return HttpResponse::BadRequest().body("No se recibió archivo");
+            return Err(ApiError::validation("No se recibió archivo"));
         }
     };
     let oid_str = match object_id {
         Some(id) => id,
         None => {
             write_log("POST /image - No se recibió objectID").ok();
-            return HttpResponse::BadRequest().body("No se recibió objectID");
+            return Err(ApiError::validation("No se recibió objectID"));
         }
     };
     let item_obj_id = match ObjectId::parse_str(&oid_str) {
         Ok(oid) => oid,
         Err(_) => {
             write_log(&format!("POST /image - objectID inválido: {}", oid_str)).ok();
-            return HttpResponse::BadRequest().body("objectID inválido");
+            return Err(ApiError::validation("objectID inválido"));
         }
     };
-    let items_collection = db.collection::<mongodb::bson::Document>("items");
-    let existing_item = match items_collection
-        .find_one(doc! {"_id": item_obj_id.clone()})
-        .await
-    {
-        Ok(Some(doc)) => doc,
+    let group_id = authorize(
+        &repos,
+        &user,
+        Resource::Item(item_obj_id),
+        GroupRole::Editor,
+        "POST /image",
+    )
+    .await?;
+    let existing_item = match repos.items.find_by_id(item_obj_id).await {
+        Ok(Some(item)) => item,
         _ => {
             write_log(&format!("POST /image - Item no encontrado: {}", oid_str)).ok();
-            return HttpResponse::BadRequest().body("Item no encontrado");
+            return Err(ApiError::validation("Item no encontrado"));
         }
     };
     // Eliminar imagen anterior si existe
-    if let Some(old_pic) = existing_item.get_str("pictureUrl").ok() {
+    if let Some(old_pic) = &existing_item.picture_url {
         let old_file_path = Path::new("images").join(old_pic);
         if old_file_path.exists() {
             let _ = fs::remove_file(old_file_path);
         }
     }
     let images_dir = Path::new("images");
-    if !images_dir.exists() {
-        if let Err(_) = fs::create_dir_all(&images_dir) {
-            write_log("POST /image - Error creando directorio").ok();
-            return HttpResponse::InternalServerError().body("Error creando directorio");
-        }
+    if !images_dir.exists() && fs::create_dir_all(images_dir).is_err() {
+        write_log("POST /image - Error creando directorio").ok();
+        return Err(ApiError::internal("Error creando directorio"));
     }
     let file_name = format!("{}.png", oid_str);
     let file_path = images_dir.join(&file_name);
-    if let Err(_) = fs::write(&file_path, &file_data) {
+    if fs::write(&file_path, &file_data).is_err() {
         write_log(&format!(
             "POST /image - Error guardando archivo: {}",
             file_name
         ))
         .ok();
-        return HttpResponse::InternalServerError().body("Error guardando archivo");
+        return Err(ApiError::internal("Error guardando archivo"));
     }
-    if let Err(_) = items_collection
-        .update_one(
-            doc! {"_id": item_obj_id},
-            doc! { "$set": { "pictureUrl": file_name.clone() } },
-        )
-        .await
-    {
+    let mut changes = Changes::new();
+    changes.set("pictureUrl", file_name.clone());
+    if repos.items.update(item_obj_id, changes).await.is_err() {
         write_log(&format!(
             "POST /image - Error actualizando item: {}",
             file_name
         ))
         .ok();
-        return HttpResponse::InternalServerError().body("Error actualizando item");
+        return Err(ApiError::internal("Error actualizando item"));
     }
     write_log(&format!(
         "POST /image - Imagen actualizada correctamente: {} ({} bytes)",
@@ -167,7 +175,22 @@ pub async fn post_image_handler(mut payload: Multipart, db: web::Data<Database>)
         file_data.len()
     ))
     .ok();
-    HttpResponse::Ok().body("Imagen actualizada")
+    // El nombre no cambia al sustituir la imagen, así que la subida se registra siempre
+    let before = match &existing_item.picture_url {
+        Some(old_pic) => doc! {"pictureUrl": old_pic},
+        None => doc! {},
+    };
+    audit_service::record_update(
+        &repos,
+        user.id,
+        group_id,
+        AuditEntity::Item,
+        item_obj_id,
+        before,
+        doc! {"pictureUrl": &file_name},
+    )
+    .await;
+    Ok(HttpResponse::Ok().body("Imagen actualizada"))
 }
 
 // #[patch("/image/{id}")]
@@ -294,8 +317,11 @@ pub async fn post_image_handler(mut payload: Multipart, db: web::Data<Database>)
 //     HttpResponse::Ok().body("Imagen eliminada")
 // }
 
-pub fn configure_routes(cfg: &mut web::ServiceConfig) {
+pub fn configure_public_routes(cfg: &mut web::ServiceConfig) {
     cfg.service(get_image_by_name_handler);
+}
+
+pub fn configure_private_routes(cfg: &mut web::ServiceConfig) {
     cfg.service(post_image_handler);
     //cfg.service(patch_image_handler);
     //cfg.service(delete_image_handler);
@@ -1,10 +1,17 @@
+use crate::access::{authorize, Resource};
+use crate::entities::audit::AuditEntity;
+use crate::entities::user_group::GroupRole;
+use crate::error::ApiError;
 use crate::log::write_log;
-use actix_web::{delete, get, patch, post, web, HttpMessage, HttpRequest, HttpResponse, Responder};
-use futures_util::stream::TryStreamExt;
-use mongodb::{
-    bson::{doc, oid::ObjectId, Document},
-    Database,
-};
+use crate::middleware::auth::AuthUser;
+use crate::repository::Repositories;
+use crate::services::audit as audit_service;
+use crate::services::bulk as bulk_service;
+use crate::services::item as item_service;
+use crate::services::template as template_service;
+use crate::services::trash as trash_service;
+use actix_web::{delete, get, patch, post, web, HttpResponse};
+use mongodb::bson::{oid::ObjectId, DateTime, Document};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -20,6 +27,48 @@ pub struct Item {
     pub zone_id: ObjectId,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tags: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quantity: Option<f64>,
+    /// Unidad de la cantidad (`kg`, `l`, `uds`...).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unit: Option<String>,
+    /// Por debajo de esta cantidad el item aparece en las alertas de stock bajo.
+    #[serde(rename = "minQuantity", skip_serializing_if = "Option::is_none")]
+    pub min_quantity: Option<f64>,
+    #[serde(
+        rename = "expiresAt",
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "crate::dates::optional_date"
+    )]
+    pub expires_at: Option<DateTime>,
+    #[serde(
+        rename = "purchasedAt",
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "crate::dates::optional_date"
+    )]
+    pub purchased_at: Option<DateTime>,
+    /// Fecha en la que pasó a la papelera. Mientras la tenga, no aparece en las consultas.
+    #[serde(
+        rename = "deletedAt",
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "crate::dates::optional_date"
+    )]
+    pub deleted_at: Option<DateTime>,
+    /// Entrada de la papelera con la que se borró, junto con lo que contenía.
+    #[serde(rename = "trashId", skip_serializing_if = "Option::is_none")]
+    pub trash_id: Option<ObjectId>,
+    /// Categoría del grupo en la que está el item.
+    #[serde(rename = "categoryId", skip_serializing_if = "Option::is_none")]
+    pub category_id: Option<ObjectId>,
+    /// Valores de los atributos del grupo, por nombre.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attributes: Option<Document>,
+    /// Préstamo en curso, si el item está prestado.
+    #[serde(rename = "loanId", skip_serializing_if = "Option::is_none")]
+    pub loan_id: Option<ObjectId>,
 }
 
 // impl Item {
@@ -77,20 +126,31 @@ pub struct Item {
 // }
 
 #[get("/items/{id}")]
-async fn get_item_handler(db: web::Data<Database>, path: web::Path<String>) -> impl Responder {
-    let collection = db.collection::<Item>("items");
+async fn get_item_handler(
+    repos: web::Data<Repositories>,
+    path: web::Path<String>,
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
     let id_str = path.into_inner();
     let obj_id = match ObjectId::parse_str(&id_str) {
         Ok(obj_id) => obj_id,
         Err(_) => {
             write_log(&format!("GET /items/{{id}} - ID inválido: {}", id_str)).ok();
-            return HttpResponse::BadRequest().body("ID inválido");
+            return Err(ApiError::validation("ID inválido"));
         }
     };
-    match collection.find_one(doc! {"_id":obj_id}).await {
+    authorize(
+        &repos,
+        &user,
+        Resource::Item(obj_id),
+        GroupRole::Viewer,
+        "GET /items/{id}",
+    )
+    .await?;
+    match repos.items.find_by_id(obj_id).await {
         Ok(Some(item)) => {
             write_log(&format!("GET /items/{{id}} - Item encontrado: {:?}", item)).ok();
-            HttpResponse::Ok().json(item)
+            Ok(HttpResponse::Ok().json(item))
         }
         Ok(None) => {
             write_log(&format!(
@@ -98,11 +158,11 @@ async fn get_item_handler(db: web::Data<Database>, path: web::Path<String>) -> i
                 obj_id
             ))
             .ok();
-            HttpResponse::NotFound().body("Objeto no encontrado")
+            Err(ApiError::not_found("Objeto no encontrado"))
         }
         Err(e) => {
             write_log(&format!("GET /items/{{id}} - Error: {}", e)).ok();
-            HttpResponse::BadRequest().body(e.to_string())
+            Err(e.into())
         }
     }
 }
@@ -166,232 +226,290 @@ async fn get_item_handler(db: web::Data<Database>, path: web::Path<String>) -> i
 //     HttpResponse::Ok().json(items)
 // }
 
+/// Con `templateId` el item se crea a partir de esa plantilla del grupo.
 #[post("/items")]
-async fn create_item_handler(db: web::Data<Database>, new_item: web::Json<Item>) -> impl Responder {
-    let collection = db.collection::<Item>("items");
-    let mut item = new_item.into_inner();
-    item.id = None;
-    match collection.insert_one(item).await {
-        Ok(result) => {
+async fn create_item_handler(
+    repos: web::Data<Repositories>,
+    body: web::Json<serde_json::Value>,
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
+    let (new_item, template) = match template_service::instantiate(&repos, &body).await {
+        Ok(instance) => instance,
+        Err(e) => {
+            write_log(&format!("POST /items - Error al leer el item: {}", e)).ok();
+            return Err(e);
+        }
+    };
+    let group_id = authorize(
+        &repos,
+        &user,
+        Resource::Zone(new_item.zone_id),
+        GroupRole::Editor,
+        "POST /items",
+    )
+    .await?;
+    template_service::check_template_group(template.as_ref(), group_id)?;
+    match item_service::create_item(&repos, group_id, new_item, user.id).await {
+        Ok(item_id) => {
             write_log(&format!(
                 "POST /items - Item creado correctamente: {:?}",
-                result.inserted_id
+                item_id
             ))
             .ok();
-            HttpResponse::Ok().json(result.inserted_id)
+            let after = repos.items.find_by_id(item_id).await.ok().flatten();
+            audit_service::record(
+                &repos,
+                user.id,
+                group_id,
+                AuditEntity::Item,
+                item_id,
+                None,
+                after.as_ref(),
+            )
+            .await;
+            Ok(HttpResponse::Ok().json(item_id))
         }
-        Err(_) => {
-            write_log("POST /items - Error inesperado al crear item").ok();
-            HttpResponse::BadRequest().body("Error inesperado, inténtelo  nuevamente")
+        Err(e) => {
+            write_log(&format!("POST /items - Error al crear item: {}", e)).ok();
+            Err(e)
         }
     }
 }
 
 #[patch("/items/{id}")]
 async fn patch_item_handler(
-    db: web::Data<Database>,
+    repos: web::Data<Repositories>,
     path: web::Path<String>,
     updated_item: web::Json<serde_json::Value>,
-) -> impl Responder {
-    let collection = db.collection::<Item>("items");
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
     let id_str = path.into_inner();
     let obj_id = match ObjectId::parse_str(&id_str) {
         Ok(id) => id,
         Err(_) => {
             write_log(&format!("PATCH /items/{{id}} - ID inválido: {}", id_str)).ok();
-            return HttpResponse::BadRequest().body("ID inválido");
+            return Err(ApiError::validation("ID inválido"));
         }
     };
-    let mut set_doc = Document::new();
-    let mut unset_doc = Document::new();
-    if let Some(value) = updated_item.get("name") {
-        match value {
-            serde_json::Value::String(name) => set_doc.insert("name", name.clone()),
-            serde_json::Value::Null => {
-                return HttpResponse::BadRequest().body("'name' no puede ser null")
-            }
-            _ => return HttpResponse::BadRequest().body("Valor inválido para 'name'"),
-        };
-    }
-    if let Some(value) = updated_item.get("description") {
-        match value {
-            serde_json::Value::String(desc) => set_doc.insert("description", desc.clone()),
-            serde_json::Value::Null => unset_doc.insert("description", ""),
-            _ => return HttpResponse::BadRequest().body("Valor inválido para 'description'"),
-        };
-    }
-    if let Some(value) = updated_item.get("pictureUrl") {
-        match value {
-            serde_json::Value::String(url) => set_doc.insert("pictureUrl", url.clone()),
-            serde_json::Value::Null => unset_doc.insert("pictureUrl", ""),
-            _ => return HttpResponse::BadRequest().body("Valor inválido para 'pictureUrl'"),
-        };
-    }
-    if let Some(value) = updated_item.get("tags") {
-        match value {
-            serde_json::Value::Array(tags) => {
-                let string_tags: Vec<String> = tags
-                    .iter()
-                    .filter_map(|v| v.as_str().map(String::from))
-                    .collect();
-                set_doc.insert("tags", string_tags)
-            }
-            serde_json::Value::Null => unset_doc.insert("tags", ""),
-            _ => return HttpResponse::BadRequest().body("Valor inválido para 'tags'"),
-        };
-    }
-    if set_doc.is_empty() && unset_doc.is_empty() {
-        return HttpResponse::BadRequest().body("No hay campos para actualizar");
-    }
-    let mut update_doc = Document::new();
-    if !set_doc.is_empty() {
-        update_doc.insert("$set", set_doc);
-    }
-    if !unset_doc.is_empty() {
-        update_doc.insert("$unset", unset_doc);
-    }
-    match collection
-        .update_one(doc! {"_id": obj_id}, update_doc)
-        .await
-    {
-        Ok(result) if result.matched_count == 1 => {
+    let group_id = authorize(
+        &repos,
+        &user,
+        Resource::Item(obj_id),
+        GroupRole::Editor,
+        "PATCH /items/{id}",
+    )
+    .await?;
+    let before = repos.items.find_by_id(obj_id).await?;
+    let result = match item_service::item_changes(&updated_item) {
+        Ok(changes) => item_service::update_item(&repos, group_id, obj_id, changes).await,
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok(()) => {
             write_log(&format!(
                 "PATCH /items/{{id}} - Objeto actualizado: {}",
                 obj_id
             ))
             .ok();
-            HttpResponse::Ok().body("Objeto actualizado")
-        }
-        Ok(_) => {
-            write_log(&format!(
-                "PATCH /items/{{id}} - Objeto no encontrado: {}",
-                obj_id
-            ))
-            .ok();
-            HttpResponse::NotFound().body("Objeto no encontrado")
+            let after = repos.items.find_by_id(obj_id).await.ok().flatten();
+            audit_service::record(
+                &repos,
+                user.id,
+                group_id,
+                AuditEntity::Item,
+                obj_id,
+                before.as_ref(),
+                after.as_ref(),
+            )
+            .await;
+            Ok(HttpResponse::Ok().body("Objeto actualizado"))
         }
-        Err(_) => {
+        Err(e) => {
             write_log(&format!(
-                "PATCH /items/{{id}} - Error inesperado al actualizar objeto: {}",
-                obj_id
+                "PATCH /items/{{id}} - Error al actualizar objeto {}: {}",
+                obj_id, e
             ))
             .ok();
-            HttpResponse::BadRequest().body("Error inesperado, inténtelo  nuevamente")
+            Err(e)
         }
     }
 }
 
-pub async fn delete_item(db: &Database, item_id: String) -> HttpResponse {
-    use std::fs;
-    use std::path::Path;
-    let collection = db.collection::<Item>("items");
-    let obj_id = match ObjectId::parse_str(item_id.clone()) {
+#[delete("/items/{id}")]
+async fn delete_item_handler(
+    repos: web::Data<Repositories>,
+    path: web::Path<String>,
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
+    let obj_id = match ObjectId::parse_str(path.into_inner()) {
         Ok(id) => id,
         Err(_) => {
-            write_log(&format!(
-                "DELETE /items/{{id}} - Id incorrecto: {}",
-                item_id
-            ))
-            .ok();
-            return HttpResponse::BadRequest().body("Id incorrecto");
+            write_log("DELETE /items/{id} - Id incorrecto").ok();
+            return Err(ApiError::validation("Id incorrecto"));
         }
     };
-    // Buscar el item antes de eliminarlo
-    let item = match collection.find_one(doc! {"_id": obj_id.clone()}).await {
-        Ok(Some(item)) => item,
-        Ok(None) => {
+    let group_id = authorize(
+        &repos,
+        &user,
+        Resource::Item(obj_id),
+        GroupRole::Editor,
+        "DELETE /items/{id}",
+    )
+    .await?;
+    let before = repos.items.find_by_id(obj_id).await?;
+    match trash_service::move_to_trash(&repos, user.id, group_id, AuditEntity::Item, obj_id).await {
+        Ok(_) => {
             write_log(&format!(
-                "DELETE /items/{{id}} - Item no encontrado: {}",
-                item_id
+                "DELETE /items/{{id}} - Item {} eliminado correctamente",
+                obj_id
             ))
             .ok();
-            return HttpResponse::NotFound().body("Item no encontrado");
+            audit_service::record(
+                &repos,
+                user.id,
+                group_id,
+                AuditEntity::Item,
+                obj_id,
+                before.as_ref(),
+                None,
+            )
+            .await;
+            Ok(HttpResponse::Ok().body("Item Eliminado"))
         }
         Err(e) => {
             write_log(&format!(
-                "DELETE /items/{{id}} - Error buscando item: {}: {}",
-                item_id, e
+                "DELETE /items/{{id}} - Error al eliminar item {}: {}",
+                obj_id, e
             ))
             .ok();
-            return HttpResponse::InternalServerError().body("Error buscando item");
+            Err(e)
+        }
+    }
+}
+
+#[post("/items/{id}/move")]
+async fn move_item_handler(
+    repos: web::Data<Repositories>,
+    path: web::Path<String>,
+    body: web::Json<serde_json::Value>,
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
+    let item_id = match ObjectId::parse_str(path.into_inner()) {
+        Ok(id) => id,
+        Err(_) => {
+            write_log("POST /items/{id}/move - ID inválido").ok();
+            return Err(ApiError::validation("ID inválido"));
         }
     };
-    // Si tiene imagen, eliminar el archivo
-    if let Some(picture_url) = &item.picture_url {
-        let image_path = Path::new("images").join(picture_url);
-        if image_path.exists() {
-            if let Err(e) = fs::remove_file(&image_path) {
-                write_log(&format!(
-                    "DELETE /items/{{id}} - Error eliminando imagen '{}': {}",
-                    picture_url, e
-                ))
-                .ok();
-                // No retornamos error, solo lo registramos
-            } else {
-                write_log(&format!(
-                    "DELETE /items/{{id}} - Imagen '{}' eliminada",
-                    picture_url
-                ))
-                .ok();
+    let zone_id = match body.get("zoneId").and_then(|v| v.as_str()) {
+        Some(id) => match ObjectId::parse_str(id) {
+            Ok(id) => id,
+            Err(_) => {
+                write_log("POST /items/{id}/move - zoneId inválido").ok();
+                return Err(ApiError::validation("zoneId inválido")
+                    .with_details(serde_json::json!({"field": "zoneId"})));
             }
+        },
+        None => {
+            write_log("POST /items/{id}/move - zoneId es requerido").ok();
+            return Err(ApiError::validation("zoneId es requerido")
+                .with_details(serde_json::json!({"field": "zoneId"})));
         }
-    }
-    // Eliminar el item de la base de datos
-    match collection.delete_one(doc! {"_id": obj_id}).await {
-        Ok(_) => {
+    };
+    // Hay que poder editar tanto el origen como el destino
+    let group_id = authorize(
+        &repos,
+        &user,
+        Resource::Item(item_id),
+        GroupRole::Editor,
+        "POST /items/{id}/move",
+    )
+    .await?;
+    let target_group_id = authorize(
+        &repos,
+        &user,
+        Resource::Zone(zone_id),
+        GroupRole::Editor,
+        "POST /items/{id}/move",
+    )
+    .await?;
+    let before = repos.items.find_by_id(item_id).await?;
+    match item_service::move_item(&repos, item_id, zone_id, group_id, target_group_id).await {
+        Ok(()) => {
             write_log(&format!(
-                "DELETE /items/{{id}} - Item eliminado: {}",
-                item_id
+                "POST /items/{{id}}/move - Item {} movido a la zona {}",
+                item_id, zone_id
             ))
             .ok();
-            HttpResponse::Ok().body("Item Eliminado")
+            let after = repos.items.find_by_id(item_id).await.ok().flatten();
+            // Si cambia de grupo, el movimiento queda en el historial de los dos
+            let mut groups = vec![group_id];
+            if target_group_id != group_id {
+                groups.push(target_group_id);
+            }
+            for group_id in groups {
+                audit_service::record(
+                    &repos,
+                    user.id,
+                    group_id,
+                    AuditEntity::Item,
+                    item_id,
+                    before.as_ref(),
+                    after.as_ref(),
+                )
+                .await;
+            }
+            Ok(HttpResponse::Ok().body("Objeto movido"))
         }
-        Err(_) => {
-            write_log(&format!(
-                "DELETE /items/{{id}} - Error inesperado al eliminar item: {}",
-                item_id
-            ))
-            .ok();
-            HttpResponse::BadRequest().body("Error inesperado, inténtelo  nuevamente")
+        Err(e) => {
+            write_log(&format!("POST /items/{{id}}/move - {}", e)).ok();
+            Err(e)
         }
     }
 }
 
-#[delete("/items/{id}")]
-async fn delete_item_handler(db: web::Data<Database>, path: web::Path<String>) -> impl Responder {
-    let item_id = path.into_inner();
-    let client = db.client();
-    let mut session = match client.start_session().await {
-        Ok(s) => s,
-        Err(_) => {
-            write_log("DELETE /items/{id} - Error inesperado al iniciar sesión").ok();
-            return HttpResponse::BadRequest().body("Error inesperado, inténtelo  nuevamente");
+#[post("/items/bulk")]
+async fn bulk_items_handler(
+    repos: web::Data<Repositories>,
+    body: web::Json<serde_json::Value>,
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
+    let request = match bulk_service::bulk_request(&body) {
+        Ok(request) => request,
+        Err(e) => {
+            write_log(&format!("POST /items/bulk - {}", e)).ok();
+            return Err(e);
         }
     };
-    session.start_transaction().await.ok();
-    let response = delete_item(&db, item_id.clone()).await;
-    if response.status().is_success() {
-        session.commit_transaction().await.ok();
-        write_log(&format!(
-            "DELETE /items/{{id}} - Item {} eliminado correctamente",
-            item_id
-        ))
-        .ok();
-    } else {
-        session.abort_transaction().await.ok();
-        write_log(&format!(
-            "DELETE /items/{{id}} - Error al eliminar item {}",
-            item_id
-        ))
-        .ok();
+    let total = request.operations.len();
+    match bulk_service::run_bulk(&repos, &user, request).await {
+        Ok(result) => {
+            let applied = result
+                .results
+                .iter()
+                .filter(|r| r.status == bulk_service::OperationStatus::Ok)
+                .count();
+            write_log(&format!(
+                "POST /items/bulk - Usuario {}: {} de {} operaciones aplicadas",
+                user.id, applied, total
+            ))
+            .ok();
+            Ok(HttpResponse::Ok().json(result))
+        }
+        Err(e) => {
+            write_log(&format!("POST /items/bulk - Error: {}", e)).ok();
+            Err(e)
+        }
     }
-    response
 }
+
 pub fn configure_routes(cfg: &mut web::ServiceConfig) {
     cfg.service(get_item_handler)
         //.service(get_items_handler)
         //.service(get_items_from_zone_handler)
         .service(create_item_handler)
+        .service(bulk_items_handler)
         .service(patch_item_handler)
-        .service(delete_item_handler);
+        .service(delete_item_handler)
+        .service(move_item_handler);
 }
@@ -0,0 +1,324 @@
+use actix_web::{get, post, web, HttpResponse};
+use mongodb::bson::{oid::ObjectId, DateTime};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::access::{authorize, Resource};
+use crate::entities::audit::AuditEntity;
+use crate::entities::item::Item;
+use crate::entities::session::to_rfc3339;
+use crate::entities::user_group::GroupRole;
+use crate::error::ApiError;
+use crate::log::write_log;
+use crate::middleware::auth::AuthUser;
+use crate::repository::Repositories;
+use crate::services::audit as audit_service;
+use crate::services::loan as loan_service;
+
+/// Préstamo de un item, guardado en la colección `loans`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Loan {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    #[serde(rename = "itemId")]
+    pub item_id: ObjectId,
+    /// Miembro del grupo que se lo lleva; no lo hay si es una persona de fuera.
+    #[serde(rename = "borrowerId", skip_serializing_if = "Option::is_none")]
+    pub borrower_id: Option<ObjectId>,
+    #[serde(rename = "borrowerName")]
+    pub borrower_name: String,
+    #[serde(
+        rename = "dueAt",
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "crate::dates::optional_date"
+    )]
+    pub due_at: Option<DateTime>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+    /// Usuario que registró el préstamo.
+    #[serde(rename = "userId")]
+    pub user_id: ObjectId,
+    #[serde(rename = "lentAt")]
+    pub lent_at: DateTime,
+    /// Devolución; mientras no la haya, el préstamo sigue en curso.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub returned: Option<LoanReturn>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoanReturn {
+    #[serde(rename = "returnedAt")]
+    pub returned_at: DateTime,
+    /// Usuario que registró la devolución.
+    #[serde(rename = "userId")]
+    pub user_id: ObjectId,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+}
+
+impl Loan {
+    /// En curso y con la fecha de devolución ya pasada.
+    pub fn is_overdue(&self, now: DateTime) -> bool {
+        self.returned.is_none() && self.due_at.is_some_and(|due_at| due_at < now)
+    }
+}
+
+/// Préstamo tal y como se devuelve en la API, con las fechas en RFC 3339.
+#[derive(Debug, Serialize)]
+struct LoanInfo {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    id: Option<ObjectId>,
+    #[serde(rename = "itemId")]
+    item_id: ObjectId,
+    #[serde(rename = "borrowerId", skip_serializing_if = "Option::is_none")]
+    borrower_id: Option<ObjectId>,
+    #[serde(rename = "borrowerName")]
+    borrower_name: String,
+    #[serde(rename = "dueAt", skip_serializing_if = "Option::is_none")]
+    due_at: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    notes: Option<String>,
+    #[serde(rename = "userId")]
+    user_id: ObjectId,
+    #[serde(rename = "lentAt")]
+    lent_at: String,
+    #[serde(rename = "returnedAt", skip_serializing_if = "Option::is_none")]
+    returned_at: Option<String>,
+    #[serde(rename = "returnedBy", skip_serializing_if = "Option::is_none")]
+    returned_by: Option<ObjectId>,
+    #[serde(rename = "returnNotes", skip_serializing_if = "Option::is_none")]
+    return_notes: Option<String>,
+    overdue: bool,
+}
+
+impl From<Loan> for LoanInfo {
+    fn from(l: Loan) -> Self {
+        let overdue = l.is_overdue(DateTime::now());
+        Self {
+            id: l.id,
+            item_id: l.item_id,
+            borrower_id: l.borrower_id,
+            borrower_name: l.borrower_name,
+            due_at: l.due_at.map(to_rfc3339),
+            notes: l.notes,
+            user_id: l.user_id,
+            lent_at: to_rfc3339(l.lent_at),
+            returned_at: l.returned.as_ref().map(|r| to_rfc3339(r.returned_at)),
+            returned_by: l.returned.as_ref().map(|r| r.user_id),
+            return_notes: l.returned.and_then(|r| r.notes),
+            overdue,
+        }
+    }
+}
+
+/// Item prestado con su préstamo en curso.
+#[derive(Debug, Serialize)]
+struct LentItemInfo {
+    item: Item,
+    loan: LoanInfo,
+}
+
+fn parse_id(id: String, route: &str) -> Result<ObjectId, ApiError> {
+    ObjectId::parse_str(id).map_err(|_| {
+        write_log(&format!("{} - ID inválido", route)).ok();
+        ApiError::validation("ID inválido")
+    })
+}
+
+#[post("/items/{id}/checkout")]
+async fn checkout_item_handler(
+    repos: web::Data<Repositories>,
+    path: web::Path<String>,
+    body: web::Json<serde_json::Value>,
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
+    let route = "POST /items/{id}/checkout";
+    let item_id = parse_id(path.into_inner(), route)?;
+    let group_id = authorize(
+        &repos,
+        &user,
+        Resource::Item(item_id),
+        GroupRole::Editor,
+        route,
+    )
+    .await?;
+    let before = repos.items.find_by_id(item_id).await?;
+    let result = match loan_service::checkout_request(&body) {
+        Ok(request) => loan_service::check_out(&repos, item_id, group_id, user.id, request).await,
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok(loan) => {
+            write_log(&format!(
+                "POST /items/{{id}}/checkout - Item {} prestado a {} por el usuario {}",
+                item_id, loan.borrower_name, user.id
+            ))
+            .ok();
+            let after = repos.items.find_by_id(item_id).await.ok().flatten();
+            audit_service::record(
+                &repos,
+                user.id,
+                group_id,
+                AuditEntity::Item,
+                item_id,
+                before.as_ref(),
+                after.as_ref(),
+            )
+            .await;
+            Ok(HttpResponse::Ok().json(LoanInfo::from(loan)))
+        }
+        Err(e) => {
+            write_log(&format!("POST /items/{{id}}/checkout - {}", e)).ok();
+            Err(e)
+        }
+    }
+}
+
+#[post("/items/{id}/checkin")]
+async fn checkin_item_handler(
+    repos: web::Data<Repositories>,
+    path: web::Path<String>,
+    body: Option<web::Json<serde_json::Value>>,
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
+    let route = "POST /items/{id}/checkin";
+    let item_id = parse_id(path.into_inner(), route)?;
+    let group_id = authorize(
+        &repos,
+        &user,
+        Resource::Item(item_id),
+        GroupRole::Editor,
+        route,
+    )
+    .await?;
+    let before = repos.items.find_by_id(item_id).await?;
+    let notes = body
+        .as_ref()
+        .and_then(|body| body.get("notes"))
+        .and_then(|notes| notes.as_str())
+        .map(|notes| notes.trim().to_string())
+        .filter(|notes| !notes.is_empty());
+    match loan_service::check_in(&repos, item_id, user.id, notes).await {
+        Ok(loan) => {
+            write_log(&format!(
+                "POST /items/{{id}}/checkin - Item {} devuelto por {}",
+                item_id, loan.borrower_name
+            ))
+            .ok();
+            let after = repos.items.find_by_id(item_id).await.ok().flatten();
+            audit_service::record(
+                &repos,
+                user.id,
+                group_id,
+                AuditEntity::Item,
+                item_id,
+                before.as_ref(),
+                after.as_ref(),
+            )
+            .await;
+            Ok(HttpResponse::Ok().json(LoanInfo::from(loan)))
+        }
+        Err(e) => {
+            write_log(&format!("POST /items/{{id}}/checkin - {}", e)).ok();
+            Err(e)
+        }
+    }
+}
+
+#[get("/items/{id}/loans")]
+async fn get_item_loans_handler(
+    repos: web::Data<Repositories>,
+    path: web::Path<String>,
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
+    let route = "GET /items/{id}/loans";
+    let item_id = parse_id(path.into_inner(), route)?;
+    authorize(
+        &repos,
+        &user,
+        Resource::Item(item_id),
+        GroupRole::Viewer,
+        route,
+    )
+    .await?;
+    match repos.loans.find_by_item(item_id).await {
+        Ok(loans) => {
+            write_log(&format!(
+                "GET /items/{{id}}/loans - {} préstamos del item {}",
+                loans.len(),
+                item_id
+            ))
+            .ok();
+            let loans: Vec<LoanInfo> = loans.into_iter().map(Into::into).collect();
+            Ok(HttpResponse::Ok().json(loans))
+        }
+        Err(e) => {
+            write_log(&format!("GET /items/{{id}}/loans - Error: {}", e)).ok();
+            Err(e.into())
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct GroupLoansQuery {
+    overdue: Option<String>,
+}
+
+#[get("/groups/{id}/loans")]
+async fn get_group_loans_handler(
+    repos: web::Data<Repositories>,
+    path: web::Path<String>,
+    query: web::Query<GroupLoansQuery>,
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
+    let route = "GET /groups/{id}/loans";
+    let group_id = parse_id(path.into_inner(), route)?;
+    let overdue_only = match query.overdue.as_deref() {
+        None | Some("false") => false,
+        Some("true") => true,
+        Some(value) => {
+            write_log(&format!("{} - overdue inválido: {}", route, value)).ok();
+            return Err(ApiError::validation("'overdue' debe ser 'true' o 'false'")
+                .with_details(json!({"field": "overdue"})));
+        }
+    };
+    authorize(
+        &repos,
+        &user,
+        Resource::Group(group_id),
+        GroupRole::Viewer,
+        route,
+    )
+    .await?;
+    let viewer = if user.is_admin() { None } else { Some(user.id) };
+    match loan_service::group_loans(&repos, group_id, viewer, overdue_only).await {
+        Ok(lent) => {
+            write_log(&format!(
+                "GET /groups/{{id}}/loans - {} items prestados en el grupo {}",
+                lent.len(),
+                group_id
+            ))
+            .ok();
+            let lent: Vec<LentItemInfo> = lent
+                .into_iter()
+                .map(|(item, loan)| LentItemInfo {
+                    item,
+                    loan: loan.into(),
+                })
+                .collect();
+            Ok(HttpResponse::Ok().json(lent))
+        }
+        Err(e) => {
+            write_log(&format!("GET /groups/{{id}}/loans - {}", e)).ok();
+            Err(e)
+        }
+    }
+}
+
+pub fn configure_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(checkout_item_handler)
+        .service(checkin_item_handler)
+        .service(get_item_loans_handler)
+        .service(get_group_loans_handler);
+}
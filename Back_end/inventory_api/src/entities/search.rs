@@ -1,211 +1,228 @@
+use std::collections::HashMap;
+
+use crate::error::ApiError;
 use crate::log::write_log;
-use actix_web::{get, web, HttpMessage, HttpRequest, HttpResponse, Responder};
-use futures_util::stream::TryStreamExt;
-use mongodb::{
-    bson::{doc, oid::ObjectId},
-    Database,
-};
+use actix_web::{get, web, HttpResponse};
+use mongodb::bson::oid::ObjectId;
 use serde::Serialize;
+use serde_json::json;
 
-use crate::entities::{
-    group::Group, item::Item, property::Property, user_group::UserGroup, zone::Zone,
-};
+use crate::access::{authorize, Resource};
+use crate::entities::user_group::GroupRole;
+use crate::entities::{group::Group, item::Item, property::Property, zone::Zone};
+use crate::middleware::auth::AuthUser;
+use crate::pagination::{self, FilterKind, ListSpec, Page};
+use crate::query as search_query;
+use crate::repository::Repositories;
+use crate::services::ancestors as ancestors_service;
+use crate::services::attribute as attribute_service;
+use crate::services::search::{self as search_service, SearchRequest, Within};
 
-#[derive(Serialize)]
-struct SearchResponse {
-    groups: Vec<Group>,
-    properties: Vec<Property>,
-    zones: Vec<Zone>,
-    items: Vec<Item>,
+/// Elemento encontrado, con su tipo en el campo `type`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum SearchEntity {
+    Group(Group),
+    Property(Property),
+    Zone(Zone),
+    Item(Box<Item>),
 }
 
-#[get("/search/{name}")]
-pub async fn search_endpoint(
-    db: web::Data<Database>,
-    req: HttpRequest,
-    name: web::Path<String>,
-) -> impl Responder {
-    let search_str = name.into_inner().to_lowercase();
-    let claims = req
-        .extensions()
-        .get::<crate::middleware::auth::Claims>()
-        .unwrap()
-        .clone();
-    let user_id = ObjectId::parse_str(&claims.sub).unwrap();
-    let is_admin = claims.role == "admin";
-
-    let mut groups_res = Vec::new();
-    let mut properties_res = Vec::new();
-    let mut zones_res = Vec::new();
-    let mut items_res = Vec::new();
-
-    let mut group_ids = Vec::new();
-    let group_coll = db.collection::<Group>("groups");
-    if is_admin {
-        // Admin: puede ver todos los grupos
-        let group_cursor = match group_coll.find(doc! {}).await {
-            Ok(cursor) => cursor,
-            Err(e) => {
-                write_log(&format!(
-                    "GET /search/{{name}} - Error buscando grupos (admin): {}",
-                    e
-                ))
-                .ok();
-                return HttpResponse::InternalServerError().body(e.to_string());
-            }
-        };
-        let all_groups: Vec<Group> = match group_cursor.try_collect().await {
-            Ok(gs) => gs,
-            Err(e) => {
-                write_log(&format!(
-                    "GET /search/{{name}} - Error recogiendo grupos (admin): {}",
-                    e
-                ))
-                .ok();
-                return HttpResponse::InternalServerError().body(e.to_string());
-            }
-        };
-        for group in all_groups {
-            if let Some(gid) = group.id.clone() {
-                group_ids.push(gid);
-            }
-            if group.name.to_lowercase().contains(&search_str) {
-                groups_res.push(group);
-            }
-        }
-    } else {
-        // Usuario normal: solo sus grupos
-        let user_group_coll = db.collection::<UserGroup>("userGroup");
-        let filter = doc! { "userId": user_id.clone() };
-        let usergroups_cursor = match user_group_coll.find(filter).await {
-            Ok(cursor) => cursor,
-            Err(e) => {
-                write_log(&format!(
-                    "GET /search/{{name}} - Error buscando userGroup: {}",
-                    e
-                ))
-                .ok();
-                return HttpResponse::InternalServerError().body(e.to_string());
-            }
-        };
-        let user_groups: Vec<UserGroup> = match usergroups_cursor.try_collect().await {
-            Ok(ugs) => ugs,
-            Err(e) => {
-                write_log(&format!(
-                    "GET /search/{{name}} - Error recogiendo userGroup: {}",
-                    e
-                ))
-                .ok();
-                return HttpResponse::InternalServerError().body(e.to_string());
-            }
-        };
-        for ug in user_groups {
-            if let Ok(Some(group)) = group_coll.find_one(doc! { "_id": &ug.group_id }).await {
-                if let Some(gid) = group.id.clone() {
-                    group_ids.push(gid);
-                }
-                if group.name.to_lowercase().contains(&search_str) {
-                    groups_res.push(group);
-                }
-            }
+/// Resultado de la búsqueda en la base de datos. Se pagina con los mismos campos que
+/// [`SearchHit`], así que el cursor sirve para los dos.
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchMatch {
+    #[serde(flatten)]
+    pub entity: SearchEntity,
+    /// Relevancia según el índice de texto; 0 si no se buscó texto.
+    pub score: f64,
+    /// Grupo al que pertenece el elemento.
+    #[serde(skip)]
+    pub group_id: ObjectId,
+}
+
+/// Campo en el que aparecen los términos buscados.
+#[derive(Debug, Clone, Serialize)]
+pub struct Highlight {
+    /// `name`, `description` o `tags.<posición>`.
+    pub field: String,
+    pub value: String,
+    /// Tramos `[inicio, fin)` del valor, en caracteres, que coinciden.
+    pub ranges: Vec<(usize, usize)>,
+}
+
+/// Paso de la ruta de un resultado.
+#[derive(Debug, Clone, Serialize)]
+pub struct Crumb {
+    /// `group`, `property` o `zone`.
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+    pub name: String,
+}
+
+/// Resultado de la búsqueda tal y como se devuelve.
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchHit {
+    #[serde(flatten)]
+    pub entity: SearchEntity,
+    pub score: f64,
+    pub highlights: Vec<Highlight>,
+    /// Grupo, propiedad y zonas en los que está, del más externo al más interno.
+    pub breadcrumb: Vec<Crumb>,
+}
+
+/// Página de resultados. `truncated` indica que la búsqueda con erratas solo ha puntuado
+/// los primeros candidatos: puede haber más resultados de los que cuenta `total`.
+#[derive(Debug, Serialize)]
+pub struct SearchResults {
+    #[serde(flatten)]
+    pub page: Page<SearchHit>,
+    pub truncated: bool,
+}
+
+const SEARCH_LIST: ListSpec = ListSpec {
+    sorts: &[("relevance", "score"), ("name", "name"), ("created", "_id")],
+    filters: &[("type", "type", FilterKind::Equals)],
+};
+
+async fn run_search(
+    repos: &Repositories,
+    user: &AuthUser,
+    search_str: &str,
+    query: &HashMap<String, String>,
+    route: &str,
+) -> Result<HttpResponse, ApiError> {
+    let mut page = match pagination::parse(query, &SEARCH_LIST) {
+        Ok(page) => page,
+        Err(e) => {
+            write_log(&format!("{} - {}", route, e)).ok();
+            return Err(e);
         }
+    };
+    // Sin orden indicado, los más relevantes primero
+    if !query.contains_key("sort") {
+        page.sort.descending = true;
     }
 
-    // Paso 2: Por cada grupo, obtener todas las propiedades
-    let mut property_ids = Vec::new();
-    let property_coll = db.collection::<Property>("properties");
-    for gid in group_ids {
-        let prop_filter = doc! { "groupId": gid.clone() };
-        if let Ok(prop_cursor) = property_coll.find(prop_filter).await {
-            let props: Vec<Property> = match prop_cursor.try_collect().await {
-                Ok(ps) => ps,
-                Err(_) => Vec::new(),
-            };
-            for prop in props {
-                // Si NO es admin, omitir privadas ajenas
-                if !is_admin {
-                    if let Some(prop_owner) = &prop.user_id {
-                        if *prop_owner != user_id {
-                            continue;
-                        }
-                    }
-                }
-                // Si es admin, ve todo
-                if prop.name.to_lowercase().contains(&search_str) {
-                    properties_res.push(prop.clone());
-                }
-                if let Some(pid) = prop.id {
-                    property_ids.push(pid);
-                }
-            }
+    let search = match search_query::parse(search_str) {
+        Ok(search) => search,
+        Err(e) => {
+            write_log(&format!("{} - {}", route, e)).ok();
+            return Err(e);
         }
-    }
+    };
 
-    // Paso 3: Por cada propiedad, obtener todas las zonas
-    let mut zone_ids = Vec::new();
-    let zone_coll = db.collection::<Zone>("zones");
-    for pid in property_ids {
-        let zone_filter = doc! { "propertyId": pid.clone() };
-        if let Ok(zone_cursor) = zone_coll.find(zone_filter).await {
-            let zones: Vec<Zone> = match zone_cursor.try_collect().await {
-                Ok(zs) => zs,
-                Err(_) => Vec::new(),
-            };
-            for zone in zones {
-                // Si NO es admin, omitir privadas ajenas
-                if !is_admin {
-                    if let Some(zone_owner) = &zone.user_id {
-                        if *zone_owner != user_id {
-                            continue;
-                        }
-                    }
-                }
-                // Si es admin, ve todo
-                if zone.name.to_lowercase().contains(&search_str) {
-                    zones_res.push(zone.clone());
-                }
-                if let Some(zid) = zone.id {
-                    zone_ids.push(zid);
-                }
-            }
+    let filters = match attribute_service::parse_filters(query) {
+        Ok(filters) => filters,
+        Err(e) => {
+            write_log(&format!("{} - {}", route, e)).ok();
+            return Err(e);
         }
-    }
+    };
 
-    // Paso 4: Por cada zona, obtener todos los ítems
-    let item_coll = db.collection::<Item>("items");
-    for zid in zone_ids {
-        let item_filter = doc! { "zoneId": zid };
-        if let Ok(item_cursor) = item_coll.find(item_filter).await {
-            let items: Vec<Item> = match item_cursor.try_collect().await {
-                Ok(is) => is,
-                Err(_) => Vec::new(),
-            };
-            for item in items {
-                if item.name.to_lowercase().contains(&search_str) {
-                    items_res.push(item.clone());
+    let category_id = match query.get("categoryId").map(ObjectId::parse_str) {
+        None => None,
+        Some(Ok(id)) => Some(id),
+        Some(Err(_)) => {
+            write_log(&format!("{} - categoryId inválido", route)).ok();
+            return Err(ApiError::validation("'categoryId' inválido")
+                .with_details(json!({"field": "categoryId"})));
+        }
+    };
+
+    let fuzzy = match query.get("fuzzy").map(String::as_str) {
+        None => None,
+        Some("true") => Some(true),
+        Some("false") => Some(false),
+        Some(_) => {
+            write_log(&format!("{} - fuzzy inválido", route)).ok();
+            return Err(ApiError::validation("'fuzzy' debe ser true o false")
+                .with_details(json!({"field": "fuzzy"})));
+        }
+    };
+
+    let within = match query.get("within").map(ObjectId::parse_str) {
+        None => None,
+        Some(Ok(id)) => {
+            let resource = match repos.groups.find_by_id(id).await {
+                Ok(Some(_)) => Resource::Group(id),
+                Ok(None) => ancestors_service::resource_of(repos, id).await?,
+                Err(_) => {
+                    write_log(&format!("{} - Error buscando within {}", route, id)).ok();
+                    return Err(ApiError::unexpected());
                 }
-            }
+            };
+            let group_id = authorize(repos, user, resource, GroupRole::Viewer, route).await?;
+            Some(Within { resource, group_id })
         }
-    }
+        Some(Err(_)) => {
+            write_log(&format!("{} - within inválido", route)).ok();
+            return Err(
+                ApiError::validation("'within' inválido").with_details(json!({"field": "within"}))
+            );
+        }
+    };
 
-    let response = SearchResponse {
-        groups: groups_res,
-        properties: properties_res,
-        zones: zones_res,
-        items: items_res,
+    let request = SearchRequest {
+        query: search,
+        attributes: filters,
+        category_id,
+        fuzzy,
+        within,
     };
+    let response =
+        match search_service::search(repos, user.id, user.is_admin(), &request, &page).await {
+            Ok(response) => response,
+            Err(e) => {
+                write_log(&format!("{} - {}", route, e)).ok();
+                return Err(e);
+            }
+        };
 
     write_log(&format!(
-        "GET /search/{{name}} - Búsqueda realizada: grupos={}, propiedades={}, zonas={}, items={}",
-        response.groups.len(),
-        response.properties.len(),
-        response.zones.len(),
-        response.items.len()
+        "{} - Búsqueda realizada: {} de {} resultados",
+        route,
+        response.page.data.len(),
+        response.page.total
     ))
     .ok();
-    HttpResponse::Ok().json(response)
+    Ok(HttpResponse::Ok().json(response))
+}
+
+/// Busca por palabras completas en nombres, etiquetas y descripciones, y devuelve los
+/// resultados con su relevancia (`score`) y las coincidencias marcadas (`highlights`).
+/// La búsqueda admite frases y filtros por campo (ver [`crate::query`]), y no distingue
+/// mayúsculas ni acentos. Si no hay resultados exactos, se repite admitiendo erratas;
+/// `fuzzy=true` la hace siempre así y `fuzzy=false` nunca.
+/// Admite filtros de atributos en la query (`attr.<nombre>`, `.min`, `.max`), de
+/// categoría (`categoryId`) y de tipo de resultado (`type`), además de la paginación.
+/// Con `within` (id de un grupo, una propiedad o una zona) solo se busca en su contenido.
+/// Cada resultado lleva su ruta (`breadcrumb`): grupo, propiedad y zonas.
+#[get("/search/{name}")]
+pub async fn search_endpoint(
+    repos: web::Data<Repositories>,
+    user: AuthUser,
+    name: web::Path<String>,
+    query: web::Query<HashMap<String, String>>,
+) -> Result<HttpResponse, ApiError> {
+    let search_str = name.into_inner();
+    run_search(&repos, &user, &search_str, &query, "GET /search/{name}").await
+}
+
+/// Igual que `/search/{name}` con la búsqueda en `q`, que puede omitirse para filtrar
+/// solo por atributos o categoría.
+#[get("/search")]
+pub async fn search_query_endpoint(
+    repos: web::Data<Repositories>,
+    user: AuthUser,
+    query: web::Query<HashMap<String, String>>,
+) -> Result<HttpResponse, ApiError> {
+    let search_str = query.get("q").cloned().unwrap_or_default();
+    run_search(&repos, &user, &search_str, &query, "GET /search").await
 }
 
 pub fn configure_routes(cfg: &mut web::ServiceConfig) {
-    cfg.service(search_endpoint);
+    cfg.service(search_endpoint).service(search_query_endpoint);
 }
@@ -0,0 +1,186 @@
+use actix_web::{delete, get, post, web, HttpRequest, HttpResponse};
+use mongodb::bson::{oid::ObjectId, DateTime};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::error::ApiError;
+use crate::log::write_log;
+use crate::middleware::auth::AuthUser;
+use crate::repository::Repositories;
+use crate::services::session as session_service;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Session {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    #[serde(rename = "userId")]
+    pub user_id: ObjectId,
+    #[serde(rename = "refreshTokenHash")]
+    pub refresh_token_hash: String,
+    #[serde(rename = "userAgent", skip_serializing_if = "Option::is_none")]
+    pub user_agent: Option<String>,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime,
+    #[serde(rename = "lastUsedAt")]
+    pub last_used_at: DateTime,
+    #[serde(rename = "expiresAt")]
+    pub expires_at: DateTime,
+    #[serde(rename = "revokedAt", skip_serializing_if = "Option::is_none")]
+    pub revoked_at: Option<DateTime>,
+}
+
+/// Vista pública de una sesión, sin el hash del refresh token.
+#[derive(Debug, Serialize)]
+struct SessionInfo {
+    #[serde(rename = "_id")]
+    id: ObjectId,
+    #[serde(rename = "userAgent", skip_serializing_if = "Option::is_none")]
+    user_agent: Option<String>,
+    #[serde(rename = "createdAt")]
+    created_at: String,
+    #[serde(rename = "lastUsedAt")]
+    last_used_at: String,
+    #[serde(rename = "expiresAt")]
+    expires_at: String,
+    current: bool,
+}
+
+/// Cabecera `User-Agent` de la petición, que se guarda con la sesión.
+pub fn user_agent(req: &HttpRequest) -> Option<String> {
+    req.headers()
+        .get("User-Agent")
+        .and_then(|v| v.to_str().ok())
+        .map(String::from)
+}
+
+pub fn to_rfc3339(date: DateTime) -> String {
+    date.try_to_rfc3339_string().unwrap_or_default()
+}
+
+#[post("/users/refresh")]
+async fn refresh_handler(
+    repos: web::Data<Repositories>,
+    body: web::Json<serde_json::Value>,
+) -> Result<HttpResponse, ApiError> {
+    let token = match body.get("refreshToken").and_then(|v| v.as_str()) {
+        Some(t) => t,
+        None => {
+            write_log("POST /users/refresh - Falta el campo 'refreshToken'").ok();
+            return Err(ApiError::validation("Falta el campo 'refreshToken'")
+                .with_details(json!({"field": "refreshToken"})));
+        }
+    };
+    match session_service::refresh(&repos, token).await {
+        Ok(tokens) => {
+            write_log("POST /users/refresh - Sesión renovada").ok();
+            Ok(HttpResponse::Ok().json(serde_json::json!({
+                "token": tokens.token,
+                "refreshToken": tokens.refresh_token,
+            })))
+        }
+        Err(e) => {
+            write_log(&format!("POST /users/refresh - {}", e)).ok();
+            Err(e)
+        }
+    }
+}
+
+#[get("/users/me/sessions")]
+async fn get_my_sessions_handler(
+    repos: web::Data<Repositories>,
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
+    let sessions = match repos.sessions.find_active_by_user(user.id).await {
+        Ok(sessions) => sessions,
+        Err(_) => {
+            write_log("GET /users/me/sessions - Error buscando sesiones").ok();
+            return Err(ApiError::unexpected());
+        }
+    };
+    let sessions: Vec<SessionInfo> = sessions
+        .into_iter()
+        .filter_map(|s| {
+            let id = s.id?;
+            Some(SessionInfo {
+                id,
+                user_agent: s.user_agent,
+                created_at: to_rfc3339(s.created_at),
+                last_used_at: to_rfc3339(s.last_used_at),
+                expires_at: to_rfc3339(s.expires_at),
+                current: id.to_hex() == user.session_id,
+            })
+        })
+        .collect();
+    write_log(&format!(
+        "GET /users/me/sessions - {} sesiones recuperadas",
+        sessions.len()
+    ))
+    .ok();
+    Ok(HttpResponse::Ok().json(sessions))
+}
+
+#[delete("/users/me/sessions/{id}")]
+async fn revoke_my_session_handler(
+    repos: web::Data<Repositories>,
+    path: web::Path<String>,
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
+    let user_id = user.id;
+    let session_id = match ObjectId::parse_str(path.into_inner()) {
+        Ok(session_id) => session_id,
+        Err(_) => {
+            write_log("DELETE /users/me/sessions/{id} - ID inválido").ok();
+            return Err(ApiError::validation("ID inválido"));
+        }
+    };
+    match repos.sessions.revoke_for_user(session_id, user_id).await {
+        Ok(true) => {
+            write_log(&format!(
+                "DELETE /users/me/sessions/{{id}} - Sesión {} revocada",
+                session_id
+            ))
+            .ok();
+            Ok(HttpResponse::Ok().body("Sesión cerrada"))
+        }
+        Ok(false) => {
+            write_log("DELETE /users/me/sessions/{id} - Sesión no encontrada").ok();
+            Err(ApiError::not_found("Sesión no encontrada"))
+        }
+        Err(_) => {
+            write_log("DELETE /users/me/sessions/{id} - Error inesperado").ok();
+            Err(ApiError::unexpected())
+        }
+    }
+}
+
+#[delete("/users/me/sessions")]
+async fn revoke_other_sessions_handler(
+    repos: web::Data<Repositories>,
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
+    let user_id = user.id;
+    let current = ObjectId::parse_str(&user.session_id).ok();
+    match repos.sessions.revoke_all_for_user(user_id, current).await {
+        Ok(count) => {
+            write_log(&format!(
+                "DELETE /users/me/sessions - {} sesiones revocadas",
+                count
+            ))
+            .ok();
+            Ok(HttpResponse::Ok().json(serde_json::json!({ "revoked": count })))
+        }
+        Err(_) => {
+            write_log("DELETE /users/me/sessions - Error inesperado").ok();
+            Err(ApiError::unexpected())
+        }
+    }
+}
+
+pub fn configure_private_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_my_sessions_handler)
+        .service(revoke_my_session_handler)
+        .service(revoke_other_sessions_handler);
+}
+pub fn configure_public_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(refresh_handler);
+}
@@ -0,0 +1,177 @@
+use actix_web::{get, post, web, HttpResponse};
+use mongodb::bson::{oid::ObjectId, DateTime};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::access::{authorize, Resource};
+use crate::entities::audit::AuditEntity;
+use crate::entities::session::to_rfc3339;
+use crate::entities::user_group::GroupRole;
+use crate::error::ApiError;
+use crate::log::write_log;
+use crate::middleware::auth::AuthUser;
+use crate::repository::Repositories;
+use crate::services::audit as audit_service;
+use crate::services::stock as stock_service;
+
+/// Tipo de movimiento de stock: entrada, consumo o ajuste a un recuento.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MovementKind {
+    Add,
+    Consume,
+    Adjust,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StockMovement {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    #[serde(rename = "itemId")]
+    pub item_id: ObjectId,
+    #[serde(rename = "type")]
+    pub kind: MovementKind,
+    /// Variación de la cantidad (negativa en los consumos).
+    pub delta: f64,
+    #[serde(rename = "quantityAfter")]
+    pub quantity_after: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    #[serde(rename = "userId")]
+    pub user_id: ObjectId,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime,
+}
+
+/// Movimiento tal y como se devuelve en la API, con la fecha en RFC 3339.
+#[derive(Debug, Serialize)]
+struct MovementInfo {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    id: Option<ObjectId>,
+    #[serde(rename = "type")]
+    kind: MovementKind,
+    delta: f64,
+    #[serde(rename = "quantityAfter")]
+    quantity_after: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reason: Option<String>,
+    #[serde(rename = "userId")]
+    user_id: ObjectId,
+    #[serde(rename = "createdAt")]
+    created_at: String,
+}
+
+impl From<StockMovement> for MovementInfo {
+    fn from(m: StockMovement) -> Self {
+        Self {
+            id: m.id,
+            kind: m.kind,
+            delta: m.delta,
+            quantity_after: m.quantity_after,
+            reason: m.reason,
+            user_id: m.user_id,
+            created_at: to_rfc3339(m.created_at),
+        }
+    }
+}
+
+#[get("/items/{id}/movements")]
+async fn get_item_movements_handler(
+    repos: web::Data<Repositories>,
+    path: web::Path<String>,
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
+    let item_id = match ObjectId::parse_str(path.into_inner()) {
+        Ok(id) => id,
+        Err(_) => {
+            write_log("GET /items/{id}/movements - ID inválido").ok();
+            return Err(ApiError::validation("ID inválido"));
+        }
+    };
+    authorize(
+        &repos,
+        &user,
+        Resource::Item(item_id),
+        GroupRole::Viewer,
+        "GET /items/{id}/movements",
+    )
+    .await?;
+    match repos.stock_movements.find_by_item(item_id).await {
+        Ok(movements) => {
+            write_log(&format!(
+                "GET /items/{{id}}/movements - {} movimientos del item {}",
+                movements.len(),
+                item_id
+            ))
+            .ok();
+            let movements: Vec<MovementInfo> = movements.into_iter().map(Into::into).collect();
+            Ok(HttpResponse::Ok().json(movements))
+        }
+        Err(e) => {
+            write_log(&format!("GET /items/{{id}}/movements - Error: {}", e)).ok();
+            Err(e.into())
+        }
+    }
+}
+
+#[post("/items/{id}/movements")]
+async fn create_item_movement_handler(
+    repos: web::Data<Repositories>,
+    path: web::Path<String>,
+    body: web::Json<serde_json::Value>,
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
+    let item_id = match ObjectId::parse_str(path.into_inner()) {
+        Ok(id) => id,
+        Err(_) => {
+            write_log("POST /items/{id}/movements - ID inválido").ok();
+            return Err(ApiError::validation("ID inválido"));
+        }
+    };
+    let group_id = authorize(
+        &repos,
+        &user,
+        Resource::Item(item_id),
+        GroupRole::Editor,
+        "POST /items/{id}/movements",
+    )
+    .await?;
+    let before = repos.items.find_by_id(item_id).await?;
+    let result = match stock_service::movement_request(&body) {
+        Ok(request) => stock_service::record_movement(&repos, item_id, user.id, request).await,
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok(movement) => {
+            write_log(&format!(
+                "POST /items/{{id}}/movements - {:?} de {} en el item {}, quedan {}",
+                movement.kind, movement.delta, item_id, movement.quantity_after
+            ))
+            .ok();
+            let after = repos.items.find_by_id(item_id).await.ok().flatten();
+            audit_service::record(
+                &repos,
+                user.id,
+                group_id,
+                AuditEntity::Item,
+                item_id,
+                before.as_ref(),
+                after.as_ref(),
+            )
+            .await;
+            Ok(HttpResponse::Ok().json(json!({
+                "quantity": movement.quantity_after,
+                "movement": MovementInfo::from(movement),
+            })))
+        }
+        Err(e) => {
+            write_log(&format!("POST /items/{{id}}/movements - {}", e)).ok();
+            Err(e)
+        }
+    }
+}
+
+pub fn configure_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_item_movements_handler)
+        .service(create_item_movement_handler);
+}
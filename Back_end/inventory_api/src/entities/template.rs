@@ -0,0 +1,207 @@
+use actix_web::{delete, get, post, web, HttpResponse};
+use mongodb::bson::{oid::ObjectId, Document};
+use serde::{Deserialize, Serialize};
+
+use crate::access::{authorize, Resource};
+use crate::entities::user_group::GroupRole;
+use crate::error::ApiError;
+use crate::log::write_log;
+use crate::middleware::auth::AuthUser;
+use crate::repository::Repositories;
+use crate::services::template as template_service;
+
+/// Plantilla de item de un grupo, guardada en la colección `templates`. Sus campos son los
+/// valores por defecto de los items que se crean con `templateId`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ItemTemplate {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    #[serde(rename = "groupId")]
+    pub group_id: ObjectId,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(rename = "pictureUrl", skip_serializing_if = "Option::is_none")]
+    pub picture_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tags: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unit: Option<String>,
+    #[serde(rename = "minQuantity", skip_serializing_if = "Option::is_none")]
+    pub min_quantity: Option<f64>,
+    #[serde(rename = "categoryId", skip_serializing_if = "Option::is_none")]
+    pub category_id: Option<ObjectId>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attributes: Option<Document>,
+}
+
+/// Plantilla del path, con el acceso a su grupo ya comprobado.
+async fn authorized_template(
+    repos: &Repositories,
+    user: &AuthUser,
+    id_str: &str,
+    role: GroupRole,
+    route: &str,
+) -> Result<ItemTemplate, ApiError> {
+    let template_id = match ObjectId::parse_str(id_str) {
+        Ok(id) => id,
+        Err(_) => {
+            write_log(&format!("{} - ID inválido: {}", route, id_str)).ok();
+            return Err(ApiError::validation("ID inválido"));
+        }
+    };
+    let template = match repos.templates.find_by_id(template_id).await? {
+        Some(template) => template,
+        None => {
+            write_log(&format!(
+                "{} - Plantilla no encontrada: {}",
+                route, template_id
+            ))
+            .ok();
+            return Err(ApiError::not_found("Plantilla no encontrada"));
+        }
+    };
+    authorize(repos, user, Resource::Group(template.group_id), role, route).await?;
+    Ok(template)
+}
+
+#[get("/groups/{id}/templates")]
+async fn get_templates_handler(
+    repos: web::Data<Repositories>,
+    path: web::Path<String>,
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
+    let group_id = match ObjectId::parse_str(path.into_inner()) {
+        Ok(id) => id,
+        Err(_) => {
+            write_log("GET /groups/{id}/templates - ID inválido").ok();
+            return Err(ApiError::validation("ID inválido"));
+        }
+    };
+    authorize(
+        &repos,
+        &user,
+        Resource::Group(group_id),
+        GroupRole::Viewer,
+        "GET /groups/{id}/templates",
+    )
+    .await?;
+    match repos.templates.find_by_group(group_id).await {
+        Ok(templates) => {
+            write_log(&format!(
+                "GET /groups/{{id}}/templates - {} plantillas en el grupo {}",
+                templates.len(),
+                group_id
+            ))
+            .ok();
+            Ok(HttpResponse::Ok().json(templates))
+        }
+        Err(e) => {
+            write_log(&format!("GET /groups/{{id}}/templates - Error: {}", e)).ok();
+            Err(e.into())
+        }
+    }
+}
+
+#[post("/groups/{id}/templates")]
+async fn create_template_handler(
+    repos: web::Data<Repositories>,
+    path: web::Path<String>,
+    body: web::Json<serde_json::Value>,
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
+    let group_id = match ObjectId::parse_str(path.into_inner()) {
+        Ok(id) => id,
+        Err(_) => {
+            write_log("POST /groups/{id}/templates - ID inválido").ok();
+            return Err(ApiError::validation("ID inválido"));
+        }
+    };
+    authorize(
+        &repos,
+        &user,
+        Resource::Group(group_id),
+        GroupRole::Editor,
+        "POST /groups/{id}/templates",
+    )
+    .await?;
+    match template_service::create_template(&repos, group_id, &body).await {
+        Ok(template_id) => {
+            write_log(&format!(
+                "POST /groups/{{id}}/templates - Plantilla {} creada en el grupo {}",
+                template_id, group_id
+            ))
+            .ok();
+            Ok(HttpResponse::Ok().json(template_id))
+        }
+        Err(e) => {
+            write_log(&format!("POST /groups/{{id}}/templates - {}", e)).ok();
+            Err(e)
+        }
+    }
+}
+
+#[get("/templates/{id}")]
+async fn get_template_handler(
+    repos: web::Data<Repositories>,
+    path: web::Path<String>,
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
+    let template = authorized_template(
+        &repos,
+        &user,
+        &path.into_inner(),
+        GroupRole::Viewer,
+        "GET /templates/{id}",
+    )
+    .await?;
+    write_log(&format!(
+        "GET /templates/{{id}} - Plantilla encontrada: {:?}",
+        template.id
+    ))
+    .ok();
+    Ok(HttpResponse::Ok().json(template))
+}
+
+/// Los items creados con la plantilla no cambian.
+#[delete("/templates/{id}")]
+async fn delete_template_handler(
+    repos: web::Data<Repositories>,
+    path: web::Path<String>,
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
+    let template = authorized_template(
+        &repos,
+        &user,
+        &path.into_inner(),
+        GroupRole::Editor,
+        "DELETE /templates/{id}",
+    )
+    .await?;
+    let template_id = match template.id {
+        Some(id) => id,
+        None => return Err(ApiError::internal("No hay ID")),
+    };
+    match repos.templates.delete(template_id).await {
+        Ok(true) => {
+            write_log(&format!(
+                "DELETE /templates/{{id}} - Plantilla eliminada: {}",
+                template_id
+            ))
+            .ok();
+            Ok(HttpResponse::Ok().body("Plantilla eliminada"))
+        }
+        Ok(false) => Err(ApiError::not_found("Plantilla no encontrada")),
+        Err(e) => {
+            write_log(&format!("DELETE /templates/{{id}} - Error: {}", e)).ok();
+            Err(e.into())
+        }
+    }
+}
+
+pub fn configure_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_templates_handler)
+        .service(create_template_handler)
+        .service(get_template_handler)
+        .service(delete_template_handler);
+}
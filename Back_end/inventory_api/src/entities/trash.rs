@@ -0,0 +1,216 @@
+use actix_web::{delete, get, post, web, HttpResponse};
+use mongodb::bson::{oid::ObjectId, DateTime};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::config;
+use crate::entities::audit::AuditEntity;
+use crate::entities::session::to_rfc3339;
+use crate::entities::user_group::GroupRole;
+use crate::error::ApiError;
+use crate::log::write_log;
+use crate::middleware::auth::AuthUser;
+use crate::repository::Repositories;
+use crate::services::trash as trash_service;
+
+/// Borrado guardado en la papelera (colección `trash`). Los elementos borrados siguen en sus
+/// colecciones, marcados con `deletedAt` y con el `trashId` de esta entrada.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrashEntry {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    #[serde(rename = "groupId")]
+    pub group_id: ObjectId,
+    /// Elemento que se borró; el resto se borró en cascada con él.
+    #[serde(rename = "entityType")]
+    pub entity_type: AuditEntity,
+    #[serde(rename = "entityId")]
+    pub entity_id: ObjectId,
+    pub name: String,
+    /// Zona, propiedad o grupo del que colgaba; tiene que existir para poder restaurarlo.
+    #[serde(rename = "parentId", skip_serializing_if = "Option::is_none")]
+    pub parent_id: Option<ObjectId>,
+    /// Usuario que lo borró.
+    #[serde(rename = "userId")]
+    pub user_id: ObjectId,
+    #[serde(rename = "deletedAt")]
+    pub deleted_at: DateTime,
+    pub properties: u32,
+    pub zones: u32,
+    pub items: u32,
+}
+
+/// Entrada tal y como se devuelve en la API.
+#[derive(Debug, Serialize)]
+struct TrashEntryInfo {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    id: Option<ObjectId>,
+    #[serde(rename = "groupId")]
+    group_id: ObjectId,
+    #[serde(rename = "entityType")]
+    entity_type: AuditEntity,
+    #[serde(rename = "entityId")]
+    entity_id: ObjectId,
+    name: String,
+    #[serde(rename = "userId")]
+    user_id: ObjectId,
+    #[serde(rename = "deletedAt")]
+    deleted_at: String,
+    /// Fecha a partir de la cual se eliminará definitivamente.
+    #[serde(rename = "purgeAt")]
+    purge_at: String,
+    properties: u32,
+    zones: u32,
+    items: u32,
+}
+
+impl From<TrashEntry> for TrashEntryInfo {
+    fn from(e: TrashEntry) -> Self {
+        let retention_days = i64::from(config::get().trash.retention_days);
+        let purge_at = DateTime::from_millis(
+            e.deleted_at.timestamp_millis() + retention_days * 24 * 60 * 60 * 1000,
+        );
+        Self {
+            id: e.id,
+            group_id: e.group_id,
+            entity_type: e.entity_type,
+            entity_id: e.entity_id,
+            name: e.name,
+            user_id: e.user_id,
+            deleted_at: to_rfc3339(e.deleted_at),
+            purge_at: to_rfc3339(purge_at),
+            properties: e.properties,
+            zones: e.zones,
+            items: e.items,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct TrashQuery {
+    #[serde(rename = "groupId")]
+    group_id: Option<String>,
+}
+
+#[get("/trash")]
+async fn get_trash_handler(
+    repos: web::Data<Repositories>,
+    query: web::Query<TrashQuery>,
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
+    let group_id = match query.group_id.as_deref().map(ObjectId::parse_str) {
+        Some(Ok(id)) => id,
+        _ => {
+            write_log("GET /trash - groupId inválido").ok();
+            return Err(ApiError::validation("'groupId' inválido")
+                .with_details(json!({"field": "groupId"})));
+        }
+    };
+    trash_service::authorize_trash(&repos, &user, group_id, GroupRole::Viewer, "GET /trash")
+        .await?;
+    match repos.trash.find_by_group(group_id).await {
+        Ok(entries) => {
+            write_log(&format!(
+                "GET /trash - {} elementos en la papelera del grupo {}",
+                entries.len(),
+                group_id
+            ))
+            .ok();
+            let entries: Vec<TrashEntryInfo> = entries.into_iter().map(Into::into).collect();
+            Ok(HttpResponse::Ok().json(entries))
+        }
+        Err(e) => {
+            write_log(&format!("GET /trash - Error: {}", e)).ok();
+            Err(e.into())
+        }
+    }
+}
+
+#[post("/trash/{id}/restore")]
+async fn restore_trash_handler(
+    repos: web::Data<Repositories>,
+    path: web::Path<String>,
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
+    let trash_id = match ObjectId::parse_str(path.into_inner()) {
+        Ok(id) => id,
+        Err(_) => {
+            write_log("POST /trash/{id}/restore - Id incorrecto").ok();
+            return Err(ApiError::validation("Id incorrecto"));
+        }
+    };
+    let entry = trash_service::find_entry(&repos, trash_id).await?;
+    // Restaurar un grupo entero queda para sus propietarios
+    let required = match entry.entity_type {
+        AuditEntity::Group => GroupRole::Owner,
+        _ => GroupRole::Editor,
+    };
+    trash_service::authorize_trash(
+        &repos,
+        &user,
+        entry.group_id,
+        required,
+        "POST /trash/{id}/restore",
+    )
+    .await?;
+    match trash_service::restore(&repos, user.id, &entry).await {
+        Ok(()) => {
+            write_log(&format!(
+                "POST /trash/{{id}}/restore - {} {} restaurado por el usuario {}",
+                entry.entity_type.as_str(),
+                entry.entity_id,
+                user.id
+            ))
+            .ok();
+            Ok(HttpResponse::Ok().json(TrashEntryInfo::from(entry)))
+        }
+        Err(e) => {
+            write_log(&format!("POST /trash/{{id}}/restore - {}", e)).ok();
+            Err(e)
+        }
+    }
+}
+
+#[delete("/trash/{id}")]
+async fn purge_trash_handler(
+    repos: web::Data<Repositories>,
+    path: web::Path<String>,
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
+    let trash_id = match ObjectId::parse_str(path.into_inner()) {
+        Ok(id) => id,
+        Err(_) => {
+            write_log("DELETE /trash/{id} - Id incorrecto").ok();
+            return Err(ApiError::validation("Id incorrecto"));
+        }
+    };
+    let entry = trash_service::find_entry(&repos, trash_id).await?;
+    trash_service::authorize_trash(
+        &repos,
+        &user,
+        entry.group_id,
+        GroupRole::Owner,
+        "DELETE /trash/{id}",
+    )
+    .await?;
+    match trash_service::purge(&repos, trash_id).await {
+        Ok(()) => {
+            write_log(&format!(
+                "DELETE /trash/{{id}} - Entrada {} eliminada definitivamente por el usuario {}",
+                trash_id, user.id
+            ))
+            .ok();
+            Ok(HttpResponse::Ok().body("Eliminado definitivamente"))
+        }
+        Err(e) => {
+            write_log(&format!("DELETE /trash/{{id}} - {}", e)).ok();
+            Err(e)
+        }
+    }
+}
+
+pub fn configure_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_trash_handler)
+        .service(restore_trash_handler)
+        .service(purge_trash_handler);
+}
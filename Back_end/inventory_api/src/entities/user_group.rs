@@ -1,15 +1,34 @@
-use actix_web::{delete, get, patch, post, web, HttpMessage, HttpRequest, HttpResponse, Responder};
-use futures_util::stream::TryStreamExt;
-use mongodb::{
-    bson::{doc, oid::ObjectId},
-    Database,
-};
+use actix_web::{delete, get, patch, web, HttpResponse};
+use mongodb::bson::oid::ObjectId;
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 
-use crate::entities::{group::Group, user::User};
+use crate::access::{authorize, Resource};
+use crate::error::ApiError;
 use crate::log::write_log;
+use crate::middleware::auth::AuthUser;
+use crate::repository::Repositories;
+use crate::services::user_group as user_group_service;
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Rol de un usuario dentro de un grupo, ordenado de menor a mayor permiso.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GroupRole {
+    /// Solo lectura.
+    Viewer,
+    /// Puede crear, modificar y eliminar propiedades, zonas e items.
+    Editor,
+    /// Además gestiona los miembros y puede eliminar el grupo.
+    Owner,
+}
+
+/// Las relaciones anteriores a los roles no tienen campo `role`; todos sus miembros
+/// podían hacer cualquier cosa, así que se conservan como propietarios.
+fn legacy_role() -> GroupRole {
+    GroupRole::Owner
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UserGroup {
     #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
     pub id: Option<ObjectId>,
@@ -17,7 +36,10 @@ pub struct UserGroup {
     pub group_id: ObjectId,
     #[serde(rename = "userId")]
     pub user_id: ObjectId,
+    #[serde(default = "legacy_role")]
+    pub role: GroupRole,
 }
+
 // impl UserGroup {
 //     fn new(group_id: ObjectId, user_id: ObjectId) -> UserGroup {
 //         Self {
@@ -167,94 +189,25 @@ pub struct UserGroup {
 // }
 
 #[get("/users/me/groups")]
-async fn get_groups_from_user_handler(db: web::Data<Database>, req: HttpRequest) -> impl Responder {
-    let claims = match req
-        .extensions()
-        .get::<crate::middleware::auth::Claims>()
-        .cloned()
-    {
-        Some(claims) => claims,
-        None => {
-            write_log("GET /users/me/groups - Token no encontrado").ok();
-            return HttpResponse::Unauthorized().body("Token no encontrado");
-        }
-    };
-
+async fn get_groups_from_user_handler(
+    repos: web::Data<Repositories>,
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
     // Si es admin, devolver todos los grupos
-    let group_collection = db.collection::<Group>("groups");
-    if claims.role == "admin" {
-        // Admin: devolver todos los grupos
-        let cursor = match group_collection.find(doc! {}).await {
-            Ok(cursor) => cursor,
-            Err(_) => {
-                write_log("GET /users/me/groups - Error buscando todos los grupos").ok();
-                return HttpResponse::BadRequest().body("Error inesperado, inténtelo  nuevamente");
-            }
-        };
-        let groups: Vec<Group> = match cursor.try_collect().await {
-            Ok(groups) => groups,
-            Err(_) => {
-                write_log("GET /users/me/groups - Error recogiendo todos los grupos").ok();
-                return HttpResponse::BadRequest().body("Error inesperado, inténtelo  nuevamente");
-            }
-        };
-        if groups.is_empty() {
-            write_log("GET /users/me/groups - No hay grupos en la base de datos").ok();
-            return HttpResponse::BadRequest().body("No hay grupos en la base de datos");
+    match user_group_service::groups_of_user(&repos, user.id, user.is_admin()).await {
+        Ok(groups) => {
+            write_log(&format!(
+                "GET /users/me/groups - {} grupos recuperados",
+                groups.len()
+            ))
+            .ok();
+            Ok(HttpResponse::Ok().json(groups))
         }
-        write_log(&format!(
-            "GET /users/me/groups - {} grupos recuperados (admin)",
-            groups.len()
-        ))
-        .ok();
-        return HttpResponse::Ok().json(groups);
-    }
-
-    // Si no es admin, devolver solo los grupos del usuario
-    let user_id = match ObjectId::parse_str(&claims.sub) {
-        Ok(id) => id,
-        Err(_) => {
-            write_log("GET /users/me/groups - ID de usuario inválido").ok();
-            return HttpResponse::BadRequest().body("ID de usuario inválido");
-        }
-    };
-    let user_group_collection = db.collection::<UserGroup>("userGroup");
-    let user_group_cursor = match user_group_collection.find(doc! {"userId": user_id}).await {
-        Ok(cursor) => cursor,
-        Err(_) => {
-            write_log("GET /users/me/groups - Error buscando relaciones").ok();
-            return HttpResponse::BadRequest().body("Error inesperado, inténtelo  nuevamente");
+        Err(e) => {
+            write_log(&format!("GET /users/me/groups - {}", e)).ok();
+            Err(e)
         }
-    };
-    let user_groups: Vec<UserGroup> = match user_group_cursor.try_collect().await {
-        Ok(groups) => groups,
-        Err(_) => {
-            write_log("GET /users/me/groups - Error recogiendo relaciones").ok();
-            return HttpResponse::BadRequest().body("Error inesperado, inténtelo  nuevamente");
-        }
-    };
-    let group_ids: Vec<ObjectId> = user_groups.iter().map(|ug| ug.group_id).collect();
-    let mut groups: Vec<Group> = Vec::new();
-    for id in group_ids {
-        match group_collection.find_one(doc! {"_id": id}).await {
-            Ok(Some(group)) => groups.push(group),
-            Ok(None) => continue,
-            Err(_) => {
-                write_log("GET /users/me/groups - Error buscando grupo").ok();
-                return HttpResponse::BadRequest().body("Error inesperado, inténtelo  nuevamente");
-            }
-        }
-    }
-    if groups.is_empty() {
-        write_log("GET /users/me/groups - No hay grupos asociados a ese usuario").ok();
-        return HttpResponse::BadRequest().body("No hay grupos asociados a ese usuario");
     }
-    write_log(&format!(
-        "GET /users/me/groups - {} grupos recuperados",
-        groups.len()
-    ))
-    .ok();
-    HttpResponse::Ok().json(groups)
 }
 
 // #[post("/user-group-relationships")]
@@ -344,28 +297,136 @@ async fn get_groups_from_user_handler(db: web::Data<Database>, req: HttpRequest)
 //     response
 // }
 
-pub async fn delete_user_group(db: &Database, user_group_id: String) -> HttpResponse {
-    let collection = db.collection::<UserGroup>("userGroup");
-    let obj_id = match ObjectId::parse_str(user_group_id) {
+#[get("/groups/{id}/members")]
+async fn get_group_members_handler(
+    repos: web::Data<Repositories>,
+    path: web::Path<String>,
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
+    let group_id = match ObjectId::parse_str(path.into_inner()) {
         Ok(id) => id,
         Err(_) => {
-            write_log("DELETE /user-group-relationships/{id} - ID inválido").ok();
-            return HttpResponse::BadRequest().body("ID inválido");
+            write_log("GET /groups/{id}/members - ID inválido").ok();
+            return Err(ApiError::validation("ID inválido"));
         }
     };
-    match collection.delete_one(doc! {"_id": obj_id}).await {
-        Ok(result) if result.deleted_count == 1 => {
-            write_log("DELETE /user-group-relationships/{id} - Relación eliminada correctamente")
-                .ok();
-            HttpResponse::Ok().body("grupo usuario eliminado")
+    authorize(
+        &repos,
+        &user,
+        Resource::Group(group_id),
+        GroupRole::Viewer,
+        "GET /groups/{id}/members",
+    )
+    .await?;
+    match user_group_service::members(&repos, group_id).await {
+        Ok(members) => {
+            write_log(&format!(
+                "GET /groups/{{id}}/members - {} miembros recuperados",
+                members.len()
+            ))
+            .ok();
+            Ok(HttpResponse::Ok().json(members))
         }
-        Ok(_) => {
-            write_log("DELETE /user-group-relationships/{id} - Relación no encontrada").ok();
-            HttpResponse::NotFound().body("grupo usuario no encontrado")
+        Err(e) => {
+            write_log(&format!("GET /groups/{{id}}/members - {}", e)).ok();
+            Err(e)
         }
-        Err(_) => {
-            write_log("DELETE /user-group-relationships/{id} - Error inesperado").ok();
-            HttpResponse::BadRequest().body("Error inesperado, inténtelo  nuevamente")
+    }
+}
+
+#[patch("/groups/{id}/members/{userId}")]
+async fn patch_group_member_handler(
+    repos: web::Data<Repositories>,
+    path: web::Path<(String, String)>,
+    body: web::Json<serde_json::Value>,
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
+    let (group_str, user_str) = path.into_inner();
+    let (group_id, user_id) = match (
+        ObjectId::parse_str(&group_str),
+        ObjectId::parse_str(&user_str),
+    ) {
+        (Ok(group_id), Ok(user_id)) => (group_id, user_id),
+        _ => {
+            write_log("PATCH /groups/{id}/members/{userId} - ID inválido").ok();
+            return Err(ApiError::validation("ID inválido"));
+        }
+    };
+    let role = match body
+        .get("role")
+        .cloned()
+        .map(serde_json::from_value::<GroupRole>)
+    {
+        Some(Ok(role)) => role,
+        _ => {
+            write_log("PATCH /groups/{id}/members/{userId} - Rol inválido").ok();
+            return Err(
+                ApiError::validation("'role' debe ser 'owner', 'editor' o 'viewer'")
+                    .with_details(json!({"field": "role"})),
+            );
+        }
+    };
+    authorize(
+        &repos,
+        &user,
+        Resource::Group(group_id),
+        GroupRole::Owner,
+        "PATCH /groups/{id}/members/{userId}",
+    )
+    .await?;
+    match user_group_service::set_member_role(&repos, group_id, user_id, role).await {
+        Ok(()) => {
+            write_log(&format!(
+                "PATCH /groups/{{id}}/members/{{userId}} - Usuario {} ahora es {:?} en el grupo {}",
+                user_id, role, group_id
+            ))
+            .ok();
+            Ok(HttpResponse::Ok().body("Rol actualizado"))
+        }
+        Err(e) => {
+            write_log(&format!("PATCH /groups/{{id}}/members/{{userId}} - {}", e)).ok();
+            Err(e)
+        }
+    }
+}
+
+#[delete("/groups/{id}/members/{userId}")]
+async fn delete_group_member_handler(
+    repos: web::Data<Repositories>,
+    path: web::Path<(String, String)>,
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
+    let (group_str, user_str) = path.into_inner();
+    let (group_id, user_id) = match (
+        ObjectId::parse_str(&group_str),
+        ObjectId::parse_str(&user_str),
+    ) {
+        (Ok(group_id), Ok(user_id)) => (group_id, user_id),
+        _ => {
+            write_log("DELETE /groups/{id}/members/{userId} - ID inválido").ok();
+            return Err(ApiError::validation("ID inválido"));
+        }
+    };
+    authorize(
+        &repos,
+        &user,
+        Resource::Group(group_id),
+        GroupRole::Owner,
+        "DELETE /groups/{id}/members/{userId}",
+    )
+    .await?;
+    match user_group_service::remove_member(&repos, user.id, group_id, user_id).await {
+        Ok(()) => {
+            write_log(&format!(
+                "DELETE /groups/{{id}}/members/{{userId}} - Usuario {} expulsado del grupo {}",
+                user_id, group_id
+            ))
+            .ok();
+            Ok(HttpResponse::Ok().body("Miembro eliminado del grupo"))
+        }
+        Err(e) => {
+            write_log(&format!("DELETE /groups/{{id}}/members/{{userId}} - {}", e)).ok();
+            Err(e)
         }
     }
 }
@@ -374,7 +435,10 @@ pub fn configure_routes(cfg: &mut web::ServiceConfig) {
     // cfg.service(get_user_group_handler)
     //     .service(get_users_groups_handler)
     //     .service(get_user_group_id_handler)
-    cfg.service(get_groups_from_user_handler);
+    cfg.service(get_groups_from_user_handler)
+        .service(get_group_members_handler)
+        .service(patch_group_member_handler)
+        .service(delete_group_member_handler);
     // .service(get_users_from_group_handler)
     // .service(create_user_group_handler)
     // .service(patch_user_group_handler)
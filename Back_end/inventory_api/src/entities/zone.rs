@@ -1,11 +1,16 @@
-use crate::entities::item::{delete_item, Item};
+use std::collections::HashMap;
+
+use crate::access::{authorize, parent_resource, Resource};
+use crate::entities::audit::AuditEntity;
+use crate::entities::user_group::GroupRole;
+use crate::error::ApiError;
 use crate::log::write_log;
-use actix_web::{delete, get, patch, post, web, HttpMessage, HttpRequest, HttpResponse, Responder};
-use futures_util::stream::TryStreamExt;
-use mongodb::{
-    bson::{doc, oid::ObjectId, Document},
-    Database,
-};
+use crate::middleware::auth::AuthUser;
+use crate::pagination::{self, FilterKind, ListSpec};
+use crate::repository::Repositories;
+use crate::services::{audit as audit_service, trash as trash_service, zone as zone_service};
+use actix_web::{delete, get, patch, post, web, HttpResponse};
+use mongodb::bson::{oid::ObjectId, DateTime};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -19,6 +24,17 @@ pub struct Zone {
     pub user_id: Option<ObjectId>,
     #[serde(rename = "parentZoneId", skip_serializing_if = "Option::is_none")]
     pub parent_zone_id: Option<ObjectId>,
+    /// Fecha en la que pasó a la papelera. Mientras la tenga, no aparece en las consultas.
+    #[serde(
+        rename = "deletedAt",
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "crate::dates::optional_date"
+    )]
+    pub deleted_at: Option<DateTime>,
+    /// Entrada de la papelera con la que se borró, junto con lo que contenía.
+    #[serde(rename = "trashId", skip_serializing_if = "Option::is_none")]
+    pub trash_id: Option<ObjectId>,
 }
 
 //deprecated
@@ -62,156 +78,131 @@ pub struct Zone {
 // }
 
 #[get("/zones/{id}")]
-async fn get_zone_handler(db: web::Data<Database>, path: web::Path<String>) -> impl Responder {
-    let collection = db.collection::<Zone>("zones");
-    let obj_id = match ObjectId::parse_str(&path.into_inner()) {
+async fn get_zone_handler(
+    repos: web::Data<Repositories>,
+    path: web::Path<String>,
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
+    let obj_id = match ObjectId::parse_str(path.into_inner()) {
         Ok(obj_id) => obj_id,
         Err(_) => {
             write_log("GET /zones/{id} - ID inválido").ok();
-            return HttpResponse::BadRequest().body("ID inválido");
+            return Err(ApiError::validation("ID inválido"));
         }
     };
-    match collection.find_one(doc! {"_id":obj_id}).await {
+    authorize(
+        &repos,
+        &user,
+        Resource::Zone(obj_id),
+        GroupRole::Viewer,
+        "GET /zones/{id}",
+    )
+    .await?;
+    match repos.zones.find_by_id(obj_id).await {
         Ok(Some(zone)) => {
             write_log("GET /zones/{id} - Zona encontrada").ok();
-            HttpResponse::Ok().json(zone)
+            Ok(HttpResponse::Ok().json(zone))
         }
         Ok(None) => {
             write_log("GET /zones/{id} - Zona no encontrada").ok();
-            HttpResponse::NotFound().body("zona no encontrada")
+            Err(ApiError::not_found("zona no encontrada"))
         }
         Err(e) => {
             write_log(&format!("GET /zones/{} - Error: {}", obj_id, e)).ok();
-            HttpResponse::BadRequest().body(e.to_string())
+            Err(e.into())
         }
     }
 }
 
+const CHILDREN_LIST: ListSpec = ListSpec {
+    sorts: &[("name", "name"), ("created", "_id")],
+    filters: &[
+        ("name", "name", FilterKind::Contains),
+        ("categoryId", "categoryId", FilterKind::Id),
+        ("tag", "tags", FilterKind::Equals),
+    ],
+};
+
+/// Zonas hijas e items, por páginas. Con `categoryId` o `tag` solo se devuelven items.
 #[get("/zones/parent/{id}")]
 async fn get_zone_from_parent_handler(
-    db: web::Data<Database>,
+    repos: web::Data<Repositories>,
     path: web::Path<String>,
-    req: HttpRequest,
-) -> impl Responder {
+    query: web::Query<HashMap<String, String>>,
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
     // Parsear parent_id desde la ruta
-    let parent_id = match ObjectId::parse_str(&path.into_inner()) {
+    let parent_id = match ObjectId::parse_str(path.into_inner()) {
         Ok(id) => id,
         Err(_) => {
             write_log("GET /zones/parent/{id} - ID inválido").ok();
-            return HttpResponse::BadRequest().body("ID inválido");
-        }
-    };
-
-    // Recupera las claims inyectadas por el middleware
-    let claims = match req
-        .extensions()
-        .get::<crate::middleware::auth::Claims>()
-        .cloned()
-    {
-        Some(claims) => claims,
-        None => {
-            write_log("GET /zones/parent/{id} - Token no encontrado").ok();
-            return HttpResponse::Unauthorized().body("Token no encontrado");
+            return Err(ApiError::validation("ID inválido"));
         }
     };
 
-    // Convertir claims.sub a ObjectId para comparar correctamente
-    let user_obj_id = match ObjectId::parse_str(&claims.sub) {
-        Ok(id) => id,
-        Err(_) => {
-            write_log("GET /zones/parent/{id} - ID de usuario inválido").ok();
-            return HttpResponse::Unauthorized().body("ID de usuario inválido");
-        }
-    };
-
-    // Buscar todas las zonas cuyo parentZoneId sea igual a parent_id
-    let zone_collection = db.collection::<Zone>("zones");
-    let zone_filter = if claims.role == "admin" {
-        doc! { "parentZoneId": parent_id }
-    } else {
-        doc! { "parentZoneId": parent_id, "$or": [ { "userId": { "$exists": false } }, { "userId": &user_obj_id } ] }
-    };
-    let zone_cursor = match zone_collection.find(zone_filter).await {
-        Ok(cursor) => cursor,
-        Err(_) => {
-            write_log("GET /zones/parent/{id} - Error al obtener zonas").ok();
-            return HttpResponse::BadRequest().body("Error al obtener zonas");
-        }
-    };
-    let zones: Vec<Zone> = match zone_cursor.try_collect().await {
-        Ok(zones) => zones,
-        Err(_) => {
-            write_log("GET /zones/parent/{id} - Error al procesar zonas").ok();
-            return HttpResponse::BadRequest().body("Error al procesar zonas");
+    let page = match pagination::parse(&query, &CHILDREN_LIST) {
+        Ok(page) => page,
+        Err(e) => {
+            write_log(&format!("GET /zones/parent/{{id}} - {}", e)).ok();
+            return Err(e);
         }
     };
 
-    // Obtener los ítems de la zona proporcionada (parent_id)
-    let items_collection = db.collection::<crate::entities::item::Item>("items");
-    let items_filter = if claims.role == "admin" {
-        doc! { "zoneId": parent_id.clone() }
-    } else {
-        doc! { "zoneId": parent_id.clone(), "$or": [ { "userId": { "$exists": false } }, { "userId": &user_obj_id } ] }
-    };
-    let items_cursor = match items_collection.find(items_filter).await {
-        Ok(cursor) => cursor,
+    let parent = match parent_resource(&repos, parent_id).await {
+        Ok(parent) => parent,
         Err(_) => {
-            write_log("GET /zones/parent/{id} - Error al obtener ítems").ok();
-            return HttpResponse::BadRequest().body("Error al obtener ítems");
+            write_log("GET /zones/parent/{id} - Error buscando el padre").ok();
+            return Err(ApiError::unexpected());
         }
     };
-    let items: Vec<crate::entities::item::Item> = match items_cursor.try_collect().await {
-        Ok(items) => items,
-        Err(_) => {
-            write_log("GET /zones/parent/{id} - Error al procesar ítems").ok();
-            return HttpResponse::BadRequest().body("Error al procesar ítems");
+    authorize(
+        &repos,
+        &user,
+        parent,
+        GroupRole::Viewer,
+        "GET /zones/parent/{id}",
+    )
+    .await?;
+    let viewer = if user.is_admin() { None } else { Some(user.id) };
+
+    // Zonas cuyo parentZoneId sea igual a parent_id e ítems de la zona proporcionada
+    let children = match zone_service::children(&repos, parent_id, viewer, &page).await {
+        Ok(children) => children,
+        Err(e) => {
+            write_log(&format!("GET /zones/parent/{{id}} - {}", e)).ok();
+            return Err(e);
         }
     };
 
-    // Retornar zonas e ítems en la respuesta
     write_log(&format!(
-        "GET /zones/parent/{{id}} - {} zonas y {} items recuperados",
-        zones.len(),
-        items.len()
+        "GET /zones/parent/{{id}} - {} de {} hijos recuperados",
+        children.data.len(),
+        children.total
     ))
     .ok();
-    HttpResponse::Ok().json(serde_json::json!({
-        "zones": zones,
-        "items": items
-    }))
+    Ok(HttpResponse::Ok().json(children))
 }
 
 #[post("/zones")]
 async fn create_zone_handler(
-    db: web::Data<Database>,
+    repos: web::Data<Repositories>,
     new_zone: web::Json<serde_json::Value>,
-    req: HttpRequest,
-) -> impl Responder {
-    // Extraer claims del request
-    let claims = match req
-        .extensions()
-        .get::<crate::middleware::auth::Claims>()
-        .cloned()
-    {
-        Some(claims) => claims,
-        None => {
-            write_log("POST /zones - Token no encontrado").ok();
-            return HttpResponse::Unauthorized().body("Token no encontrado");
-        }
-    };
-
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
     // Extraer "name"
     let name = match new_zone.get("name") {
         Some(value) => match value.as_str() {
             Some(s) => s.to_string(),
             None => {
                 write_log("POST /zones - El nombre debe ser una cadena de texto").ok();
-                return HttpResponse::BadRequest().body("El nombre debe ser una cadena de texto");
+                return Err(ApiError::validation(
+                    "El nombre debe ser una cadena de texto",
+                ));
             }
         },
         None => {
             write_log("POST /zones - El nombre es requerido").ok();
-            return HttpResponse::BadRequest().body("El nombre es requerido");
+            return Err(ApiError::validation("El nombre es requerido"));
         }
     };
 
@@ -221,65 +212,40 @@ async fn create_zone_handler(
             Some(s) => s.to_string(),
             None => {
                 write_log("POST /zones - parentZoneId debe ser una cadena de texto").ok();
-                return HttpResponse::BadRequest()
-                    .body("parentZoneId debe ser una cadena de texto");
+                return Err(ApiError::validation(
+                    "parentZoneId debe ser una cadena de texto",
+                ));
             }
         },
         None => {
             write_log("POST /zones - parentZoneId es requerido").ok();
-            return HttpResponse::BadRequest().body("parentZoneId es requerido");
+            return Err(ApiError::validation("parentZoneId es requerido"));
         }
     };
     let parent_zone_id = match ObjectId::parse_str(&parent_zone_str) {
         Ok(id) => id,
         Err(_) => {
             write_log("POST /zones - parentZoneId inválido").ok();
-            return HttpResponse::BadRequest().body("parentZoneId inválido");
+            return Err(ApiError::validation("parentZoneId inválido"));
         }
     };
 
-    // Determinar property_id:
-    // Primero, buscar en la colección properties
-    let property_collection = db.collection::<crate::entities::property::Property>("properties");
-    let property = property_collection
-        .find_one(doc! {"_id": parent_zone_id.clone()})
-        .await
-        .ok()
-        .flatten();
-    let property_id = if let Some(_) = property {
-        // Si se encontró, se usa parent_zone_id como property_id
-        parent_zone_id.clone()
-    } else {
-        // Sino, buscar en la colección zones
-        let zone_collection = db.collection::<Zone>("zones");
-        if let Ok(Some(zone)) = zone_collection
-            .find_one(doc! {"_id": parent_zone_id.clone()})
-            .await
-        {
-            zone.property_id
-        } else {
-            write_log("POST /zones - parentZoneId no corresponde a propiedad ni zona válida").ok();
-            return HttpResponse::BadRequest()
-                .body("parentZoneId no corresponde a propiedad ni zona válida");
+    // Determinar property_id: el padre puede ser la propia propiedad o una zona
+    let (property_id, parent) = match zone_service::resolve_parent(&repos, parent_zone_id).await {
+        Ok(resolved) => resolved,
+        Err(e) => {
+            write_log(&format!("POST /zones - {}", e)).ok();
+            return Err(e);
         }
     };
+    let group_id = authorize(&repos, &user, parent, GroupRole::Editor, "POST /zones").await?;
 
     let is_private = new_zone
         .get("private")
         .and_then(|v| v.as_bool())
         .unwrap_or(false);
 
-    let user_id = if is_private {
-        match ObjectId::parse_str(&claims.sub) {
-            Ok(id) => Some(id),
-            Err(_) => {
-                write_log("POST /zones - ID de usuario inválido").ok();
-                return HttpResponse::BadRequest().body("ID de usuario inválido");
-            }
-        }
-    } else {
-        None
-    };
+    let user_id = if is_private { Some(user.id) } else { None };
 
     let zone = Zone {
         id: None,
@@ -287,249 +253,222 @@ async fn create_zone_handler(
         property_id,
         parent_zone_id: Some(parent_zone_id),
         user_id,
+        deleted_at: None,
+        trash_id: None,
     };
 
-    let collection = db.collection::<Zone>("zones");
-    match collection.insert_one(zone).await {
-        Ok(result) => {
+    match repos.zones.insert(&zone).await {
+        Ok(zone_id) => {
             write_log("POST /zones - Zona creada correctamente").ok();
-            HttpResponse::Ok().json(result.inserted_id)
+            audit_service::record(
+                &repos,
+                user.id,
+                group_id,
+                AuditEntity::Zone,
+                zone_id,
+                None,
+                Some(&zone),
+            )
+            .await;
+            Ok(HttpResponse::Ok().json(zone_id))
         }
         Err(_) => {
             write_log("POST /zones - Error inesperado").ok();
-            HttpResponse::BadRequest().body("Error inesperado, inténtelo nuevamente")
+            Err(ApiError::unexpected())
         }
     }
 }
 
 #[patch("/zones/{id}")]
 async fn patch_zones_handler(
-    db: web::Data<Database>,
+    repos: web::Data<Repositories>,
     path: web::Path<String>,
     updated_zone: web::Json<serde_json::Value>,
-    req: HttpRequest,
-) -> impl Responder {
-    let claims = match req
-        .extensions()
-        .get::<crate::middleware::auth::Claims>()
-        .cloned()
-    {
-        Some(claims) => claims,
-        None => {
-            write_log("PATCH /zones/{id} - Token no encontrado").ok();
-            return HttpResponse::Unauthorized().body("Token no encontrado");
-        }
-    };
-
-    let collection = db.collection::<Zone>("zones");
-    let obj_id = match ObjectId::parse_str(&path.into_inner()) {
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
+    let obj_id = match ObjectId::parse_str(path.into_inner()) {
         Ok(id) => id,
         Err(_) => {
             write_log("PATCH /zones/{id} - ID inválido").ok();
-            return HttpResponse::BadRequest().body("ID inválido");
+            return Err(ApiError::validation("ID inválido"));
         }
     };
-
-    let mut set_doc = doc! {};
-    let mut unset_doc = doc! {};
-
-    // Campo name
-    if let Some(value) = updated_zone.get("name") {
-        match value {
-            serde_json::Value::String(name) => {
-                set_doc.insert("name", name.clone());
-            }
-            _ => {
-                write_log("PATCH /zones/{id} - Valor inválido para 'name'").ok();
-                return HttpResponse::BadRequest().body("Valor inválido para 'name'");
-            }
-        }
-    }
-
-    // Campo private
-    if let Some(value) = updated_zone.get("private") {
-        match value {
-            serde_json::Value::Bool(is_private) => {
-                if *is_private {
-                    match ObjectId::parse_str(&claims.sub) {
-                        Ok(user_id) => {
-                            set_doc.insert("userId", user_id);
-                        }
-                        Err(_) => {
-                            write_log("PATCH /zones/{id} - ID de usuario inválido").ok();
-                            return HttpResponse::BadRequest().body("ID de usuario inválido");
-                        }
-                    }
-                } else {
-                    unset_doc.insert("userId", "");
-                }
-            }
-            _ => {
-                write_log("PATCH /zones/{id} - Valor inválido para 'private'").ok();
-                return HttpResponse::BadRequest().body("Valor inválido para 'private'");
-            }
-        }
-    }
-
-    if set_doc.is_empty() && unset_doc.is_empty() {
-        write_log("PATCH /zones/{id} - No hay campos para actualizar").ok();
-        return HttpResponse::BadRequest().body("No hay campos para actualizar");
-    }
-
-    let mut update_doc = Document::new();
-    if !set_doc.is_empty() {
-        update_doc.insert("$set", set_doc);
-    }
-    if !unset_doc.is_empty() {
-        update_doc.insert("$unset", unset_doc);
-    }
-
-    match collection
-        .update_one(doc! {"_id": obj_id}, update_doc)
-        .await
-    {
-        Ok(result) if result.matched_count == 1 => {
+    let group_id = authorize(
+        &repos,
+        &user,
+        Resource::Zone(obj_id),
+        GroupRole::Editor,
+        "PATCH /zones/{id}",
+    )
+    .await?;
+    let before = repos.zones.find_by_id(obj_id).await?;
+
+    let result = match zone_service::zone_changes(&updated_zone, user.id) {
+        Ok(changes) => zone_service::update_zone(&repos, obj_id, changes).await,
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok(()) => {
             write_log("PATCH /zones/{id} - Zona actualizada").ok();
-            HttpResponse::Ok().body("Zona actualizada")
-        }
-        Ok(_) => {
-            write_log("PATCH /zones/{id} - Zona no encontrada").ok();
-            HttpResponse::NotFound().body("Zona no encontrada")
+            let after = repos.zones.find_by_id(obj_id).await.ok().flatten();
+            audit_service::record(
+                &repos,
+                user.id,
+                group_id,
+                AuditEntity::Zone,
+                obj_id,
+                before.as_ref(),
+                after.as_ref(),
+            )
+            .await;
+            Ok(HttpResponse::Ok().body("Zona actualizada"))
         }
-        Err(_) => {
-            write_log("PATCH /zones/{id} - Error inesperado").ok();
-            HttpResponse::BadRequest().body("Error inesperado, inténtelo  nuevamente")
+        Err(e) => {
+            write_log(&format!("PATCH /zones/{{id}} - {}", e)).ok();
+            Err(e)
         }
     }
 }
 
-pub async fn delete_zone(db: &Database, zone_id: String) -> HttpResponse {
-    let zone_collection = db.collection::<Zone>("zones");
-    let item_collection = db.collection::<Item>("items");
-    let obj_id = match ObjectId::parse_str(zone_id) {
+#[delete("/zones/{id}")]
+async fn delete_zone_handler(
+    repos: web::Data<Repositories>,
+    path: web::Path<String>,
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
+    let obj_id = match ObjectId::parse_str(path.into_inner()) {
         Ok(id) => id,
         Err(_) => {
             write_log("DELETE /zones/{id} - Id incorrecto").ok();
-            return HttpResponse::BadRequest().body("Id incorrecto");
-        }
-    };
-    let item_cursor = match item_collection.find(doc! {"zoneId":obj_id}).await {
-        Ok(items) => items,
-        Err(_) => {
-            write_log("DELETE /zones/{id} - Error buscando items").ok();
-            return HttpResponse::BadRequest().body("Error inesperado, inténtelo  nuevamente");
-        }
-    };
-    let items: Vec<Item> = match item_cursor.try_collect().await {
-        Ok(items) => items,
-        Err(_) => {
-            write_log("DELETE /zones/{id} - Error recogiendo items").ok();
-            return HttpResponse::BadRequest().body("Error inesperado, inténtelo  nuevamente");
+            return Err(ApiError::validation("Id incorrecto"));
         }
     };
-    for item in items {
-        let id = match item.id {
-            Some(id) => id,
-            None => {
-                write_log("DELETE /zones/{id} - Item sin ID").ok();
-                return HttpResponse::BadRequest().body("No hay ID");
-            }
-        };
-        let res = delete_item(db, id.to_string()).await;
-        if !res.status().is_success() {
-            write_log("DELETE /zones/{id} - Error eliminando item asociado").ok();
-            return res; // Si falla, detenemos la ejecución y devolvemos el error
-        }
-    }
-    match zone_collection.delete_one(doc! {"_id": obj_id}).await {
+    let group_id = authorize(
+        &repos,
+        &user,
+        Resource::Zone(obj_id),
+        GroupRole::Editor,
+        "DELETE /zones/{id}",
+    )
+    .await?;
+    let before = repos.zones.find_by_id(obj_id).await?;
+    match trash_service::move_to_trash(&repos, user.id, group_id, AuditEntity::Zone, obj_id).await {
         Ok(_) => {
-            write_log("DELETE /zones/{id} - Zona eliminada correctamente").ok();
-            HttpResponse::Ok().body("Zona eliminada")
-        }
-        Err(_) => {
-            write_log("DELETE /zones/{id} - Error eliminando zona").ok();
-            HttpResponse::BadRequest().body("Error inesperado, inténtelo  nuevamente")
+            write_log("DELETE /zones/{id} - Zona y subzonas eliminadas").ok();
+            audit_service::record(
+                &repos,
+                user.id,
+                group_id,
+                AuditEntity::Zone,
+                obj_id,
+                before.as_ref(),
+                None,
+            )
+            .await;
+            Ok(HttpResponse::Ok().body("Zona y subzonas eliminadas"))
         }
-    }
-}
-
-// Función auxiliar para obtener todos los IDs de zonas hijas recursivamente
-async fn get_all_child_zone_ids(db: &Database, parent_id: &ObjectId) -> Vec<ObjectId> {
-    let mut all_ids = Vec::new();
-    let zone_collection = db.collection::<Zone>("zones");
-    let mut stack = vec![parent_id.clone()];
-    while let Some(current_id) = stack.pop() {
-        // Buscar zonas hijas directas
-        if let Ok(mut cursor) = zone_collection
-            .find(doc! {"parentZoneId": &current_id})
-            .await
-        {
-            while let Some(result) = cursor.try_next().await.transpose() {
-                match result {
-                    Ok(child_zone) => {
-                        if let Some(child_id) = child_zone.id.clone() {
-                            stack.push(child_id.clone());
-                            all_ids.push(child_id);
-                        }
-                    }
-                    Err(_) => {
-                        // Puedes registrar el error si lo deseas
-                        continue;
-                    }
-                }
-            }
+        Err(e) => {
+            write_log(&format!("DELETE /zones/{{id}} - {}", e)).ok();
+            Err(e)
         }
     }
-    all_ids
 }
 
-#[delete("/zones/{id}")]
-async fn delete_zone_handler(db: web::Data<Database>, path: web::Path<String>) -> impl Responder {
-    let client = db.client();
-    let mut session = match client.start_session().await {
-        Ok(s) => s,
+#[post("/zones/{id}/move")]
+async fn move_zone_handler(
+    repos: web::Data<Repositories>,
+    path: web::Path<String>,
+    body: web::Json<serde_json::Value>,
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
+    let zone_id = match ObjectId::parse_str(path.into_inner()) {
+        Ok(id) => id,
         Err(_) => {
-            write_log("DELETE /zones/{id} - Error iniciando sesión").ok();
-            return HttpResponse::BadRequest().body("Error inesperado, inténtelo  nuevamente");
+            write_log("POST /zones/{id}/move - ID inválido").ok();
+            return Err(ApiError::validation("ID inválido"));
         }
     };
-    session.start_transaction().await.ok();
-    let id_str = path.into_inner();
-    let obj_id = match ObjectId::parse_str(&id_str) {
-        Ok(id) => id,
-        Err(_) => {
-            write_log("DELETE /zones/{id} - Id incorrecto").ok();
-            session.abort_transaction().await.ok();
-            return HttpResponse::BadRequest().body("Id incorrecto");
+    let target_id = match body.get("parentZoneId").and_then(|v| v.as_str()) {
+        Some(id) => match ObjectId::parse_str(id) {
+            Ok(id) => id,
+            Err(_) => {
+                write_log("POST /zones/{id}/move - parentZoneId inválido").ok();
+                return Err(ApiError::validation("parentZoneId inválido")
+                    .with_details(serde_json::json!({"field": "parentZoneId"})));
+            }
+        },
+        None => {
+            write_log("POST /zones/{id}/move - parentZoneId es requerido").ok();
+            return Err(ApiError::validation("parentZoneId es requerido")
+                .with_details(serde_json::json!({"field": "parentZoneId"})));
         }
     };
-
-    // Obtener todos los IDs de zonas hijas recursivamente
-    let mut all_zone_ids = get_all_child_zone_ids(&db, &obj_id).await;
-    // Incluir la zona original
-    all_zone_ids.push(obj_id.clone());
-
-    // Eliminar todas las zonas (y sus ítems) en orden inverso (de hojas a raíz)
-    let mut error_response = None;
-    for zone_id in all_zone_ids.iter().rev() {
-        let res = delete_zone(&db, zone_id.to_hex()).await;
-        if !res.status().is_success() {
-            error_response = Some(res);
-            break;
+    let group_id = authorize(
+        &repos,
+        &user,
+        Resource::Zone(zone_id),
+        GroupRole::Editor,
+        "POST /zones/{id}/move",
+    )
+    .await?;
+    // El destino puede ser una propiedad o una zona, de este u otro grupo editable
+    let (property_id, target) = match zone_service::resolve_parent(&repos, target_id).await {
+        Ok(resolved) => resolved,
+        Err(e) => {
+            write_log(&format!("POST /zones/{{id}}/move - {}", e)).ok();
+            return Err(e);
         }
-    }
-
-    let response = if let Some(err) = error_response {
-        session.abort_transaction().await.ok();
-        write_log("DELETE /zones/{id} - Transacción abortada").ok();
-        err
-    } else {
-        session.commit_transaction().await.ok();
-        write_log("DELETE /zones/{id} - Transacción confirmada").ok();
-        HttpResponse::Ok().body("Zona y subzonas eliminadas")
     };
-
-    response
+    let target_group_id = authorize(
+        &repos,
+        &user,
+        target,
+        GroupRole::Editor,
+        "POST /zones/{id}/move",
+    )
+    .await?;
+    let before = repos.zones.find_by_id(zone_id).await?;
+    match zone_service::move_zone(
+        &repos,
+        zone_id,
+        target_id,
+        property_id,
+        group_id,
+        target_group_id,
+    )
+    .await
+    {
+        Ok(()) => {
+            write_log(&format!(
+                "POST /zones/{{id}}/move - Zona {} movida bajo {}",
+                zone_id, target_id
+            ))
+            .ok();
+            let after = repos.zones.find_by_id(zone_id).await.ok().flatten();
+            // Si cambia de grupo, el movimiento queda en el historial de los dos
+            let mut groups = vec![group_id];
+            if target_group_id != group_id {
+                groups.push(target_group_id);
+            }
+            for group_id in groups {
+                audit_service::record(
+                    &repos,
+                    user.id,
+                    group_id,
+                    AuditEntity::Zone,
+                    zone_id,
+                    before.as_ref(),
+                    after.as_ref(),
+                )
+                .await;
+            }
+            Ok(HttpResponse::Ok().body("Zona movida"))
+        }
+        Err(e) => {
+            write_log(&format!("POST /zones/{{id}}/move - {}", e)).ok();
+            Err(e)
+        }
+    }
 }
 
 pub fn configure_routes(cfg: &mut web::ServiceConfig) {
@@ -538,5 +477,6 @@ pub fn configure_routes(cfg: &mut web::ServiceConfig) {
         .service(get_zone_from_parent_handler)
         .service(create_zone_handler)
         .service(patch_zones_handler)
-        .service(delete_zone_handler);
+        .service(delete_zone_handler)
+        .service(move_zone_handler);
 }
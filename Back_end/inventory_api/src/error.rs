@@ -0,0 +1,234 @@
+use actix_web::{http::StatusCode, web, HttpResponse, ResponseError};
+use serde::Serialize;
+use serde_json::Value;
+use std::fmt;
+
+use crate::log::write_log;
+use crate::repository::RepoError;
+
+pub const UNEXPECTED_ERROR: &str = "Error inesperado, inténtelo nuevamente";
+
+/// Error de la API. Todas las respuestas de error se devuelven como
+/// `{"code": ..., "message": ..., "details": ...}`, donde `code` es estable y permite al
+/// cliente traducir el mensaje sin depender del texto.
+#[derive(Debug)]
+pub enum ApiError {
+    /// Datos de entrada incorrectos (422).
+    Validation {
+        message: String,
+        details: Option<Value>,
+    },
+    /// Falta la autenticación o no es válida (401).
+    Unauthorized {
+        message: String,
+        details: Option<Value>,
+    },
+    /// El usuario no tiene permisos sobre el recurso (403).
+    Forbidden {
+        message: String,
+        details: Option<Value>,
+    },
+    /// El recurso no existe (404).
+    NotFound {
+        message: String,
+        details: Option<Value>,
+    },
+    /// La operación choca con el estado actual del recurso (409).
+    Conflict {
+        message: String,
+        details: Option<Value>,
+    },
+    /// Demasiadas peticiones; `retry_after` en segundos (429).
+    TooManyRequests { message: String, retry_after: u64 },
+    /// Fallo de la base de datos o del servidor (500).
+    Internal {
+        message: String,
+        details: Option<Value>,
+    },
+}
+
+#[derive(Serialize)]
+struct ErrorBody<'a> {
+    code: &'a str,
+    message: &'a str,
+    details: Option<Value>,
+}
+
+impl ApiError {
+    pub fn validation(message: impl Into<String>) -> Self {
+        ApiError::Validation {
+            message: message.into(),
+            details: None,
+        }
+    }
+
+    pub fn unauthorized(message: impl Into<String>) -> Self {
+        ApiError::Unauthorized {
+            message: message.into(),
+            details: None,
+        }
+    }
+
+    pub fn forbidden(message: impl Into<String>) -> Self {
+        ApiError::Forbidden {
+            message: message.into(),
+            details: None,
+        }
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        ApiError::NotFound {
+            message: message.into(),
+            details: None,
+        }
+    }
+
+    pub fn conflict(message: impl Into<String>) -> Self {
+        ApiError::Conflict {
+            message: message.into(),
+            details: None,
+        }
+    }
+
+    pub fn too_many_requests(message: impl Into<String>, retry_after: u64) -> Self {
+        ApiError::TooManyRequests {
+            message: message.into(),
+            retry_after,
+        }
+    }
+
+    pub fn internal(message: impl Into<String>) -> Self {
+        ApiError::Internal {
+            message: message.into(),
+            details: None,
+        }
+    }
+
+    /// Error interno con el mensaje genérico.
+    pub fn unexpected() -> Self {
+        Self::internal(UNEXPECTED_ERROR)
+    }
+
+    /// Añade información adicional (por ejemplo el campo que no es válido).
+    pub fn with_details(mut self, value: Value) -> Self {
+        match &mut self {
+            ApiError::Validation { details, .. }
+            | ApiError::Unauthorized { details, .. }
+            | ApiError::Forbidden { details, .. }
+            | ApiError::NotFound { details, .. }
+            | ApiError::Conflict { details, .. }
+            | ApiError::Internal { details, .. } => *details = Some(value),
+            ApiError::TooManyRequests { .. } => {}
+        }
+        self
+    }
+
+    /// Código estable que identifica el tipo de error.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ApiError::Validation { .. } => "validation_error",
+            ApiError::Unauthorized { .. } => "unauthorized",
+            ApiError::Forbidden { .. } => "forbidden",
+            ApiError::NotFound { .. } => "not_found",
+            ApiError::Conflict { .. } => "conflict",
+            ApiError::TooManyRequests { .. } => "too_many_requests",
+            ApiError::Internal { .. } => "internal_error",
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            ApiError::Validation { message, .. }
+            | ApiError::Unauthorized { message, .. }
+            | ApiError::Forbidden { message, .. }
+            | ApiError::NotFound { message, .. }
+            | ApiError::Conflict { message, .. }
+            | ApiError::TooManyRequests { message, .. }
+            | ApiError::Internal { message, .. } => message,
+        }
+    }
+
+    pub fn details(&self) -> Option<&Value> {
+        match self {
+            ApiError::Validation { details, .. }
+            | ApiError::Unauthorized { details, .. }
+            | ApiError::Forbidden { details, .. }
+            | ApiError::NotFound { details, .. }
+            | ApiError::Conflict { details, .. }
+            | ApiError::Internal { details, .. } => details.as_ref(),
+            ApiError::TooManyRequests { .. } => None,
+        }
+    }
+
+    /// Cuerpo JSON del error, el mismo que se envía en la respuesta.
+    pub fn body(&self) -> Value {
+        serde_json::to_value(ErrorBody {
+            code: self.code(),
+            message: self.message(),
+            details: self.details().cloned(),
+        })
+        .unwrap_or(Value::Null)
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.code(), self.message())
+    }
+}
+
+impl ResponseError for ApiError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            ApiError::Validation { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+            ApiError::Unauthorized { .. } => StatusCode::UNAUTHORIZED,
+            ApiError::Forbidden { .. } => StatusCode::FORBIDDEN,
+            ApiError::NotFound { .. } => StatusCode::NOT_FOUND,
+            ApiError::Conflict { .. } => StatusCode::CONFLICT,
+            ApiError::TooManyRequests { .. } => StatusCode::TOO_MANY_REQUESTS,
+            ApiError::Internal { .. } => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let mut builder = HttpResponse::build(self.status_code());
+        if let ApiError::TooManyRequests { retry_after, .. } = self {
+            builder.insert_header(("Retry-After", retry_after.to_string()));
+        }
+        builder.json(self.body())
+    }
+}
+
+impl From<RepoError> for ApiError {
+    fn from(e: RepoError) -> Self {
+        write_log(&format!("Error de base de datos: {}", e)).ok();
+        ApiError::unexpected()
+    }
+}
+
+/// Los cuerpos JSON que no se pueden deserializar también se devuelven con el formato común.
+pub fn json_config() -> web::JsonConfig {
+    web::JsonConfig::default().error_handler(|err, _req| {
+        ApiError::validation("Cuerpo de la petición inválido")
+            .with_details(serde_json::json!({"reason": err.to_string()}))
+            .into()
+    })
+}
+
+/// Lo mismo para los segmentos de la ruta que no encajan con el tipo esperado.
+pub fn path_config() -> web::PathConfig {
+    web::PathConfig::default().error_handler(|err, _req| {
+        ApiError::validation("Ruta de la petición inválida")
+            .with_details(serde_json::json!({"reason": err.to_string()}))
+            .into()
+    })
+}
+
+/// Y para los parámetros de la query string.
+pub fn query_config() -> web::QueryConfig {
+    web::QueryConfig::default().error_handler(|err, _req| {
+        ApiError::validation("Parámetros de la petición inválidos")
+            .with_details(serde_json::json!({"reason": err.to_string()}))
+            .into()
+    })
+}
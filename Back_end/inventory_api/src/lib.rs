@@ -0,0 +1,49 @@
+use actix_web::web;
+use std::sync::Arc;
+
+use crate::mail::Mailer;
+use crate::middleware::auth::AuthMiddleware;
+use crate::middleware::rate_limit::RateLimiter;
+use crate::repository::Repositories;
+
+pub mod access;
+pub mod config;
+pub mod dates;
+pub mod db;
+pub mod entities;
+pub mod error;
+pub mod log;
+pub mod mail;
+pub mod middleware;
+pub mod pagination;
+pub mod query;
+pub mod repository;
+pub mod routes;
+pub mod services;
+pub mod text;
+
+/// Datos compartidos y rutas de la API: `/public`, con el limitador de intentos, y
+/// `/private`, con autenticación. `main` y los tests de integración montan la misma
+/// aplicación a partir de aquí.
+pub fn configure_app(
+    cfg: &mut web::ServiceConfig,
+    repos: Repositories,
+    mailer: Arc<dyn Mailer>,
+    rate_limiter: RateLimiter,
+) {
+    cfg.app_data(web::Data::new(repos))
+        .app_data(web::Data::from(mailer))
+        .app_data(error::json_config())
+        .app_data(error::path_config())
+        .app_data(error::query_config())
+        .service(
+            web::scope("/public")
+                .wrap(rate_limiter)
+                .configure(routes::configure_public_routes),
+        )
+        .service(
+            web::scope("/private")
+                .wrap(AuthMiddleware)
+                .configure(routes::configure_private_routes),
+        );
+}
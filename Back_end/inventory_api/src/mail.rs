@@ -0,0 +1,164 @@
+use async_trait::async_trait;
+use chrono::Local;
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::config::{MailConfig, MailTransport};
+use crate::log::write_log;
+
+/// Correo saliente en texto plano.
+#[derive(Debug, Clone)]
+pub struct MailMessage {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+#[derive(Debug)]
+pub struct MailError(pub String);
+
+impl fmt::Display for MailError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Transporte de correo. Permite cambiar SMTP por un transporte local en desarrollo y pruebas.
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    async fn send(&self, message: &MailMessage) -> Result<(), MailError>;
+}
+
+/// Envía los correos a través de un servidor SMTP (STARTTLS).
+pub struct SmtpMailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl SmtpMailer {
+    pub fn new(
+        host: &str,
+        port: u16,
+        credentials: Option<(String, String)>,
+        from: &str,
+    ) -> Result<Self, MailError> {
+        let mut builder = AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host)
+            .map_err(|e| MailError(e.to_string()))?
+            .port(port);
+        if let Some((user, password)) = credentials {
+            builder = builder.credentials(Credentials::new(user, password));
+        }
+        let from = from
+            .parse()
+            .map_err(|_| MailError(format!("Remitente inválido: {}", from)))?;
+        Ok(Self {
+            transport: builder.build(),
+            from,
+        })
+    }
+}
+
+#[async_trait]
+impl Mailer for SmtpMailer {
+    async fn send(&self, message: &MailMessage) -> Result<(), MailError> {
+        let to: Mailbox = message
+            .to
+            .parse()
+            .map_err(|_| MailError(format!("Destinatario inválido: {}", message.to)))?;
+        let email = Message::builder()
+            .from(self.from.clone())
+            .to(to)
+            .subject(message.subject.clone())
+            .body(message.body.clone())
+            .map_err(|e| MailError(e.to_string()))?;
+        self.transport
+            .send(email)
+            .await
+            .map_err(|e| MailError(e.to_string()))?;
+        Ok(())
+    }
+}
+
+/// Escribe los correos en un directorio (un fichero `.eml` por correo) o, sin directorio,
+/// en la salida estándar. Pensado para desarrollo local sin servidor de correo.
+pub struct FileMailer {
+    dir: Option<PathBuf>,
+}
+
+impl FileMailer {
+    pub fn new(dir: Option<PathBuf>) -> Self {
+        Self { dir }
+    }
+}
+
+#[async_trait]
+impl Mailer for FileMailer {
+    async fn send(&self, message: &MailMessage) -> Result<(), MailError> {
+        let content = format!(
+            "To: {}\nSubject: {}\nDate: {}\n\n{}\n",
+            message.to,
+            message.subject,
+            Local::now().to_rfc2822(),
+            message.body
+        );
+        match &self.dir {
+            Some(dir) => {
+                tokio::fs::create_dir_all(dir)
+                    .await
+                    .map_err(|e| MailError(e.to_string()))?;
+                let file_name = format!(
+                    "{}-{}.eml",
+                    Local::now().format("%Y%m%d%H%M%S%f"),
+                    message.to.replace(['@', '/', '\\'], "_")
+                );
+                tokio::fs::write(dir.join(file_name), content)
+                    .await
+                    .map_err(|e| MailError(e.to_string()))
+            }
+            None => {
+                println!("{}", content);
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Crea el transporte indicado en `mail.transport`.
+pub fn mailer_from_config(config: &MailConfig) -> Result<Arc<dyn Mailer>, MailError> {
+    match config.transport {
+        MailTransport::Smtp => {
+            let credentials = config
+                .smtp_username
+                .clone()
+                .zip(config.smtp_password.clone());
+            let mailer = SmtpMailer::new(
+                &config.smtp_host,
+                config.smtp_port,
+                credentials,
+                &config.from,
+            )?;
+            write_log(&format!(
+                "[START] Correo por SMTP en {}:{}",
+                config.smtp_host, config.smtp_port
+            ))
+            .ok();
+            Ok(Arc::new(mailer))
+        }
+        MailTransport::File => {
+            write_log(&format!(
+                "[START] Correo guardado en el directorio {}",
+                config.dir
+            ))
+            .ok();
+            Ok(Arc::new(FileMailer::new(Some(PathBuf::from(&config.dir)))))
+        }
+        MailTransport::Stdout => {
+            write_log("[START] Correo volcado en la salida estándar").ok();
+            Ok(Arc::new(FileMailer::new(None)))
+        }
+    }
+}
@@ -1,45 +1,98 @@
 use actix_cors::Cors;
-use actix_web::{web, App, HttpServer};
-use middleware::auth::AuthMiddleware;
+use std::time::Duration;
 
-use crate::log::write_log;
+use actix_web::{rt, App, HttpServer};
+use inventory_api::config::{self, Config};
+use inventory_api::log::write_log;
+use inventory_api::middleware::rate_limit::{RateLimitConfig, RateLimiter};
+use inventory_api::services::trash as trash_service;
+use inventory_api::{configure_app, mail, repository};
 
-mod db;
-mod entities;
-mod log;
-mod middleware;
-mod routes;
+/// Cada cuánto se buscan elementos de la papelera que hayan superado la retención.
+const PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     write_log("[START] Iniciando el programa").ok();
 
-    let database = db::init_db()
-        .await
-        .map(|db| {
-            write_log("[START] Base de datos inicializada correctamente").ok();
-            db
-        })
-        .expect("Error al inicializar la base de datos");
+    // Sin una configuración válida no se arranca
+    let config = match Config::load() {
+        Ok(config) => config,
+        Err(e) => {
+            write_log(&format!("[START] {}", e)).ok();
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                e.to_string(),
+            ));
+        }
+    };
+    write_log(&format!(
+        "[START] Perfil de configuración: {:?}",
+        config.profile
+    ))
+    .ok();
+    config::init(config.clone());
+
+    let repos = match repository::repositories_from_config(&config.database).await {
+        Ok(repos) => repos,
+        Err(e) => {
+            write_log(&format!(
+                "[START] Error al inicializar la base de datos: {}",
+                e
+            ))
+            .ok();
+            return Err(std::io::Error::other(e.to_string()));
+        }
+    };
+
+    let purge_repos = repos.clone();
+    let retention_days = config.trash.retention_days;
+    rt::spawn(async move {
+        let mut interval = rt::time::interval(PURGE_INTERVAL);
+        loop {
+            interval.tick().await;
+            match trash_service::purge_expired(&purge_repos, retention_days).await {
+                Ok(0) => {}
+                Ok(purged) => {
+                    write_log(&format!(
+                        "[PURGE] {} elementos eliminados de la papelera",
+                        purged
+                    ))
+                    .ok();
+                }
+                Err(e) => {
+                    write_log(&format!("[PURGE] Error vaciando la papelera: {}", e)).ok();
+                }
+            }
+        }
+    });
 
+    let mailer = match mail::mailer_from_config(&config.mail) {
+        Ok(mailer) => mailer,
+        Err(e) => {
+            write_log(&format!("[START] Configuración de correo inválida: {}", e)).ok();
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                e.to_string(),
+            ));
+        }
+    };
+    let rate_limiter = RateLimiter::new(RateLimitConfig::from_config(&config.rate_limit));
+
+    let allowed_origins = config.cors.allowed_origins.clone();
     let result = HttpServer::new(move || {
-        let cors = Cors::default()
-            .allowed_origin("http://localhost:8081")
+        let cors = allowed_origins
+            .iter()
+            .fold(Cors::default(), |cors, origin| cors.allowed_origin(origin))
             .allow_any_header()
             .allow_any_method()
             .supports_credentials();
 
-        App::new()
-            .wrap(cors)
-            .app_data(web::Data::new(database.clone()))
-            .service(web::scope("/public").configure(routes::configure_public_routes))
-            .service(
-                web::scope("/private")
-                    .wrap(AuthMiddleware)
-                    .configure(routes::configure_private_routes),
-            )
+        App::new().wrap(cors).configure(|cfg| {
+            configure_app(cfg, repos.clone(), mailer.clone(), rate_limiter.clone())
+        })
     })
-    .bind(("192.168.1.142", 8000))?
+    .bind((config.server.host.as_str(), config.server.port))?
     .run()
     .await;
 
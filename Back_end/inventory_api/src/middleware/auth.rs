@@ -1,16 +1,23 @@
-use actix_web::dev::{Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::dev::{Payload, Service, ServiceRequest, ServiceResponse, Transform};
 use actix_web::{
     body::{BoxBody, MessageBody},
-    Error, HttpMessage,
+    web, Error, FromRequest, HttpMessage, HttpRequest,
 };
-use futures_util::future::{ok, LocalBoxFuture, Ready};
+use futures_util::future::{ok, ready, LocalBoxFuture, Ready};
 use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
+use mongodb::bson::oid::ObjectId;
 use std::{
-    env,
+    rc::Rc,
     task::Context,
     time::{SystemTime, UNIX_EPOCH},
 };
 
+use crate::config;
+use crate::error::ApiError;
+use crate::log::write_log;
+use crate::repository::Repositories;
+use crate::services::session::is_session_active;
+
 /// Middleware de autenticación.
 pub struct AuthMiddleware;
 
@@ -19,6 +26,50 @@ pub struct Claims {
     pub sub: String,
     exp: usize,
     pub role: String,
+    /// Sesión (refresh token) a la que pertenece el token de acceso.
+    pub sid: String,
+}
+
+/// Usuario autenticado de la petición, construido a partir de las claims que deja
+/// `AuthMiddleware`. Solo puede usarse en las rutas del scope privado.
+#[derive(Debug, Clone)]
+pub struct AuthUser {
+    pub id: ObjectId,
+    pub role: String,
+    pub session_id: String,
+}
+
+impl AuthUser {
+    pub fn is_admin(&self) -> bool {
+        self.role == "admin"
+    }
+}
+
+impl FromRequest for AuthUser {
+    type Error = Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let route = format!("{} {}", req.method(), req.path());
+        let result = match req.extensions().get::<Claims>() {
+            Some(claims) => match ObjectId::parse_str(&claims.sub) {
+                Ok(id) => Ok(AuthUser {
+                    id,
+                    role: claims.role.clone(),
+                    session_id: claims.sid.clone(),
+                }),
+                Err(_) => {
+                    write_log(&format!("{} - ID de usuario inválido", route)).ok();
+                    Err(ApiError::unauthorized("ID de usuario inválido").into())
+                }
+            },
+            None => {
+                write_log(&format!("{} - Token no encontrado", route)).ok();
+                Err(ApiError::unauthorized("Token no encontrado").into())
+            }
+        };
+        ready(result)
+    }
 }
 
 // Se elimina el bound `Clone` ya que no clonaremos el servicio.
@@ -34,13 +85,15 @@ where
     type Future = Ready<Result<Self::Transform, Self::InitError>>;
 
     fn new_transform(&self, service: S) -> Self::Future {
-        ok(AuthMiddlewareService { service })
+        ok(AuthMiddlewareService {
+            service: Rc::new(service),
+        })
     }
 }
 
 /// Servicio que envuelve el inner service y ejecuta la autenticación.
 pub struct AuthMiddlewareService<S> {
-    service: S,
+    service: Rc<S>,
 }
 
 impl<S, B> Service<ServiceRequest> for AuthMiddlewareService<S>
@@ -64,7 +117,7 @@ where
             .and_then(|hv| hv.to_str().ok())
             .map(|t| t.trim_start_matches("Bearer ").to_string());
 
-        let secret = env::var("API_KEY").unwrap_or_else(|_| "clave_secreta".to_string());
+        let secret = &config::get().auth.api_key;
 
         let auth_result: Result<ServiceRequest, Error> = if let Some(token) = token {
             match decode::<Claims>(
@@ -82,47 +135,64 @@ where
                         .to_string();
 
                     if claims.sub.is_empty() {
-                        Err(actix_web::error::ErrorBadRequest("ID inválido"))
+                        Err(ApiError::unauthorized("ID inválido").into())
                     } else {
                         // Inserta el ID en las extensiones de la request.
                         req.extensions_mut().insert(claims);
                         Ok(req)
                     }
                 }
-                Err(_) => Err(actix_web::error::ErrorUnauthorized(
-                    "Token inválido o expirado",
-                )),
+                Err(_) => Err(ApiError::unauthorized("Token inválido o expirado").into()),
             }
         } else {
-            Err(actix_web::error::ErrorUnauthorized("Token ausente"))
+            Err(ApiError::unauthorized("Token ausente").into())
         };
 
-        match auth_result {
-            Ok(req) => {
-                let fut = self.service.call(req);
-                Box::pin(async move {
-                    let res = fut.await?;
-                    Ok(res.map_into_boxed_body())
-                })
+        let service = Rc::clone(&self.service);
+        Box::pin(async move {
+            let req = auth_result?;
+            // Se rechazan los tokens cuya sesión haya sido revocada o haya caducado
+            let (sid, sub) = match req.extensions().get::<Claims>() {
+                Some(claims) => (claims.sid.clone(), claims.sub.clone()),
+                None => return Err(ApiError::unauthorized("Token ausente").into()),
+            };
+            let repos = match req.app_data::<web::Data<Repositories>>() {
+                Some(repos) => repos.clone(),
+                None => return Err(ApiError::internal("Base de datos no disponible").into()),
+            };
+            let active = match (ObjectId::parse_str(&sid), ObjectId::parse_str(&sub)) {
+                (Ok(sid), Ok(user_id)) => is_session_active(&repos, sid, user_id).await,
+                _ => false,
+            };
+            if !active {
+                write_log(&format!("AUTH - Sesión revocada o caducada: {}", sid)).ok();
+                return Err(ApiError::unauthorized("Sesión revocada o caducada").into());
             }
-            Err(err) => Box::pin(async move { Err(err) }),
-        }
+            let res = service.call(req).await?;
+            Ok(res.map_into_boxed_body())
+        })
     }
 }
 
-/// Función para generar un token a partir de un usuario.
-pub fn generate_token(user_id: String, role: String) -> String {
-    let clave = env::var("API_KEY").unwrap_or_else(|_| "clave_secreta".into());
+/// Duración del token de acceso en segundos (`auth.access_token_ttl_secs`).
+pub fn access_token_ttl() -> u64 {
+    config::get().auth.access_token_ttl_secs
+}
+
+/// Función para generar un token a partir de un usuario y su sesión.
+pub fn generate_token(user_id: String, role: String, session_id: String) -> String {
+    let clave = &config::get().auth.api_key;
     let exp = SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .unwrap()
-        .as_secs() as usize
-        + 3600;
+        .as_secs()
+        + access_token_ttl();
 
     let claims = Claims {
         sub: user_id,
-        exp,
+        exp: exp as usize,
         role,
+        sid: session_id,
     };
     encode(
         &Header::default(),
@@ -131,8 +201,77 @@ pub fn generate_token(user_id: String, role: String) -> String {
     )
     .unwrap()
 }
+/// Finalidad de un token de un solo uso enviado por correo.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ActionPurpose {
+    Reset,
+    Verify,
+}
+
+impl ActionPurpose {
+    fn ttl(self) -> u64 {
+        match self {
+            ActionPurpose::Reset => 3600,
+            ActionPurpose::Verify => 48 * 3600,
+        }
+    }
+}
+
+/// Claims de los tokens de restablecimiento de contraseña y verificación de correo.
+/// `fp` es una huella del estado del usuario (hash de la contraseña o correo) que hace
+/// que el token deje de ser válido en cuanto se usa.
+#[derive(Debug, serde::Deserialize, serde::Serialize, Clone)]
+pub struct ActionClaims {
+    pub sub: String,
+    exp: usize,
+    pub purpose: ActionPurpose,
+    pub fp: String,
+}
+
+/// Huella corta de un valor para incluirla en un token de acción.
+pub fn fingerprint(value: &str) -> String {
+    use sha2::{Digest, Sha256};
+    format!("{:x}", Sha256::digest(value.as_bytes()))[..16].to_string()
+}
+
+/// Genera un token firmado y con caducidad para la acción indicada.
+pub fn generate_action_token(user_id: String, purpose: ActionPurpose, fp: String) -> String {
+    let clave = &config::get().auth.api_key;
+    let exp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+        + purpose.ttl();
+    let claims = ActionClaims {
+        sub: user_id,
+        exp: exp as usize,
+        purpose,
+        fp,
+    };
+    encode(
+        &Header::default(),
+        &claims,
+        &EncodingKey::from_secret(clave.as_ref()),
+    )
+    .unwrap()
+}
+
+/// Valida la firma, la caducidad y la finalidad de un token de acción.
+pub fn decode_action_token(token: &str, purpose: ActionPurpose) -> Option<ActionClaims> {
+    let clave = &config::get().auth.api_key;
+    decode::<ActionClaims>(
+        token,
+        &DecodingKey::from_secret(clave.as_ref()),
+        &Validation::default(),
+    )
+    .ok()
+    .map(|data| data.claims)
+    .filter(|claims| claims.purpose == purpose)
+}
+
 // pub fn decode_token(token: &str)->Result<Claims,Error>{
-//     let clave = env::var("API_KEY").unwrap_or_else(|_| "clave_secreta".into());
+//     let clave = &config::get().auth.api_key;
 //     decode::<Claims>(token, &DecodingKey::from_secret(clave.as_ref()), &Validation::default())
 //         .map(|data| data.claims)
 //         .map_err(|_| actix_web::error::ErrorUnauthorized("Token inválido o expirado"))
@@ -0,0 +1,374 @@
+use actix_web::dev::{Payload, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::{
+    body::{BoxBody, MessageBody},
+    http::StatusCode,
+    web, Error, ResponseError,
+};
+use futures_util::future::{ok, LocalBoxFuture, Ready};
+use std::{
+    collections::HashMap,
+    rc::Rc,
+    sync::{Arc, Mutex},
+    task::Context,
+    time::{Duration, Instant},
+};
+
+use crate::config::RateLimitSettings;
+use crate::error::ApiError;
+use crate::log::write_log;
+
+/// Rutas públicas protegidas por el limitador.
+const LIMITED_PATHS: [&str; 2] = ["/users/login", "/users/register"];
+
+/// Número máximo de entradas antes de purgar las que ya no aportan información.
+const MAX_ENTRIES: usize = 10_000;
+
+#[derive(Debug, Clone)]
+pub struct RateLimitConfig {
+    /// Ventana en la que se cuentan las peticiones.
+    pub window: Duration,
+    /// Peticiones permitidas por ventana y clave (IP o correo).
+    pub max_requests: u32,
+    /// Fallos consecutivos que provocan un bloqueo.
+    pub max_failures: u32,
+    /// Duración del primer bloqueo; cada bloqueo posterior dura el doble.
+    pub base_lockout: Duration,
+    pub max_lockout: Duration,
+    /// Tiempo sin bloqueos tras el que se olvidan los anteriores.
+    pub lockout_decay: Duration,
+    /// De dónde sale la IP del cliente.
+    pub client_ip: ClientIp,
+}
+
+/// Origen de la IP con la que se limitan las peticiones.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClientIp {
+    /// La dirección de la conexión. Detrás de un proxy es la del proxy para todos.
+    Peer,
+    /// La cabecera que rellena el proxy (`X-Real-IP`, `X-Forwarded-For`...). Se usa el
+    /// último valor, que es el que añade el proxy más cercano; sin cabecera, la conexión.
+    Header(String),
+}
+
+impl RateLimitConfig {
+    /// Traduce la sección `[rate_limit]` de la configuración.
+    pub fn from_config(settings: &RateLimitSettings) -> Self {
+        Self {
+            window: Duration::from_secs(settings.window_secs),
+            max_requests: settings.max_requests,
+            max_failures: settings.max_failures,
+            base_lockout: Duration::from_secs(settings.lockout_secs),
+            max_lockout: Duration::from_secs(settings.max_lockout_secs),
+            lockout_decay: Duration::from_secs(settings.lockout_decay_secs),
+            client_ip: match &settings.ip_header {
+                Some(header) => ClientIp::Header(header.clone()),
+                None => ClientIp::Peer,
+            },
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Entry {
+    window_start: Instant,
+    requests: u32,
+    failures: u32,
+    lockouts: u32,
+    locked_until: Option<Instant>,
+}
+
+impl Entry {
+    fn new(now: Instant) -> Self {
+        Self {
+            window_start: now,
+            requests: 0,
+            failures: 0,
+            lockouts: 0,
+            locked_until: None,
+        }
+    }
+
+    /// Si los bloqueos anteriores siguen contando para alargar el siguiente.
+    fn remembers_lockouts(&self, now: Instant, decay: Duration) -> bool {
+        self.lockouts > 0 && self.locked_until.is_some_and(|until| now < until + decay)
+    }
+}
+
+/// Descarta las entradas que ya no limitan nada: sin bloqueo activo, fuera de la ventana
+/// y con los bloqueos anteriores ya olvidados.
+fn prune(entries: &mut HashMap<String, Entry>, config: &RateLimitConfig, now: Instant) {
+    entries.retain(|_, e| {
+        e.locked_until.is_some_and(|until| until > now)
+            || now.duration_since(e.window_start) < config.window
+            || e.remembers_lockouts(now, config.lockout_decay)
+    });
+}
+
+/// Limitador por IP y por correo de las rutas de login y registro, con bloqueo
+/// exponencial tras fallos repetidos. El estado se comparte entre todos los workers.
+#[derive(Clone)]
+pub struct RateLimiter {
+    config: RateLimitConfig,
+    entries: Arc<Mutex<HashMap<String, Entry>>>,
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self {
+            config,
+            entries: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Registra la petición para cada clave. Devuelve los segundos de espera si alguna está limitada.
+    fn check(&self, keys: &[String]) -> Option<u64> {
+        let now = Instant::now();
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() > MAX_ENTRIES {
+            prune(&mut entries, &self.config, now);
+        }
+        let mut wait: Option<Duration> = None;
+        for key in keys {
+            let entry = entries
+                .entry(key.clone())
+                .or_insert_with(|| Entry::new(now));
+            if let Some(until) = entry.locked_until.filter(|until| *until > now) {
+                wait = wait.max(Some(until - now));
+                continue;
+            }
+            if now.duration_since(entry.window_start) >= self.config.window {
+                entry.window_start = now;
+                entry.requests = 0;
+            }
+            if !entry.remembers_lockouts(now, self.config.lockout_decay) {
+                entry.lockouts = 0;
+            }
+            entry.requests += 1;
+            if entry.requests > self.config.max_requests {
+                let until = entry.window_start + self.config.window;
+                wait = wait.max(Some(until - now));
+            }
+        }
+        // Se redondea hacia arriba para no invitar a reintentar antes de tiempo
+        wait.map(|w| w.as_secs() + u64::from(w.subsec_nanos() > 0))
+    }
+
+    /// Actualiza el contador de fallos según el resultado de la petición. Un acierto solo
+    /// limpia el correo: si limpiara la IP, entrar con una cuenta propia permitiría seguir
+    /// probando contraseñas de otras.
+    fn record(&self, keys: &[String], success: bool) {
+        let now = Instant::now();
+        let mut entries = self.entries.lock().unwrap();
+        for key in keys {
+            let Some(entry) = entries.get_mut(key) else {
+                continue;
+            };
+            if success {
+                if !key.starts_with("mail:") {
+                    continue;
+                }
+                entry.failures = 0;
+                entry.lockouts = 0;
+                continue;
+            }
+            entry.failures += 1;
+            if entry.failures >= self.config.max_failures {
+                entry.failures = 0;
+                entry.lockouts += 1;
+                let factor = 2u32.saturating_pow(entry.lockouts - 1);
+                let lockout = self
+                    .config
+                    .base_lockout
+                    .saturating_mul(factor)
+                    .min(self.config.max_lockout);
+                entry.locked_until = Some(now + lockout);
+                write_log(&format!(
+                    "RATE LIMIT - {} bloqueado durante {} s (bloqueo nº {})",
+                    key,
+                    lockout.as_secs(),
+                    entry.lockouts
+                ))
+                .ok();
+            }
+        }
+    }
+
+    fn limited_path(path: &str) -> Option<&'static str> {
+        LIMITED_PATHS.into_iter().find(|p| path.ends_with(p))
+    }
+
+    fn client_ip(&self, req: &ServiceRequest) -> String {
+        let forwarded = match &self.config.client_ip {
+            ClientIp::Peer => None,
+            ClientIp::Header(name) => req
+                .headers()
+                .get(name.as_str())
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.rsplit(',').next())
+                .map(|ip| ip.trim().to_string())
+                .filter(|ip| !ip.is_empty()),
+        };
+        forwarded
+            .or_else(|| req.peer_addr().map(|addr| addr.ip().to_string()))
+            .unwrap_or_else(|| "desconocida".to_string())
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for RateLimiter
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Transform = RateLimiterService<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(RateLimiterService {
+            service: Rc::new(service),
+            limiter: self.clone(),
+        })
+    }
+}
+
+pub struct RateLimiterService<S> {
+    service: Rc<S>,
+    limiter: RateLimiter,
+}
+
+impl<S, B> Service<ServiceRequest> for RateLimiterService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> std::task::Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&self, mut req: ServiceRequest) -> Self::Future {
+        let service = Rc::clone(&self.service);
+        let limiter = self.limiter.clone();
+        Box::pin(async move {
+            let path = match RateLimiter::limited_path(req.path()) {
+                Some(path) => path,
+                None => return Ok(service.call(req).await?.map_into_boxed_body()),
+            };
+
+            // Se lee el cuerpo para obtener el correo y se vuelve a inyectar para el handler
+            let body = req.extract::<web::Bytes>().await?;
+            let mail = serde_json::from_slice::<serde_json::Value>(&body)
+                .ok()
+                .and_then(|v| v.get("mail")?.as_str().map(|m| m.trim().to_lowercase()));
+            req.set_payload(Payload::from(body));
+
+            let ip = limiter.client_ip(&req);
+            let mut keys = vec![format!("ip:{}:{}", path, ip)];
+            if let Some(mail) = mail {
+                keys.push(format!("mail:{}:{}", path, mail));
+            }
+
+            if let Some(retry_after) = limiter.check(&keys) {
+                write_log(&format!(
+                    "RATE LIMIT - {} rechazado para {}, reintentar en {} s",
+                    path, ip, retry_after
+                ))
+                .ok();
+                let error = ApiError::too_many_requests(
+                    "Demasiados intentos, inténtelo más tarde",
+                    retry_after,
+                );
+                return Ok(req.into_response(error.error_response()));
+            }
+
+            let res = service.call(req).await?;
+            let status = res.status();
+            // Los errores del servidor no cuentan como intentos fallidos del cliente
+            if status.is_success() {
+                limiter.record(&keys, true);
+            } else if status.is_client_error() && status != StatusCode::TOO_MANY_REQUESTS {
+                limiter.record(&keys, false);
+            }
+            Ok(res.map_into_boxed_body())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> RateLimitConfig {
+        RateLimitConfig {
+            window: Duration::from_secs(60),
+            max_requests: 100,
+            max_failures: 1,
+            base_lockout: Duration::from_secs(10),
+            max_lockout: Duration::from_secs(60),
+            lockout_decay: Duration::from_secs(300),
+            client_ip: ClientIp::Peer,
+        }
+    }
+
+    #[test]
+    fn locked_entries_are_pruned_once_the_lockout_decays() {
+        let limiter = RateLimiter::new(config());
+        let keys = vec!["mail:/users/login:ana@example.com".to_string()];
+        limiter.check(&keys);
+        limiter.record(&keys, false);
+        let mut entries = limiter.entries.lock().unwrap();
+        let start = entries[&keys[0]].window_start;
+
+        // Pasada la ventana y el bloqueo, se sigue recordando hasta que decae
+        prune(
+            &mut entries,
+            &limiter.config,
+            start + Duration::from_secs(120),
+        );
+        assert_eq!(entries.len(), 1);
+        prune(
+            &mut entries,
+            &limiter.config,
+            start + Duration::from_secs(311),
+        );
+        assert!(entries.is_empty());
+    }
+
+    #[test]
+    fn lockouts_stop_growing_after_the_decay() {
+        let limiter = RateLimiter::new(config());
+        let keys = vec!["ip:/users/login:10.0.0.1".to_string()];
+        limiter.check(&keys);
+        limiter.record(&keys, false);
+        let mut entries = limiter.entries.lock().unwrap();
+        let entry = entries.get_mut(&keys[0]).unwrap();
+        assert_eq!(entry.lockouts, 1);
+        let until = entry.locked_until.unwrap();
+        assert!(entry.remembers_lockouts(until, Duration::from_secs(300)));
+        assert!(
+            !entry.remembers_lockouts(until + Duration::from_secs(300), Duration::from_secs(300))
+        );
+    }
+
+    #[test]
+    fn success_only_clears_the_mail_failures() {
+        let mut config = config();
+        config.max_failures = 3;
+        let limiter = RateLimiter::new(config);
+        let keys = vec![
+            "ip:/users/login:10.0.0.1".to_string(),
+            "mail:/users/login:ana@example.com".to_string(),
+        ];
+        limiter.check(&keys);
+        limiter.record(&keys, false);
+        limiter.record(&keys, true);
+        let entries = limiter.entries.lock().unwrap();
+        assert_eq!(entries[&keys[0]].failures, 1);
+        assert_eq!(entries[&keys[1]].failures, 0);
+    }
+}
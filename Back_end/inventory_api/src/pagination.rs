@@ -0,0 +1,332 @@
+//! Paginación por cursor de los listados.
+//!
+//! Los listados aceptan `limit`, `after` (el `nextCursor` de la página anterior), `sort`
+//! (`campo` o `-campo` para orden descendente) y filtros por campo, y responden con
+//! `{data, nextCursor, total}`. El cursor guarda el valor del campo de orden y el `_id` del
+//! último elemento, así que las páginas siguen siendo correctas aunque se inserten o
+//! borren elementos entre peticiones.
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use mongodb::bson::{self, oid::ObjectId, Bson, Document};
+use serde::{Deserialize, Serialize, Serializer};
+use serde_json::json;
+
+use crate::error::ApiError;
+
+pub const DEFAULT_LIMIT: usize = 50;
+pub const MAX_LIMIT: usize = 200;
+
+/// Posición tras la que empieza una página.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Cursor {
+    /// Tipo de elemento, en los listados que mezclan varios (ver `zone_service::children`).
+    #[serde(rename = "k", default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+    /// Valor del campo de orden del último elemento devuelto.
+    #[serde(rename = "v", default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<Bson>,
+    /// `_id` del último elemento devuelto; sin él, se empieza desde el principio.
+    #[serde(rename = "id", default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+}
+
+impl Cursor {
+    pub fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(serde_json::to_vec(self).unwrap_or_default())
+    }
+
+    pub fn decode(value: &str) -> Option<Cursor> {
+        let bytes = URL_SAFE_NO_PAD.decode(value).ok()?;
+        serde_json::from_slice(&bytes).ok()
+    }
+
+    /// Valor de orden e `_id` desde los que continuar, si el cursor los tiene.
+    pub fn position(&self) -> Option<(&Bson, ObjectId)> {
+        match (&self.value, self.id) {
+            (Some(value), Some(id)) => Some((value, id)),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SortKey {
+    pub field: &'static str,
+    pub descending: bool,
+}
+
+#[derive(Debug, Clone)]
+pub enum FieldFilter {
+    /// El texto del campo contiene el valor, sin distinguir mayúsculas.
+    Contains(&'static str, String),
+    /// El campo es igual al valor; si es un array, basta con que lo contenga.
+    Equals(&'static str, Bson),
+}
+
+/// Cómo se interpreta el valor de un filtro de la query.
+#[derive(Debug, Clone, Copy)]
+pub enum FilterKind {
+    Contains,
+    Equals,
+    Id,
+    Bool,
+}
+
+/// Ordenaciones y filtros que admite un listado. La primera ordenación es la de por defecto.
+pub struct ListSpec {
+    /// Nombre en la query y campo del documento.
+    pub sorts: &'static [(&'static str, &'static str)],
+    /// Nombre en la query, campo del documento y tipo de filtro.
+    pub filters: &'static [(&'static str, &'static str, FilterKind)],
+}
+
+#[derive(Debug, Clone)]
+pub struct PageQuery {
+    pub sort: SortKey,
+    pub filters: Vec<FieldFilter>,
+    pub after: Option<Cursor>,
+    pub limit: usize,
+}
+
+impl PageQuery {
+    /// Misma ordenación y filtros, empezando desde `after`.
+    pub fn starting_at(&self, after: Option<Cursor>, limit: usize) -> PageQuery {
+        PageQuery {
+            sort: self.sort.clone(),
+            filters: self.filters.clone(),
+            after,
+            limit,
+        }
+    }
+
+    /// Solo los filtros que son de `fields`, para listados que mezclan varios tipos.
+    pub fn with_filters_on(&self, fields: &[&str]) -> PageQuery {
+        let mut query = self.clone();
+        query.filters.retain(|filter| {
+            let field = match filter {
+                FieldFilter::Contains(field, _) | FieldFilter::Equals(field, _) => field,
+            };
+            fields.contains(field)
+        });
+        query
+    }
+}
+
+/// Lee la paginación, la ordenación y los filtros de la query de un listado.
+pub fn parse(query: &HashMap<String, String>, spec: &ListSpec) -> Result<PageQuery, ApiError> {
+    let limit = match query.get("limit").map(|limit| limit.parse::<usize>()) {
+        None => DEFAULT_LIMIT,
+        Some(Ok(limit)) if (1..=MAX_LIMIT).contains(&limit) => limit,
+        Some(_) => {
+            return Err(
+                ApiError::validation(format!("'limit' debe estar entre 1 y {}", MAX_LIMIT))
+                    .with_details(json!({"field": "limit"})),
+            )
+        }
+    };
+    let after = match query.get("after").map(|after| Cursor::decode(after)) {
+        None => None,
+        Some(Some(cursor)) => Some(cursor),
+        Some(None) => {
+            return Err(
+                ApiError::validation("Cursor inválido").with_details(json!({"field": "after"}))
+            )
+        }
+    };
+    let (name, descending) = match query.get("sort") {
+        Some(sort) => match sort.strip_prefix('-') {
+            Some(name) => (name, true),
+            None => (sort.as_str(), false),
+        },
+        None => (spec.sorts[0].0, false),
+    };
+    let field = match spec.sorts.iter().find(|(param, _)| *param == name) {
+        Some((_, field)) => *field,
+        None => {
+            let allowed: Vec<&str> = spec.sorts.iter().map(|(param, _)| *param).collect();
+            return Err(ApiError::validation(format!(
+                "'sort' debe ser uno de: {}",
+                allowed.join(", ")
+            ))
+            .with_details(json!({"field": "sort"})));
+        }
+    };
+    let mut filters = Vec::new();
+    for (param, field, kind) in spec.filters {
+        let Some(value) = query.get(*param) else {
+            continue;
+        };
+        let filter = match kind {
+            FilterKind::Contains => Some(FieldFilter::Contains(field, value.clone())),
+            FilterKind::Equals => Some(FieldFilter::Equals(field, Bson::String(value.clone()))),
+            FilterKind::Id => ObjectId::parse_str(value)
+                .ok()
+                .map(|id| FieldFilter::Equals(field, Bson::ObjectId(id))),
+            FilterKind::Bool => value
+                .parse::<bool>()
+                .ok()
+                .map(|b| FieldFilter::Equals(field, Bson::Boolean(b))),
+        };
+        match filter {
+            Some(filter) => filters.push(filter),
+            None => {
+                return Err(
+                    ApiError::validation(format!("Valor inválido para '{}'", param))
+                        .with_details(json!({"field": param})),
+                )
+            }
+        }
+    }
+    Ok(PageQuery {
+        sort: SortKey { field, descending },
+        filters,
+        after,
+        limit,
+    })
+}
+
+/// Página de un listado.
+#[derive(Debug, Serialize)]
+pub struct Page<T> {
+    pub data: Vec<T>,
+    #[serde(rename = "nextCursor", serialize_with = "serialize_cursor")]
+    pub next_cursor: Option<Cursor>,
+    pub total: u64,
+}
+
+fn serialize_cursor<S: Serializer>(
+    cursor: &Option<Cursor>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    cursor.as_ref().map(Cursor::encode).serialize(serializer)
+}
+
+impl<T: Serialize> Page<T> {
+    /// Página a partir de hasta `limit + 1` filas ya ordenadas: si sobra una, hay más
+    /// páginas y el cursor apunta a la última que se devuelve.
+    pub fn new(mut rows: Vec<T>, total: u64, query: &PageQuery) -> Self {
+        let next_cursor = if rows.len() > query.limit {
+            rows.truncate(query.limit);
+            rows.last()
+                .and_then(|row| bson::to_document(row).ok())
+                .map(|doc| cursor_for(&doc, query.sort.field))
+        } else {
+            None
+        };
+        Page {
+            data: rows,
+            next_cursor,
+            total,
+        }
+    }
+
+    pub fn map<U>(self, f: impl FnMut(T) -> U) -> Page<U> {
+        Page {
+            data: self.data.into_iter().map(f).collect(),
+            next_cursor: self.next_cursor,
+            total: self.total,
+        }
+    }
+}
+
+fn cursor_for(doc: &Document, field: &str) -> Cursor {
+    Cursor {
+        kind: None,
+        value: Some(doc.get(field).cloned().unwrap_or(Bson::Null)),
+        id: doc.get_object_id("_id").ok(),
+    }
+}
+
+/// Orden entre tipos como el de MongoDB: nulo, números, texto, documentos, arrays,
+/// ObjectId, booleanos y fechas.
+fn type_rank(value: &Bson) -> u8 {
+    match value {
+        Bson::Null | Bson::Undefined => 0,
+        Bson::Int32(_) | Bson::Int64(_) | Bson::Double(_) | Bson::Decimal128(_) => 1,
+        Bson::String(_) | Bson::Symbol(_) => 2,
+        Bson::Document(_) => 3,
+        Bson::Array(_) => 4,
+        Bson::ObjectId(_) => 5,
+        Bson::Boolean(_) => 6,
+        Bson::DateTime(_) => 7,
+        _ => 8,
+    }
+}
+
+fn as_f64(value: &Bson) -> Option<f64> {
+    match value {
+        Bson::Int32(n) => Some(*n as f64),
+        Bson::Int64(n) => Some(*n as f64),
+        Bson::Double(n) => Some(*n),
+        _ => None,
+    }
+}
+
+pub fn compare_bson(a: &Bson, b: &Bson) -> Ordering {
+    match (a, b) {
+        (Bson::String(a), Bson::String(b)) => a.cmp(b),
+        (Bson::ObjectId(a), Bson::ObjectId(b)) => a.bytes().cmp(&b.bytes()),
+        (Bson::Boolean(a), Bson::Boolean(b)) => a.cmp(b),
+        (Bson::DateTime(a), Bson::DateTime(b)) => a.cmp(b),
+        _ => match (as_f64(a), as_f64(b)) {
+            (Some(a), Some(b)) => a.partial_cmp(&b).unwrap_or(Ordering::Equal),
+            _ => type_rank(a).cmp(&type_rank(b)),
+        },
+    }
+}
+
+fn matches_filter(doc: &Document, filter: &FieldFilter) -> bool {
+    match filter {
+        FieldFilter::Contains(field, text) => doc
+            .get_str(field)
+            .is_ok_and(|value| value.to_lowercase().contains(&text.to_lowercase())),
+        FieldFilter::Equals(field, expected) => match doc.get(field) {
+            Some(Bson::Array(values)) => values.contains(expected),
+            Some(value) => value == expected,
+            None => false,
+        },
+    }
+}
+
+/// Orden de dos documentos por el campo de la consulta y, a igualdad, por `_id`.
+fn compare_docs(a: &Document, b: &Document, sort: &SortKey) -> Ordering {
+    let field = |doc: &Document, name: &str| doc.get(name).cloned().unwrap_or(Bson::Null);
+    let order = compare_bson(&field(a, sort.field), &field(b, sort.field))
+        .then_with(|| compare_bson(&field(a, "_id"), &field(b, "_id")));
+    if sort.descending {
+        order.reverse()
+    } else {
+        order
+    }
+}
+
+/// Pagina en memoria una lista completa, con el mismo resultado que la consulta en MongoDB.
+pub fn paginate<T: Serialize>(rows: Vec<T>, query: &PageQuery) -> Page<T> {
+    let mut rows: Vec<(Document, T)> = rows
+        .into_iter()
+        .filter_map(|row| bson::to_document(&row).ok().map(|doc| (doc, row)))
+        .filter(|(doc, _)| {
+            query
+                .filters
+                .iter()
+                .all(|filter| matches_filter(doc, filter))
+        })
+        .collect();
+    let total = rows.len() as u64;
+    rows.sort_by(|(a, _), (b, _)| compare_docs(a, b, &query.sort));
+    if let Some((value, id)) = query.after.as_ref().and_then(Cursor::position) {
+        let mut last = Document::new();
+        last.insert(query.sort.field, value.clone());
+        last.insert("_id", id);
+        rows.retain(|(doc, _)| compare_docs(doc, &last, &query.sort) == Ordering::Greater);
+    }
+    let rows = rows
+        .into_iter()
+        .take(query.limit + 1)
+        .map(|(_, row)| row)
+        .collect();
+    Page::new(rows, total, query)
+}
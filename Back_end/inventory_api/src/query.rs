@@ -0,0 +1,343 @@
+//! Lenguaje de consulta de la búsqueda.
+//!
+//! Una consulta es una lista de cláusulas separadas por espacios que se tienen que cumplir
+//! todas, salvo las palabras sueltas, de las que basta con una:
+//!
+//! - `taladro`: palabra que se busca en el índice de texto.
+//! - `"caja roja"`: frase que tiene que aparecer tal cual.
+//! - `tag:cable`, `zone:garaje`, `property:piso`, `description:hdmi`: filtros por campo.
+//!   El valor puede ir entre comillas (`zone:"sala de estar"`).
+//! - `expires:<2026-12-01`, `purchased:>=2025-01-01`, `expires:2026-01-01..2026-06-30`:
+//!   filtros por fecha (día, en UTC). Sin operador, el día exacto.
+//!
+//! Los filtros por campo solo los tienen los items, así que con ellos no se devuelven
+//! grupos, propiedades ni zonas.
+
+use chrono::NaiveDate;
+use mongodb::bson::DateTime;
+use serde_json::json;
+
+use crate::dates::parse_date;
+use crate::entities::item::Item;
+use crate::error::ApiError;
+use crate::text::fold;
+
+const DAY_MILLIS: i64 = 24 * 60 * 60 * 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateField {
+    Expires,
+    Purchased,
+}
+
+impl DateField {
+    /// Campo del item en la base de datos.
+    pub fn field(self) -> &'static str {
+        match self {
+            DateField::Expires => "expiresAt",
+            DateField::Purchased => "purchasedAt",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Clause {
+    /// Palabra suelta para el índice de texto.
+    Word(String),
+    /// Frase que tiene que aparecer tal cual en el texto indexado.
+    Phrase(String),
+    /// Alguna etiqueta es el valor (sin distinguir mayúsculas ni acentos).
+    Tag(String),
+    /// El nombre de la zona del item contiene el valor. Tampoco aquí ni en los demás
+    /// filtros de texto se distinguen mayúsculas ni acentos.
+    Zone(String),
+    /// El nombre de la propiedad del item contiene el valor.
+    Property(String),
+    /// La descripción contiene el valor.
+    Description(String),
+    /// La fecha está en `[from, to)`; sin uno de los extremos, no hay límite por ese lado.
+    Date {
+        field: DateField,
+        from: Option<DateTime>,
+        to: Option<DateTime>,
+    },
+}
+
+/// Consulta ya analizada y validada.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Query {
+    pub clauses: Vec<Clause>,
+}
+
+impl Query {
+    pub fn words(&self) -> impl Iterator<Item = &str> {
+        self.clauses.iter().filter_map(|clause| match clause {
+            Clause::Word(word) => Some(word.as_str()),
+            _ => None,
+        })
+    }
+
+    pub fn phrases(&self) -> impl Iterator<Item = &str> {
+        self.clauses.iter().filter_map(|clause| match clause {
+            Clause::Phrase(phrase) => Some(phrase.as_str()),
+            _ => None,
+        })
+    }
+
+    /// Cláusulas que no son texto libre.
+    pub fn filters(&self) -> impl Iterator<Item = &Clause> {
+        self.clauses
+            .iter()
+            .filter(|clause| !matches!(clause, Clause::Word(_) | Clause::Phrase(_)))
+    }
+
+    /// Si tiene filtros por campo, que solo encajan con items.
+    pub fn items_only(&self) -> bool {
+        self.filters().next().is_some()
+    }
+
+    /// Palabras y frases, con las frases entre comillas, como las entiende `$text`.
+    pub fn text(&self) -> String {
+        let mut parts: Vec<String> = self.words().map(str::to_string).collect();
+        parts.extend(self.phrases().map(|phrase| format!("\"{}\"", phrase)));
+        parts.join(" ")
+    }
+
+    /// La misma consulta sin las palabras sueltas: solo frases y filtros.
+    pub fn without_words(&self) -> Query {
+        Query {
+            clauses: self
+                .clauses
+                .iter()
+                .filter(|clause| !matches!(clause, Clause::Word(_)))
+                .cloned()
+                .collect(),
+        }
+    }
+
+    /// Comprueba en memoria los filtros por campo (no el texto) sobre un item, su zona
+    /// (si no está directamente en la propiedad) y su propiedad.
+    pub fn matches_item(&self, item: &Item, zone: Option<&str>, property: &str) -> bool {
+        let contains = |value: &str, wanted: &str| fold(value).contains(&fold(wanted));
+        self.filters().all(|clause| match clause {
+            Clause::Tag(tag) => item.tags.iter().flatten().any(|t| fold(t) == fold(tag)),
+            Clause::Zone(name) => zone.is_some_and(|zone| contains(zone, name)),
+            Clause::Property(name) => contains(property, name),
+            Clause::Description(text) => item
+                .description
+                .as_deref()
+                .is_some_and(|description| contains(description, text)),
+            Clause::Date { field, from, to } => {
+                let date = match field {
+                    DateField::Expires => item.expires_at,
+                    DateField::Purchased => item.purchased_at,
+                };
+                date.is_some_and(|date| {
+                    from.is_none_or(|from| date >= from) && to.is_none_or(|to| date < to)
+                })
+            }
+            Clause::Word(_) | Clause::Phrase(_) => true,
+        })
+    }
+}
+
+fn invalid(message: String) -> ApiError {
+    ApiError::validation(message).with_details(json!({"field": "q"}))
+}
+
+/// Separa la consulta en trozos: texto entre comillas, `campo:valor` (con el valor
+/// opcionalmente entre comillas) o palabras.
+fn tokens(input: &str) -> Result<Vec<(Option<String>, String, bool)>, ApiError> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        let Some(&first) = chars.peek() else {
+            break;
+        };
+        let mut field = None;
+        let mut value = String::new();
+        let mut quoted = false;
+        if first != '"' {
+            while let Some(c) = chars.next_if(|c| !c.is_whitespace() && *c != ':') {
+                value.push(c);
+            }
+            if chars.next_if_eq(&':').is_some() {
+                field = Some(std::mem::take(&mut value));
+            }
+        }
+        if chars.next_if_eq(&'"').is_some() {
+            quoted = true;
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some(c) => value.push(c),
+                    None => return Err(invalid("Falta cerrar las comillas".to_string())),
+                }
+            }
+        } else {
+            while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+                value.push(c);
+            }
+        }
+        tokens.push((field, value, quoted));
+    }
+    Ok(tokens)
+}
+
+/// Día en formato `AAAA-MM-DD`, a las 00:00 UTC.
+fn day(value: &str, field: &str) -> Result<DateTime, ApiError> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .ok()
+        .and_then(|_| parse_date(value))
+        .ok_or_else(|| {
+            invalid(format!(
+                "Fecha inválida en '{}': usa el formato AAAA-MM-DD",
+                field
+            ))
+        })
+}
+
+/// Filtro de fecha de `expires:` o `purchased:`, con `<`, `<=`, `>`, `>=`, `=`, un
+/// rango `desde..hasta` (ambos incluidos) o un día suelto.
+fn date_clause(field: DateField, name: &str, value: &str) -> Result<Clause, ApiError> {
+    let next_day = |date: DateTime| DateTime::from_millis(date.timestamp_millis() + DAY_MILLIS);
+    let (from, to) = if let Some((start, end)) = value.split_once("..") {
+        (Some(day(start, name)?), Some(next_day(day(end, name)?)))
+    } else if let Some(date) = value.strip_prefix("<=") {
+        (None, Some(next_day(day(date, name)?)))
+    } else if let Some(date) = value.strip_prefix(">=") {
+        (Some(day(date, name)?), None)
+    } else if let Some(date) = value.strip_prefix('<') {
+        (None, Some(day(date, name)?))
+    } else if let Some(date) = value.strip_prefix('>') {
+        (Some(next_day(day(date, name)?)), None)
+    } else {
+        let date = day(value.strip_prefix('=').unwrap_or(value), name)?;
+        (Some(date), Some(next_day(date)))
+    };
+    if let (Some(from), Some(to)) = (from, to) {
+        if from >= to {
+            return Err(invalid(format!("El rango de '{}' está vacío", name)));
+        }
+    }
+    Ok(Clause::Date { field, from, to })
+}
+
+/// Analiza y valida una consulta.
+pub fn parse(input: &str) -> Result<Query, ApiError> {
+    let mut clauses = Vec::new();
+    for (field, value, quoted) in tokens(input)? {
+        let value = value.trim().to_string();
+        let Some(field) = field else {
+            if quoted {
+                if !value.is_empty() {
+                    clauses.push(Clause::Phrase(value));
+                }
+            } else if !value.is_empty() {
+                clauses.push(Clause::Word(value));
+            }
+            continue;
+        };
+        if value.is_empty() {
+            return Err(invalid(format!("Falta el valor de '{}:'", field)));
+        }
+        let clause = match field.to_lowercase().as_str() {
+            "tag" => Clause::Tag(value),
+            "zone" => Clause::Zone(value),
+            "property" => Clause::Property(value),
+            "description" => Clause::Description(value),
+            "expires" => date_clause(DateField::Expires, &field, &value)?,
+            "purchased" => date_clause(DateField::Purchased, &field, &value)?,
+            _ => {
+                return Err(invalid(format!(
+                    "Campo de búsqueda desconocido: '{}'. Usa tag, zone, property, description, expires o purchased",
+                    field
+                )))
+            }
+        };
+        clauses.push(clause);
+    }
+    Ok(Query { clauses })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day_of(value: &str) -> DateTime {
+        parse_date(value).unwrap()
+    }
+
+    fn dates(input: &str) -> (Option<DateTime>, Option<DateTime>) {
+        match parse(input).unwrap().clauses.as_slice() {
+            [Clause::Date { from, to, .. }] => (*from, *to),
+            other => panic!("{}: {:?}", input, other),
+        }
+    }
+
+    /// Mensaje del error de validación de la consulta.
+    fn error(input: &str) -> String {
+        let error = parse(input).expect_err(input);
+        assert_eq!(error.code(), "validation_error", "{}", input);
+        assert_eq!(error.details(), Some(&json!({"field": "q"})), "{}", input);
+        error.message().to_string()
+    }
+
+    #[test]
+    fn tokens_split_words_phrases_and_quoted_field_values() {
+        let query = parse(r#"  taladro  "caja roja" zone:"sala de estar" TAG:cable "" "#).unwrap();
+        assert_eq!(
+            query.clauses,
+            vec![
+                Clause::Word("taladro".to_string()),
+                Clause::Phrase("caja roja".to_string()),
+                Clause::Zone("sala de estar".to_string()),
+                Clause::Tag("cable".to_string()),
+            ]
+        );
+        assert_eq!(query.text(), r#"taladro "caja roja""#);
+        assert_eq!(query.without_words().clauses, query.clauses[1..].to_vec());
+        assert_eq!(parse("   ").unwrap(), Query::default());
+    }
+
+    #[test]
+    fn date_operators_use_whole_days() {
+        let (first, second) = (day_of("2026-01-01"), day_of("2026-01-02"));
+        assert_eq!(dates("expires:2026-01-01"), (Some(first), Some(second)));
+        assert_eq!(dates("expires:=2026-01-01"), (Some(first), Some(second)));
+        assert_eq!(dates("expires:<2026-01-02"), (None, Some(second)));
+        assert_eq!(dates("expires:<=2026-01-01"), (None, Some(second)));
+        assert_eq!(dates("purchased:>2026-01-01"), (Some(second), None));
+        assert_eq!(dates("purchased:>=2026-01-01"), (Some(first), None));
+        assert_eq!(
+            dates("expires:2026-01-01..2026-01-01"),
+            (Some(first), Some(second))
+        );
+        assert!(matches!(
+            parse("purchased:2026-01-01").unwrap().clauses[0],
+            Clause::Date {
+                field: DateField::Purchased,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn bad_queries_are_rejected() {
+        assert!(error(r#"caja "roja"#).contains("comillas"));
+        assert!(error("color:rojo").contains("'color'"));
+        assert!(error("tag:").contains("'tag:'"));
+        assert!(error(r#"zone:"""#).contains("'zone:'"));
+        for bad in [
+            "expires:mañana",
+            "expires:2026-02-30",
+            "expires:2026-01-01T00:00:00Z",
+            "expires:<",
+            "expires:2026-01-01..",
+        ] {
+            assert!(error(bad).contains("AAAA-MM-DD"), "{}", bad);
+        }
+        assert!(error("expires:2026-02-01..2026-01-01").contains("vacío"));
+    }
+}
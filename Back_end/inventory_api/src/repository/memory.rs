@@ -0,0 +1,1273 @@
+use async_trait::async_trait;
+use mongodb::bson::{self, oid::ObjectId, DateTime};
+use regex::RegexBuilder;
+use serde::{de::DeserializeOwned, Serialize};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use super::{
+    AuditRepo, BulkRepo, CategoryRepo, Changes, GroupRepo, ItemRepo, ItemWrite, LoanRepo,
+    PropertyRepo, QuantityChange, RepoError, RepoResult, SearchQuery, SearchRepo, SearchScope,
+    SessionRepo, StockMovementRepo, TemplateRepo, TrashRepo, TrashSet, UserGroupRepo, UserRepo,
+    ZoneRepo,
+};
+use crate::entities::{
+    audit::{AuditEntity, AuditEvent},
+    category::Category,
+    group::Group,
+    item::Item,
+    loan::{Loan, LoanReturn},
+    property::Property,
+    search::{SearchEntity, SearchMatch},
+    session::Session,
+    stock_movement::StockMovement,
+    template::ItemTemplate,
+    trash::TrashEntry,
+    user::User,
+    user_group::{GroupRole, UserGroup},
+    zone::Zone,
+};
+use crate::pagination::{paginate, Page, PageQuery};
+use crate::text::{self, Matching, DESCRIPTION_WEIGHT, NAME_WEIGHT, TAGS_WEIGHT};
+
+/// Documento almacenado en memoria con su identificador.
+trait Record: Clone + Serialize + DeserializeOwned + Send {
+    fn id(&self) -> Option<ObjectId>;
+    fn set_id(&mut self, id: ObjectId);
+    /// Entrada de la papelera del documento, si está en ella.
+    fn trash_id(&self) -> Option<ObjectId> {
+        None
+    }
+    fn set_trash(&mut self, _trash: Option<(ObjectId, DateTime)>) {}
+}
+
+macro_rules! impl_record {
+    ($($ty:ty),*) => {
+        $(impl Record for $ty {
+            fn id(&self) -> Option<ObjectId> {
+                self.id
+            }
+            fn set_id(&mut self, id: ObjectId) {
+                self.id = Some(id);
+            }
+        })*
+    };
+    (trash: $($ty:ty),*) => {
+        $(impl Record for $ty {
+            fn id(&self) -> Option<ObjectId> {
+                self.id
+            }
+            fn set_id(&mut self, id: ObjectId) {
+                self.id = Some(id);
+            }
+            fn trash_id(&self) -> Option<ObjectId> {
+                self.trash_id
+            }
+            fn set_trash(&mut self, trash: Option<(ObjectId, DateTime)>) {
+                self.trash_id = trash.map(|(id, _)| id);
+                self.deleted_at = trash.map(|(_, at)| at);
+            }
+        })*
+    };
+}
+
+impl_record!(
+    User,
+    UserGroup,
+    StockMovement,
+    Loan,
+    Category,
+    ItemTemplate,
+    Session,
+    AuditEvent,
+    TrashEntry
+);
+impl_record!(trash: Group, Property, Zone, Item);
+
+/// Colección en memoria. Conserva el orden de inserción, como el orden natural de MongoDB.
+/// Los documentos que están en la papelera solo se ven con los métodos de la papelera.
+struct Table<T> {
+    rows: Mutex<Vec<T>>,
+}
+
+impl<T> Default for Table<T> {
+    fn default() -> Self {
+        Self {
+            rows: Mutex::new(Vec::new()),
+        }
+    }
+}
+
+fn live<T: Record>(row: &T) -> bool {
+    row.trash_id().is_none()
+}
+
+impl<T: Record> Table<T> {
+    fn find(&self, filter: impl Fn(&T) -> bool) -> Vec<T> {
+        let rows = self.rows.lock().unwrap();
+        rows.iter()
+            .filter(|row| live(*row) && filter(row))
+            .cloned()
+            .collect()
+    }
+
+    fn find_one(&self, filter: impl Fn(&T) -> bool) -> Option<T> {
+        let rows = self.rows.lock().unwrap();
+        rows.iter().find(|row| live(*row) && filter(row)).cloned()
+    }
+
+    fn find_by_id(&self, id: ObjectId) -> Option<T> {
+        self.find_one(|row| row.id() == Some(id))
+    }
+
+    fn count(&self, filter: impl Fn(&T) -> bool) -> u64 {
+        let rows = self.rows.lock().unwrap();
+        rows.iter().filter(|row| live(*row) && filter(row)).count() as u64
+    }
+
+    /// Como `insertOne`, respeta el `_id` del documento si ya lo trae.
+    fn insert(&self, row: &T) -> ObjectId {
+        let id = row.id().unwrap_or_default();
+        let mut row = row.clone();
+        row.set_id(id);
+        self.rows.lock().unwrap().push(row);
+        id
+    }
+
+    /// Como `update`, pero incluye los documentos que están en la papelera.
+    fn update_all(&self, filter: impl Fn(&T) -> bool, mut update: impl FnMut(&mut T)) -> u64 {
+        let mut rows = self.rows.lock().unwrap();
+        let mut matched = 0;
+        for row in rows.iter_mut().filter(|row| filter(row)) {
+            update(row);
+            matched += 1;
+        }
+        matched
+    }
+
+    /// Aplica `update` a los documentos que cumplen el filtro y devuelve cuántos eran.
+    fn update(&self, filter: impl Fn(&T) -> bool, mut update: impl FnMut(&mut T)) -> u64 {
+        let mut rows = self.rows.lock().unwrap();
+        let mut matched = 0;
+        for row in rows.iter_mut().filter(|row| live(*row) && filter(row)) {
+            update(row);
+            matched += 1;
+        }
+        matched
+    }
+
+    /// Aplica los cambios pasando por BSON, con el mismo resultado que `$set` / `$unset`.
+    fn apply(&self, id: ObjectId, changes: &Changes) -> RepoResult<bool> {
+        let mut rows = self.rows.lock().unwrap();
+        match rows
+            .iter_mut()
+            .find(|row| live(*row) && row.id() == Some(id))
+        {
+            Some(row) => apply_changes(row, changes).map(|_| true),
+            None => Ok(false),
+        }
+    }
+
+    fn delete(&self, mut filter: impl FnMut(&T) -> bool) -> u64 {
+        let mut rows = self.rows.lock().unwrap();
+        let before = rows.len();
+        rows.retain(|row| !(live(row) && filter(row)));
+        (before - rows.len()) as u64
+    }
+
+    fn delete_by_id(&self, id: ObjectId) -> bool {
+        self.delete(|row| row.id() == Some(id)) == 1
+    }
+
+    /// Mueve a la papelera los documentos con esos IDs que no estuvieran ya en ella.
+    fn trash(&self, ids: &[ObjectId], trash: (ObjectId, DateTime)) {
+        let mut rows = self.rows.lock().unwrap();
+        for row in rows.iter_mut() {
+            if live(row) && row.id().is_some_and(|id| ids.contains(&id)) {
+                row.set_trash(Some(trash));
+            }
+        }
+    }
+
+    fn restore(&self, trash_id: ObjectId) {
+        let mut rows = self.rows.lock().unwrap();
+        for row in rows.iter_mut() {
+            if row.trash_id() == Some(trash_id) {
+                row.set_trash(None);
+            }
+        }
+    }
+
+    fn find_trashed(&self, trash_id: ObjectId) -> Vec<T> {
+        let rows = self.rows.lock().unwrap();
+        rows.iter()
+            .filter(|row| row.trash_id() == Some(trash_id))
+            .cloned()
+            .collect()
+    }
+
+    /// Elimina los documentos de la entrada de la papelera y devuelve sus IDs.
+    fn purge(&self, trash_id: ObjectId) -> Vec<ObjectId> {
+        let mut rows = self.rows.lock().unwrap();
+        let ids = rows
+            .iter()
+            .filter(|row| row.trash_id() == Some(trash_id))
+            .filter_map(|row| row.id())
+            .collect();
+        rows.retain(|row| row.trash_id() != Some(trash_id));
+        ids
+    }
+}
+
+fn apply_changes<T: Record>(row: &mut T, changes: &Changes) -> RepoResult<()> {
+    let mut document = bson::to_document(row)?;
+    for (field, value) in changes.set_fields() {
+        document.insert(field, value.clone());
+    }
+    for field in changes.unset_fields() {
+        document.remove(field);
+    }
+    *row = bson::from_document(document)?;
+    Ok(())
+}
+
+/// Los elementos públicos y los privados del propio usuario; todos si no hay `viewer`.
+fn visible_to(owner: Option<ObjectId>, viewer: Option<ObjectId>) -> bool {
+    match (owner, viewer) {
+        (Some(owner), Some(viewer)) => owner == viewer,
+        _ => true,
+    }
+}
+
+#[derive(Default)]
+pub struct MemoryUserRepo {
+    table: Table<User>,
+}
+
+#[async_trait]
+impl UserRepo for MemoryUserRepo {
+    async fn find_all(&self) -> RepoResult<Vec<User>> {
+        Ok(self.table.find(|_| true))
+    }
+
+    async fn find_page(&self, query: &PageQuery) -> RepoResult<Page<User>> {
+        Ok(paginate(self.table.find(|_| true), query))
+    }
+
+    async fn find_by_id(&self, id: ObjectId) -> RepoResult<Option<User>> {
+        Ok(self.table.find_by_id(id))
+    }
+
+    async fn find_by_mail(&self, mail: &str) -> RepoResult<Option<User>> {
+        Ok(self.table.find_one(|user| user.mail == mail))
+    }
+
+    async fn insert(&self, user: &User) -> RepoResult<ObjectId> {
+        Ok(self.table.insert(user))
+    }
+
+    async fn update(&self, id: ObjectId, changes: Changes) -> RepoResult<bool> {
+        self.table.apply(id, &changes)
+    }
+
+    async fn replace_password_hash(
+        &self,
+        id: ObjectId,
+        current: &str,
+        new_hash: &str,
+    ) -> RepoResult<bool> {
+        Ok(self.table.update(
+            |user| user.id == Some(id) && user.password_hash == current,
+            |user| user.password_hash = new_hash.to_string(),
+        ) == 1)
+    }
+
+    async fn mark_verified(&self, id: ObjectId) -> RepoResult<bool> {
+        Ok(self.table.update(
+            |user| user.id == Some(id) && !user.verified,
+            |user| user.verified = true,
+        ) == 1)
+    }
+
+    async fn delete(&self, id: ObjectId) -> RepoResult<bool> {
+        Ok(self.table.delete_by_id(id))
+    }
+}
+
+#[derive(Default)]
+pub struct MemoryGroupRepo {
+    table: Table<Group>,
+}
+
+#[async_trait]
+impl GroupRepo for MemoryGroupRepo {
+    async fn find_all(&self) -> RepoResult<Vec<Group>> {
+        Ok(self.table.find(|_| true))
+    }
+
+    async fn find_page(&self, query: &PageQuery) -> RepoResult<Page<Group>> {
+        Ok(paginate(self.table.find(|_| true), query))
+    }
+
+    async fn find_by_id(&self, id: ObjectId) -> RepoResult<Option<Group>> {
+        Ok(self.table.find_by_id(id))
+    }
+
+    async fn find_by_ids(&self, ids: &[ObjectId]) -> RepoResult<Vec<Group>> {
+        Ok(self
+            .table
+            .find(|g| g.id.is_some_and(|id| ids.contains(&id))))
+    }
+
+    async fn find_by_code(&self, code: &str) -> RepoResult<Option<Group>> {
+        Ok(self.table.find_one(|group| group.group_code == code))
+    }
+
+    async fn insert(&self, group: &Group) -> RepoResult<ObjectId> {
+        Ok(self.table.insert(group))
+    }
+
+    async fn update(&self, id: ObjectId, changes: Changes) -> RepoResult<bool> {
+        self.table.apply(id, &changes)
+    }
+
+    async fn add_user_count(&self, id: ObjectId, delta: i32) -> RepoResult<()> {
+        self.table.update(
+            |group| group.id == Some(id),
+            |group| group.user_count += delta,
+        );
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct MemoryUserGroupRepo {
+    table: Table<UserGroup>,
+}
+
+#[async_trait]
+impl UserGroupRepo for MemoryUserGroupRepo {
+    async fn find(&self, user_id: ObjectId, group_id: ObjectId) -> RepoResult<Option<UserGroup>> {
+        Ok(self
+            .table
+            .find_one(|ug| ug.user_id == user_id && ug.group_id == group_id))
+    }
+
+    async fn find_by_user(&self, user_id: ObjectId) -> RepoResult<Vec<UserGroup>> {
+        Ok(self.table.find(|ug| ug.user_id == user_id))
+    }
+
+    async fn find_by_group(&self, group_id: ObjectId) -> RepoResult<Vec<UserGroup>> {
+        Ok(self.table.find(|ug| ug.group_id == group_id))
+    }
+
+    async fn count_by_group(&self, group_id: ObjectId) -> RepoResult<u64> {
+        Ok(self.table.count(|ug| ug.group_id == group_id))
+    }
+
+    async fn count_owners(&self, group_id: ObjectId) -> RepoResult<u64> {
+        Ok(self
+            .table
+            .count(|ug| ug.group_id == group_id && ug.role == GroupRole::Owner))
+    }
+
+    async fn insert(&self, user_group: &UserGroup) -> RepoResult<ObjectId> {
+        Ok(self.table.insert(user_group))
+    }
+
+    async fn set_role(
+        &self,
+        group_id: ObjectId,
+        user_id: ObjectId,
+        role: GroupRole,
+    ) -> RepoResult<bool> {
+        Ok(self.table.update(
+            |ug| ug.group_id == group_id && ug.user_id == user_id,
+            |ug| ug.role = role,
+        ) > 0)
+    }
+
+    async fn delete(&self, id: ObjectId) -> RepoResult<bool> {
+        Ok(self.table.delete_by_id(id))
+    }
+
+    async fn delete_member(&self, group_id: ObjectId, user_id: ObjectId) -> RepoResult<bool> {
+        let mut deleted = false;
+        // Como `delete_one`, solo se elimina la primera relación que coincide
+        self.table.delete(|ug| {
+            let matches = !deleted && ug.group_id == group_id && ug.user_id == user_id;
+            deleted |= matches;
+            matches
+        });
+        Ok(deleted)
+    }
+}
+
+#[derive(Default)]
+pub struct MemoryPropertyRepo {
+    table: Table<Property>,
+}
+
+#[async_trait]
+impl PropertyRepo for MemoryPropertyRepo {
+    async fn find_by_id(&self, id: ObjectId) -> RepoResult<Option<Property>> {
+        Ok(self.table.find_by_id(id))
+    }
+
+    async fn find_by_ids(&self, ids: &[ObjectId]) -> RepoResult<Vec<Property>> {
+        Ok(self
+            .table
+            .find(|p| p.id.is_some_and(|id| ids.contains(&id))))
+    }
+
+    async fn find_by_group(
+        &self,
+        group_id: ObjectId,
+        viewer: Option<ObjectId>,
+    ) -> RepoResult<Vec<Property>> {
+        Ok(self
+            .table
+            .find(|p| p.group_id == group_id && visible_to(p.user_id, viewer)))
+    }
+
+    async fn find_page_by_group(
+        &self,
+        group_id: ObjectId,
+        viewer: Option<ObjectId>,
+        query: &PageQuery,
+    ) -> RepoResult<Page<Property>> {
+        Ok(paginate(self.find_by_group(group_id, viewer).await?, query))
+    }
+
+    async fn insert(&self, property: &Property) -> RepoResult<ObjectId> {
+        Ok(self.table.insert(property))
+    }
+
+    async fn update(&self, id: ObjectId, changes: Changes) -> RepoResult<bool> {
+        self.table.apply(id, &changes)
+    }
+}
+
+#[derive(Default)]
+pub struct MemoryZoneRepo {
+    table: Table<Zone>,
+}
+
+#[async_trait]
+impl ZoneRepo for MemoryZoneRepo {
+    async fn find_by_id(&self, id: ObjectId) -> RepoResult<Option<Zone>> {
+        Ok(self.table.find_by_id(id))
+    }
+
+    async fn find_by_ids(&self, ids: &[ObjectId]) -> RepoResult<Vec<Zone>> {
+        Ok(self
+            .table
+            .find(|z| z.id.is_some_and(|id| ids.contains(&id))))
+    }
+
+    async fn find_by_parent(
+        &self,
+        parent_id: ObjectId,
+        viewer: Option<ObjectId>,
+    ) -> RepoResult<Vec<Zone>> {
+        Ok(self
+            .table
+            .find(|z| z.parent_zone_id == Some(parent_id) && visible_to(z.user_id, viewer)))
+    }
+
+    async fn find_page_by_parent(
+        &self,
+        parent_id: ObjectId,
+        viewer: Option<ObjectId>,
+        query: &PageQuery,
+    ) -> RepoResult<Page<Zone>> {
+        Ok(paginate(
+            self.find_by_parent(parent_id, viewer).await?,
+            query,
+        ))
+    }
+
+    async fn find_by_property(&self, property_id: ObjectId) -> RepoResult<Vec<Zone>> {
+        Ok(self.table.find(|z| z.property_id == property_id))
+    }
+
+    async fn insert(&self, zone: &Zone) -> RepoResult<ObjectId> {
+        Ok(self.table.insert(zone))
+    }
+
+    async fn update(&self, id: ObjectId, changes: Changes) -> RepoResult<bool> {
+        self.table.apply(id, &changes)
+    }
+
+    async fn set_property(&self, ids: &[ObjectId], property_id: ObjectId) -> RepoResult<u64> {
+        Ok(self.table.update(
+            |zone| zone.id.is_some_and(|id| ids.contains(&id)),
+            |zone| zone.property_id = property_id,
+        ))
+    }
+}
+
+#[derive(Default)]
+pub struct MemoryItemRepo {
+    table: Table<Item>,
+}
+
+#[async_trait]
+impl ItemRepo for MemoryItemRepo {
+    async fn find_by_id(&self, id: ObjectId) -> RepoResult<Option<Item>> {
+        Ok(self.table.find_by_id(id))
+    }
+
+    async fn find_by_zone(&self, zone_id: ObjectId) -> RepoResult<Vec<Item>> {
+        Ok(self.table.find(|item| item.zone_id == zone_id))
+    }
+
+    async fn find_page_by_zone(
+        &self,
+        zone_id: ObjectId,
+        query: &PageQuery,
+    ) -> RepoResult<Page<Item>> {
+        Ok(paginate(self.find_by_zone(zone_id).await?, query))
+    }
+
+    async fn find_expiring(
+        &self,
+        zone_ids: &[ObjectId],
+        before: DateTime,
+    ) -> RepoResult<Vec<Item>> {
+        let mut items = self.table.find(|item| {
+            zone_ids.contains(&item.zone_id) && item.expires_at.is_some_and(|at| at <= before)
+        });
+        items.sort_by_key(|item| item.expires_at);
+        Ok(items)
+    }
+
+    async fn find_low_stock(&self, zone_ids: &[ObjectId]) -> RepoResult<Vec<Item>> {
+        Ok(self.table.find(|item| {
+            zone_ids.contains(&item.zone_id)
+                && item
+                    .min_quantity
+                    .is_some_and(|min| item.quantity.unwrap_or(0.0) < min)
+        }))
+    }
+
+    async fn insert(&self, item: &Item) -> RepoResult<ObjectId> {
+        Ok(self.table.insert(item))
+    }
+
+    async fn update(&self, id: ObjectId, changes: Changes) -> RepoResult<bool> {
+        self.table.apply(id, &changes)
+    }
+
+    async fn find_lent(&self, zone_ids: &[ObjectId]) -> RepoResult<Vec<Item>> {
+        Ok(self
+            .table
+            .find(|item| zone_ids.contains(&item.zone_id) && item.loan_id.is_some()))
+    }
+
+    async fn clear_category(&self, category_id: ObjectId) -> RepoResult<u64> {
+        Ok(self.table.update_all(
+            |item| item.category_id == Some(category_id),
+            |item| item.category_id = None,
+        ))
+    }
+}
+
+#[derive(Default)]
+pub struct MemoryCategoryRepo {
+    table: Table<Category>,
+}
+
+#[async_trait]
+impl CategoryRepo for MemoryCategoryRepo {
+    async fn find_by_id(&self, id: ObjectId) -> RepoResult<Option<Category>> {
+        Ok(self.table.find_by_id(id))
+    }
+
+    async fn find_by_group(&self, group_id: ObjectId) -> RepoResult<Vec<Category>> {
+        let mut categories = self.table.find(|category| category.group_id == group_id);
+        categories.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(categories)
+    }
+
+    async fn insert(&self, category: &Category) -> RepoResult<ObjectId> {
+        Ok(self.table.insert(category))
+    }
+
+    async fn update(&self, id: ObjectId, changes: Changes) -> RepoResult<bool> {
+        self.table.apply(id, &changes)
+    }
+
+    async fn delete(&self, id: ObjectId) -> RepoResult<bool> {
+        Ok(self.table.delete_by_id(id))
+    }
+
+    async fn delete_by_group(&self, group_id: ObjectId) -> RepoResult<u64> {
+        Ok(self.table.delete(|category| category.group_id == group_id))
+    }
+}
+
+#[derive(Default)]
+pub struct MemoryTemplateRepo {
+    table: Table<ItemTemplate>,
+}
+
+#[async_trait]
+impl TemplateRepo for MemoryTemplateRepo {
+    async fn find_by_id(&self, id: ObjectId) -> RepoResult<Option<ItemTemplate>> {
+        Ok(self.table.find_by_id(id))
+    }
+
+    async fn find_by_group(&self, group_id: ObjectId) -> RepoResult<Vec<ItemTemplate>> {
+        let mut templates = self.table.find(|template| template.group_id == group_id);
+        templates.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(templates)
+    }
+
+    async fn insert(&self, template: &ItemTemplate) -> RepoResult<ObjectId> {
+        Ok(self.table.insert(template))
+    }
+
+    async fn delete(&self, id: ObjectId) -> RepoResult<bool> {
+        Ok(self.table.delete_by_id(id))
+    }
+
+    async fn delete_by_group(&self, group_id: ObjectId) -> RepoResult<u64> {
+        Ok(self.table.delete(|template| template.group_id == group_id))
+    }
+
+    async fn clear_category(&self, category_id: ObjectId) -> RepoResult<u64> {
+        Ok(self.table.update(
+            |template| template.category_id == Some(category_id),
+            |template| template.category_id = None,
+        ))
+    }
+}
+
+#[derive(Default)]
+pub struct MemoryLoanRepo {
+    table: Table<Loan>,
+}
+
+#[async_trait]
+impl LoanRepo for MemoryLoanRepo {
+    async fn insert(&self, loan: &Loan) -> RepoResult<ObjectId> {
+        Ok(self.table.insert(loan))
+    }
+
+    async fn find_by_ids(&self, ids: &[ObjectId]) -> RepoResult<Vec<Loan>> {
+        Ok(self
+            .table
+            .find(|loan| loan.id.is_some_and(|id| ids.contains(&id))))
+    }
+
+    async fn find_by_item(&self, item_id: ObjectId) -> RepoResult<Vec<Loan>> {
+        let mut loans = self.table.find(|loan| loan.item_id == item_id);
+        loans.reverse();
+        Ok(loans)
+    }
+}
+
+#[derive(Default)]
+pub struct MemoryStockMovementRepo {
+    table: Table<StockMovement>,
+}
+
+#[async_trait]
+impl StockMovementRepo for MemoryStockMovementRepo {
+    async fn insert(&self, movement: &StockMovement) -> RepoResult<ObjectId> {
+        Ok(self.table.insert(movement))
+    }
+
+    async fn find_by_item(&self, item_id: ObjectId) -> RepoResult<Vec<StockMovement>> {
+        // Orden de inserción invertido: del más reciente al más antiguo
+        let mut movements = self.table.find(|m| m.item_id == item_id);
+        movements.reverse();
+        Ok(movements)
+    }
+
+    async fn delete_by_item(&self, item_id: ObjectId) -> RepoResult<u64> {
+        Ok(self.table.delete(|m| m.item_id == item_id))
+    }
+}
+
+/// Operaciones masivas sobre las tablas de items, movimientos y papelera. Se aplican sobre
+/// una copia con las tablas bloqueadas, y la copia solo sustituye a los datos si se confirma.
+pub struct MemoryBulkRepo {
+    items: Arc<MemoryItemRepo>,
+    movements: Arc<MemoryStockMovementRepo>,
+    loans: Arc<MemoryLoanRepo>,
+    trash: Arc<MemoryTrashRepo>,
+}
+
+impl MemoryBulkRepo {
+    pub fn new(
+        items: Arc<MemoryItemRepo>,
+        movements: Arc<MemoryStockMovementRepo>,
+        loans: Arc<MemoryLoanRepo>,
+        trash: Arc<MemoryTrashRepo>,
+    ) -> Self {
+        Self {
+            items,
+            movements,
+            loans,
+            trash,
+        }
+    }
+}
+
+#[async_trait]
+impl BulkRepo for MemoryBulkRepo {
+    async fn write_items(
+        &self,
+        writes: &[ItemWrite],
+        all_or_nothing: bool,
+    ) -> RepoResult<Vec<bool>> {
+        let mut item_rows = self.items.table.rows.lock().unwrap();
+        let mut movement_rows = self.movements.table.rows.lock().unwrap();
+        let mut entry_rows = self.trash.entries.rows.lock().unwrap();
+        let mut items = item_rows.clone();
+        let mut movements = movement_rows.clone();
+        let mut entries = entry_rows.clone();
+        let mut found = Vec::with_capacity(writes.len());
+        for write in writes {
+            match write {
+                ItemWrite::Insert { item, movement } => {
+                    items.push(item.as_ref().clone());
+                    if let Some(movement) = movement {
+                        let mut movement = movement.clone();
+                        movement.set_id(ObjectId::new());
+                        movements.push(movement);
+                    }
+                    found.push(true);
+                }
+                ItemWrite::Update { id, changes } => {
+                    match items
+                        .iter_mut()
+                        .find(|item| live(*item) && item.id == Some(*id))
+                    {
+                        Some(item) => {
+                            apply_changes(item, changes)?;
+                            found.push(true);
+                        }
+                        None => found.push(false),
+                    }
+                }
+                ItemWrite::Delete { id, entry } => {
+                    match items
+                        .iter_mut()
+                        .find(|item| live(*item) && item.id == Some(*id))
+                    {
+                        Some(item) => {
+                            if let Some(trash_id) = entry.id {
+                                item.set_trash(Some((trash_id, entry.deleted_at)));
+                            }
+                            entries.push(entry.clone());
+                            found.push(true);
+                        }
+                        None => found.push(false),
+                    }
+                }
+            }
+        }
+        if !all_or_nothing || found.iter().all(|f| *f) {
+            *item_rows = items;
+            *movement_rows = movements;
+            *entry_rows = entries;
+        }
+        Ok(found)
+    }
+
+    async fn record_movement(
+        &self,
+        change: QuantityChange,
+        movement: &StockMovement,
+    ) -> RepoResult<Option<StockMovement>> {
+        let mut item_rows = self.items.table.rows.lock().unwrap();
+        let mut movement_rows = self.movements.table.rows.lock().unwrap();
+        let Some(item) = item_rows
+            .iter_mut()
+            .find(|item| live(*item) && item.id == Some(movement.item_id))
+        else {
+            return Ok(None);
+        };
+        let before = item.quantity.unwrap_or(0.0);
+        let (delta, quantity_after) = match change {
+            QuantityChange::Add(delta) => (delta, before + delta),
+            QuantityChange::Set(quantity) => (quantity - before, quantity),
+        };
+        if quantity_after < 0.0 {
+            return Ok(None);
+        }
+        item.quantity = Some(quantity_after);
+        let mut movement = StockMovement {
+            delta,
+            quantity_after,
+            ..movement.clone()
+        };
+        movement.set_id(ObjectId::new());
+        movement_rows.push(movement.clone());
+        Ok(Some(movement))
+    }
+
+    async fn lend(&self, loan: &Loan) -> RepoResult<bool> {
+        let mut item_rows = self.items.table.rows.lock().unwrap();
+        let mut loan_rows = self.loans.table.rows.lock().unwrap();
+        let item = item_rows
+            .iter_mut()
+            .find(|item| live(*item) && item.id == Some(loan.item_id) && item.loan_id.is_none());
+        match item {
+            Some(item) => {
+                item.loan_id = loan.id;
+                loan_rows.push(loan.clone());
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    async fn return_loan(
+        &self,
+        item_id: ObjectId,
+        loan_id: ObjectId,
+        returned: &LoanReturn,
+    ) -> RepoResult<bool> {
+        let mut item_rows = self.items.table.rows.lock().unwrap();
+        let mut loan_rows = self.loans.table.rows.lock().unwrap();
+        let loan = loan_rows
+            .iter_mut()
+            .find(|loan| loan.id == Some(loan_id) && loan.returned.is_none());
+        let item = item_rows
+            .iter_mut()
+            .find(|item| live(*item) && item.id == Some(item_id) && item.loan_id == Some(loan_id));
+        match (loan, item) {
+            (Some(loan), Some(item)) => {
+                loan.returned = Some(returned.clone());
+                item.loan_id = None;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+}
+
+/// Papelera sobre las mismas tablas que los repositorios de cada colección.
+pub struct MemoryTrashRepo {
+    entries: Table<TrashEntry>,
+    groups: Arc<MemoryGroupRepo>,
+    user_groups: Arc<MemoryUserGroupRepo>,
+    properties: Arc<MemoryPropertyRepo>,
+    zones: Arc<MemoryZoneRepo>,
+    items: Arc<MemoryItemRepo>,
+    movements: Arc<MemoryStockMovementRepo>,
+    loans: Arc<MemoryLoanRepo>,
+}
+
+impl MemoryTrashRepo {
+    pub fn new(
+        groups: Arc<MemoryGroupRepo>,
+        user_groups: Arc<MemoryUserGroupRepo>,
+        properties: Arc<MemoryPropertyRepo>,
+        zones: Arc<MemoryZoneRepo>,
+        items: Arc<MemoryItemRepo>,
+        movements: Arc<MemoryStockMovementRepo>,
+        loans: Arc<MemoryLoanRepo>,
+    ) -> Self {
+        Self {
+            entries: Table::default(),
+            groups,
+            user_groups,
+            properties,
+            zones,
+            items,
+            movements,
+            loans,
+        }
+    }
+}
+
+#[async_trait]
+impl TrashRepo for MemoryTrashRepo {
+    async fn trash(&self, entry: &TrashEntry, set: &TrashSet) -> RepoResult<ObjectId> {
+        let id = self.entries.insert(entry);
+        let trash = (id, entry.deleted_at);
+        self.groups.table.trash(&set.groups, trash);
+        self.properties.table.trash(&set.properties, trash);
+        self.zones.table.trash(&set.zones, trash);
+        self.items.table.trash(&set.items, trash);
+        Ok(id)
+    }
+
+    async fn find_by_id(&self, id: ObjectId) -> RepoResult<Option<TrashEntry>> {
+        Ok(self.entries.find_by_id(id))
+    }
+
+    async fn find_by_group(&self, group_id: ObjectId) -> RepoResult<Vec<TrashEntry>> {
+        let mut entries = self.entries.find(|e| e.group_id == group_id);
+        entries.reverse();
+        Ok(entries)
+    }
+
+    async fn find_older_than(&self, before: DateTime) -> RepoResult<Vec<TrashEntry>> {
+        Ok(self.entries.find(|e| e.deleted_at < before))
+    }
+
+    async fn find_items(&self, id: ObjectId) -> RepoResult<Vec<Item>> {
+        Ok(self.items.table.find_trashed(id))
+    }
+
+    async fn restore(&self, id: ObjectId) -> RepoResult<bool> {
+        if !self.entries.delete_by_id(id) {
+            return Ok(false);
+        }
+        self.groups.table.restore(id);
+        self.properties.table.restore(id);
+        self.zones.table.restore(id);
+        self.items.table.restore(id);
+        Ok(true)
+    }
+
+    async fn purge(&self, id: ObjectId) -> RepoResult<()> {
+        let groups = self.groups.table.purge(id);
+        self.user_groups
+            .table
+            .delete(|ug| groups.contains(&ug.group_id));
+        self.properties.table.purge(id);
+        self.zones.table.purge(id);
+        let items = self.items.table.purge(id);
+        self.movements.table.delete(|m| items.contains(&m.item_id));
+        self.loans.table.delete(|l| items.contains(&l.item_id));
+        self.entries.delete_by_id(id);
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct MemorySessionRepo {
+    table: Table<Session>,
+}
+
+fn is_active(session: &Session, now: DateTime) -> bool {
+    session.revoked_at.is_none() && session.expires_at > now
+}
+
+#[async_trait]
+impl SessionRepo for MemorySessionRepo {
+    async fn insert(&self, session: &Session) -> RepoResult<ObjectId> {
+        Ok(self.table.insert(session))
+    }
+
+    async fn find_by_id(&self, id: ObjectId) -> RepoResult<Option<Session>> {
+        Ok(self.table.find_by_id(id))
+    }
+
+    async fn find_active(&self, id: ObjectId, user_id: ObjectId) -> RepoResult<Option<Session>> {
+        let now = DateTime::now();
+        Ok(self
+            .table
+            .find_one(|s| s.id == Some(id) && s.user_id == user_id && is_active(s, now)))
+    }
+
+    async fn find_active_by_user(&self, user_id: ObjectId) -> RepoResult<Vec<Session>> {
+        let now = DateTime::now();
+        let mut sessions = self
+            .table
+            .find(|s| s.user_id == user_id && is_active(s, now));
+        sessions.sort_by_key(|s| std::cmp::Reverse(s.last_used_at));
+        Ok(sessions)
+    }
+
+    async fn rotate(
+        &self,
+        id: ObjectId,
+        current_hash: &str,
+        new_hash: &str,
+        expires_at: DateTime,
+    ) -> RepoResult<bool> {
+        let now = DateTime::now();
+        Ok(self.table.update(
+            |s| s.id == Some(id) && s.refresh_token_hash == current_hash,
+            |s| {
+                s.refresh_token_hash = new_hash.to_string();
+                s.last_used_at = now;
+                s.expires_at = expires_at;
+            },
+        ) == 1)
+    }
+
+    async fn revoke(&self, id: ObjectId) -> RepoResult<()> {
+        let now = DateTime::now();
+        self.table
+            .update(|s| s.id == Some(id), |s| s.revoked_at = Some(now));
+        Ok(())
+    }
+
+    async fn revoke_for_user(&self, id: ObjectId, user_id: ObjectId) -> RepoResult<bool> {
+        let now = DateTime::now();
+        Ok(self.table.update(
+            |s| s.id == Some(id) && s.user_id == user_id && s.revoked_at.is_none(),
+            |s| s.revoked_at = Some(now),
+        ) == 1)
+    }
+
+    async fn revoke_all_for_user(
+        &self,
+        user_id: ObjectId,
+        except: Option<ObjectId>,
+    ) -> RepoResult<u64> {
+        let now = DateTime::now();
+        Ok(self.table.update(
+            |s| {
+                s.user_id == user_id
+                    && s.revoked_at.is_none()
+                    && (except.is_none() || s.id != except)
+            },
+            |s| s.revoked_at = Some(now),
+        ))
+    }
+
+    async fn delete_by_user(&self, user_id: ObjectId) -> RepoResult<()> {
+        self.table.delete(|s| s.user_id == user_id);
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct MemoryAuditRepo {
+    table: Table<AuditEvent>,
+}
+
+impl MemoryAuditRepo {
+    /// Eventos que cumplen el filtro, del más reciente al más antiguo.
+    fn latest(&self, filter: impl Fn(&AuditEvent) -> bool, limit: i64) -> Vec<AuditEvent> {
+        let mut events = self.table.find(filter);
+        events.reverse();
+        events.truncate(limit.max(0) as usize);
+        events
+    }
+}
+
+#[async_trait]
+impl AuditRepo for MemoryAuditRepo {
+    async fn insert(&self, event: &AuditEvent) -> RepoResult<ObjectId> {
+        Ok(self.table.insert(event))
+    }
+
+    async fn find_by_group(&self, group_id: ObjectId, limit: i64) -> RepoResult<Vec<AuditEvent>> {
+        Ok(self.latest(|e| e.group_id == group_id, limit))
+    }
+
+    async fn find_by_entity(
+        &self,
+        entity_type: AuditEntity,
+        entity_id: ObjectId,
+        limit: i64,
+    ) -> RepoResult<Vec<AuditEvent>> {
+        Ok(self.latest(
+            |e| e.entity_type == entity_type && e.entity_id == entity_id,
+            limit,
+        ))
+    }
+}
+
+/// Búsqueda sobre las tablas de grupos, propiedades, zonas e items, con la misma
+/// puntuación que los índices de texto de MongoDB.
+pub struct MemorySearchRepo {
+    groups: Arc<MemoryGroupRepo>,
+    properties: Arc<MemoryPropertyRepo>,
+    zones: Arc<MemoryZoneRepo>,
+    items: Arc<MemoryItemRepo>,
+}
+
+impl MemorySearchRepo {
+    pub fn new(
+        groups: Arc<MemoryGroupRepo>,
+        properties: Arc<MemoryPropertyRepo>,
+        zones: Arc<MemoryZoneRepo>,
+        items: Arc<MemoryItemRepo>,
+    ) -> Self {
+        Self {
+            groups,
+            properties,
+            zones,
+            items,
+        }
+    }
+}
+
+#[async_trait]
+impl SearchRepo for MemorySearchRepo {
+    async fn search(&self, query: &SearchQuery) -> RepoResult<Vec<SearchMatch>> {
+        let terms = text::terms(&query.query.text());
+        let phrases: Vec<String> = query.query.phrases().map(str::to_lowercase).collect();
+        let similar = match query.similar_pattern() {
+            Some(pattern) => Some(
+                RegexBuilder::new(&pattern)
+                    .case_insensitive(true)
+                    .build()
+                    .map_err(|e| RepoError(e.to_string()))?,
+            ),
+            None => None,
+        };
+        // Sin texto todo encaja; con texto, solo lo que tiene todas las frases y algún término
+        let score = |fields: &[(&str, i32)]| -> Option<f64> {
+            let has_phrase = |phrase: &String| {
+                fields
+                    .iter()
+                    .any(|(value, _)| text::fold(value).contains(phrase))
+            };
+            if !phrases.iter().all(has_phrase) {
+                return None;
+            }
+            if let Some(similar) = &similar {
+                if !fields.iter().any(|(value, _)| similar.is_match(value)) {
+                    return None;
+                }
+            }
+            if terms.is_empty() {
+                return Some(0.0);
+            }
+            let score: f64 = fields
+                .iter()
+                .map(|(value, weight)| text::field_score(value, &terms, *weight, Matching::Exact))
+                .sum();
+            (score > 0.0).then_some(score)
+        };
+        let visible = |owner: Option<ObjectId>| match (query.viewer, owner) {
+            (Some(viewer), Some(owner)) => viewer == owner,
+            _ => true,
+        };
+        let in_scope = |zone: &Zone| match &query.scope {
+            Some(SearchScope::Property(property_id)) => zone.property_id == *property_id,
+            Some(SearchScope::Zone { descendants, .. }) => {
+                zone.id.is_some_and(|id| descendants.contains(&id))
+            }
+            _ => true,
+        };
+        let item_zones = query.scope.as_ref().and_then(SearchScope::item_zones);
+
+        let properties: HashMap<ObjectId, Property> = self
+            .properties
+            .table
+            .find(|p| query.group_ids.contains(&p.group_id) && visible(p.user_id))
+            .into_iter()
+            .filter_map(|p| p.id.map(|id| (id, p)))
+            .collect();
+        let zones: HashMap<ObjectId, Zone> = self
+            .zones
+            .table
+            .find(|z| properties.contains_key(&z.property_id) && visible(z.user_id))
+            .into_iter()
+            .filter_map(|z| z.id.map(|id| (id, z)))
+            .collect();
+
+        let mut found = Vec::new();
+        if !query.items_only {
+            let groups = match query.scope {
+                None => self
+                    .groups
+                    .table
+                    .find(|g| g.id.is_some_and(|id| query.group_ids.contains(&id))),
+                Some(_) => Vec::new(),
+            };
+            for group in groups {
+                if let (Some(score), Some(group_id)) =
+                    (score(&[(&group.name, NAME_WEIGHT)]), group.id)
+                {
+                    found.push(SearchMatch {
+                        entity: SearchEntity::Group(group),
+                        score,
+                        group_id,
+                    });
+                }
+            }
+            let with_properties = matches!(query.scope, None | Some(SearchScope::Group(_)));
+            for property in properties.values().filter(|_| with_properties) {
+                if let Some(score) = score(&[(&property.name, NAME_WEIGHT)]) {
+                    found.push(SearchMatch {
+                        entity: SearchEntity::Property(property.clone()),
+                        score,
+                        group_id: property.group_id,
+                    });
+                }
+            }
+            for zone in zones.values().filter(|zone| in_scope(zone)) {
+                if let Some(score) = score(&[(&zone.name, NAME_WEIGHT)]) {
+                    found.push(SearchMatch {
+                        entity: SearchEntity::Zone(zone.clone()),
+                        score,
+                        group_id: properties[&zone.property_id].group_id,
+                    });
+                }
+            }
+        }
+        let items = self.items.table.find(|item| {
+            query
+                .category_id
+                .is_none_or(|category_id| item.category_id == Some(category_id))
+        });
+        for item in items {
+            // Los items pueden estar en una zona o directamente en la propiedad
+            let zone = zones.get(&item.zone_id);
+            let property = match zone {
+                Some(zone) => properties.get(&zone.property_id),
+                None => properties.get(&item.zone_id),
+            };
+            let Some(property) = property else {
+                continue;
+            };
+            let in_scope = match (&query.scope, &item_zones) {
+                (_, Some(item_zones)) => item_zones.contains(&item.zone_id),
+                (Some(SearchScope::Property(property_id)), _) => property.id == Some(*property_id),
+                _ => true,
+            };
+            if !in_scope {
+                continue;
+            }
+            if let Some(groups) = &query.attributes {
+                let matches = groups.get(&property.group_id).is_some_and(|conditions| {
+                    conditions
+                        .iter()
+                        .all(|condition| condition.matches(item.attributes.as_ref()))
+                });
+                if !matches {
+                    continue;
+                }
+            }
+            let zone_name = zone.map(|zone| zone.name.as_str());
+            if !query.query.matches_item(&item, zone_name, &property.name) {
+                continue;
+            }
+            let group_id = property.group_id;
+            // Cada etiqueta puntúa por separado, como los arrays en los índices de MongoDB
+            let mut fields = vec![(item.name.as_str(), NAME_WEIGHT)];
+            for tag in item.tags.iter().flatten() {
+                fields.push((tag.as_str(), TAGS_WEIGHT));
+            }
+            if let Some(description) = &item.description {
+                fields.push((description.as_str(), DESCRIPTION_WEIGHT));
+            }
+            if let Some(score) = score(&fields) {
+                found.push(SearchMatch {
+                    entity: SearchEntity::Item(Box::new(item)),
+                    score,
+                    group_id,
+                });
+            }
+        }
+        found.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(Ordering::Equal));
+        if let Some(limit) = query.limit {
+            found.truncate(limit);
+        }
+        Ok(found)
+    }
+
+    async fn search_page(
+        &self,
+        query: &SearchQuery,
+        page: &PageQuery,
+    ) -> RepoResult<Page<SearchMatch>> {
+        Ok(paginate(self.search(query).await?, page))
+    }
+}
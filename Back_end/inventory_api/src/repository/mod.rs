@@ -0,0 +1,632 @@
+use async_trait::async_trait;
+use mongodb::bson::{oid::ObjectId, Bson, Document};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::config::{DatabaseConfig, StorageBackend};
+use crate::db;
+use crate::entities::{
+    audit::{AuditEntity, AuditEvent},
+    category::Category,
+    group::Group,
+    item::Item,
+    loan::{Loan, LoanReturn},
+    property::Property,
+    search::SearchMatch,
+    session::Session,
+    stock_movement::StockMovement,
+    template::ItemTemplate,
+    trash::TrashEntry,
+    user::User,
+    user_group::{GroupRole, UserGroup},
+    zone::Zone,
+};
+use crate::log::write_log;
+use crate::pagination::{Page, PageQuery};
+use crate::query::Query;
+
+pub mod memory;
+pub mod mongo;
+
+/// Error de acceso a los datos, independiente del almacenamiento.
+#[derive(Debug)]
+pub struct RepoError(pub String);
+
+impl fmt::Display for RepoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl From<mongodb::error::Error> for RepoError {
+    fn from(e: mongodb::error::Error) -> Self {
+        RepoError(e.to_string())
+    }
+}
+
+impl From<mongodb::bson::ser::Error> for RepoError {
+    fn from(e: mongodb::bson::ser::Error) -> Self {
+        RepoError(e.to_string())
+    }
+}
+
+impl From<mongodb::bson::de::Error> for RepoError {
+    fn from(e: mongodb::bson::de::Error) -> Self {
+        RepoError(e.to_string())
+    }
+}
+
+pub type RepoResult<T> = Result<T, RepoError>;
+
+/// Modificación parcial de un documento: campos que se asignan y campos que se eliminan.
+#[derive(Debug, Clone, Default)]
+pub struct Changes {
+    set: Document,
+    unset: Vec<String>,
+}
+
+impl Changes {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set(&mut self, field: &str, value: impl Into<Bson>) {
+        self.unset.retain(|f| f != field);
+        self.set.insert(field, value);
+    }
+
+    pub fn unset(&mut self, field: &str) {
+        self.set.remove(field);
+        if !self.unset.iter().any(|f| f == field) {
+            self.unset.push(field.to_string());
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.set.is_empty() && self.unset.is_empty()
+    }
+
+    /// Indica si el campo se asigna (no si se elimina).
+    pub fn sets(&self, field: &str) -> bool {
+        self.set.contains_key(field)
+    }
+
+    pub fn set_fields(&self) -> &Document {
+        &self.set
+    }
+
+    pub fn unset_fields(&self) -> &[String] {
+        &self.unset
+    }
+}
+
+#[async_trait]
+pub trait UserRepo: Send + Sync {
+    async fn find_all(&self) -> RepoResult<Vec<User>>;
+    async fn find_page(&self, query: &PageQuery) -> RepoResult<Page<User>>;
+    async fn find_by_id(&self, id: ObjectId) -> RepoResult<Option<User>>;
+    async fn find_by_mail(&self, mail: &str) -> RepoResult<Option<User>>;
+    async fn insert(&self, user: &User) -> RepoResult<ObjectId>;
+    /// Devuelve `false` si el usuario no existe.
+    async fn update(&self, id: ObjectId, changes: Changes) -> RepoResult<bool>;
+    /// Cambia el hash de la contraseña solo si sigue siendo `current`.
+    async fn replace_password_hash(
+        &self,
+        id: ObjectId,
+        current: &str,
+        new_hash: &str,
+    ) -> RepoResult<bool>;
+    /// Marca el correo como verificado. Devuelve `false` si ya lo estaba.
+    async fn mark_verified(&self, id: ObjectId) -> RepoResult<bool>;
+    async fn delete(&self, id: ObjectId) -> RepoResult<bool>;
+}
+
+#[async_trait]
+pub trait GroupRepo: Send + Sync {
+    async fn find_all(&self) -> RepoResult<Vec<Group>>;
+    async fn find_page(&self, query: &PageQuery) -> RepoResult<Page<Group>>;
+    async fn find_by_id(&self, id: ObjectId) -> RepoResult<Option<Group>>;
+    async fn find_by_ids(&self, ids: &[ObjectId]) -> RepoResult<Vec<Group>>;
+    async fn find_by_code(&self, code: &str) -> RepoResult<Option<Group>>;
+    async fn insert(&self, group: &Group) -> RepoResult<ObjectId>;
+    async fn update(&self, id: ObjectId, changes: Changes) -> RepoResult<bool>;
+    /// Suma `delta` al contador de usuarios de forma atómica.
+    async fn add_user_count(&self, id: ObjectId, delta: i32) -> RepoResult<()>;
+}
+
+#[async_trait]
+pub trait UserGroupRepo: Send + Sync {
+    async fn find(&self, user_id: ObjectId, group_id: ObjectId) -> RepoResult<Option<UserGroup>>;
+    async fn find_by_user(&self, user_id: ObjectId) -> RepoResult<Vec<UserGroup>>;
+    async fn find_by_group(&self, group_id: ObjectId) -> RepoResult<Vec<UserGroup>>;
+    async fn count_by_group(&self, group_id: ObjectId) -> RepoResult<u64>;
+    /// Número de propietarios del grupo (las relaciones sin rol cuentan como propietarias).
+    async fn count_owners(&self, group_id: ObjectId) -> RepoResult<u64>;
+    async fn insert(&self, user_group: &UserGroup) -> RepoResult<ObjectId>;
+    async fn set_role(
+        &self,
+        group_id: ObjectId,
+        user_id: ObjectId,
+        role: GroupRole,
+    ) -> RepoResult<bool>;
+    async fn delete(&self, id: ObjectId) -> RepoResult<bool>;
+    async fn delete_member(&self, group_id: ObjectId, user_id: ObjectId) -> RepoResult<bool>;
+}
+
+#[async_trait]
+pub trait PropertyRepo: Send + Sync {
+    async fn find_by_id(&self, id: ObjectId) -> RepoResult<Option<Property>>;
+    async fn find_by_ids(&self, ids: &[ObjectId]) -> RepoResult<Vec<Property>>;
+    /// Propiedades del grupo. Con `viewer` se excluyen las privadas de otros usuarios.
+    async fn find_by_group(
+        &self,
+        group_id: ObjectId,
+        viewer: Option<ObjectId>,
+    ) -> RepoResult<Vec<Property>>;
+    /// Como `find_by_group`, por páginas.
+    async fn find_page_by_group(
+        &self,
+        group_id: ObjectId,
+        viewer: Option<ObjectId>,
+        query: &PageQuery,
+    ) -> RepoResult<Page<Property>>;
+    async fn insert(&self, property: &Property) -> RepoResult<ObjectId>;
+    async fn update(&self, id: ObjectId, changes: Changes) -> RepoResult<bool>;
+}
+
+#[async_trait]
+pub trait ZoneRepo: Send + Sync {
+    async fn find_by_id(&self, id: ObjectId) -> RepoResult<Option<Zone>>;
+    async fn find_by_ids(&self, ids: &[ObjectId]) -> RepoResult<Vec<Zone>>;
+    /// Zonas cuyo `parentZoneId` es `parent_id`. Con `viewer` se excluyen las privadas de
+    /// otros usuarios.
+    async fn find_by_parent(
+        &self,
+        parent_id: ObjectId,
+        viewer: Option<ObjectId>,
+    ) -> RepoResult<Vec<Zone>>;
+    /// Como `find_by_parent`, por páginas.
+    async fn find_page_by_parent(
+        &self,
+        parent_id: ObjectId,
+        viewer: Option<ObjectId>,
+        query: &PageQuery,
+    ) -> RepoResult<Page<Zone>>;
+    async fn find_by_property(&self, property_id: ObjectId) -> RepoResult<Vec<Zone>>;
+    async fn insert(&self, zone: &Zone) -> RepoResult<ObjectId>;
+    async fn update(&self, id: ObjectId, changes: Changes) -> RepoResult<bool>;
+    /// Cambia la propiedad de todas esas zonas a la vez.
+    async fn set_property(&self, ids: &[ObjectId], property_id: ObjectId) -> RepoResult<u64>;
+}
+
+#[async_trait]
+pub trait ItemRepo: Send + Sync {
+    async fn find_by_id(&self, id: ObjectId) -> RepoResult<Option<Item>>;
+    async fn find_by_zone(&self, zone_id: ObjectId) -> RepoResult<Vec<Item>>;
+    async fn find_page_by_zone(
+        &self,
+        zone_id: ObjectId,
+        query: &PageQuery,
+    ) -> RepoResult<Page<Item>>;
+    /// Items de esas zonas con `minQuantity` y una cantidad menor (sin cantidad cuenta como 0).
+    async fn find_low_stock(&self, zone_ids: &[ObjectId]) -> RepoResult<Vec<Item>>;
+    /// Items de esas zonas que caducan antes de `before`, del que antes caduca al último.
+    async fn find_expiring(
+        &self,
+        zone_ids: &[ObjectId],
+        before: mongodb::bson::DateTime,
+    ) -> RepoResult<Vec<Item>>;
+    async fn insert(&self, item: &Item) -> RepoResult<ObjectId>;
+    async fn update(&self, id: ObjectId, changes: Changes) -> RepoResult<bool>;
+    /// Items prestados de esas zonas.
+    async fn find_lent(&self, zone_ids: &[ObjectId]) -> RepoResult<Vec<Item>>;
+    /// Quita la categoría a todos los items que la tienen, también a los de la papelera.
+    async fn clear_category(&self, category_id: ObjectId) -> RepoResult<u64>;
+}
+
+#[async_trait]
+pub trait CategoryRepo: Send + Sync {
+    async fn find_by_id(&self, id: ObjectId) -> RepoResult<Option<Category>>;
+    /// Categorías del grupo, ordenadas por nombre.
+    async fn find_by_group(&self, group_id: ObjectId) -> RepoResult<Vec<Category>>;
+    async fn insert(&self, category: &Category) -> RepoResult<ObjectId>;
+    async fn update(&self, id: ObjectId, changes: Changes) -> RepoResult<bool>;
+    async fn delete(&self, id: ObjectId) -> RepoResult<bool>;
+    async fn delete_by_group(&self, group_id: ObjectId) -> RepoResult<u64>;
+}
+
+#[async_trait]
+pub trait TemplateRepo: Send + Sync {
+    async fn find_by_id(&self, id: ObjectId) -> RepoResult<Option<ItemTemplate>>;
+    /// Plantillas del grupo, ordenadas por nombre.
+    async fn find_by_group(&self, group_id: ObjectId) -> RepoResult<Vec<ItemTemplate>>;
+    async fn insert(&self, template: &ItemTemplate) -> RepoResult<ObjectId>;
+    async fn delete(&self, id: ObjectId) -> RepoResult<bool>;
+    async fn delete_by_group(&self, group_id: ObjectId) -> RepoResult<u64>;
+    /// Quita la categoría a las plantillas que la tienen.
+    async fn clear_category(&self, category_id: ObjectId) -> RepoResult<u64>;
+}
+
+#[async_trait]
+pub trait StockMovementRepo: Send + Sync {
+    async fn insert(&self, movement: &StockMovement) -> RepoResult<ObjectId>;
+    /// Movimientos del item, del más reciente al más antiguo.
+    async fn find_by_item(&self, item_id: ObjectId) -> RepoResult<Vec<StockMovement>>;
+    async fn delete_by_item(&self, item_id: ObjectId) -> RepoResult<u64>;
+}
+
+#[async_trait]
+pub trait LoanRepo: Send + Sync {
+    async fn insert(&self, loan: &Loan) -> RepoResult<ObjectId>;
+    async fn find_by_ids(&self, ids: &[ObjectId]) -> RepoResult<Vec<Loan>>;
+    /// Préstamos del item, del más reciente al más antiguo.
+    async fn find_by_item(&self, item_id: ObjectId) -> RepoResult<Vec<Loan>>;
+}
+
+#[async_trait]
+pub trait AuditRepo: Send + Sync {
+    async fn insert(&self, event: &AuditEvent) -> RepoResult<ObjectId>;
+    /// Últimos `limit` eventos del grupo, del más reciente al más antiguo.
+    async fn find_by_group(&self, group_id: ObjectId, limit: i64) -> RepoResult<Vec<AuditEvent>>;
+    /// Últimos `limit` eventos del elemento, del más reciente al más antiguo.
+    async fn find_by_entity(
+        &self,
+        entity_type: AuditEntity,
+        entity_id: ObjectId,
+        limit: i64,
+    ) -> RepoResult<Vec<AuditEvent>>;
+}
+
+/// Elementos que van juntos a la papelera: lo que se borra y todo lo que contiene.
+#[derive(Debug, Clone, Default)]
+pub struct TrashSet {
+    pub groups: Vec<ObjectId>,
+    pub properties: Vec<ObjectId>,
+    pub zones: Vec<ObjectId>,
+    pub items: Vec<ObjectId>,
+}
+
+#[async_trait]
+pub trait TrashRepo: Send + Sync {
+    /// Guarda la entrada y marca con ella los elementos del conjunto que no estuvieran ya en
+    /// la papelera.
+    async fn trash(&self, entry: &TrashEntry, set: &TrashSet) -> RepoResult<ObjectId>;
+    async fn find_by_id(&self, id: ObjectId) -> RepoResult<Option<TrashEntry>>;
+    /// Entradas del grupo, de la más reciente a la más antigua.
+    async fn find_by_group(&self, group_id: ObjectId) -> RepoResult<Vec<TrashEntry>>;
+    /// Entradas borradas antes de `before`.
+    async fn find_older_than(&self, before: mongodb::bson::DateTime)
+        -> RepoResult<Vec<TrashEntry>>;
+    /// Items que se borraron con la entrada.
+    async fn find_items(&self, id: ObjectId) -> RepoResult<Vec<Item>>;
+    /// Saca de la papelera todo lo que se borró con la entrada y elimina la entrada.
+    /// Devuelve `false` si no existe.
+    async fn restore(&self, id: ObjectId) -> RepoResult<bool>;
+    /// Elimina definitivamente la entrada y lo que se borró con ella, junto con los
+    /// movimientos de stock y préstamos de sus items y los miembros de sus grupos.
+    async fn purge(&self, id: ObjectId) -> RepoResult<()>;
+}
+
+/// Rama a la que se limita la búsqueda. El elemento que la encabeza no se devuelve.
+#[derive(Debug, Clone)]
+pub enum SearchScope {
+    /// Propiedades, zonas e items del grupo, que tiene que ser el único de `group_ids`.
+    Group(ObjectId),
+    /// Zonas e items de la propiedad.
+    Property(ObjectId),
+    /// Subzonas de la zona, a cualquier profundidad (`descendants`), y los items de todas
+    /// ellas y de la propia zona.
+    Zone {
+        zone_id: ObjectId,
+        descendants: Vec<ObjectId>,
+    },
+}
+
+impl SearchScope {
+    /// Zonas cuyos items entran en la búsqueda.
+    pub fn item_zones(&self) -> Option<Vec<ObjectId>> {
+        match self {
+            SearchScope::Zone {
+                zone_id,
+                descendants,
+            } => {
+                let mut zones = descendants.clone();
+                zones.push(*zone_id);
+                Some(zones)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Condición sobre un atributo de los items, con el valor ya en el tipo que tiene el
+/// atributo en su grupo.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AttributeCondition {
+    /// Igual al valor; los textos, sin distinguir mayúsculas.
+    Equals(String, Bson),
+    /// Mayor o igual que el valor (números y fechas).
+    AtLeast(String, Bson),
+    /// Menor o igual que el valor (números y fechas).
+    AtMost(String, Bson),
+}
+
+impl AttributeCondition {
+    pub fn name(&self) -> &str {
+        match self {
+            AttributeCondition::Equals(name, _)
+            | AttributeCondition::AtLeast(name, _)
+            | AttributeCondition::AtMost(name, _) => name,
+        }
+    }
+
+    /// Comprueba la condición en memoria, con el mismo resultado que en MongoDB.
+    pub fn matches(&self, attributes: Option<&Document>) -> bool {
+        let Some(value) = attributes.and_then(|values| values.get(self.name())) else {
+            return false;
+        };
+        match (self, value) {
+            (AttributeCondition::Equals(_, Bson::String(b)), Bson::String(a)) => {
+                a.to_lowercase() == b.to_lowercase()
+            }
+            (AttributeCondition::Equals(_, b), a) => a == b,
+            (AttributeCondition::AtLeast(_, Bson::Double(b)), Bson::Double(a)) => a >= b,
+            (AttributeCondition::AtMost(_, Bson::Double(b)), Bson::Double(a)) => a <= b,
+            (AttributeCondition::AtLeast(_, Bson::String(b)), Bson::String(a)) => a >= b,
+            (AttributeCondition::AtMost(_, Bson::String(b)), Bson::String(a)) => a <= b,
+            _ => false,
+        }
+    }
+}
+
+/// Qué se busca y en qué parte de los datos.
+#[derive(Debug, Clone, Default)]
+pub struct SearchQuery {
+    /// Grupos en los que se busca.
+    pub group_ids: Vec<ObjectId>,
+    /// Usuario del que se ven las propiedades y zonas privadas; sin él se ven todas.
+    pub viewer: Option<ObjectId>,
+    /// Consulta del usuario. Sin palabras ni frases, encaja todo lo que hay en los grupos.
+    pub query: Query,
+    pub category_id: Option<ObjectId>,
+    /// Solo se buscan items, para los filtros que solo tienen ellos.
+    pub items_only: bool,
+    pub scope: Option<SearchScope>,
+    /// Con filtros de atributos, las condiciones de cada grupo: solo se devuelven los items
+    /// de los grupos que están y que las cumplen todas.
+    pub attributes: Option<HashMap<ObjectId, Vec<AttributeCondition>>>,
+    /// Términos con erratas: solo se devuelve lo que tiene en algún campo de texto algo
+    /// parecido a alguno de ellos (ver [`crate::text::typo_pattern`]).
+    pub similar_to: Vec<String>,
+    /// Máximo de resultados que se devuelven, los más relevantes.
+    pub limit: Option<usize>,
+}
+
+impl SearchQuery {
+    /// Expresión regular que tiene que encontrarse en algún campo de texto, si se buscan
+    /// términos con erratas.
+    pub fn similar_pattern(&self) -> Option<String> {
+        if self.similar_to.is_empty() {
+            return None;
+        }
+        let patterns: Vec<String> = self
+            .similar_to
+            .iter()
+            .map(|term| crate::text::typo_pattern(term))
+            .collect();
+        Some(patterns.join("|"))
+    }
+}
+
+#[async_trait]
+pub trait SearchRepo: Send + Sync {
+    /// Grupos, propiedades, zonas e items visibles que contienen algún término del texto y
+    /// cumplen los filtros de la consulta, de más a menos relevantes.
+    async fn search(&self, query: &SearchQuery) -> RepoResult<Vec<SearchMatch>>;
+    /// Como `search`, pero solo la página pedida, con el orden y los filtros de `page` sobre
+    /// los campos de [`SearchMatch`].
+    async fn search_page(
+        &self,
+        query: &SearchQuery,
+        page: &PageQuery,
+    ) -> RepoResult<Page<SearchMatch>>;
+}
+
+/// Escritura ya validada de una operación masiva sobre items.
+#[derive(Debug, Clone)]
+pub enum ItemWrite {
+    /// El item lleva ya su `_id`; `movement` es, si lo hay, el de su cantidad inicial.
+    Insert {
+        item: Box<Item>,
+        movement: Option<StockMovement>,
+    },
+    Update {
+        id: ObjectId,
+        changes: Changes,
+    },
+    /// Mueve el item a la papelera con esa entrada (que ya lleva su `_id`).
+    Delete {
+        id: ObjectId,
+        entry: TrashEntry,
+    },
+}
+
+/// Cambio de cantidad de un movimiento de stock.
+#[derive(Debug, Clone, Copy)]
+pub enum QuantityChange {
+    /// Suma (o resta, si es negativo) sin dejar la cantidad por debajo de cero.
+    Add(f64),
+    /// Fija la cantidad total.
+    Set(f64),
+}
+
+/// Escrituras sobre items que afectan a más de una colección y se aplican juntas.
+#[async_trait]
+pub trait BulkRepo: Send + Sync {
+    /// Ejecuta las escrituras en una sola transacción y devuelve, para cada una, si
+    /// encontró su item (las inserciones siempre). Con `all_or_nothing`, si alguna no lo
+    /// encuentra no se aplica ninguna.
+    async fn write_items(
+        &self,
+        writes: &[ItemWrite],
+        all_or_nothing: bool,
+    ) -> RepoResult<Vec<bool>>;
+    /// Cambia la cantidad del item de `movement` y guarda el movimiento en la misma
+    /// transacción, con `delta` y `quantityAfter` según el resultado. Devuelve `None` (sin
+    /// escribir nada) si el item no existe o no tiene cantidad suficiente.
+    async fn record_movement(
+        &self,
+        change: QuantityChange,
+        movement: &StockMovement,
+    ) -> RepoResult<Option<StockMovement>>;
+    /// Marca el item como prestado y guarda el préstamo (que ya lleva su `_id`) en la misma
+    /// transacción. Devuelve `false` (sin escribir nada) si el item no existe o ya está
+    /// prestado.
+    async fn lend(&self, loan: &Loan) -> RepoResult<bool>;
+    /// Cierra el préstamo y libera el item en la misma transacción. Devuelve `false` (sin
+    /// escribir nada) si el préstamo ya estaba cerrado o el item no lo tiene.
+    async fn return_loan(
+        &self,
+        item_id: ObjectId,
+        loan_id: ObjectId,
+        returned: &LoanReturn,
+    ) -> RepoResult<bool>;
+}
+
+#[async_trait]
+pub trait SessionRepo: Send + Sync {
+    async fn insert(&self, session: &Session) -> RepoResult<ObjectId>;
+    async fn find_by_id(&self, id: ObjectId) -> RepoResult<Option<Session>>;
+    /// Sesión no revocada ni caducada del usuario.
+    async fn find_active(&self, id: ObjectId, user_id: ObjectId) -> RepoResult<Option<Session>>;
+    /// Sesiones activas del usuario, de la más a la menos usada recientemente.
+    async fn find_active_by_user(&self, user_id: ObjectId) -> RepoResult<Vec<Session>>;
+    /// Sustituye el hash del refresh token solo si sigue siendo `current_hash`.
+    async fn rotate(
+        &self,
+        id: ObjectId,
+        current_hash: &str,
+        new_hash: &str,
+        expires_at: mongodb::bson::DateTime,
+    ) -> RepoResult<bool>;
+    async fn revoke(&self, id: ObjectId) -> RepoResult<()>;
+    /// Revoca la sesión si pertenece al usuario y sigue activa.
+    async fn revoke_for_user(&self, id: ObjectId, user_id: ObjectId) -> RepoResult<bool>;
+    /// Revoca todas las sesiones activas del usuario, salvo `except`.
+    async fn revoke_all_for_user(
+        &self,
+        user_id: ObjectId,
+        except: Option<ObjectId>,
+    ) -> RepoResult<u64>;
+    async fn delete_by_user(&self, user_id: ObjectId) -> RepoResult<()>;
+}
+
+/// Repositorios de la aplicación. Se registra como `web::Data<Repositories>` y es lo único
+/// que los handlers y servicios usan para acceder a los datos.
+#[derive(Clone)]
+pub struct Repositories {
+    pub users: Arc<dyn UserRepo>,
+    pub groups: Arc<dyn GroupRepo>,
+    pub user_groups: Arc<dyn UserGroupRepo>,
+    pub properties: Arc<dyn PropertyRepo>,
+    pub zones: Arc<dyn ZoneRepo>,
+    pub items: Arc<dyn ItemRepo>,
+    pub stock_movements: Arc<dyn StockMovementRepo>,
+    pub loans: Arc<dyn LoanRepo>,
+    pub categories: Arc<dyn CategoryRepo>,
+    pub templates: Arc<dyn TemplateRepo>,
+    pub sessions: Arc<dyn SessionRepo>,
+    pub audit: Arc<dyn AuditRepo>,
+    pub trash: Arc<dyn TrashRepo>,
+    pub bulk: Arc<dyn BulkRepo>,
+    pub search: Arc<dyn SearchRepo>,
+}
+
+impl Repositories {
+    pub fn mongo(db: &mongodb::Database) -> Self {
+        Self {
+            users: Arc::new(mongo::MongoUserRepo::new(db)),
+            groups: Arc::new(mongo::MongoGroupRepo::new(db)),
+            user_groups: Arc::new(mongo::MongoUserGroupRepo::new(db)),
+            properties: Arc::new(mongo::MongoPropertyRepo::new(db)),
+            zones: Arc::new(mongo::MongoZoneRepo::new(db)),
+            items: Arc::new(mongo::MongoItemRepo::new(db)),
+            stock_movements: Arc::new(mongo::MongoStockMovementRepo::new(db)),
+            loans: Arc::new(mongo::MongoLoanRepo::new(db)),
+            categories: Arc::new(mongo::MongoCategoryRepo::new(db)),
+            templates: Arc::new(mongo::MongoTemplateRepo::new(db)),
+            sessions: Arc::new(mongo::MongoSessionRepo::new(db)),
+            audit: Arc::new(mongo::MongoAuditRepo::new(db)),
+            trash: Arc::new(mongo::MongoTrashRepo::new(db)),
+            bulk: Arc::new(mongo::MongoBulkRepo::new(db)),
+            search: Arc::new(mongo::MongoSearchRepo::new(db)),
+        }
+    }
+
+    /// Repositorios en memoria, vacíos. Los datos se pierden al parar el servidor.
+    pub fn in_memory() -> Self {
+        // La papelera, las operaciones masivas y la búsqueda trabajan sobre las mismas tablas
+        let groups = Arc::new(memory::MemoryGroupRepo::default());
+        let user_groups = Arc::new(memory::MemoryUserGroupRepo::default());
+        let properties = Arc::new(memory::MemoryPropertyRepo::default());
+        let zones = Arc::new(memory::MemoryZoneRepo::default());
+        let items = Arc::new(memory::MemoryItemRepo::default());
+        let stock_movements = Arc::new(memory::MemoryStockMovementRepo::default());
+        let loans = Arc::new(memory::MemoryLoanRepo::default());
+        let trash = Arc::new(memory::MemoryTrashRepo::new(
+            groups.clone(),
+            user_groups.clone(),
+            properties.clone(),
+            zones.clone(),
+            items.clone(),
+            stock_movements.clone(),
+            loans.clone(),
+        ));
+        let search = Arc::new(memory::MemorySearchRepo::new(
+            groups.clone(),
+            properties.clone(),
+            zones.clone(),
+            items.clone(),
+        ));
+        Self {
+            users: Arc::new(memory::MemoryUserRepo::default()),
+            groups,
+            user_groups,
+            properties,
+            zones,
+            items: items.clone(),
+            stock_movements: stock_movements.clone(),
+            loans: loans.clone(),
+            categories: Arc::new(memory::MemoryCategoryRepo::default()),
+            templates: Arc::new(memory::MemoryTemplateRepo::default()),
+            sessions: Arc::new(memory::MemorySessionRepo::default()),
+            audit: Arc::new(memory::MemoryAuditRepo::default()),
+            trash: trash.clone(),
+            bulk: Arc::new(memory::MemoryBulkRepo::new(
+                items,
+                stock_movements,
+                loans,
+                trash,
+            )),
+            search,
+        }
+    }
+}
+
+/// Crea los repositorios del backend configurado (`mongo`, por defecto, o `memory`).
+pub async fn repositories_from_config(config: &DatabaseConfig) -> RepoResult<Repositories> {
+    match config.backend {
+        StorageBackend::Memory => {
+            write_log("[START] Datos en memoria (no se conservan al parar)").ok();
+            Ok(Repositories::in_memory())
+        }
+        StorageBackend::Mongo => {
+            let database = db::init_db(config).await?;
+            mongo::check_transactions(&database).await?;
+            mongo::ensure_indexes(&database).await?;
+            write_log("[START] Base de datos inicializada correctamente").ok();
+            Ok(Repositories::mongo(&database))
+        }
+    }
+}
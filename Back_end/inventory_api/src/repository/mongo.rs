@@ -0,0 +1,1682 @@
+use async_trait::async_trait;
+use futures_util::stream::TryStreamExt;
+use mongodb::{
+    bson::{self, doc, oid::ObjectId, DateTime, Document},
+    options::{IndexOptions, ReturnDocument},
+    Client, Collection, Database, IndexModel,
+};
+use serde::{de::DeserializeOwned, Serialize};
+
+use super::{
+    AttributeCondition, AuditRepo, BulkRepo, CategoryRepo, Changes, GroupRepo, ItemRepo, ItemWrite,
+    LoanRepo, PropertyRepo, QuantityChange, RepoError, RepoResult, SearchQuery, SearchRepo,
+    SearchScope, SessionRepo, StockMovementRepo, TemplateRepo, TrashRepo, TrashSet, UserGroupRepo,
+    UserRepo, ZoneRepo,
+};
+use crate::entities::{
+    audit::{AuditEntity, AuditEvent},
+    category::Category,
+    group::Group,
+    item::Item,
+    loan::{Loan, LoanReturn},
+    property::Property,
+    search::{SearchEntity, SearchMatch},
+    session::Session,
+    stock_movement::StockMovement,
+    template::ItemTemplate,
+    trash::TrashEntry,
+    user::User,
+    user_group::{GroupRole, UserGroup},
+    zone::Zone,
+};
+use crate::pagination::{Cursor, FieldFilter, Page, PageQuery};
+use crate::query::{Clause, Query};
+use crate::text::{accent_insensitive_pattern, DESCRIPTION_WEIGHT, NAME_WEIGHT, TAGS_WEIGHT};
+
+/// Documento de actualización (`$set` / `$unset`) equivalente a los cambios.
+fn update_doc(changes: &Changes) -> Document {
+    let mut update = Document::new();
+    if !changes.set_fields().is_empty() {
+        update.insert("$set", changes.set_fields().clone());
+    }
+    if !changes.unset_fields().is_empty() {
+        let unset: Document = changes
+            .unset_fields()
+            .iter()
+            .map(|field| (field.clone(), "".into()))
+            .collect();
+        update.insert("$unset", unset);
+    }
+    update
+}
+
+fn inserted_id(result: mongodb::results::InsertOneResult) -> RepoResult<ObjectId> {
+    result
+        .inserted_id
+        .as_object_id()
+        .ok_or_else(|| RepoError("El documento insertado no tiene ObjectId".to_string()))
+}
+
+/// Filtro que excluye los elementos privados de otros usuarios.
+fn visible_to(mut filter: Document, viewer: Option<ObjectId>) -> Document {
+    if let Some(viewer) = viewer {
+        filter.insert(
+            "$or",
+            vec![doc! {"userId": {"$exists": false}}, doc! {"userId": viewer}],
+        );
+    }
+    filter
+}
+
+/// Filtro que excluye los elementos que están en la papelera.
+fn live(mut filter: Document) -> Document {
+    filter.insert("deletedAt", doc! {"$exists": false});
+    filter
+}
+
+async fn update_where<T: Send + Sync>(
+    collection: &Collection<T>,
+    filter: Document,
+    changes: Changes,
+) -> RepoResult<bool> {
+    if changes.is_empty() {
+        return Ok(collection.count_documents(filter).await? > 0);
+    }
+    let result = collection.update_one(filter, update_doc(&changes)).await?;
+    Ok(result.matched_count == 1)
+}
+
+async fn update_by_id<T: Send + Sync>(
+    collection: &Collection<T>,
+    id: ObjectId,
+    changes: Changes,
+) -> RepoResult<bool> {
+    update_where(collection, doc! {"_id": id}, changes).await
+}
+
+/// Página de los documentos que cumplen `filter` y los filtros de la consulta. Solo se
+/// leen `limit + 1` documentos, además de contar el total.
+async fn find_page<T>(
+    collection: &Collection<T>,
+    filter: Document,
+    query: &PageQuery,
+) -> RepoResult<Page<T>>
+where
+    T: DeserializeOwned + Serialize + Send + Sync,
+{
+    let mut conditions = vec![filter];
+    for filter in &query.filters {
+        conditions.push(match filter {
+            FieldFilter::Contains(field, text) => {
+                doc! {*field: {"$regex": regex::escape(text), "$options": "i"}}
+            }
+            FieldFilter::Equals(field, value) => doc! {*field: value.clone()},
+        });
+    }
+    let total = collection
+        .count_documents(doc! {"$and": conditions.clone()})
+        .await?;
+    let (field, direction, after) = match query.sort.descending {
+        true => (query.sort.field, -1, "$lt"),
+        false => (query.sort.field, 1, "$gt"),
+    };
+    if let Some((value, id)) = query.after.as_ref().and_then(Cursor::position) {
+        conditions.push(doc! {"$or": [
+            {field: {after: value.clone()}},
+            {field: value.clone(), "_id": {after: id}},
+        ]});
+    }
+    let rows = collection
+        .find(doc! {"$and": conditions})
+        .sort(doc! {field: direction, "_id": direction})
+        .limit(query.limit as i64 + 1)
+        .await?
+        .try_collect()
+        .await?;
+    Ok(Page::new(rows, total, query))
+}
+
+async fn delete_by_id<T: Send + Sync>(
+    collection: &Collection<T>,
+    id: ObjectId,
+) -> RepoResult<bool> {
+    let result = collection.delete_one(doc! {"_id": id}).await?;
+    Ok(result.deleted_count == 1)
+}
+
+pub struct MongoUserRepo {
+    collection: Collection<User>,
+}
+
+impl MongoUserRepo {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            collection: db.collection("users"),
+        }
+    }
+}
+
+#[async_trait]
+impl UserRepo for MongoUserRepo {
+    async fn find_all(&self) -> RepoResult<Vec<User>> {
+        Ok(self.collection.find(doc! {}).await?.try_collect().await?)
+    }
+
+    async fn find_page(&self, query: &PageQuery) -> RepoResult<Page<User>> {
+        find_page(&self.collection, doc! {}, query).await
+    }
+
+    async fn find_by_id(&self, id: ObjectId) -> RepoResult<Option<User>> {
+        Ok(self.collection.find_one(doc! {"_id": id}).await?)
+    }
+
+    async fn find_by_mail(&self, mail: &str) -> RepoResult<Option<User>> {
+        Ok(self.collection.find_one(doc! {"mail": mail}).await?)
+    }
+
+    async fn insert(&self, user: &User) -> RepoResult<ObjectId> {
+        inserted_id(self.collection.insert_one(user).await?)
+    }
+
+    async fn update(&self, id: ObjectId, changes: Changes) -> RepoResult<bool> {
+        update_by_id(&self.collection, id, changes).await
+    }
+
+    async fn replace_password_hash(
+        &self,
+        id: ObjectId,
+        current: &str,
+        new_hash: &str,
+    ) -> RepoResult<bool> {
+        let result = self
+            .collection
+            .update_one(
+                doc! {"_id": id, "passwordHash": current},
+                doc! {"$set": {"passwordHash": new_hash}},
+            )
+            .await?;
+        Ok(result.modified_count == 1)
+    }
+
+    async fn mark_verified(&self, id: ObjectId) -> RepoResult<bool> {
+        let result = self
+            .collection
+            .update_one(
+                doc! {"_id": id, "verified": {"$ne": true}},
+                doc! {"$set": {"verified": true}},
+            )
+            .await?;
+        Ok(result.modified_count == 1)
+    }
+
+    async fn delete(&self, id: ObjectId) -> RepoResult<bool> {
+        delete_by_id(&self.collection, id).await
+    }
+}
+
+pub struct MongoGroupRepo {
+    collection: Collection<Group>,
+}
+
+impl MongoGroupRepo {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            collection: db.collection("groups"),
+        }
+    }
+}
+
+#[async_trait]
+impl GroupRepo for MongoGroupRepo {
+    async fn find_all(&self) -> RepoResult<Vec<Group>> {
+        Ok(self
+            .collection
+            .find(live(doc! {}))
+            .await?
+            .try_collect()
+            .await?)
+    }
+
+    async fn find_page(&self, query: &PageQuery) -> RepoResult<Page<Group>> {
+        find_page(&self.collection, live(doc! {}), query).await
+    }
+
+    async fn find_by_id(&self, id: ObjectId) -> RepoResult<Option<Group>> {
+        Ok(self.collection.find_one(live(doc! {"_id": id})).await?)
+    }
+
+    async fn find_by_ids(&self, ids: &[ObjectId]) -> RepoResult<Vec<Group>> {
+        let filter = live(doc! {"_id": {"$in": ids}});
+        Ok(self.collection.find(filter).await?.try_collect().await?)
+    }
+
+    async fn find_by_code(&self, code: &str) -> RepoResult<Option<Group>> {
+        Ok(self
+            .collection
+            .find_one(live(doc! {"groupCode": code}))
+            .await?)
+    }
+
+    async fn insert(&self, group: &Group) -> RepoResult<ObjectId> {
+        inserted_id(self.collection.insert_one(group).await?)
+    }
+
+    async fn update(&self, id: ObjectId, changes: Changes) -> RepoResult<bool> {
+        update_where(&self.collection, live(doc! {"_id": id}), changes).await
+    }
+
+    async fn add_user_count(&self, id: ObjectId, delta: i32) -> RepoResult<()> {
+        self.collection
+            .update_one(live(doc! {"_id": id}), doc! {"$inc": {"userCount": delta}})
+            .await?;
+        Ok(())
+    }
+}
+
+pub struct MongoUserGroupRepo {
+    collection: Collection<UserGroup>,
+}
+
+impl MongoUserGroupRepo {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            collection: db.collection("userGroup"),
+        }
+    }
+}
+
+#[async_trait]
+impl UserGroupRepo for MongoUserGroupRepo {
+    async fn find(&self, user_id: ObjectId, group_id: ObjectId) -> RepoResult<Option<UserGroup>> {
+        Ok(self
+            .collection
+            .find_one(doc! {"userId": user_id, "groupId": group_id})
+            .await?)
+    }
+
+    async fn find_by_user(&self, user_id: ObjectId) -> RepoResult<Vec<UserGroup>> {
+        Ok(self
+            .collection
+            .find(doc! {"userId": user_id})
+            .await?
+            .try_collect()
+            .await?)
+    }
+
+    async fn find_by_group(&self, group_id: ObjectId) -> RepoResult<Vec<UserGroup>> {
+        Ok(self
+            .collection
+            .find(doc! {"groupId": group_id})
+            .await?
+            .try_collect()
+            .await?)
+    }
+
+    async fn count_by_group(&self, group_id: ObjectId) -> RepoResult<u64> {
+        Ok(self
+            .collection
+            .count_documents(doc! {"groupId": group_id})
+            .await?)
+    }
+
+    async fn count_owners(&self, group_id: ObjectId) -> RepoResult<u64> {
+        Ok(self
+            .collection
+            .count_documents(doc! {"groupId": group_id, "role": {"$in": ["owner", null]}})
+            .await?)
+    }
+
+    async fn insert(&self, user_group: &UserGroup) -> RepoResult<ObjectId> {
+        inserted_id(self.collection.insert_one(user_group).await?)
+    }
+
+    async fn set_role(
+        &self,
+        group_id: ObjectId,
+        user_id: ObjectId,
+        role: GroupRole,
+    ) -> RepoResult<bool> {
+        let role = mongodb::bson::to_bson(&role)?;
+        let result = self
+            .collection
+            .update_one(
+                doc! {"groupId": group_id, "userId": user_id},
+                doc! {"$set": {"role": role}},
+            )
+            .await?;
+        Ok(result.matched_count == 1)
+    }
+
+    async fn delete(&self, id: ObjectId) -> RepoResult<bool> {
+        delete_by_id(&self.collection, id).await
+    }
+
+    async fn delete_member(&self, group_id: ObjectId, user_id: ObjectId) -> RepoResult<bool> {
+        let result = self
+            .collection
+            .delete_one(doc! {"groupId": group_id, "userId": user_id})
+            .await?;
+        Ok(result.deleted_count == 1)
+    }
+}
+
+pub struct MongoPropertyRepo {
+    collection: Collection<Property>,
+}
+
+impl MongoPropertyRepo {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            collection: db.collection("properties"),
+        }
+    }
+}
+
+#[async_trait]
+impl PropertyRepo for MongoPropertyRepo {
+    async fn find_by_id(&self, id: ObjectId) -> RepoResult<Option<Property>> {
+        Ok(self.collection.find_one(live(doc! {"_id": id})).await?)
+    }
+
+    async fn find_by_ids(&self, ids: &[ObjectId]) -> RepoResult<Vec<Property>> {
+        let filter = live(doc! {"_id": {"$in": ids}});
+        Ok(self.collection.find(filter).await?.try_collect().await?)
+    }
+
+    async fn find_by_group(
+        &self,
+        group_id: ObjectId,
+        viewer: Option<ObjectId>,
+    ) -> RepoResult<Vec<Property>> {
+        let filter = visible_to(live(doc! {"groupId": group_id}), viewer);
+        Ok(self.collection.find(filter).await?.try_collect().await?)
+    }
+
+    async fn find_page_by_group(
+        &self,
+        group_id: ObjectId,
+        viewer: Option<ObjectId>,
+        query: &PageQuery,
+    ) -> RepoResult<Page<Property>> {
+        let filter = visible_to(live(doc! {"groupId": group_id}), viewer);
+        find_page(&self.collection, filter, query).await
+    }
+
+    async fn insert(&self, property: &Property) -> RepoResult<ObjectId> {
+        inserted_id(self.collection.insert_one(property).await?)
+    }
+
+    async fn update(&self, id: ObjectId, changes: Changes) -> RepoResult<bool> {
+        update_where(&self.collection, live(doc! {"_id": id}), changes).await
+    }
+}
+
+pub struct MongoZoneRepo {
+    collection: Collection<Zone>,
+}
+
+impl MongoZoneRepo {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            collection: db.collection("zones"),
+        }
+    }
+}
+
+#[async_trait]
+impl ZoneRepo for MongoZoneRepo {
+    async fn find_by_id(&self, id: ObjectId) -> RepoResult<Option<Zone>> {
+        Ok(self.collection.find_one(live(doc! {"_id": id})).await?)
+    }
+
+    async fn find_by_ids(&self, ids: &[ObjectId]) -> RepoResult<Vec<Zone>> {
+        let filter = live(doc! {"_id": {"$in": ids}});
+        Ok(self.collection.find(filter).await?.try_collect().await?)
+    }
+
+    async fn find_by_parent(
+        &self,
+        parent_id: ObjectId,
+        viewer: Option<ObjectId>,
+    ) -> RepoResult<Vec<Zone>> {
+        let filter = visible_to(live(doc! {"parentZoneId": parent_id}), viewer);
+        Ok(self.collection.find(filter).await?.try_collect().await?)
+    }
+
+    async fn find_page_by_parent(
+        &self,
+        parent_id: ObjectId,
+        viewer: Option<ObjectId>,
+        query: &PageQuery,
+    ) -> RepoResult<Page<Zone>> {
+        let filter = visible_to(live(doc! {"parentZoneId": parent_id}), viewer);
+        find_page(&self.collection, filter, query).await
+    }
+
+    async fn find_by_property(&self, property_id: ObjectId) -> RepoResult<Vec<Zone>> {
+        Ok(self
+            .collection
+            .find(live(doc! {"propertyId": property_id}))
+            .await?
+            .try_collect()
+            .await?)
+    }
+
+    async fn insert(&self, zone: &Zone) -> RepoResult<ObjectId> {
+        inserted_id(self.collection.insert_one(zone).await?)
+    }
+
+    async fn update(&self, id: ObjectId, changes: Changes) -> RepoResult<bool> {
+        update_where(&self.collection, live(doc! {"_id": id}), changes).await
+    }
+
+    async fn set_property(&self, ids: &[ObjectId], property_id: ObjectId) -> RepoResult<u64> {
+        let result = self
+            .collection
+            .update_many(
+                live(doc! {"_id": {"$in": ids}}),
+                doc! {"$set": {"propertyId": property_id}},
+            )
+            .await?;
+        Ok(result.matched_count)
+    }
+}
+
+pub struct MongoItemRepo {
+    collection: Collection<Item>,
+}
+
+impl MongoItemRepo {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            collection: db.collection("items"),
+        }
+    }
+}
+
+#[async_trait]
+impl ItemRepo for MongoItemRepo {
+    async fn find_by_id(&self, id: ObjectId) -> RepoResult<Option<Item>> {
+        Ok(self.collection.find_one(live(doc! {"_id": id})).await?)
+    }
+
+    async fn find_by_zone(&self, zone_id: ObjectId) -> RepoResult<Vec<Item>> {
+        Ok(self
+            .collection
+            .find(live(doc! {"zoneId": zone_id}))
+            .await?
+            .try_collect()
+            .await?)
+    }
+
+    async fn find_page_by_zone(
+        &self,
+        zone_id: ObjectId,
+        query: &PageQuery,
+    ) -> RepoResult<Page<Item>> {
+        find_page(&self.collection, live(doc! {"zoneId": zone_id}), query).await
+    }
+
+    async fn find_low_stock(&self, zone_ids: &[ObjectId]) -> RepoResult<Vec<Item>> {
+        Ok(self
+            .collection
+            .find(live(doc! {
+                "zoneId": {"$in": zone_ids},
+                "minQuantity": {"$exists": true},
+                "$expr": {"$lt": [{"$ifNull": ["$quantity", 0]}, "$minQuantity"]},
+            }))
+            .await?
+            .try_collect()
+            .await?)
+    }
+
+    async fn find_expiring(
+        &self,
+        zone_ids: &[ObjectId],
+        before: DateTime,
+    ) -> RepoResult<Vec<Item>> {
+        Ok(self
+            .collection
+            .find(live(
+                doc! {"zoneId": {"$in": zone_ids}, "expiresAt": {"$lte": before}},
+            ))
+            .sort(doc! {"expiresAt": 1})
+            .await?
+            .try_collect()
+            .await?)
+    }
+
+    async fn insert(&self, item: &Item) -> RepoResult<ObjectId> {
+        inserted_id(self.collection.insert_one(item).await?)
+    }
+
+    async fn update(&self, id: ObjectId, changes: Changes) -> RepoResult<bool> {
+        update_where(&self.collection, live(doc! {"_id": id}), changes).await
+    }
+
+    async fn find_lent(&self, zone_ids: &[ObjectId]) -> RepoResult<Vec<Item>> {
+        Ok(self
+            .collection
+            .find(live(
+                doc! {"zoneId": {"$in": zone_ids}, "loanId": {"$exists": true}},
+            ))
+            .await?
+            .try_collect()
+            .await?)
+    }
+
+    async fn clear_category(&self, category_id: ObjectId) -> RepoResult<u64> {
+        let result = self
+            .collection
+            .update_many(
+                doc! {"categoryId": category_id},
+                doc! {"$unset": {"categoryId": ""}},
+            )
+            .await?;
+        Ok(result.matched_count)
+    }
+}
+
+pub struct MongoCategoryRepo {
+    collection: Collection<Category>,
+}
+
+impl MongoCategoryRepo {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            collection: db.collection("categories"),
+        }
+    }
+}
+
+#[async_trait]
+impl CategoryRepo for MongoCategoryRepo {
+    async fn find_by_id(&self, id: ObjectId) -> RepoResult<Option<Category>> {
+        Ok(self.collection.find_one(doc! {"_id": id}).await?)
+    }
+
+    async fn find_by_group(&self, group_id: ObjectId) -> RepoResult<Vec<Category>> {
+        Ok(self
+            .collection
+            .find(doc! {"groupId": group_id})
+            .sort(doc! {"name": 1})
+            .await?
+            .try_collect()
+            .await?)
+    }
+
+    async fn insert(&self, category: &Category) -> RepoResult<ObjectId> {
+        inserted_id(self.collection.insert_one(category).await?)
+    }
+
+    async fn update(&self, id: ObjectId, changes: Changes) -> RepoResult<bool> {
+        update_by_id(&self.collection, id, changes).await
+    }
+
+    async fn delete(&self, id: ObjectId) -> RepoResult<bool> {
+        delete_by_id(&self.collection, id).await
+    }
+
+    async fn delete_by_group(&self, group_id: ObjectId) -> RepoResult<u64> {
+        let result = self
+            .collection
+            .delete_many(doc! {"groupId": group_id})
+            .await?;
+        Ok(result.deleted_count)
+    }
+}
+
+pub struct MongoTemplateRepo {
+    collection: Collection<ItemTemplate>,
+}
+
+impl MongoTemplateRepo {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            collection: db.collection("templates"),
+        }
+    }
+}
+
+#[async_trait]
+impl TemplateRepo for MongoTemplateRepo {
+    async fn find_by_id(&self, id: ObjectId) -> RepoResult<Option<ItemTemplate>> {
+        Ok(self.collection.find_one(doc! {"_id": id}).await?)
+    }
+
+    async fn find_by_group(&self, group_id: ObjectId) -> RepoResult<Vec<ItemTemplate>> {
+        Ok(self
+            .collection
+            .find(doc! {"groupId": group_id})
+            .sort(doc! {"name": 1})
+            .await?
+            .try_collect()
+            .await?)
+    }
+
+    async fn insert(&self, template: &ItemTemplate) -> RepoResult<ObjectId> {
+        inserted_id(self.collection.insert_one(template).await?)
+    }
+
+    async fn delete(&self, id: ObjectId) -> RepoResult<bool> {
+        delete_by_id(&self.collection, id).await
+    }
+
+    async fn delete_by_group(&self, group_id: ObjectId) -> RepoResult<u64> {
+        let result = self
+            .collection
+            .delete_many(doc! {"groupId": group_id})
+            .await?;
+        Ok(result.deleted_count)
+    }
+
+    async fn clear_category(&self, category_id: ObjectId) -> RepoResult<u64> {
+        let result = self
+            .collection
+            .update_many(
+                doc! {"categoryId": category_id},
+                doc! {"$unset": {"categoryId": ""}},
+            )
+            .await?;
+        Ok(result.matched_count)
+    }
+}
+
+pub struct MongoStockMovementRepo {
+    collection: Collection<StockMovement>,
+}
+
+impl MongoStockMovementRepo {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            collection: db.collection("stockMovements"),
+        }
+    }
+}
+
+#[async_trait]
+impl StockMovementRepo for MongoStockMovementRepo {
+    async fn insert(&self, movement: &StockMovement) -> RepoResult<ObjectId> {
+        inserted_id(self.collection.insert_one(movement).await?)
+    }
+
+    async fn find_by_item(&self, item_id: ObjectId) -> RepoResult<Vec<StockMovement>> {
+        Ok(self
+            .collection
+            .find(doc! {"itemId": item_id})
+            .sort(doc! {"createdAt": -1, "_id": -1})
+            .await?
+            .try_collect()
+            .await?)
+    }
+
+    async fn delete_by_item(&self, item_id: ObjectId) -> RepoResult<u64> {
+        let result = self
+            .collection
+            .delete_many(doc! {"itemId": item_id})
+            .await?;
+        Ok(result.deleted_count)
+    }
+}
+
+pub struct MongoLoanRepo {
+    collection: Collection<Loan>,
+}
+
+impl MongoLoanRepo {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            collection: db.collection("loans"),
+        }
+    }
+}
+
+#[async_trait]
+impl LoanRepo for MongoLoanRepo {
+    async fn insert(&self, loan: &Loan) -> RepoResult<ObjectId> {
+        inserted_id(self.collection.insert_one(loan).await?)
+    }
+
+    async fn find_by_ids(&self, ids: &[ObjectId]) -> RepoResult<Vec<Loan>> {
+        Ok(self
+            .collection
+            .find(doc! {"_id": {"$in": ids}})
+            .await?
+            .try_collect()
+            .await?)
+    }
+
+    async fn find_by_item(&self, item_id: ObjectId) -> RepoResult<Vec<Loan>> {
+        Ok(self
+            .collection
+            .find(doc! {"itemId": item_id})
+            .sort(doc! {"lentAt": -1, "_id": -1})
+            .await?
+            .try_collect()
+            .await?)
+    }
+}
+
+/// Operaciones masivas sobre items. Usa transacciones, así que MongoDB tiene que ser un
+/// replica set (o un clúster shardeado); un servidor suelto las rechaza.
+pub struct MongoBulkRepo {
+    client: Client,
+    items: Collection<Item>,
+    movements: Collection<StockMovement>,
+    loans: Collection<Loan>,
+    trash: Collection<TrashEntry>,
+}
+
+impl MongoBulkRepo {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            client: db.client().clone(),
+            items: db.collection("items"),
+            movements: db.collection("stockMovements"),
+            loans: db.collection("loans"),
+            trash: db.collection("trash"),
+        }
+    }
+}
+
+#[async_trait]
+impl BulkRepo for MongoBulkRepo {
+    async fn write_items(
+        &self,
+        writes: &[ItemWrite],
+        all_or_nothing: bool,
+    ) -> RepoResult<Vec<bool>> {
+        let mut session = self.client.start_session().await?;
+        session.start_transaction().await?;
+        let mut found = Vec::with_capacity(writes.len());
+        for write in writes {
+            let result = match write {
+                ItemWrite::Insert { item, movement } => {
+                    self.items
+                        .insert_one(item.as_ref())
+                        .session(&mut session)
+                        .await?;
+                    if let Some(movement) = movement {
+                        self.movements
+                            .insert_one(movement)
+                            .session(&mut session)
+                            .await?;
+                    }
+                    true
+                }
+                ItemWrite::Update { id, changes } => {
+                    let result = self
+                        .items
+                        .update_one(live(doc! {"_id": id}), update_doc(changes))
+                        .session(&mut session)
+                        .await?;
+                    result.matched_count == 1
+                }
+                ItemWrite::Delete { id, entry } => {
+                    let result = self
+                        .items
+                        .update_one(
+                            live(doc! {"_id": id}),
+                            doc! {"$set": {"deletedAt": entry.deleted_at, "trashId": entry.id}},
+                        )
+                        .session(&mut session)
+                        .await?;
+                    if result.matched_count == 1 {
+                        self.trash.insert_one(entry).session(&mut session).await?;
+                    }
+                    result.matched_count == 1
+                }
+            };
+            found.push(result);
+        }
+        if all_or_nothing && found.iter().any(|f| !f) {
+            session.abort_transaction().await?;
+        } else {
+            session.commit_transaction().await?;
+        }
+        Ok(found)
+    }
+
+    async fn record_movement(
+        &self,
+        change: QuantityChange,
+        movement: &StockMovement,
+    ) -> RepoResult<Option<StockMovement>> {
+        // Si algo falla antes de confirmar, la transacción se aborta al soltar la sesión
+        let mut session = self.client.start_session().await?;
+        session.start_transaction().await?;
+        let filter = live(doc! {"_id": movement.item_id});
+        let (delta, quantity_after) = match change {
+            QuantityChange::Add(delta) => {
+                let mut filter = filter;
+                if delta < 0.0 {
+                    filter.insert("quantity", doc! {"$gte": -delta});
+                }
+                let item = self
+                    .items
+                    .find_one_and_update(filter, doc! {"$inc": {"quantity": delta}})
+                    .return_document(ReturnDocument::After)
+                    .session(&mut session)
+                    .await?;
+                match item {
+                    Some(item) => (delta, item.quantity.unwrap_or(0.0)),
+                    None => {
+                        session.abort_transaction().await?;
+                        return Ok(None);
+                    }
+                }
+            }
+            QuantityChange::Set(quantity) => {
+                let item = self
+                    .items
+                    .find_one_and_update(filter, doc! {"$set": {"quantity": quantity}})
+                    .return_document(ReturnDocument::Before)
+                    .session(&mut session)
+                    .await?;
+                match item {
+                    Some(item) => (quantity - item.quantity.unwrap_or(0.0), quantity),
+                    None => {
+                        session.abort_transaction().await?;
+                        return Ok(None);
+                    }
+                }
+            }
+        };
+        let mut movement = StockMovement {
+            delta,
+            quantity_after,
+            ..movement.clone()
+        };
+        let result = self
+            .movements
+            .insert_one(&movement)
+            .session(&mut session)
+            .await?;
+        movement.id = Some(inserted_id(result)?);
+        session.commit_transaction().await?;
+        Ok(Some(movement))
+    }
+
+    async fn lend(&self, loan: &Loan) -> RepoResult<bool> {
+        let mut session = self.client.start_session().await?;
+        session.start_transaction().await?;
+        // Marcar el item primero evita dos préstamos simultáneos del mismo item
+        let lent = self
+            .items
+            .update_one(
+                live(doc! {"_id": loan.item_id, "loanId": {"$exists": false}}),
+                doc! {"$set": {"loanId": loan.id}},
+            )
+            .session(&mut session)
+            .await?
+            .matched_count
+            == 1;
+        if !lent {
+            session.abort_transaction().await?;
+            return Ok(false);
+        }
+        self.loans.insert_one(loan).session(&mut session).await?;
+        session.commit_transaction().await?;
+        Ok(true)
+    }
+
+    async fn return_loan(
+        &self,
+        item_id: ObjectId,
+        loan_id: ObjectId,
+        returned: &LoanReturn,
+    ) -> RepoResult<bool> {
+        let mut session = self.client.start_session().await?;
+        session.start_transaction().await?;
+        let closed = self
+            .loans
+            .update_one(
+                doc! {"_id": loan_id, "returned": {"$exists": false}},
+                doc! {"$set": {"returned": bson::to_bson(returned)?}},
+            )
+            .session(&mut session)
+            .await?;
+        let released = closed.matched_count == 1
+            && self
+                .items
+                .update_one(
+                    live(doc! {"_id": item_id, "loanId": loan_id}),
+                    doc! {"$unset": {"loanId": ""}},
+                )
+                .session(&mut session)
+                .await?
+                .matched_count
+                == 1;
+        if released {
+            session.commit_transaction().await?;
+        } else {
+            session.abort_transaction().await?;
+        }
+        Ok(released)
+    }
+}
+
+pub struct MongoSessionRepo {
+    collection: Collection<Session>,
+}
+
+impl MongoSessionRepo {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            collection: db.collection("sessions"),
+        }
+    }
+}
+
+#[async_trait]
+impl SessionRepo for MongoSessionRepo {
+    async fn insert(&self, session: &Session) -> RepoResult<ObjectId> {
+        inserted_id(self.collection.insert_one(session).await?)
+    }
+
+    async fn find_by_id(&self, id: ObjectId) -> RepoResult<Option<Session>> {
+        Ok(self.collection.find_one(doc! {"_id": id}).await?)
+    }
+
+    async fn find_active(&self, id: ObjectId, user_id: ObjectId) -> RepoResult<Option<Session>> {
+        Ok(self
+            .collection
+            .find_one(doc! {
+                "_id": id,
+                "userId": user_id,
+                "revokedAt": {"$exists": false},
+                "expiresAt": {"$gt": DateTime::now()},
+            })
+            .await?)
+    }
+
+    async fn find_active_by_user(&self, user_id: ObjectId) -> RepoResult<Vec<Session>> {
+        Ok(self
+            .collection
+            .find(doc! {
+                "userId": user_id,
+                "revokedAt": {"$exists": false},
+                "expiresAt": {"$gt": DateTime::now()},
+            })
+            .sort(doc! {"lastUsedAt": -1})
+            .await?
+            .try_collect()
+            .await?)
+    }
+
+    async fn rotate(
+        &self,
+        id: ObjectId,
+        current_hash: &str,
+        new_hash: &str,
+        expires_at: DateTime,
+    ) -> RepoResult<bool> {
+        let result = self
+            .collection
+            .update_one(
+                doc! {"_id": id, "refreshTokenHash": current_hash},
+                doc! {"$set": {
+                    "refreshTokenHash": new_hash,
+                    "lastUsedAt": DateTime::now(),
+                    "expiresAt": expires_at,
+                }},
+            )
+            .await?;
+        Ok(result.modified_count == 1)
+    }
+
+    async fn revoke(&self, id: ObjectId) -> RepoResult<()> {
+        self.collection
+            .update_one(
+                doc! {"_id": id},
+                doc! {"$set": {"revokedAt": DateTime::now()}},
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn revoke_for_user(&self, id: ObjectId, user_id: ObjectId) -> RepoResult<bool> {
+        let result = self
+            .collection
+            .update_one(
+                doc! {"_id": id, "userId": user_id, "revokedAt": {"$exists": false}},
+                doc! {"$set": {"revokedAt": DateTime::now()}},
+            )
+            .await?;
+        Ok(result.matched_count == 1)
+    }
+
+    async fn revoke_all_for_user(
+        &self,
+        user_id: ObjectId,
+        except: Option<ObjectId>,
+    ) -> RepoResult<u64> {
+        let mut filter = doc! {"userId": user_id, "revokedAt": {"$exists": false}};
+        if let Some(except) = except {
+            filter.insert("_id", doc! {"$ne": except});
+        }
+        let result = self
+            .collection
+            .update_many(filter, doc! {"$set": {"revokedAt": DateTime::now()}})
+            .await?;
+        Ok(result.modified_count)
+    }
+
+    async fn delete_by_user(&self, user_id: ObjectId) -> RepoResult<()> {
+        self.collection
+            .delete_many(doc! {"userId": user_id})
+            .await?;
+        Ok(())
+    }
+}
+
+pub struct MongoAuditRepo {
+    collection: Collection<AuditEvent>,
+}
+
+impl MongoAuditRepo {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            collection: db.collection("auditLog"),
+        }
+    }
+
+    async fn latest(&self, filter: Document, limit: i64) -> RepoResult<Vec<AuditEvent>> {
+        Ok(self
+            .collection
+            .find(filter)
+            .sort(doc! {"createdAt": -1, "_id": -1})
+            .limit(limit)
+            .await?
+            .try_collect()
+            .await?)
+    }
+}
+
+#[async_trait]
+impl AuditRepo for MongoAuditRepo {
+    async fn insert(&self, event: &AuditEvent) -> RepoResult<ObjectId> {
+        inserted_id(self.collection.insert_one(event).await?)
+    }
+
+    async fn find_by_group(&self, group_id: ObjectId, limit: i64) -> RepoResult<Vec<AuditEvent>> {
+        self.latest(doc! {"groupId": group_id}, limit).await
+    }
+
+    async fn find_by_entity(
+        &self,
+        entity_type: AuditEntity,
+        entity_id: ObjectId,
+        limit: i64,
+    ) -> RepoResult<Vec<AuditEvent>> {
+        self.latest(
+            doc! {"entityType": entity_type.as_str(), "entityId": entity_id},
+            limit,
+        )
+        .await
+    }
+}
+
+pub struct MongoTrashRepo {
+    collection: Collection<TrashEntry>,
+    groups: Collection<Document>,
+    user_groups: Collection<Document>,
+    properties: Collection<Document>,
+    zones: Collection<Document>,
+    items: Collection<Document>,
+    movements: Collection<Document>,
+    loans: Collection<Document>,
+}
+
+impl MongoTrashRepo {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            collection: db.collection("trash"),
+            groups: db.collection("groups"),
+            user_groups: db.collection("userGroup"),
+            properties: db.collection("properties"),
+            zones: db.collection("zones"),
+            items: db.collection("items"),
+            movements: db.collection("stockMovements"),
+            loans: db.collection("loans"),
+        }
+    }
+
+    fn collections(&self) -> [&Collection<Document>; 4] {
+        [&self.groups, &self.properties, &self.zones, &self.items]
+    }
+
+    /// `_id` de los elementos de la colección borrados con la entrada.
+    async fn trashed_ids(
+        &self,
+        collection: &Collection<Document>,
+        id: ObjectId,
+    ) -> RepoResult<Vec<ObjectId>> {
+        let docs: Vec<Document> = collection
+            .find(doc! {"trashId": id})
+            .projection(doc! {"_id": 1})
+            .await?
+            .try_collect()
+            .await?;
+        Ok(docs
+            .iter()
+            .filter_map(|d| d.get_object_id("_id").ok())
+            .collect())
+    }
+}
+
+#[async_trait]
+impl TrashRepo for MongoTrashRepo {
+    async fn trash(&self, entry: &TrashEntry, set: &TrashSet) -> RepoResult<ObjectId> {
+        let id = inserted_id(self.collection.insert_one(entry).await?)?;
+        let marks = [&set.groups, &set.properties, &set.zones, &set.items];
+        for (collection, ids) in self.collections().into_iter().zip(marks) {
+            if ids.is_empty() {
+                continue;
+            }
+            collection
+                .update_many(
+                    live(doc! {"_id": {"$in": ids}}),
+                    doc! {"$set": {"deletedAt": entry.deleted_at, "trashId": id}},
+                )
+                .await?;
+        }
+        Ok(id)
+    }
+
+    async fn find_by_id(&self, id: ObjectId) -> RepoResult<Option<TrashEntry>> {
+        Ok(self.collection.find_one(doc! {"_id": id}).await?)
+    }
+
+    async fn find_by_group(&self, group_id: ObjectId) -> RepoResult<Vec<TrashEntry>> {
+        Ok(self
+            .collection
+            .find(doc! {"groupId": group_id})
+            .sort(doc! {"deletedAt": -1, "_id": -1})
+            .await?
+            .try_collect()
+            .await?)
+    }
+
+    async fn find_older_than(&self, before: DateTime) -> RepoResult<Vec<TrashEntry>> {
+        Ok(self
+            .collection
+            .find(doc! {"deletedAt": {"$lt": before}})
+            .await?
+            .try_collect()
+            .await?)
+    }
+
+    async fn find_items(&self, id: ObjectId) -> RepoResult<Vec<Item>> {
+        Ok(self
+            .items
+            .clone_with_type::<Item>()
+            .find(doc! {"trashId": id})
+            .await?
+            .try_collect()
+            .await?)
+    }
+
+    async fn restore(&self, id: ObjectId) -> RepoResult<bool> {
+        let result = self.collection.delete_one(doc! {"_id": id}).await?;
+        if result.deleted_count == 0 {
+            return Ok(false);
+        }
+        for collection in self.collections() {
+            collection
+                .update_many(
+                    doc! {"trashId": id},
+                    doc! {"$unset": {"deletedAt": "", "trashId": ""}},
+                )
+                .await?;
+        }
+        Ok(true)
+    }
+
+    async fn purge(&self, id: ObjectId) -> RepoResult<()> {
+        let groups = self.trashed_ids(&self.groups, id).await?;
+        if !groups.is_empty() {
+            self.user_groups
+                .delete_many(doc! {"groupId": {"$in": &groups}})
+                .await?;
+        }
+        let items = self.trashed_ids(&self.items, id).await?;
+        if !items.is_empty() {
+            self.movements
+                .delete_many(doc! {"itemId": {"$in": &items}})
+                .await?;
+            self.loans
+                .delete_many(doc! {"itemId": {"$in": &items}})
+                .await?;
+        }
+        for collection in self.collections() {
+            collection.delete_many(doc! {"trashId": id}).await?;
+        }
+        self.collection.delete_one(doc! {"_id": id}).await?;
+        Ok(())
+    }
+}
+
+/// Crea los índices de texto de la búsqueda, si no existen. Los textos son en castellano,
+/// así que las palabras se comparan sin acentos y por su raíz (`taladros` encuentra
+/// `taladro`).
+/// Las escrituras de [`MongoBulkRepo`] usan transacciones, que un `mongod` suelto rechaza:
+/// hace falta un replica set (basta con uno de un solo nodo) o un clúster shardeado.
+pub async fn check_transactions(db: &Database) -> RepoResult<()> {
+    let hello = db.run_command(doc! {"hello": 1}).await?;
+    if hello.contains_key("setName") || hello.get_str("msg") == Ok("isdbgrid") {
+        return Ok(());
+    }
+    Err(RepoError(
+        "MongoDB no admite transacciones: tiene que ser un replica set (arranca mongod con \
+         --replSet e inicialízalo con rs.initiate())"
+            .to_string(),
+    ))
+}
+
+pub async fn ensure_indexes(db: &Database) -> mongodb::error::Result<()> {
+    let text_index = |keys: Document, weights: Document| {
+        IndexModel::builder()
+            .keys(keys)
+            .options(
+                IndexOptions::builder()
+                    .name("search".to_string())
+                    .weights(weights)
+                    .default_language("spanish".to_string())
+                    .build(),
+            )
+            .build()
+    };
+    for collection in ["groups", "properties", "zones"] {
+        db.collection::<Document>(collection)
+            .create_index(text_index(
+                doc! {"name": "text"},
+                doc! {"name": NAME_WEIGHT},
+            ))
+            .await?;
+    }
+    db.collection::<Document>("items")
+        .create_index(text_index(
+            doc! {"name": "text", "tags": "text", "description": "text"},
+            doc! {
+                "name": NAME_WEIGHT,
+                "tags": TAGS_WEIGHT,
+                "description": DESCRIPTION_WEIGHT,
+            },
+        ))
+        .await?;
+    Ok(())
+}
+
+/// Búsqueda con los índices de texto, en una sola agregación que une las cuatro
+/// colecciones.
+pub struct MongoSearchRepo {
+    db: Database,
+}
+
+impl MongoSearchRepo {
+    pub fn new(db: &Database) -> Self {
+        Self { db: db.clone() }
+    }
+}
+
+/// `$match` inicial de cada colección: el texto (que tiene que ir primero) y el resto de
+/// condiciones.
+fn text_match(text: &str, filter: Document) -> Document {
+    let mut filter = live(filter);
+    if !text.is_empty() {
+        filter.insert("$text", doc! {"$search": text});
+    }
+    doc! {"$match": filter}
+}
+
+/// Texto que contiene `value`, sin distinguir mayúsculas ni acentos.
+fn contains(value: &str) -> Document {
+    doc! {"$regex": accent_insensitive_pattern(value), "$options": "i"}
+}
+
+/// Añade al filtro que alguno de los campos encaje con la expresión de los términos con
+/// erratas, si la hay.
+fn similar(filter: Document, pattern: Option<&str>, fields: &[&str]) -> Document {
+    let Some(pattern) = pattern else {
+        return filter;
+    };
+    let any: Vec<Document> = fields
+        .iter()
+        .map(|field| doc! {*field: {"$regex": pattern, "$options": "i"}})
+        .collect();
+    doc! {"$and": [filter, {"$or": any}]}
+}
+
+/// Condición de `$match` para los atributos de los items de un grupo, ya unidos con su
+/// propiedad.
+fn attribute_match(group_id: ObjectId, conditions: &[AttributeCondition]) -> Document {
+    let mut all = vec![doc! {"_property.groupId": group_id}];
+    for condition in conditions {
+        let field = format!("attributes.{}", condition.name());
+        all.push(match condition {
+            AttributeCondition::Equals(_, bson::Bson::String(text)) => doc! {field: {
+                "$regex": format!("^{}$", regex::escape(text)),
+                "$options": "i",
+            }},
+            AttributeCondition::Equals(_, value) => doc! {field: value.clone()},
+            AttributeCondition::AtLeast(_, value) => doc! {field: {"$gte": value.clone()}},
+            AttributeCondition::AtMost(_, value) => doc! {field: {"$lte": value.clone()}},
+        });
+    }
+    doc! {"$and": all}
+}
+
+/// Filtros por campo de la consulta: los de los propios items y los de su zona y su
+/// propiedad, que se comprueban después de buscarlas. Siempre hay alguno, para que
+/// `$and` no quede vacío.
+fn clause_filters(query: &Query) -> (Vec<Document>, Vec<Document>) {
+    let mut own = Vec::new();
+    let mut joined = vec![doc! {}];
+    for clause in query.filters() {
+        match clause {
+            Clause::Tag(tag) => own.push(doc! {"tags": {
+                "$regex": format!("^{}$", accent_insensitive_pattern(tag)),
+                "$options": "i",
+            }}),
+            Clause::Description(text) => own.push(doc! {"description": contains(text)}),
+            Clause::Date { field, from, to } => {
+                let mut range = Document::new();
+                if let Some(from) = from {
+                    range.insert("$gte", *from);
+                }
+                if let Some(to) = to {
+                    range.insert("$lt", *to);
+                }
+                own.push(doc! {field.field(): range});
+            }
+            Clause::Zone(name) => joined.push(doc! {"_zone.name": contains(name)}),
+            Clause::Property(name) => joined.push(doc! {"_property.name": contains(name)}),
+            Clause::Word(_) | Clause::Phrase(_) => {}
+        }
+    }
+    (own, joined)
+}
+
+/// Marca cada documento con su tipo, su relevancia y su grupo.
+fn tag_stage(text: &str, kind: &str, group_id: &str) -> Document {
+    let score = if text.is_empty() {
+        bson::Bson::Double(0.0)
+    } else {
+        bson::Bson::Document(doc! {"$meta": "textScore"})
+    };
+    doc! {"$addFields": {"_type": kind, "_score": score, "_groupId": group_id}}
+}
+
+impl MongoSearchRepo {
+    /// Propiedades visibles de los grupos buscados, como condición de `$elemMatch`.
+    fn visible_property(query: &SearchQuery) -> Document {
+        visible_to(
+            live(doc! {"groupId": {"$in": &query.group_ids}}),
+            query.viewer,
+        )
+    }
+
+    fn pipelines(query: &SearchQuery) -> Vec<(&'static str, Vec<Document>)> {
+        let text = query.query.text();
+        let text = text.as_str();
+        let pattern = query.similar_pattern();
+        let pattern = pattern.as_deref();
+        let mut pipelines = Vec::new();
+        let mut zone_filter = Document::new();
+        let mut item_filter = Document::new();
+        let mut joined_scope = Document::new();
+        match &query.scope {
+            Some(SearchScope::Property(property_id)) => {
+                zone_filter.insert("propertyId", property_id);
+                joined_scope.insert("_property._id", property_id);
+            }
+            Some(scope @ SearchScope::Zone { descendants, .. }) => {
+                zone_filter.insert("_id", doc! {"$in": descendants});
+                item_filter.insert(
+                    "zoneId",
+                    doc! {"$in": scope.item_zones().unwrap_or_default()},
+                );
+            }
+            _ => {}
+        }
+        if !query.items_only {
+            if query.scope.is_none() {
+                pipelines.push((
+                    "groups",
+                    vec![
+                        text_match(
+                            text,
+                            similar(doc! {"_id": {"$in": &query.group_ids}}, pattern, &["name"]),
+                        ),
+                        tag_stage(text, "group", "$_id"),
+                    ],
+                ));
+            }
+            if matches!(query.scope, None | Some(SearchScope::Group(_))) {
+                pipelines.push((
+                    "properties",
+                    vec![
+                        text_match(
+                            text,
+                            similar(Self::visible_property(query), pattern, &["name"]),
+                        ),
+                        tag_stage(text, "property", "$groupId"),
+                    ],
+                ));
+            }
+            pipelines.push((
+                "zones",
+                vec![
+                    text_match(
+                        text,
+                        similar(visible_to(zone_filter, query.viewer), pattern, &["name"]),
+                    ),
+                    doc! {"$lookup": {
+                        "from": "properties",
+                        "localField": "propertyId",
+                        "foreignField": "_id",
+                        "as": "_property",
+                    }},
+                    doc! {"$match": {"_property": {"$elemMatch": Self::visible_property(query)}}},
+                    tag_stage(text, "zone", "$_property.groupId"),
+                ],
+            ));
+        }
+        let (own, mut joined) = clause_filters(&query.query);
+        joined.push(joined_scope);
+        if let Some(category_id) = query.category_id {
+            item_filter.insert("categoryId", category_id);
+        }
+        if !own.is_empty() {
+            item_filter.insert("$and", own);
+        }
+        if let Some(groups) = &query.attributes {
+            let any: Vec<Document> = groups
+                .iter()
+                .map(|(group_id, conditions)| attribute_match(*group_id, conditions))
+                .collect();
+            // Sin ningún grupo posible no encaja nada (`$or` no admite una lista vacía)
+            joined.push(match any.is_empty() {
+                true => doc! {"_id": {"$exists": false}},
+                false => doc! {"$or": any},
+            });
+        }
+        let visible_zone = visible_to(live(Document::new()), query.viewer);
+        pipelines.push((
+            "items",
+            vec![
+                text_match(
+                    text,
+                    similar(item_filter, pattern, &["name", "tags", "description"]),
+                ),
+                doc! {"$lookup": {
+                    "from": "zones",
+                    "localField": "zoneId",
+                    "foreignField": "_id",
+                    "as": "_zone",
+                }},
+                // Los items pueden estar en una zona o directamente en la propiedad
+                doc! {"$lookup": {
+                    "from": "properties",
+                    "let": {"propertyId": {"$ifNull": [{"$first": "$_zone.propertyId"}, "$zoneId"]}},
+                    "pipeline": [{"$match": {"$expr": {"$eq": ["$_id", "$$propertyId"]}}}],
+                    "as": "_property",
+                }},
+                doc! {"$match": {"$and": [
+                    {"_property": {"$elemMatch": Self::visible_property(query)}},
+                    {"$or": [
+                        {"_zone": {"$size": 0}},
+                        {"_zone": {"$elemMatch": visible_zone}},
+                    ]},
+                ]}},
+                doc! {"$match": {"$and": joined}},
+                tag_stage(text, "item", "$_property.groupId"),
+            ],
+        ));
+        pipelines
+    }
+}
+
+/// Resultado de la agregación, o `None` si no tiene grupo.
+fn search_match(document: Document) -> RepoResult<Option<SearchMatch>> {
+    let score = document.get_f64("_score").unwrap_or_default();
+    let group_id = match document.get("_groupId") {
+        Some(bson::Bson::ObjectId(id)) => *id,
+        // `$_property.groupId` es un array de un elemento
+        Some(bson::Bson::Array(ids)) => match ids.first() {
+            Some(bson::Bson::ObjectId(id)) => *id,
+            _ => return Ok(None),
+        },
+        _ => return Ok(None),
+    };
+    let entity = match document.get_str("_type").unwrap_or_default() {
+        "group" => SearchEntity::Group(bson::from_document(document)?),
+        "property" => SearchEntity::Property(bson::from_document(document)?),
+        "zone" => SearchEntity::Zone(bson::from_document(document)?),
+        _ => SearchEntity::Item(Box::new(bson::from_document(document)?)),
+    };
+    Ok(Some(SearchMatch {
+        entity,
+        score,
+        group_id,
+    }))
+}
+
+/// Campo de la agregación que corresponde a un campo de [`SearchMatch`].
+fn search_field(field: &str) -> &str {
+    match field {
+        "score" => "_score",
+        "type" => "_type",
+        field => field,
+    }
+}
+
+impl MongoSearchRepo {
+    /// Agregación que une las búsquedas en las cuatro colecciones, con la colección en la que
+    /// empieza. `None` si no hay dónde buscar.
+    fn aggregation(query: &SearchQuery) -> Option<(&'static str, Vec<Document>)> {
+        if query.group_ids.is_empty() {
+            return None;
+        }
+        // La primera colección abre la agregación y el resto se le unen
+        let mut pipelines = Self::pipelines(query).into_iter();
+        let (first, mut pipeline) = pipelines.next()?;
+        for (collection, stages) in pipelines {
+            pipeline.push(doc! {"$unionWith": {"coll": collection, "pipeline": stages}});
+        }
+        pipeline.push(doc! {"$project": {"_zone": 0, "_property": 0}});
+        Some((first, pipeline))
+    }
+
+    async fn aggregate(
+        &self,
+        collection: &str,
+        pipeline: Vec<Document>,
+    ) -> RepoResult<Vec<Document>> {
+        Ok(self
+            .db
+            .collection::<Document>(collection)
+            .aggregate(pipeline)
+            .await?
+            .try_collect()
+            .await?)
+    }
+}
+
+#[async_trait]
+impl SearchRepo for MongoSearchRepo {
+    async fn search(&self, query: &SearchQuery) -> RepoResult<Vec<SearchMatch>> {
+        let Some((first, mut pipeline)) = Self::aggregation(query) else {
+            return Ok(Vec::new());
+        };
+        pipeline.push(doc! {"$sort": {"_score": -1, "_id": 1}});
+        if let Some(limit) = query.limit {
+            pipeline.push(doc! {"$limit": limit as i64});
+        }
+        let mut found = Vec::new();
+        for document in self.aggregate(first, pipeline).await? {
+            found.extend(search_match(document)?);
+        }
+        Ok(found)
+    }
+
+    /// Los filtros, el cursor y el límite van en la agregación, que devuelve a la vez la
+    /// página (`limit + 1` resultados, como [`find_page`]) y el total.
+    async fn search_page(
+        &self,
+        query: &SearchQuery,
+        page: &PageQuery,
+    ) -> RepoResult<Page<SearchMatch>> {
+        let Some((first, mut pipeline)) = Self::aggregation(query) else {
+            return Ok(Page::new(Vec::new(), 0, page));
+        };
+        let mut conditions = vec![doc! {}];
+        for filter in &page.filters {
+            conditions.push(match filter {
+                FieldFilter::Contains(field, text) => {
+                    doc! {search_field(field): {"$regex": regex::escape(text), "$options": "i"}}
+                }
+                FieldFilter::Equals(field, value) => doc! {search_field(field): value.clone()},
+            });
+        }
+        pipeline.push(doc! {"$match": {"$and": conditions}});
+        let (field, direction, after) = match page.sort.descending {
+            true => (search_field(page.sort.field), -1, "$lt"),
+            false => (search_field(page.sort.field), 1, "$gt"),
+        };
+        let mut rows = Vec::new();
+        if let Some((value, id)) = page.after.as_ref().and_then(Cursor::position) {
+            rows.push(doc! {"$match": {"$or": [
+                {field: {after: value.clone()}},
+                {field: value.clone(), "_id": {after: id}},
+            ]}});
+        }
+        rows.push(doc! {"$sort": {field: direction, "_id": direction}});
+        rows.push(doc! {"$limit": page.limit as i64 + 1});
+        pipeline.push(doc! {"$facet": {"rows": rows, "total": [{"$count": "count"}]}});
+
+        let Some(result) = self.aggregate(first, pipeline).await?.pop() else {
+            return Ok(Page::new(Vec::new(), 0, page));
+        };
+        let total = match result
+            .get_array("total")
+            .ok()
+            .and_then(|total| total.first())
+        {
+            Some(bson::Bson::Document(count)) => match count.get("count") {
+                Some(bson::Bson::Int32(n)) => *n as u64,
+                Some(bson::Bson::Int64(n)) => *n as u64,
+                _ => 0,
+            },
+            _ => 0,
+        };
+        let mut found = Vec::new();
+        for row in result.get_array("rows").cloned().unwrap_or_default() {
+            if let bson::Bson::Document(document) = row {
+                found.extend(search_match(document)?);
+            }
+        }
+        Ok(Page::new(found, total, page))
+    }
+}
@@ -1,19 +1,32 @@
 use actix_web::web;
 
-use crate::entities::{ancestors, group, image, item, property, search, user, user_group, zone};
+use crate::entities::{
+    alerts, ancestors, attribute, audit, category, group, image, item, loan, property, search,
+    session, stock_movement, template, trash, user, user_group, zone,
+};
 
 pub fn configure_private_routes(cfg: &mut web::ServiceConfig) {
+    alerts::configure_routes(cfg);
     ancestors::configure_routes(cfg);
+    attribute::configure_routes(cfg);
+    audit::configure_routes(cfg);
+    category::configure_routes(cfg);
     group::configure_routes(cfg);
-    //image::configure_routes(cfg);
+    image::configure_private_routes(cfg);
     item::configure_routes(cfg);
+    loan::configure_routes(cfg);
     property::configure_routes(cfg);
     search::configure_routes(cfg);
+    session::configure_private_routes(cfg);
+    stock_movement::configure_routes(cfg);
+    template::configure_routes(cfg);
+    trash::configure_routes(cfg);
     user_group::configure_routes(cfg);
     user::configure_private_routes(cfg);
     zone::configure_routes(cfg);
 }
 pub fn configure_public_routes(cfg: &mut web::ServiceConfig) {
     user::configure_public_routes(cfg);
-    image::configure_routes(cfg);
+    session::configure_public_routes(cfg);
+    image::configure_public_routes(cfg);
 }
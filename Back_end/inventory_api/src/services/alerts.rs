@@ -0,0 +1,217 @@
+use mongodb::bson::{oid::ObjectId, DateTime};
+use serde::Serialize;
+use std::collections::HashSet;
+
+use crate::entities::{item::Item, property::Property, zone::Zone};
+use crate::error::ApiError;
+use crate::repository::Repositories;
+use crate::services::group::visible_groups;
+
+/// Item con stock bajo y lo que falta para llegar a su `minQuantity`.
+#[derive(Serialize)]
+pub struct LowStockItem {
+    #[serde(flatten)]
+    pub item: Item,
+    pub missing: f64,
+}
+
+#[derive(Serialize)]
+pub struct ZoneLowStock {
+    pub zone: Zone,
+    pub items: Vec<LowStockItem>,
+}
+
+#[derive(Serialize)]
+pub struct PropertyLowStock {
+    pub property: Property,
+    pub zones: Vec<ZoneLowStock>,
+    /// Items que cuelgan directamente de la propiedad, sin zona.
+    pub items: Vec<LowStockItem>,
+}
+
+fn low_stock_item(item: Item) -> LowStockItem {
+    LowStockItem {
+        missing: item.min_quantity.unwrap_or(0.0) - item.quantity.unwrap_or(0.0),
+        item,
+    }
+}
+
+/// Zonas que el usuario puede ver: se descartan las privadas de otros usuarios y todo lo
+/// que cuelga de ellas.
+pub fn visible_zones(zones: Vec<Zone>, viewer: Option<ObjectId>) -> Vec<Zone> {
+    let viewer = match viewer {
+        Some(viewer) => viewer,
+        None => return zones,
+    };
+    let mut hidden: HashSet<ObjectId> = zones
+        .iter()
+        .filter(|zone| zone.user_id.is_some_and(|owner| owner != viewer))
+        .filter_map(|zone| zone.id)
+        .collect();
+    // Propaga la ocultación hacia abajo hasta que no cambie
+    loop {
+        let before = hidden.len();
+        for zone in &zones {
+            if let (Some(id), Some(parent)) = (zone.id, zone.parent_zone_id) {
+                if hidden.contains(&parent) {
+                    hidden.insert(id);
+                }
+            }
+        }
+        if hidden.len() == before {
+            break;
+        }
+    }
+    zones
+        .into_iter()
+        .filter(|zone| zone.id.is_some_and(|id| !hidden.contains(&id)))
+        .collect()
+}
+
+/// Propiedades visibles de los grupos del usuario, cada una con sus zonas visibles.
+async fn visible_properties(
+    repos: &Repositories,
+    user_id: ObjectId,
+    is_admin: bool,
+) -> Result<Vec<(Property, Vec<Zone>)>, ApiError> {
+    let viewer = if is_admin { None } else { Some(user_id) };
+    let mut result = Vec::new();
+    for group in visible_groups(repos, user_id, is_admin).await? {
+        let group_id = match group.id {
+            Some(id) => id,
+            None => continue,
+        };
+        for property in repos.properties.find_by_group(group_id, viewer).await? {
+            let property_id = match property.id {
+                Some(id) => id,
+                None => continue,
+            };
+            let zones = visible_zones(repos.zones.find_by_property(property_id).await?, viewer);
+            result.push((property, zones));
+        }
+    }
+    Ok(result)
+}
+
+/// Items por debajo de su mínimo en los grupos del usuario, agrupados por propiedad y zona.
+pub async fn low_stock(
+    repos: &Repositories,
+    user_id: ObjectId,
+    is_admin: bool,
+) -> Result<Vec<PropertyLowStock>, ApiError> {
+    let mut result = Vec::new();
+    for (property, zones) in visible_properties(repos, user_id, is_admin).await? {
+        // Los items antiguos pueden colgar directamente de la propiedad
+        let zone_ids: Vec<ObjectId> = property
+            .id
+            .into_iter()
+            .chain(zones.iter().filter_map(|zone| zone.id))
+            .collect();
+        let mut items = repos.items.find_low_stock(&zone_ids).await?;
+        if items.is_empty() {
+            continue;
+        }
+        let mut zone_alerts = Vec::new();
+        for zone in zones {
+            let (in_zone, rest): (Vec<Item>, Vec<Item>) = items
+                .into_iter()
+                .partition(|item| Some(item.zone_id) == zone.id);
+            items = rest;
+            if in_zone.is_empty() {
+                continue;
+            }
+            let items = in_zone.into_iter().map(low_stock_item).collect();
+            zone_alerts.push(ZoneLowStock { zone, items });
+        }
+        result.push(PropertyLowStock {
+            property,
+            zones: zone_alerts,
+            items: items.into_iter().map(low_stock_item).collect(),
+        });
+    }
+    Ok(result)
+}
+
+/// Paso de la ruta de un item: la propiedad y cada zona hasta la suya.
+#[derive(Serialize)]
+pub struct PathStep {
+    #[serde(rename = "_id")]
+    pub id: Option<ObjectId>,
+    pub name: String,
+}
+
+/// Item que caduca pronto (o ya ha caducado), con su ruta.
+#[derive(Serialize)]
+pub struct ExpiringItem {
+    #[serde(flatten)]
+    pub item: Item,
+    pub expired: bool,
+    /// Días completos hasta la caducidad, redondeando hacia abajo: -1 el primer día tras
+    /// caducar.
+    #[serde(rename = "daysLeft")]
+    pub days_left: i64,
+    pub path: Vec<PathStep>,
+}
+
+const DAY_MILLIS: i64 = 24 * 60 * 60 * 1000;
+
+/// Zonas desde la propiedad hasta `zone_id`, sacadas de las zonas ya cargadas de la
+/// propiedad. Vacía si el item cuelga directamente de la propiedad.
+fn zone_path(zones: &[Zone], zone_id: ObjectId) -> Vec<PathStep> {
+    let mut path = Vec::new();
+    let mut current = Some(zone_id);
+    while let Some(id) = current {
+        let zone = match zones.iter().find(|zone| zone.id == Some(id)) {
+            Some(zone) => zone,
+            None => break,
+        };
+        // Protege de ciclos en datos corruptos
+        if path.iter().any(|step: &PathStep| step.id == Some(id)) {
+            break;
+        }
+        path.push(PathStep {
+            id: zone.id,
+            name: zone.name.clone(),
+        });
+        current = zone.parent_zone_id;
+    }
+    path.reverse();
+    path
+}
+
+/// Items de los grupos del usuario que caducan en los próximos `days` días, incluidos los
+/// ya caducados, ordenados por fecha de caducidad.
+pub async fn expiring(
+    repos: &Repositories,
+    user_id: ObjectId,
+    is_admin: bool,
+    days: u32,
+) -> Result<Vec<ExpiringItem>, ApiError> {
+    let now = DateTime::now().timestamp_millis();
+    let before = DateTime::from_millis(now + i64::from(days) * DAY_MILLIS);
+    let mut found = Vec::new();
+    for (property, zones) in visible_properties(repos, user_id, is_admin).await? {
+        // Los items antiguos pueden colgar directamente de la propiedad
+        let zone_ids: Vec<ObjectId> = property
+            .id
+            .into_iter()
+            .chain(zones.iter().filter_map(|zone| zone.id))
+            .collect();
+        for item in repos.items.find_expiring(&zone_ids, before).await? {
+            let mut path = vec![PathStep {
+                id: property.id,
+                name: property.name.clone(),
+            }];
+            path.extend(zone_path(&zones, item.zone_id));
+            let expires_at = item.expires_at.map_or(now, |at| at.timestamp_millis());
+            found.push(ExpiringItem {
+                expired: expires_at <= now,
+                days_left: (expires_at - now).div_euclid(DAY_MILLIS),
+                path,
+                item,
+            });
+        }
+    }
+    found.sort_by_key(|expiring| expiring.item.expires_at);
+    Ok(found)
+}
@@ -0,0 +1,88 @@
+use mongodb::bson::oid::ObjectId;
+
+use crate::access::{parent_resource, Resource};
+use crate::entities::{group::Group, property::Property, zone::Zone};
+use crate::error::ApiError;
+use crate::repository::Repositories;
+
+/// Ruta de un elemento hasta su grupo: el grupo, la propiedad y las zonas intermedias
+/// (de la más externa a la más interna).
+pub struct Ancestors {
+    pub group: Option<Group>,
+    pub property: Option<Property>,
+    pub zones: Vec<Zone>,
+}
+
+/// El identificador puede ser de un item, una zona o una propiedad.
+pub async fn resource_of(repos: &Repositories, id: ObjectId) -> Result<Resource, ApiError> {
+    match repos.items.find_by_id(id).await {
+        Ok(Some(_)) => Ok(Resource::Item(id)),
+        Ok(None) => match parent_resource(repos, id).await {
+            Ok(resource) => Ok(resource),
+            Err(_) => Err(ApiError::unexpected()),
+        },
+        Err(_) => Err(ApiError::unexpected()),
+    }
+}
+
+pub async fn ancestors(repos: &Repositories, obj_id: ObjectId) -> Ancestors {
+    let mut current_zone_id: Option<ObjectId> = None;
+    let mut zones: Vec<Zone> = Vec::new();
+    let mut property: Option<Property> = None;
+    let mut group: Option<Group> = None;
+
+    // Paso 1: Buscar si es un item
+    if let Ok(Some(item)) = repos.items.find_by_id(obj_id).await {
+        current_zone_id = Some(item.zone_id);
+    } else if let Ok(Some(zone)) = repos.zones.find_by_id(obj_id).await {
+        // Paso 2: Si no es item, buscar si es zona
+        current_zone_id = zone.id;
+    }
+
+    // Paso 3: Recorrer zonas hasta llegar a la propiedad
+    if let Some(mut zone_id) = current_zone_id {
+        let mut first_time = true;
+        while let Ok(Some(zone)) = repos.zones.find_by_id(zone_id).await {
+            // Saltar el primer elemento si first_time es true
+            if first_time {
+                first_time = false;
+            } else {
+                // Insertar al principio para que la última zona esté arriba
+                zones.insert(0, zone.clone());
+            }
+            // Si parent_zone_id == property_id, parar
+            match zone.parent_zone_id {
+                Some(parent_zone_id) if parent_zone_id != zone.property_id => {
+                    zone_id = parent_zone_id;
+                }
+                _ => break,
+            }
+        }
+        // La última zona (la que cumple parent_zone_id == property_id) se usa solo para obtener la propiedad
+        if let Ok(Some(last_zone)) = repos.zones.find_by_id(zone_id).await {
+            if let Ok(Some(prop)) = repos.properties.find_by_id(last_zone.property_id).await {
+                property = Some(prop);
+            }
+        }
+    }
+
+    // Paso 4: Buscar propiedad si no se encontró antes
+    if property.is_none() {
+        if let Ok(Some(prop)) = repos.properties.find_by_id(obj_id).await {
+            property = Some(prop);
+        }
+    }
+
+    // Paso 5: Buscar grupo si se tiene propiedad
+    if let Some(ref prop) = property {
+        if let Ok(Some(gr)) = repos.groups.find_by_id(prop.group_id).await {
+            group = Some(gr);
+        }
+    }
+
+    Ancestors {
+        group,
+        property,
+        zones,
+    }
+}
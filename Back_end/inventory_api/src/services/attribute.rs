@@ -0,0 +1,298 @@
+use std::collections::HashMap;
+
+use mongodb::bson::{self, oid::ObjectId, Bson, Document};
+use serde_json::json;
+
+use crate::dates::parse_date;
+use crate::entities::attribute::{AttributeDef, AttributeType};
+use crate::entities::item::Item;
+use crate::entities::session::to_rfc3339;
+use crate::error::ApiError;
+use crate::repository::{AttributeCondition, Changes, Repositories};
+
+/// Lee y valida la lista de atributos de un grupo.
+pub fn parse_schema(body: &serde_json::Value) -> Result<Vec<AttributeDef>, ApiError> {
+    let values = match body.as_array() {
+        Some(values) => values,
+        None => return Err(ApiError::validation("Se esperaba una lista de atributos")),
+    };
+    let mut schema: Vec<AttributeDef> = Vec::new();
+    for (index, value) in values.iter().enumerate() {
+        let field = format!("attributes[{}]", index);
+        let mut def: AttributeDef = serde_json::from_value(value.clone()).map_err(|e| {
+            ApiError::validation("Atributo inválido")
+                .with_details(json!({"field": field, "reason": e.to_string()}))
+        })?;
+        def.name = def.name.trim().to_string();
+        // El nombre se usa como clave del documento del item
+        if def.name.is_empty() || def.name.contains('.') || def.name.starts_with('$') {
+            return Err(ApiError::validation(
+                "El nombre del atributo no puede estar vacío, contener '.' ni empezar por '$'",
+            )
+            .with_details(json!({"field": field})));
+        }
+        if schema.iter().any(|other| other.name == def.name) {
+            return Err(
+                ApiError::validation(format!("El atributo '{}' está repetido", def.name))
+                    .with_details(json!({"field": field})),
+            );
+        }
+        match (def.kind, &def.options) {
+            (AttributeType::Enum, Some(options)) if !options.is_empty() => {}
+            (AttributeType::Enum, _) => {
+                return Err(
+                    ApiError::validation("Los atributos 'enum' necesitan 'options'")
+                        .with_details(json!({"field": field})),
+                )
+            }
+            (_, Some(_)) => {
+                return Err(
+                    ApiError::validation("Solo los atributos 'enum' admiten 'options'")
+                        .with_details(json!({"field": field})),
+                )
+            }
+            (_, None) => {}
+        }
+        schema.push(def);
+    }
+    Ok(schema)
+}
+
+pub async fn group_schema(
+    repos: &Repositories,
+    group_id: ObjectId,
+) -> Result<Vec<AttributeDef>, ApiError> {
+    match repos.groups.find_by_id(group_id).await? {
+        Some(group) => Ok(group.attributes.unwrap_or_default()),
+        None => Err(ApiError::not_found("Grupo no encontrado")),
+    }
+}
+
+pub async fn set_schema(
+    repos: &Repositories,
+    group_id: ObjectId,
+    schema: &[AttributeDef],
+) -> Result<(), ApiError> {
+    let mut changes = Changes::new();
+    if schema.is_empty() {
+        changes.unset("attributes");
+    } else {
+        match bson::to_bson(schema) {
+            Ok(value) => changes.set("attributes", value),
+            Err(_) => return Err(ApiError::internal("Error al guardar los atributos")),
+        }
+    }
+    if !repos.groups.update(group_id, changes).await? {
+        return Err(ApiError::not_found("Grupo no encontrado"));
+    }
+    Ok(())
+}
+
+/// Valor normalizado según el tipo del atributo, o `None` si no es del tipo.
+fn normalize(def: &AttributeDef, value: &Bson) -> Option<Bson> {
+    match (def.kind, value) {
+        (AttributeType::Text, Bson::String(text)) if !text.trim().is_empty() => {
+            Some(Bson::String(text.trim().to_string()))
+        }
+        (AttributeType::Number, Bson::Int32(n)) => Some(Bson::Double(*n as f64)),
+        (AttributeType::Number, Bson::Int64(n)) => Some(Bson::Double(*n as f64)),
+        (AttributeType::Number, Bson::Double(n)) if n.is_finite() => Some(Bson::Double(*n)),
+        (AttributeType::Date, Bson::String(date)) => {
+            parse_date(date).map(|date| Bson::String(to_rfc3339(date)))
+        }
+        (AttributeType::Bool, Bson::Boolean(b)) => Some(Bson::Boolean(*b)),
+        (AttributeType::Enum, Bson::String(option)) => def
+            .options
+            .as_ref()
+            .filter(|options| options.contains(option))
+            .map(|_| Bson::String(option.clone())),
+        _ => None,
+    }
+}
+
+fn type_name(kind: AttributeType) -> &'static str {
+    match kind {
+        AttributeType::Text => "texto",
+        AttributeType::Number => "número",
+        AttributeType::Date => "fecha",
+        AttributeType::Bool => "booleano",
+        AttributeType::Enum => "una de las opciones",
+    }
+}
+
+/// Comprueba el tipo de los valores contra los atributos del grupo y los devuelve
+/// normalizados. Los valores `null` se descartan.
+pub fn normalize_values(
+    schema: &[AttributeDef],
+    values: Option<Document>,
+) -> Result<Document, ApiError> {
+    let mut validated = Document::new();
+    for (name, value) in values.unwrap_or_default() {
+        let field = format!("attributes.{}", name);
+        let def = match schema.iter().find(|def| def.name == name) {
+            Some(def) => def,
+            None => {
+                return Err(ApiError::validation(format!(
+                    "El atributo '{}' no está definido en el grupo",
+                    name
+                ))
+                .with_details(json!({"field": field})))
+            }
+        };
+        if value == Bson::Null {
+            continue;
+        }
+        match normalize(def, &value) {
+            Some(value) => {
+                validated.insert(name, value);
+            }
+            None => {
+                return Err(ApiError::validation(format!(
+                    "El atributo '{}' debe ser {}",
+                    name,
+                    type_name(def.kind)
+                ))
+                .with_details(json!({"field": field})))
+            }
+        }
+    }
+    Ok(validated)
+}
+
+/// Como [`normalize_values`], y además exige los atributos obligatorios.
+pub fn validate_values(
+    schema: &[AttributeDef],
+    values: Option<Document>,
+) -> Result<Option<Document>, ApiError> {
+    let validated = normalize_values(schema, values)?;
+    for def in schema.iter().filter(|def| def.required) {
+        if !validated.contains_key(&def.name) {
+            return Err(
+                ApiError::validation(format!("El atributo '{}' es obligatorio", def.name))
+                    .with_details(json!({"field": format!("attributes.{}", def.name)})),
+            );
+        }
+    }
+    Ok(if validated.is_empty() {
+        None
+    } else {
+        Some(validated)
+    })
+}
+
+/// Atributos de un item nuevo del grupo, ya validados.
+pub async fn new_item_attributes(
+    repos: &Repositories,
+    group_id: ObjectId,
+    item: &Item,
+) -> Result<Option<Document>, ApiError> {
+    let schema = group_schema(repos, group_id).await?;
+    validate_values(&schema, item.attributes.clone())
+}
+
+/// En un PATCH, `attributes` solo trae los valores que cambian (`null` los quita). Se
+/// combinan con los que ya tiene el item y el resultado sustituye al anterior.
+pub async fn check_changed_attributes(
+    repos: &Repositories,
+    group_id: ObjectId,
+    item_id: ObjectId,
+    changes: &mut Changes,
+) -> Result<(), ApiError> {
+    let patch = match changes.set_fields().get("attributes") {
+        Some(Bson::Document(patch)) => Some(patch.clone()),
+        Some(_) => None,
+        None if changes.unset_fields().iter().any(|f| f == "attributes") => None,
+        None => return Ok(()),
+    };
+    let item = match repos.items.find_by_id(item_id).await? {
+        Some(item) => item,
+        None => return Err(ApiError::not_found("Objeto no encontrado")),
+    };
+    let mut merged = match patch {
+        Some(_) => item.attributes.unwrap_or_default(),
+        None => Document::new(),
+    };
+    for (name, value) in patch.unwrap_or_default() {
+        merged.insert(name, value);
+    }
+    let schema = group_schema(repos, group_id).await?;
+    match validate_values(&schema, Some(merged))? {
+        Some(values) => changes.set("attributes", values),
+        None => changes.unset("attributes"),
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterOp {
+    Eq,
+    Min,
+    Max,
+}
+
+/// Filtro de búsqueda sobre un atributo: `attr.<nombre>=valor`, `attr.<nombre>.min=valor`
+/// o `attr.<nombre>.max=valor`.
+#[derive(Debug, Clone)]
+pub struct AttributeFilter {
+    pub name: String,
+    pub op: FilterOp,
+    pub value: String,
+}
+
+pub fn parse_filters(query: &HashMap<String, String>) -> Result<Vec<AttributeFilter>, ApiError> {
+    let mut filters = Vec::new();
+    for (key, value) in query {
+        let Some(rest) = key.strip_prefix("attr.") else {
+            continue;
+        };
+        let (name, op) = match rest.rsplit_once('.') {
+            Some((name, "min")) => (name, FilterOp::Min),
+            Some((name, "max")) => (name, FilterOp::Max),
+            Some(_) => return Err(ApiError::validation(
+                "Los filtros de atributos son attr.<nombre>, attr.<nombre>.min y attr.<nombre>.max",
+            )
+            .with_details(json!({"field": key}))),
+            None => (rest, FilterOp::Eq),
+        };
+        if name.is_empty() {
+            return Err(ApiError::validation("Falta el nombre del atributo")
+                .with_details(json!({"field": key})));
+        }
+        filters.push(AttributeFilter {
+            name: name.to_string(),
+            op,
+            value: value.clone(),
+        });
+    }
+    Ok(filters)
+}
+
+/// Condiciones que tienen que cumplir los atributos de los items de un grupo con su esquema,
+/// con el valor de cada filtro interpretado con el tipo que tiene el atributo en el grupo.
+/// `None` si ningún item del grupo puede cumplirlos (un atributo que el grupo no tiene, un
+/// valor que no es de su tipo o un mínimo sobre algo que no es número ni fecha).
+pub fn conditions(
+    schema: &[AttributeDef],
+    filters: &[AttributeFilter],
+) -> Option<Vec<AttributeCondition>> {
+    filters
+        .iter()
+        .map(|filter| {
+            let def = schema.iter().find(|def| def.name == filter.name)?;
+            let wanted = match def.kind {
+                AttributeType::Number => filter.value.parse::<f64>().ok().map(Bson::Double),
+                AttributeType::Bool => filter.value.parse::<bool>().ok().map(Bson::Boolean),
+                _ => Some(Bson::String(filter.value.clone())),
+            };
+            let wanted = normalize(def, &wanted?)?;
+            let ordered = matches!(def.kind, AttributeType::Number | AttributeType::Date);
+            let name = filter.name.clone();
+            match filter.op {
+                FilterOp::Eq => Some(AttributeCondition::Equals(name, wanted)),
+                FilterOp::Min if ordered => Some(AttributeCondition::AtLeast(name, wanted)),
+                FilterOp::Max if ordered => Some(AttributeCondition::AtMost(name, wanted)),
+                _ => None,
+            }
+        })
+        .collect()
+}
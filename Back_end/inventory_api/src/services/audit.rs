@@ -0,0 +1,190 @@
+use mongodb::bson::{self, oid::ObjectId, DateTime, Document};
+use serde::Serialize;
+
+use crate::access::{authorize, Resource};
+use crate::entities::audit::{AuditAction, AuditEntity, AuditEvent};
+use crate::entities::user_group::GroupRole;
+use crate::error::ApiError;
+use crate::log::write_log;
+use crate::middleware::auth::AuthUser;
+use crate::repository::Repositories;
+
+/// Eventos que se devuelven si no se indica `limit`.
+pub const DEFAULT_LIMIT: i64 = 100;
+pub const MAX_LIMIT: i64 = 500;
+
+fn snapshot<T: Serialize>(value: &T) -> Option<Document> {
+    let mut document = bson::to_document(value).ok()?;
+    document.remove("_id");
+    Some(document)
+}
+
+/// Campos que cambian entre las dos versiones, con su valor antes y después.
+fn diff(before: &Document, after: &Document) -> (Document, Document) {
+    let mut old = Document::new();
+    let mut new = Document::new();
+    for (field, value) in before {
+        if after.get(field) != Some(value) {
+            old.insert(field, value.clone());
+        }
+    }
+    for (field, value) in after {
+        if before.get(field) != Some(value) {
+            new.insert(field, value.clone());
+        }
+    }
+    (old, new)
+}
+
+/// Registra un cambio en el historial. La acción se deduce de las versiones: solo `after`
+/// es un alta, solo `before` un borrado y ambas una modificación (que no se registra si no
+/// cambia nada). Los fallos solo se anotan en el log, porque el cambio ya está hecho.
+pub async fn record<T: Serialize>(
+    repos: &Repositories,
+    user_id: ObjectId,
+    group_id: ObjectId,
+    entity_type: AuditEntity,
+    entity_id: ObjectId,
+    before: Option<&T>,
+    after: Option<&T>,
+) {
+    let before = before.and_then(snapshot);
+    let after = after.and_then(snapshot);
+    let (action, before, after) = match (before, after) {
+        (None, Some(after)) => (AuditAction::Create, None, Some(after)),
+        (Some(before), None) => (AuditAction::Delete, Some(before), None),
+        (Some(before), Some(after)) => {
+            let (before, after) = diff(&before, &after);
+            if before.is_empty() && after.is_empty() {
+                return;
+            }
+            (AuditAction::Update, Some(before), Some(after))
+        }
+        (None, None) => return,
+    };
+    insert_event(
+        repos,
+        AuditEvent {
+            id: None,
+            group_id,
+            entity_type,
+            entity_id,
+            action,
+            user_id,
+            before,
+            after,
+            created_at: DateTime::now(),
+        },
+    )
+    .await;
+}
+
+/// Registra una modificación con los campos indicados aunque su valor no cambie, como al
+/// subir una imagen nueva que se guarda con el mismo nombre.
+pub async fn record_update(
+    repos: &Repositories,
+    user_id: ObjectId,
+    group_id: ObjectId,
+    entity_type: AuditEntity,
+    entity_id: ObjectId,
+    before: Document,
+    after: Document,
+) {
+    insert_event(
+        repos,
+        AuditEvent {
+            id: None,
+            group_id,
+            entity_type,
+            entity_id,
+            action: AuditAction::Update,
+            user_id,
+            before: Some(before),
+            after: Some(after),
+            created_at: DateTime::now(),
+        },
+    )
+    .await;
+}
+
+/// Registra que el elemento ha salido de la papelera.
+pub async fn record_restore<T: Serialize>(
+    repos: &Repositories,
+    user_id: ObjectId,
+    group_id: ObjectId,
+    entity_type: AuditEntity,
+    entity_id: ObjectId,
+    after: Option<&T>,
+) {
+    insert_event(
+        repos,
+        AuditEvent {
+            id: None,
+            group_id,
+            entity_type,
+            entity_id,
+            action: AuditAction::Restore,
+            user_id,
+            before: None,
+            after: after.and_then(snapshot),
+            created_at: DateTime::now(),
+        },
+    )
+    .await;
+}
+
+async fn insert_event(repos: &Repositories, event: AuditEvent) {
+    if let Err(e) = repos.audit.insert(&event).await {
+        write_log(&format!(
+            "[AUDIT] Error registrando {:?} de {:?} {}: {}",
+            event.action, event.entity_type, event.entity_id, e
+        ))
+        .ok();
+    }
+}
+
+/// Historial de un elemento, si el usuario puede verlo. El de un elemento ya borrado solo lo
+/// ven los propietarios de su grupo.
+pub async fn entity_history(
+    repos: &Repositories,
+    user: &AuthUser,
+    entity_type: AuditEntity,
+    entity_id: ObjectId,
+    limit: i64,
+) -> Result<Vec<AuditEvent>, ApiError> {
+    let route = "GET /history/{entityType}/{id}";
+    match authorize(
+        repos,
+        user,
+        entity_type.resource(entity_id),
+        GroupRole::Viewer,
+        route,
+    )
+    .await
+    {
+        Ok(_) => Ok(repos
+            .audit
+            .find_by_entity(entity_type, entity_id, limit)
+            .await?),
+        Err(e @ ApiError::NotFound { .. }) => {
+            let events = repos
+                .audit
+                .find_by_entity(entity_type, entity_id, limit)
+                .await?;
+            let group_id = match events.first() {
+                Some(event) => event.group_id,
+                None => return Err(e),
+            };
+            authorize(
+                repos,
+                user,
+                Resource::Group(group_id),
+                GroupRole::Owner,
+                route,
+            )
+            .await?;
+            Ok(events)
+        }
+        Err(e) => Err(e),
+    }
+}
@@ -0,0 +1,333 @@
+use mongodb::bson::oid::ObjectId;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::HashSet;
+
+use crate::access::{authorize, Resource};
+use crate::entities::audit::AuditEntity;
+use crate::entities::item::Item;
+use crate::entities::user_group::GroupRole;
+use crate::error::ApiError;
+use crate::middleware::auth::AuthUser;
+use crate::repository::{ItemWrite, Repositories};
+use crate::services::attribute::{check_changed_attributes, new_item_attributes};
+use crate::services::audit::record;
+use crate::services::category::{check_category, check_changed_category};
+use crate::services::item::{check_changed_dates, item_changes, move_changes, validate_new_item};
+use crate::services::stock::initial_movement;
+use crate::services::trash::trash_entry;
+
+/// Máximo de operaciones por petición.
+pub const MAX_OPERATIONS: usize = 200;
+
+const ROUTE: &str = "POST /items/bulk";
+
+/// Petición `{allOrNothing?, operations: [...]}`.
+pub struct BulkRequest {
+    pub all_or_nothing: bool,
+    pub operations: Vec<Value>,
+}
+
+pub fn bulk_request(body: &Value) -> Result<BulkRequest, ApiError> {
+    let all_or_nothing = match body.get("allOrNothing") {
+        None | Some(Value::Null) => false,
+        Some(Value::Bool(value)) => *value,
+        Some(_) => {
+            return Err(ApiError::validation("Valor inválido para 'allOrNothing'")
+                .with_details(json!({"field": "allOrNothing"})))
+        }
+    };
+    let operations = match body.get("operations") {
+        Some(Value::Array(operations)) if !operations.is_empty() => operations.clone(),
+        _ => {
+            return Err(
+                ApiError::validation("'operations' debe ser una lista no vacía")
+                    .with_details(json!({"field": "operations"})),
+            )
+        }
+    };
+    if operations.len() > MAX_OPERATIONS {
+        return Err(ApiError::validation(format!(
+            "No se admiten más de {} operaciones por petición",
+            MAX_OPERATIONS
+        ))
+        .with_details(json!({"field": "operations", "max": MAX_OPERATIONS})));
+    }
+    Ok(BulkRequest {
+        all_or_nothing,
+        operations,
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OperationStatus {
+    Ok,
+    Error,
+    /// No se aplicó porque otra operación falló en modo todo o nada.
+    Skipped,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OperationResult {
+    pub index: usize,
+    pub op: Option<String>,
+    pub status: OperationStatus,
+    /// Item afectado (el nuevo, en las altas).
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<Value>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BulkResult {
+    /// Si se confirmó la transacción con las operaciones válidas.
+    pub committed: bool,
+    pub results: Vec<OperationResult>,
+}
+
+/// Operación validada y autorizada, lista para escribirse.
+struct Planned {
+    write: ItemWrite,
+    id: ObjectId,
+    /// Grupos en cuyo historial se anota (origen y destino en los movimientos).
+    groups: Vec<ObjectId>,
+    /// Item antes de la operación, para el historial.
+    before: Option<Item>,
+}
+
+fn object_id(operation: &Value, field: &str) -> Result<ObjectId, ApiError> {
+    match operation.get(field).and_then(|v| v.as_str()) {
+        Some(id) => ObjectId::parse_str(id).map_err(|_| {
+            ApiError::validation(format!("'{}' inválido", field))
+                .with_details(json!({"field": field}))
+        }),
+        None => Err(ApiError::validation(format!("'{}' es requerido", field))
+            .with_details(json!({"field": field}))),
+    }
+}
+
+/// Item tal y como está antes de modificarlo o borrarlo.
+async fn current_item(repos: &Repositories, id: ObjectId) -> Result<Item, ApiError> {
+    match repos.items.find_by_id(id).await? {
+        Some(item) => Ok(item),
+        None => Err(ApiError::not_found("Objeto no encontrado")),
+    }
+}
+
+/// Valida una operación y comprueba los permisos igual que su endpoint individual.
+async fn plan(
+    repos: &Repositories,
+    user: &AuthUser,
+    operation: &Value,
+) -> Result<Planned, ApiError> {
+    match operation.get("op").and_then(|v| v.as_str()) {
+        Some("create") => {
+            let item: Item = match operation.get("item") {
+                Some(item) => serde_json::from_value(item.clone()).map_err(|e| {
+                    ApiError::validation("Item inválido")
+                        .with_details(json!({"field": "item", "reason": e.to_string()}))
+                })?,
+                None => {
+                    return Err(ApiError::validation("'item' es requerido")
+                        .with_details(json!({"field": "item"})))
+                }
+            };
+            let group_id = authorize(
+                repos,
+                user,
+                Resource::Zone(item.zone_id),
+                GroupRole::Editor,
+                ROUTE,
+            )
+            .await?;
+            let mut item = validate_new_item(item)?;
+            check_category(repos, group_id, item.category_id).await?;
+            item.attributes = new_item_attributes(repos, group_id, &item).await?;
+            let id = ObjectId::new();
+            item.id = Some(id);
+            let movement = item
+                .quantity
+                .filter(|q| *q > 0.0)
+                .map(|quantity| initial_movement(id, user.id, quantity));
+            Ok(Planned {
+                write: ItemWrite::Insert {
+                    item: Box::new(item),
+                    movement,
+                },
+                id,
+                groups: vec![group_id],
+                before: None,
+            })
+        }
+        Some("patch") => {
+            let id = object_id(operation, "id")?;
+            let group_id =
+                authorize(repos, user, Resource::Item(id), GroupRole::Editor, ROUTE).await?;
+            let mut changes = item_changes(operation.get("changes").unwrap_or(&Value::Null))?;
+            check_changed_dates(repos, id, &changes).await?;
+            check_changed_category(repos, group_id, &changes).await?;
+            check_changed_attributes(repos, group_id, id, &mut changes).await?;
+            Ok(Planned {
+                write: ItemWrite::Update { id, changes },
+                id,
+                groups: vec![group_id],
+                before: Some(current_item(repos, id).await?),
+            })
+        }
+        Some("move") => {
+            let id = object_id(operation, "id")?;
+            let zone_id = object_id(operation, "zoneId")?;
+            let group_id =
+                authorize(repos, user, Resource::Item(id), GroupRole::Editor, ROUTE).await?;
+            let target_group_id = authorize(
+                repos,
+                user,
+                Resource::Zone(zone_id),
+                GroupRole::Editor,
+                ROUTE,
+            )
+            .await?;
+            let mut groups = vec![group_id];
+            if target_group_id != group_id {
+                groups.push(target_group_id);
+            }
+            let item = current_item(repos, id).await?;
+            let changes = move_changes(repos, &item, zone_id, group_id, target_group_id).await?;
+            Ok(Planned {
+                write: ItemWrite::Update { id, changes },
+                id,
+                groups,
+                before: Some(item),
+            })
+        }
+        Some("delete") => {
+            let id = object_id(operation, "id")?;
+            let group_id =
+                authorize(repos, user, Resource::Item(id), GroupRole::Editor, ROUTE).await?;
+            let item = current_item(repos, id).await?;
+            let mut entry = trash_entry(group_id, AuditEntity::Item, id, &item.name, user.id);
+            entry.id = Some(ObjectId::new());
+            entry.items = 1;
+            Ok(Planned {
+                write: ItemWrite::Delete { id, entry },
+                id,
+                groups: vec![group_id],
+                before: Some(item),
+            })
+        }
+        _ => Err(
+            ApiError::validation("'op' debe ser 'create', 'patch', 'move' o 'delete'")
+                .with_details(json!({"field": "op"})),
+        ),
+    }
+}
+
+/// Valida y autoriza todas las operaciones y aplica las correctas en una sola transacción.
+/// Con `allOrNothing`, si alguna falla no se aplica ninguna.
+pub async fn run_bulk(
+    repos: &Repositories,
+    user: &AuthUser,
+    request: BulkRequest,
+) -> Result<BulkResult, ApiError> {
+    let mut results = Vec::with_capacity(request.operations.len());
+    let mut planned = Vec::new();
+    let mut seen = HashSet::new();
+    for (index, operation) in request.operations.iter().enumerate() {
+        let op = operation
+            .get("op")
+            .and_then(|v| v.as_str())
+            .map(String::from);
+        // Cada item solo puede escribirse en una operación: la segunda se habría validado
+        // contra el estado anterior a la primera
+        let planned_op = match plan(repos, user, operation).await {
+            Ok(plan) if !seen.insert(plan.id) => Err(ApiError::validation(
+                "El item ya aparece en otra operación de la petición",
+            )
+            .with_details(json!({"field": "id"}))),
+            result => result,
+        };
+        match planned_op {
+            Ok(plan) => {
+                results.push(OperationResult {
+                    index,
+                    op,
+                    status: OperationStatus::Ok,
+                    id: Some(plan.id),
+                    error: None,
+                });
+                planned.push((index, plan));
+            }
+            Err(e) => results.push(OperationResult {
+                index,
+                op,
+                status: OperationStatus::Error,
+                id: None,
+                error: Some(e.body()),
+            }),
+        }
+    }
+
+    let any_failed = results.iter().any(|r| r.status == OperationStatus::Error);
+    if planned.is_empty() || (request.all_or_nothing && any_failed) {
+        skip_planned(&mut results, &planned);
+        return Ok(BulkResult {
+            committed: false,
+            results,
+        });
+    }
+
+    let writes: Vec<ItemWrite> = planned.iter().map(|(_, p)| p.write.clone()).collect();
+    let found = repos
+        .bulk
+        .write_items(&writes, request.all_or_nothing)
+        .await?;
+    // Elementos que desaparecieron entre la validación y la escritura
+    let mut committed = true;
+    for ((index, _), found) in planned.iter().zip(&found) {
+        if !found {
+            results[*index].status = OperationStatus::Error;
+            results[*index].id = None;
+            results[*index].error = Some(ApiError::not_found("Objeto no encontrado").body());
+            committed = !request.all_or_nothing;
+        }
+    }
+    if !committed {
+        skip_planned(&mut results, &planned);
+        return Ok(BulkResult { committed, results });
+    }
+    for ((_, plan), found) in planned.iter().zip(&found) {
+        if !found {
+            continue;
+        }
+        let after = match &plan.write {
+            ItemWrite::Insert { item, .. } => Some(item.as_ref().clone()),
+            ItemWrite::Update { id, .. } => repos.items.find_by_id(*id).await.ok().flatten(),
+            ItemWrite::Delete { .. } => None,
+        };
+        for group_id in &plan.groups {
+            record(
+                repos,
+                user.id,
+                *group_id,
+                AuditEntity::Item,
+                plan.id,
+                plan.before.as_ref(),
+                after.as_ref(),
+            )
+            .await;
+        }
+    }
+    Ok(BulkResult { committed, results })
+}
+
+/// Marca como no aplicadas las operaciones válidas.
+fn skip_planned(results: &mut [OperationResult], planned: &[(usize, Planned)]) {
+    for (index, _) in planned {
+        if results[*index].status == OperationStatus::Ok {
+            results[*index].status = OperationStatus::Skipped;
+            results[*index].id = None;
+        }
+    }
+}
@@ -0,0 +1,111 @@
+use mongodb::bson::oid::ObjectId;
+use serde_json::json;
+
+use crate::entities::category::Category;
+use crate::error::ApiError;
+use crate::repository::{Changes, Repositories};
+
+/// Nombre de la categoría en el cuerpo de la petición, sin espacios a los lados.
+pub fn category_name(body: &serde_json::Value) -> Result<String, ApiError> {
+    match body.get("name").and_then(|name| name.as_str()) {
+        Some(name) if !name.trim().is_empty() => Ok(name.trim().to_string()),
+        _ => {
+            Err(ApiError::validation("'name' es requerido").with_details(json!({"field": "name"})))
+        }
+    }
+}
+
+/// Los nombres no se repiten dentro del grupo (sin distinguir mayúsculas).
+async fn check_unique_name(
+    repos: &Repositories,
+    group_id: ObjectId,
+    name: &str,
+    except: Option<ObjectId>,
+) -> Result<(), ApiError> {
+    let taken = repos
+        .categories
+        .find_by_group(group_id)
+        .await?
+        .iter()
+        .any(|other| other.id != except && other.name.to_lowercase() == name.to_lowercase());
+    if taken {
+        return Err(ApiError::conflict(format!(
+            "Ya existe la categoría '{}' en el grupo",
+            name
+        )));
+    }
+    Ok(())
+}
+
+pub async fn create_category(
+    repos: &Repositories,
+    group_id: ObjectId,
+    name: String,
+) -> Result<ObjectId, ApiError> {
+    check_unique_name(repos, group_id, &name, None).await?;
+    let category = Category {
+        id: None,
+        group_id,
+        name,
+    };
+    Ok(repos.categories.insert(&category).await?)
+}
+
+pub async fn rename_category(
+    repos: &Repositories,
+    category: &Category,
+    name: String,
+) -> Result<(), ApiError> {
+    check_unique_name(repos, category.group_id, &name, category.id).await?;
+    let category_id = match category.id {
+        Some(id) => id,
+        None => return Err(ApiError::internal("No hay ID")),
+    };
+    let mut changes = Changes::new();
+    changes.set("name", name);
+    if !repos.categories.update(category_id, changes).await? {
+        return Err(ApiError::not_found("Categoría no encontrada"));
+    }
+    Ok(())
+}
+
+/// Borra la categoría y se la quita a los items (también a los de la papelera) y a las
+/// plantillas que la tenían.
+pub async fn delete_category(repos: &Repositories, category: &Category) -> Result<(), ApiError> {
+    let category_id = match category.id {
+        Some(id) => id,
+        None => return Err(ApiError::internal("No hay ID")),
+    };
+    if !repos.categories.delete(category_id).await? {
+        return Err(ApiError::not_found("Categoría no encontrada"));
+    }
+    repos.items.clear_category(category_id).await?;
+    repos.templates.clear_category(category_id).await?;
+    Ok(())
+}
+
+/// La categoría tiene que ser del grupo.
+pub async fn check_category(
+    repos: &Repositories,
+    group_id: ObjectId,
+    category_id: Option<ObjectId>,
+) -> Result<(), ApiError> {
+    let Some(category_id) = category_id else {
+        return Ok(());
+    };
+    match repos.categories.find_by_id(category_id).await? {
+        Some(category) if category.group_id == group_id => Ok(()),
+        _ => Err(ApiError::validation("La categoría no existe en el grupo")
+            .with_details(json!({"field": "categoryId"}))),
+    }
+}
+
+/// Comprueba la categoría que asignan los cambios de un item, si asignan alguna.
+pub async fn check_changed_category(
+    repos: &Repositories,
+    group_id: ObjectId,
+    changes: &Changes,
+) -> Result<(), ApiError> {
+    let category_id = changes.set_fields().get_object_id("categoryId").ok();
+    check_category(repos, group_id, category_id).await
+}
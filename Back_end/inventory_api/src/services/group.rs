@@ -0,0 +1,241 @@
+use mongodb::bson::oid::ObjectId;
+use rand::Rng;
+
+use crate::entities::audit::AuditEntity;
+use crate::entities::{
+    group::{CreateGroup, Group},
+    user_group::{GroupRole, UserGroup},
+};
+use crate::error::ApiError;
+use crate::repository::{Changes, Repositories};
+use crate::services::trash::move_to_trash;
+
+/// Grupos a los que pertenece el usuario; los administradores ven todos.
+pub async fn visible_groups(
+    repos: &Repositories,
+    user_id: ObjectId,
+    is_admin: bool,
+) -> Result<Vec<Group>, ApiError> {
+    if is_admin {
+        return Ok(repos.groups.find_all().await?);
+    }
+    let group_ids: Vec<ObjectId> = repos
+        .user_groups
+        .find_by_user(user_id)
+        .await?
+        .into_iter()
+        .map(|ug| ug.group_id)
+        .collect();
+    Ok(repos.groups.find_by_ids(&group_ids).await?)
+}
+
+async fn generate_unique_group_code(repos: &Repositories) -> String {
+    loop {
+        let mut rand = rand::rng();
+        let characters: Vec<char> = ('0'..='9').chain('a'..='z').chain('A'..='Z').collect();
+
+        let group_code = (0..8)
+            .map(|_| characters[rand.random_range(0..characters.len())])
+            .collect::<String>();
+
+        if repos
+            .groups
+            .find_by_code(&group_code)
+            .await
+            .unwrap_or(None)
+            .is_none()
+        {
+            return group_code;
+        }
+    }
+}
+
+/// Crea el grupo con el usuario como propietario y devuelve su ID y su código.
+pub async fn create_group(
+    repos: &Repositories,
+    user_id: ObjectId,
+    new_group: &CreateGroup,
+) -> Result<(ObjectId, String), ApiError> {
+    let group_code = generate_unique_group_code(repos).await;
+    let group = Group {
+        id: None,
+        name: new_group.name.clone(),
+        user_max: new_group.user_max,
+        user_count: 1,
+        group_code: group_code.clone(),
+        attributes: None,
+        deleted_at: None,
+        trash_id: None,
+    };
+    let group_id = repos.groups.insert(&group).await?;
+    let user_group = UserGroup {
+        id: None,
+        group_id,
+        user_id,
+        role: GroupRole::Owner,
+    };
+    if repos.user_groups.insert(&user_group).await.is_err() {
+        return Err(ApiError::internal(
+            "Error al crear la relación usuario-grupo",
+        ));
+    }
+    Ok((group_id, group_code))
+}
+
+/// Une al usuario al grupo con el código indicado, como lector.
+pub async fn join_group(
+    repos: &Repositories,
+    user_id: ObjectId,
+    group_code: &str,
+) -> Result<(), ApiError> {
+    let group = match repos.groups.find_by_code(group_code).await {
+        Ok(Some(group)) => group,
+        Ok(None) => return Err(ApiError::not_found("Grupo no encontrado")),
+        Err(_) => return Err(ApiError::internal("Error al buscar el grupo")),
+    };
+    let group_id = match group.id {
+        Some(id) => id,
+        None => return Err(ApiError::internal("No hay ID")),
+    };
+    if let Ok(Some(_)) = repos.user_groups.find(user_id, group_id).await {
+        return Err(ApiError::conflict("Ya eres miembro de este grupo"));
+    }
+    if let Some(max_users) = group.user_max {
+        if group.user_count >= max_users {
+            return Err(ApiError::conflict(
+                "El grupo ha alcanzado su límite de usuarios",
+            ));
+        }
+    }
+    let user_group = UserGroup {
+        id: None,
+        group_id,
+        user_id,
+        role: GroupRole::Viewer,
+    };
+    if repos.user_groups.insert(&user_group).await.is_err() {
+        return Err(ApiError::internal("Error al unirse al grupo"));
+    }
+    if repos.groups.add_user_count(group_id, 1).await.is_err() {
+        return Err(ApiError::internal(
+            "Error al actualizar el contador de usuarios",
+        ));
+    }
+    Ok(())
+}
+
+/// Modifica el grupo. Si `userCount` pasa a 0 el grupo pasa a la papelera y se devuelve
+/// `true`.
+pub async fn patch_group(
+    repos: &Repositories,
+    user_id: ObjectId,
+    group_id: ObjectId,
+    updated_group: &serde_json::Value,
+) -> Result<bool, ApiError> {
+    // Recuperar grupo actual para validaciones
+    let existing_group = match repos.groups.find_by_id(group_id).await {
+        Ok(Some(group)) => group,
+        Ok(None) => return Err(ApiError::not_found("Grupo no encontrado")),
+        Err(_) => return Err(ApiError::internal("Error al acceder a la base de datos")),
+    };
+
+    let name = updated_group.get("name");
+    let user_count = updated_group.get("userCount");
+
+    let user_count_val = user_count
+        .and_then(|v| v.as_i64())
+        .or(Some(existing_group.user_count.into()));
+
+    if let Some(0) = user_count_val {
+        move_to_trash(repos, user_id, group_id, AuditEntity::Group, group_id).await?;
+        return Ok(true);
+    }
+
+    let mut changes = Changes::new();
+
+    if let Some(name_val) = name.and_then(|v| v.as_str()) {
+        changes.set("name", name_val);
+    }
+
+    if let Some(user_count_val) = user_count.and_then(|v| v.as_i64()) {
+        changes.set("userCount", user_count_val);
+    }
+
+    match updated_group.get("userMax") {
+        Some(val) if val.is_null() => {
+            changes.unset("userMax");
+        }
+        Some(val) => {
+            if let Some(user_max_val) = val.as_i64() {
+                if let Some(current_user_count) = user_count_val {
+                    if user_max_val < current_user_count {
+                        return Err(ApiError::validation(
+                            "Mayor numero de usuarios de los permitidos",
+                        ));
+                    }
+                }
+                changes.set("userMax", user_max_val);
+            }
+        }
+        None => {}
+    }
+
+    if changes.is_empty() {
+        return Err(ApiError::validation(
+            "No se especificaron campos a modificar",
+        ));
+    }
+
+    if !repos.groups.update(group_id, changes).await? {
+        return Err(ApiError::not_found("Grupo no encontrado"));
+    }
+    Ok(false)
+}
+
+/// Saca al usuario del grupo. Devuelve `true` si el grupo ha pasado a la papelera por
+/// quedarse sin miembros.
+pub async fn leave_group(
+    repos: &Repositories,
+    user_id: ObjectId,
+    group_id: ObjectId,
+) -> Result<bool, ApiError> {
+    let role = match repos.user_groups.find(user_id, group_id).await? {
+        Some(user_group) => user_group.role,
+        None => return Err(ApiError::not_found("No eres miembro de este grupo")),
+    };
+    // El último propietario no puede abandonar un grupo que aún tiene otros miembros
+    if role == GroupRole::Owner {
+        let owners = repos.user_groups.count_owners(group_id).await?;
+        let members = repos.user_groups.count_by_group(group_id).await?;
+        if owners <= 1 && members > 1 {
+            return Err(ApiError::validation(
+                "Eres el único propietario: asigna otro propietario antes de salir del grupo",
+            ));
+        }
+    }
+    if repos
+        .user_groups
+        .delete_member(group_id, user_id)
+        .await
+        .is_err()
+    {
+        return Err(ApiError::internal("Error al salir del grupo"));
+    }
+    if repos.groups.add_user_count(group_id, -1).await.is_err() {
+        return Err(ApiError::internal(
+            "Error al actualizar el contador de usuarios",
+        ));
+    }
+    if let Some(group) = repos.groups.find_by_id(group_id).await.unwrap_or(None) {
+        if group.user_count <= 1 {
+            if move_to_trash(repos, user_id, group_id, AuditEntity::Group, group_id)
+                .await
+                .is_err()
+            {
+                return Err(ApiError::internal("Error al eliminar el grupo"));
+            }
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
@@ -0,0 +1,339 @@
+use mongodb::bson::{self, oid::ObjectId, DateTime};
+use serde_json::json;
+use std::fs;
+use std::path::Path;
+
+use crate::dates::parse_date;
+use crate::entities::item::Item;
+use crate::error::ApiError;
+use crate::log::write_log;
+use crate::repository::{Changes, ItemWrite, Repositories};
+use crate::services::attribute::{
+    check_changed_attributes, group_schema, new_item_attributes, validate_values,
+};
+use crate::services::category::{check_category, check_changed_category};
+use crate::services::stock::initial_movement;
+
+/// La compra no puede ser futura ni posterior a la caducidad.
+fn check_dates(
+    purchased_at: Option<DateTime>,
+    expires_at: Option<DateTime>,
+) -> Result<(), ApiError> {
+    if let Some(purchased_at) = purchased_at {
+        if purchased_at > DateTime::now() {
+            return Err(
+                ApiError::validation("'purchasedAt' no puede ser una fecha futura")
+                    .with_details(json!({"field": "purchasedAt"})),
+            );
+        }
+        if expires_at.is_some_and(|expires_at| expires_at < purchased_at) {
+            return Err(
+                ApiError::validation("'expiresAt' no puede ser anterior a 'purchasedAt'")
+                    .with_details(json!({"field": "expiresAt"})),
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Valida la cantidad, el mínimo, las fechas y la unidad de un item nuevo.
+pub fn validate_new_item(mut item: Item) -> Result<Item, ApiError> {
+    if let Some(quantity) = item.quantity {
+        if !quantity.is_finite() || quantity < 0.0 {
+            return Err(ApiError::validation("La cantidad no puede ser negativa")
+                .with_details(json!({"field": "quantity"})));
+        }
+    }
+    if let Some(min) = item.min_quantity {
+        if !min.is_finite() || min < 0.0 {
+            return Err(ApiError::validation("'minQuantity' no puede ser negativo")
+                .with_details(json!({"field": "minQuantity"})));
+        }
+    }
+    check_dates(item.purchased_at, item.expires_at)?;
+    item.unit = item
+        .unit
+        .map(|unit| unit.trim().to_string())
+        .filter(|unit| !unit.is_empty());
+    item.id = None;
+    item.deleted_at = None;
+    item.trash_id = None;
+    item.loan_id = None;
+    Ok(item)
+}
+
+/// Guarda un item nuevo y, si se da de alta con cantidad, lo anota como primer movimiento
+/// de stock en la misma transacción.
+pub async fn create_item(
+    repos: &Repositories,
+    group_id: ObjectId,
+    item: Item,
+    user_id: ObjectId,
+) -> Result<ObjectId, ApiError> {
+    let mut item = validate_new_item(item)?;
+    check_category(repos, group_id, item.category_id).await?;
+    item.attributes = new_item_attributes(repos, group_id, &item).await?;
+    let item_id = ObjectId::new();
+    item.id = Some(item_id);
+    let movement = item
+        .quantity
+        .filter(|q| *q > 0.0)
+        .map(|quantity| initial_movement(item_id, user_id, quantity));
+    let write = ItemWrite::Insert {
+        item: Box::new(item),
+        movement,
+    };
+    repos.bulk.write_items(&[write], true).await?;
+    Ok(item_id)
+}
+
+/// Convierte el cuerpo de un PATCH de item en cambios.
+pub fn item_changes(body: &serde_json::Value) -> Result<Changes, ApiError> {
+    let mut changes = Changes::new();
+    if let Some(value) = body.get("name") {
+        match value {
+            serde_json::Value::String(name) => changes.set("name", name.clone()),
+            serde_json::Value::Null => {
+                return Err(ApiError::validation("'name' no puede ser null")
+                    .with_details(json!({"field": "name"})))
+            }
+            _ => {
+                return Err(ApiError::validation("Valor inválido para 'name'")
+                    .with_details(json!({"field": "name"})))
+            }
+        };
+    }
+    if let Some(value) = body.get("description") {
+        match value {
+            serde_json::Value::String(desc) => changes.set("description", desc.clone()),
+            serde_json::Value::Null => changes.unset("description"),
+            _ => {
+                return Err(ApiError::validation("Valor inválido para 'description'")
+                    .with_details(json!({"field": "description"})))
+            }
+        };
+    }
+    if let Some(value) = body.get("pictureUrl") {
+        match value {
+            serde_json::Value::String(url) => changes.set("pictureUrl", url.clone()),
+            serde_json::Value::Null => changes.unset("pictureUrl"),
+            _ => {
+                return Err(ApiError::validation("Valor inválido para 'pictureUrl'")
+                    .with_details(json!({"field": "pictureUrl"})))
+            }
+        };
+    }
+    if let Some(value) = body.get("tags") {
+        match value {
+            serde_json::Value::Array(tags) => {
+                let string_tags: Vec<String> = tags
+                    .iter()
+                    .filter_map(|v| v.as_str().map(String::from))
+                    .collect();
+                changes.set("tags", string_tags)
+            }
+            serde_json::Value::Null => changes.unset("tags"),
+            _ => {
+                return Err(ApiError::validation("Valor inválido para 'tags'")
+                    .with_details(json!({"field": "tags"})))
+            }
+        };
+    }
+    if let Some(value) = body.get("unit") {
+        match value {
+            serde_json::Value::String(unit) if !unit.trim().is_empty() => {
+                changes.set("unit", unit.trim())
+            }
+            serde_json::Value::Null => changes.unset("unit"),
+            _ => {
+                return Err(ApiError::validation("Valor inválido para 'unit'")
+                    .with_details(json!({"field": "unit"})))
+            }
+        };
+    }
+    if let Some(value) = body.get("minQuantity") {
+        match value.as_f64() {
+            Some(min) if min >= 0.0 => changes.set("minQuantity", min),
+            _ if value.is_null() => changes.unset("minQuantity"),
+            _ => {
+                return Err(ApiError::validation("Valor inválido para 'minQuantity'")
+                    .with_details(json!({"field": "minQuantity"})))
+            }
+        };
+    }
+    for field in ["expiresAt", "purchasedAt"] {
+        if let Some(value) = body.get(field) {
+            match value {
+                serde_json::Value::String(date) => match parse_date(date) {
+                    Some(date) => changes.set(field, date),
+                    None => {
+                        return Err(ApiError::validation(format!(
+                            "'{}' debe ser una fecha (AAAA-MM-DD o RFC 3339)",
+                            field
+                        ))
+                        .with_details(json!({"field": field})))
+                    }
+                },
+                serde_json::Value::Null => changes.unset(field),
+                _ => {
+                    return Err(
+                        ApiError::validation(format!("Valor inválido para '{}'", field))
+                            .with_details(json!({"field": field})),
+                    )
+                }
+            };
+        }
+    }
+    if let Some(value) = body.get("categoryId") {
+        match value {
+            serde_json::Value::String(id) => match ObjectId::parse_str(id) {
+                Ok(id) => changes.set("categoryId", id),
+                Err(_) => {
+                    return Err(ApiError::validation("Valor inválido para 'categoryId'")
+                        .with_details(json!({"field": "categoryId"})))
+                }
+            },
+            serde_json::Value::Null => changes.unset("categoryId"),
+            _ => {
+                return Err(ApiError::validation("Valor inválido para 'categoryId'")
+                    .with_details(json!({"field": "categoryId"})))
+            }
+        };
+    }
+    // Solo los valores que cambian; se combinan con los actuales en check_changed_attributes
+    if let Some(value) = body.get("attributes") {
+        match value {
+            serde_json::Value::Object(_) => match bson::to_document(value) {
+                Ok(values) => changes.set("attributes", values),
+                Err(_) => {
+                    return Err(ApiError::validation("Valor inválido para 'attributes'")
+                        .with_details(json!({"field": "attributes"})))
+                }
+            },
+            serde_json::Value::Null => changes.unset("attributes"),
+            _ => {
+                return Err(ApiError::validation("Valor inválido para 'attributes'")
+                    .with_details(json!({"field": "attributes"})))
+            }
+        };
+    }
+    // La cantidad solo cambia con movimientos, para que quede registrada
+    if body.get("quantity").is_some() {
+        return Err(
+            ApiError::validation("La cantidad se modifica con /items/{id}/movements")
+                .with_details(json!({"field": "quantity"})),
+        );
+    }
+    if changes.is_empty() {
+        return Err(ApiError::validation("No hay campos para actualizar"));
+    }
+    Ok(changes)
+}
+
+/// Comprueba las fechas que quedarían tras aplicar los cambios junto con las que ya tiene
+/// el item.
+pub async fn check_changed_dates(
+    repos: &Repositories,
+    item_id: ObjectId,
+    changes: &Changes,
+) -> Result<(), ApiError> {
+    let touches_dates = ["expiresAt", "purchasedAt"]
+        .iter()
+        .any(|field| changes.sets(field) || changes.unset_fields().iter().any(|f| f == field));
+    if !touches_dates {
+        return Ok(());
+    }
+    let item = match repos.items.find_by_id(item_id).await? {
+        Some(item) => item,
+        None => return Err(ApiError::not_found("Objeto no encontrado")),
+    };
+    let merged = |field: &str, current: Option<DateTime>| {
+        if changes.sets(field) {
+            changes.set_fields().get_datetime(field).ok().copied()
+        } else if changes.unset_fields().iter().any(|f| f == field) {
+            None
+        } else {
+            current
+        }
+    };
+    check_dates(
+        merged("purchasedAt", item.purchased_at),
+        merged("expiresAt", item.expires_at),
+    )
+}
+
+pub async fn update_item(
+    repos: &Repositories,
+    group_id: ObjectId,
+    item_id: ObjectId,
+    mut changes: Changes,
+) -> Result<(), ApiError> {
+    check_changed_dates(repos, item_id, &changes).await?;
+    check_changed_category(repos, group_id, &changes).await?;
+    check_changed_attributes(repos, group_id, item_id, &mut changes).await?;
+    if !repos.items.update(item_id, changes).await? {
+        return Err(ApiError::not_found("Objeto no encontrado"));
+    }
+    Ok(())
+}
+
+/// Cambios para llevar el item a la zona `zone_id`, que es del grupo `target_group_id`. Si
+/// cambia de grupo, la categoría (del grupo anterior) se quita y los atributos se validan
+/// con los del grupo nuevo; si no encajan, no se puede mover.
+pub async fn move_changes(
+    repos: &Repositories,
+    item: &Item,
+    zone_id: ObjectId,
+    group_id: ObjectId,
+    target_group_id: ObjectId,
+) -> Result<Changes, ApiError> {
+    let mut changes = Changes::new();
+    changes.set("zoneId", zone_id);
+    if target_group_id != group_id {
+        if item.category_id.is_some() {
+            changes.unset("categoryId");
+        }
+        let schema = group_schema(repos, target_group_id).await?;
+        match validate_values(&schema, item.attributes.clone())? {
+            Some(values) => changes.set("attributes", values),
+            None if item.attributes.is_some() => changes.unset("attributes"),
+            None => {}
+        }
+    }
+    Ok(changes)
+}
+
+/// Cambia el item de zona.
+pub async fn move_item(
+    repos: &Repositories,
+    item_id: ObjectId,
+    zone_id: ObjectId,
+    group_id: ObjectId,
+    target_group_id: ObjectId,
+) -> Result<(), ApiError> {
+    let item = match repos.items.find_by_id(item_id).await? {
+        Some(item) => item,
+        None => return Err(ApiError::not_found("Objeto no encontrado")),
+    };
+    let changes = move_changes(repos, &item, zone_id, group_id, target_group_id).await?;
+    if !repos.items.update(item_id, changes).await? {
+        return Err(ApiError::not_found("Objeto no encontrado"));
+    }
+    Ok(())
+}
+
+/// Elimina el archivo de la imagen de un item. Los fallos solo se registran.
+pub fn remove_picture(picture_url: &str, route: &str) {
+    let image_path = Path::new("images").join(picture_url);
+    if image_path.exists() {
+        if let Err(e) = fs::remove_file(&image_path) {
+            write_log(&format!(
+                "{} - Error eliminando imagen '{}': {}",
+                route, picture_url, e
+            ))
+            .ok();
+        } else {
+            write_log(&format!("{} - Imagen '{}' eliminada", route, picture_url)).ok();
+        }
+    }
+}
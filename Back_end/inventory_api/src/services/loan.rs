@@ -0,0 +1,185 @@
+use mongodb::bson::{oid::ObjectId, DateTime};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+use crate::dates::parse_date;
+use crate::entities::item::Item;
+use crate::entities::loan::{Loan, LoanReturn};
+use crate::error::ApiError;
+use crate::repository::Repositories;
+use crate::services::alerts::visible_zones;
+
+/// Préstamo pedido por el cliente: a un miembro del grupo (`borrowerId`) o a una persona
+/// de fuera (`borrowerName`).
+pub struct CheckoutRequest {
+    pub borrower_id: Option<ObjectId>,
+    pub borrower_name: Option<String>,
+    pub due_at: Option<DateTime>,
+    pub notes: Option<String>,
+}
+
+/// Texto opcional del cuerpo; las cadenas vacías cuentan como ausentes.
+fn optional_text(body: &Value, field: &str) -> Result<Option<String>, ApiError> {
+    match body.get(field) {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::String(text)) if text.trim().is_empty() => Ok(None),
+        Some(Value::String(text)) => Ok(Some(text.trim().to_string())),
+        Some(_) => Err(
+            ApiError::validation(format!("Valor inválido para '{}'", field))
+                .with_details(json!({"field": field})),
+        ),
+    }
+}
+
+/// Lee `{borrowerId | borrowerName, dueAt?, notes?}`.
+pub fn checkout_request(body: &Value) -> Result<CheckoutRequest, ApiError> {
+    let borrower_id = match optional_text(body, "borrowerId")? {
+        Some(id) => Some(ObjectId::parse_str(&id).map_err(|_| {
+            ApiError::validation("'borrowerId' inválido")
+                .with_details(json!({"field": "borrowerId"}))
+        })?),
+        None => None,
+    };
+    let borrower_name = optional_text(body, "borrowerName")?;
+    if borrower_id.is_some() == borrower_name.is_some() {
+        return Err(
+            ApiError::validation("Indica 'borrowerId' o 'borrowerName', pero no ambos")
+                .with_details(json!({"field": "borrowerId"})),
+        );
+    }
+    let due_at = match optional_text(body, "dueAt")? {
+        Some(date) => Some(parse_date(&date).ok_or_else(|| {
+            ApiError::validation("Fecha inválida para 'dueAt'")
+                .with_details(json!({"field": "dueAt"}))
+        })?),
+        None => None,
+    };
+    Ok(CheckoutRequest {
+        borrower_id,
+        borrower_name,
+        due_at,
+        notes: optional_text(body, "notes")?,
+    })
+}
+
+/// Presta el item. Los préstamos a miembros solo se aceptan si el prestatario pertenece al
+/// grupo del item.
+pub async fn check_out(
+    repos: &Repositories,
+    item_id: ObjectId,
+    group_id: ObjectId,
+    user_id: ObjectId,
+    request: CheckoutRequest,
+) -> Result<Loan, ApiError> {
+    let borrower_name = match (request.borrower_id, request.borrower_name) {
+        (Some(borrower_id), _) => {
+            if repos
+                .user_groups
+                .find(borrower_id, group_id)
+                .await?
+                .is_none()
+            {
+                return Err(ApiError::validation("El prestatario no pertenece al grupo")
+                    .with_details(json!({"field": "borrowerId"})));
+            }
+            match repos.users.find_by_id(borrower_id).await? {
+                Some(user) => user.name,
+                None => return Err(ApiError::not_found("Usuario no encontrado")),
+            }
+        }
+        (None, Some(name)) => name,
+        (None, None) => return Err(ApiError::validation("Falta el prestatario")),
+    };
+    let loan_id = ObjectId::new();
+    let loan = Loan {
+        id: Some(loan_id),
+        item_id,
+        borrower_id: request.borrower_id,
+        borrower_name,
+        due_at: request.due_at,
+        notes: request.notes,
+        user_id,
+        lent_at: DateTime::now(),
+        returned: None,
+    };
+    if !repos.bulk.lend(&loan).await? {
+        return Err(match repos.items.find_by_id(item_id).await? {
+            Some(_) => ApiError::conflict("El objeto ya está prestado"),
+            None => ApiError::not_found("Objeto no encontrado"),
+        });
+    }
+    Ok(loan)
+}
+
+/// Da por devuelto el préstamo en curso del item.
+pub async fn check_in(
+    repos: &Repositories,
+    item_id: ObjectId,
+    user_id: ObjectId,
+    notes: Option<String>,
+) -> Result<Loan, ApiError> {
+    let loan_id = match repos.items.find_by_id(item_id).await? {
+        Some(Item {
+            loan_id: Some(loan_id),
+            ..
+        }) => loan_id,
+        Some(_) => return Err(ApiError::conflict("El objeto no está prestado")),
+        None => return Err(ApiError::not_found("Objeto no encontrado")),
+    };
+    let returned = LoanReturn {
+        returned_at: DateTime::now(),
+        user_id,
+        notes,
+    };
+    if !repos.bulk.return_loan(item_id, loan_id, &returned).await? {
+        return Err(ApiError::conflict("El objeto no está prestado"));
+    }
+    match repos.loans.find_by_ids(&[loan_id]).await?.pop() {
+        Some(loan) => Ok(loan),
+        None => Err(ApiError::internal("Préstamo no encontrado")),
+    }
+}
+
+/// Items prestados del grupo que el usuario puede ver, con su préstamo, de la fecha de
+/// devolución más próxima a la más lejana (los que no tienen fecha, al final). Con
+/// `overdue_only`, solo los que ya deberían haberse devuelto.
+pub async fn group_loans(
+    repos: &Repositories,
+    group_id: ObjectId,
+    viewer: Option<ObjectId>,
+    overdue_only: bool,
+) -> Result<Vec<(Item, Loan)>, ApiError> {
+    // Los items antiguos pueden colgar directamente de la propiedad
+    let mut zone_ids = Vec::new();
+    for property in repos.properties.find_by_group(group_id, viewer).await? {
+        if let Some(property_id) = property.id {
+            zone_ids.push(property_id);
+            let zones = repos.zones.find_by_property(property_id).await?;
+            zone_ids.extend(
+                visible_zones(zones, viewer)
+                    .into_iter()
+                    .filter_map(|z| z.id),
+            );
+        }
+    }
+    let items = repos.items.find_lent(&zone_ids).await?;
+    let loan_ids: Vec<ObjectId> = items.iter().filter_map(|item| item.loan_id).collect();
+    let mut loans: HashMap<ObjectId, Loan> = repos
+        .loans
+        .find_by_ids(&loan_ids)
+        .await?
+        .into_iter()
+        .filter_map(|loan| Some((loan.id?, loan)))
+        .collect();
+    let now = DateTime::now();
+    let mut lent: Vec<(Item, Loan)> = items
+        .into_iter()
+        .filter_map(|item| {
+            let loan = loans.remove(&item.loan_id?)?;
+            Some((item, loan))
+        })
+        .filter(|(_, loan)| !overdue_only || loan.is_overdue(now))
+        .collect();
+    lent.sort_by_key(|(_, loan)| (loan.due_at.is_none(), loan.due_at));
+    Ok(lent)
+}
@@ -0,0 +1,18 @@
+pub mod alerts;
+pub mod ancestors;
+pub mod attribute;
+pub mod audit;
+pub mod bulk;
+pub mod category;
+pub mod group;
+pub mod item;
+pub mod loan;
+pub mod property;
+pub mod search;
+pub mod session;
+pub mod stock;
+pub mod template;
+pub mod trash;
+pub mod user;
+pub mod user_group;
+pub mod zone;
@@ -0,0 +1,67 @@
+use mongodb::bson::oid::ObjectId;
+use serde_json::json;
+
+use crate::error::ApiError;
+use crate::repository::{Changes, Repositories};
+
+/// Convierte el cuerpo de un PATCH de propiedad en cambios. Marcarla como privada la
+/// asigna a `user_id`.
+pub fn property_changes(body: &serde_json::Value, user_id: ObjectId) -> Result<Changes, ApiError> {
+    let mut changes = Changes::new();
+
+    // Campo: name (solo si está presente con valor)
+    if let Some(value) = body.get("name") {
+        match value {
+            serde_json::Value::String(name) => changes.set("name", name.clone()),
+            serde_json::Value::Null => {
+                return Err(ApiError::validation("'name' no puede ser null")
+                    .with_details(json!({"field": "name"})));
+            }
+            _ => {
+                return Err(ApiError::validation("Valor inválido para 'name'")
+                    .with_details(json!({"field": "name"})));
+            }
+        };
+    }
+
+    // Campo opcional: direction
+    if let Some(value) = body.get("direction") {
+        match value {
+            serde_json::Value::String(dir) => changes.set("direction", dir.clone()),
+            serde_json::Value::Null => changes.unset("direction"),
+            _ => {
+                return Err(ApiError::validation("Valor inválido para 'direction'")
+                    .with_details(json!({"field": "direction"})));
+            }
+        };
+    }
+
+    // Campo: private
+    if let Some(value) = body.get("private") {
+        match value {
+            serde_json::Value::Bool(true) => changes.set("userId", user_id),
+            serde_json::Value::Bool(false) => changes.unset("userId"),
+            _ => {
+                return Err(ApiError::validation("Valor inválido para 'private'")
+                    .with_details(json!({"field": "private"})));
+            }
+        }
+    }
+
+    // Validar que haya algo que actualizar
+    if changes.is_empty() {
+        return Err(ApiError::validation("No hay campos para actualizar"));
+    }
+    Ok(changes)
+}
+
+pub async fn update_property(
+    repos: &Repositories,
+    property_id: ObjectId,
+    changes: Changes,
+) -> Result<(), ApiError> {
+    if !repos.properties.update(property_id, changes).await? {
+        return Err(ApiError::not_found("Propiedad no encontrada"));
+    }
+    Ok(())
+}
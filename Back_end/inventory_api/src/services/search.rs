@@ -0,0 +1,297 @@
+use std::collections::HashMap;
+
+use mongodb::bson::oid::ObjectId;
+use serde_json::json;
+
+use crate::access::Resource;
+use crate::entities::search::{
+    Crumb, Highlight, SearchEntity, SearchHit, SearchMatch, SearchResults,
+};
+use crate::entities::zone::Zone;
+use crate::error::ApiError;
+use crate::pagination::{paginate, PageQuery};
+use crate::query::Query;
+use crate::repository::{Repositories, SearchQuery, SearchScope};
+use crate::services::attribute::{conditions, AttributeFilter};
+use crate::services::group::visible_groups;
+use crate::services::zone::get_all_child_zone_ids;
+use crate::text::{self, Matching, DESCRIPTION_WEIGHT, NAME_WEIGHT, TAGS_WEIGHT};
+
+/// Máximo de candidatos que se puntúan en la búsqueda con erratas. Si hay más, los resultados
+/// se marcan como incompletos (`truncated`).
+pub const FUZZY_CANDIDATES: usize = 500;
+
+/// Búsqueda pedida por el usuario.
+#[derive(Debug, Clone, Default)]
+pub struct SearchRequest {
+    pub query: Query,
+    pub attributes: Vec<AttributeFilter>,
+    pub category_id: Option<ObjectId>,
+    /// Admitir erratas: siempre (`Some(true)`), nunca (`Some(false)`) o solo si la
+    /// búsqueda exacta no encuentra nada (`None`).
+    pub fuzzy: Option<bool>,
+    pub within: Option<Within>,
+}
+
+/// Grupo, propiedad o zona a cuyo contenido se limita la búsqueda, con el acceso ya
+/// comprobado.
+#[derive(Debug, Clone, Copy)]
+pub struct Within {
+    pub resource: Resource,
+    pub group_id: ObjectId,
+}
+
+/// Busca la consulta en los grupos del usuario y en todo lo que contienen, con el índice
+/// de texto: nombres, y además etiquetas y descripción de los items. Los administradores
+/// buscan en todos los grupos. Con filtros por campo, de atributos o de categoría solo se
+/// devuelven items, ya que son lo único que los tiene. Los resultados se paginan juntos, con el
+/// orden y los filtros de `page`, y llevan la ruta hasta su grupo.
+pub async fn search(
+    repos: &Repositories,
+    user_id: ObjectId,
+    is_admin: bool,
+    request: &SearchRequest,
+    page: &PageQuery,
+) -> Result<SearchResults, ApiError> {
+    let groups = visible_groups(repos, user_id, is_admin).await?;
+    let mut schemas = HashMap::new();
+    let mut group_names = HashMap::new();
+    for group in groups {
+        if let Some(gid) = group.id {
+            group_names.insert(gid, group.name);
+            schemas.insert(gid, group.attributes.unwrap_or_default());
+        }
+    }
+
+    let scope = match request.within {
+        None => None,
+        Some(within) => {
+            schemas.retain(|gid, _| *gid == within.group_id);
+            Some(match within.resource {
+                Resource::Group(id) => SearchScope::Group(id),
+                Resource::Property(id) => SearchScope::Property(id),
+                Resource::Zone(id) => SearchScope::Zone {
+                    zone_id: id,
+                    descendants: get_all_child_zone_ids(repos, &id).await,
+                },
+                Resource::Item(_) => {
+                    return Err(ApiError::validation(
+                        "Solo se puede buscar dentro de un grupo, una propiedad o una zona",
+                    )
+                    .with_details(json!({"field": "within"})))
+                }
+            })
+        }
+    };
+
+    let search = &request.query;
+    let mut query = SearchQuery {
+        group_ids: schemas.keys().copied().collect(),
+        // Si NO es admin, omitir privadas ajenas
+        viewer: if is_admin { None } else { Some(user_id) },
+        query: search.clone(),
+        category_id: request.category_id,
+        items_only: search.items_only()
+            || !request.attributes.is_empty()
+            || request.category_id.is_some(),
+        scope,
+        attributes: match request.attributes.is_empty() {
+            true => None,
+            false => Some(
+                schemas
+                    .iter()
+                    .filter_map(|(gid, schema)| {
+                        conditions(schema, &request.attributes).map(|found| (*gid, found))
+                    })
+                    .collect(),
+            ),
+        },
+        similar_to: Vec::new(),
+        limit: None,
+    };
+    let terms = text::terms(&search.text());
+
+    let has_words = search.words().next().is_some();
+    let always_fuzzy = request.fuzzy == Some(true) && has_words;
+    let exact = match always_fuzzy {
+        true => None,
+        false => Some(repos.search.search_page(&query, page).await?),
+    };
+    let retry = request.fuzzy.is_none()
+        && has_words
+        && exact.as_ref().is_some_and(|exact| exact.total == 0);
+    let mut truncated = false;
+    let (found, matching) = match exact {
+        Some(exact) if !retry => (exact, Matching::Exact),
+        _ => {
+            // El índice de texto no admite erratas: se piden sin las palabras sueltas los
+            // candidatos que pueden parecerse a algún término, ya filtrados, y se puntúan
+            // aquí. Si hay más de `FUZZY_CANDIDATES`, se avisa de que faltan resultados
+            query.query = search.without_words();
+            query.similar_to = terms.clone();
+            query.limit = Some(FUZZY_CANDIDATES + 1);
+            let mut candidates = repos.search.search(&query).await?;
+            truncated = candidates.len() > FUZZY_CANDIDATES;
+            candidates.truncate(FUZZY_CANDIDATES);
+            let found = candidates
+                .into_iter()
+                .filter_map(|found| {
+                    let score = fields(&found.entity)
+                        .iter()
+                        .map(|(_, value, weight)| {
+                            text::field_score(value, &terms, *weight, Matching::Fuzzy)
+                        })
+                        .sum();
+                    (score > 0.0).then_some(SearchMatch { score, ..found })
+                })
+                .collect();
+            (paginate(found, page), Matching::Fuzzy)
+        }
+    };
+
+    let mut page = found.map(|found| hit(found, &terms, matching));
+    add_breadcrumbs(repos, &group_names, &mut page.data).await?;
+    Ok(SearchResults { page, truncated })
+}
+
+/// Rellena la ruta de los resultados. Las zonas se cargan nivel a nivel para todos los
+/// resultados a la vez, y las propiedades en una sola consulta.
+async fn add_breadcrumbs(
+    repos: &Repositories,
+    group_names: &HashMap<ObjectId, String>,
+    hits: &mut [SearchHit],
+) -> Result<(), ApiError> {
+    // Los items pueden estar directamente en la propiedad: esos `zoneId` no se encuentran
+    let mut zones: HashMap<ObjectId, Zone> = HashMap::new();
+    let mut pending: Vec<ObjectId> = hits
+        .iter()
+        .filter_map(|hit| match &hit.entity {
+            SearchEntity::Zone(zone) => parent_zone(zone),
+            SearchEntity::Item(item) => Some(item.zone_id),
+            _ => None,
+        })
+        .collect();
+    while !pending.is_empty() {
+        pending.sort();
+        pending.dedup();
+        let loaded = repos.zones.find_by_ids(&pending).await?;
+        pending = loaded.iter().filter_map(parent_zone).collect();
+        for zone in loaded {
+            if let Some(id) = zone.id {
+                zones.insert(id, zone);
+            }
+        }
+        pending.retain(|id| !zones.contains_key(id));
+    }
+
+    // Propiedad de cada resultado y zona de la que cuelga
+    let places: Vec<(Option<ObjectId>, Option<ObjectId>)> = hits
+        .iter()
+        .map(|hit| match &hit.entity {
+            SearchEntity::Group(_) | SearchEntity::Property(_) => (None, None),
+            SearchEntity::Zone(zone) => (Some(zone.property_id), parent_zone(zone)),
+            SearchEntity::Item(item) => match zones.get(&item.zone_id) {
+                Some(zone) => (Some(zone.property_id), Some(item.zone_id)),
+                None => (Some(item.zone_id), None),
+            },
+        })
+        .collect();
+    let mut property_ids: Vec<ObjectId> = places.iter().filter_map(|(id, _)| *id).collect();
+    property_ids.sort();
+    property_ids.dedup();
+    let properties: HashMap<ObjectId, _> = repos
+        .properties
+        .find_by_ids(&property_ids)
+        .await?
+        .into_iter()
+        .filter_map(|property| property.id.map(|id| (id, property)))
+        .collect();
+
+    for (hit, (property_id, zone_id)) in hits.iter_mut().zip(places) {
+        let property = property_id.and_then(|id| properties.get(&id));
+        let group_id = match (&hit.entity, property) {
+            (SearchEntity::Property(property), _) => Some(property.group_id),
+            (_, Some(property)) => Some(property.group_id),
+            _ => None,
+        };
+        let mut breadcrumb = Vec::new();
+        if let Some((id, name)) = group_id.and_then(|id| group_names.get_key_value(&id)) {
+            breadcrumb.push(crumb("group", *id, name));
+        }
+        if let Some(property) = property {
+            breadcrumb.extend(property.id.map(|id| crumb("property", id, &property.name)));
+        }
+        let mut chain = Vec::new();
+        let mut current = zone_id;
+        while let Some(zone) = current.and_then(|id| zones.get(&id)) {
+            if chain.len() > zones.len() {
+                break;
+            }
+            chain.extend(zone.id.map(|id| crumb("zone", id, &zone.name)));
+            current = parent_zone(zone);
+        }
+        chain.reverse();
+        breadcrumb.extend(chain);
+        hit.breadcrumb = breadcrumb;
+    }
+    Ok(())
+}
+
+/// Zona de la que cuelga, si no está directamente en la propiedad.
+fn parent_zone(zone: &Zone) -> Option<ObjectId> {
+    zone.parent_zone_id
+        .filter(|parent| *parent != zone.property_id)
+}
+
+fn crumb(kind: &'static str, id: ObjectId, name: &str) -> Crumb {
+    Crumb {
+        kind,
+        id,
+        name: name.to_string(),
+    }
+}
+
+/// Campos de texto indexados de un resultado, con su peso.
+fn fields(entity: &SearchEntity) -> Vec<(String, String, i32)> {
+    let name = |name: &str| ("name".to_string(), name.to_string(), NAME_WEIGHT);
+    match entity {
+        SearchEntity::Group(group) => vec![name(&group.name)],
+        SearchEntity::Property(property) => vec![name(&property.name)],
+        SearchEntity::Zone(zone) => vec![name(&zone.name)],
+        SearchEntity::Item(item) => {
+            let mut fields = vec![name(&item.name)];
+            for (position, tag) in item.tags.iter().flatten().enumerate() {
+                fields.push((format!("tags.{}", position), tag.clone(), TAGS_WEIGHT));
+            }
+            if let Some(description) = &item.description {
+                fields.push((
+                    "description".to_string(),
+                    description.clone(),
+                    DESCRIPTION_WEIGHT,
+                ));
+            }
+            fields
+        }
+    }
+}
+
+/// Resultado con los campos en los que aparece algún término marcado.
+fn hit(found: SearchMatch, terms: &[String], matching: Matching) -> SearchHit {
+    let highlights = fields(&found.entity)
+        .into_iter()
+        .filter_map(|(field, value, _)| {
+            let ranges = text::highlight(&value, terms, matching);
+            (!ranges.is_empty()).then_some(Highlight {
+                field,
+                value,
+                ranges,
+            })
+        })
+        .collect();
+    SearchHit {
+        entity: found.entity,
+        score: found.score,
+        highlights,
+        breadcrumb: Vec::new(),
+    }
+}
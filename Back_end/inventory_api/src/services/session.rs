@@ -0,0 +1,149 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use mongodb::bson::{oid::ObjectId, DateTime};
+use rand::Rng;
+use sha2::{Digest, Sha256};
+
+use crate::config;
+use crate::entities::session::Session;
+use crate::entities::user::User;
+use crate::error::ApiError;
+use crate::log::write_log;
+use crate::middleware::auth;
+use crate::repository::{RepoResult, Repositories};
+
+/// Duración del refresh token (`auth.refresh_token_ttl_days`) en milisegundos.
+fn refresh_token_ttl_millis() -> i64 {
+    i64::from(config::get().auth.refresh_token_ttl_days) * 24 * 60 * 60 * 1000
+}
+
+fn expires_from_now() -> DateTime {
+    DateTime::from_millis(DateTime::now().timestamp_millis() + refresh_token_ttl_millis())
+}
+
+fn hash_secret(secret: &str) -> String {
+    format!("{:x}", Sha256::digest(secret.as_bytes()))
+}
+
+/// Genera el secreto aleatorio del refresh token.
+fn generate_secret() -> String {
+    let mut bytes = [0u8; 32];
+    rand::rng().fill(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// El refresh token tiene el formato `<id de sesión>.<secreto>`.
+fn parse_refresh_token(token: &str) -> Option<(ObjectId, &str)> {
+    let (id, secret) = token.split_once('.')?;
+    let id = ObjectId::parse_str(id).ok()?;
+    if secret.is_empty() {
+        return None;
+    }
+    Some((id, secret))
+}
+
+pub fn role_of(user: &User) -> String {
+    if user.admin.unwrap_or(false) {
+        "admin".to_string()
+    } else {
+        "user".to_string()
+    }
+}
+
+/// Tokens devueltos al iniciar o renovar una sesión.
+pub struct Tokens {
+    pub token: String,
+    pub refresh_token: String,
+}
+
+/// Crea una sesión nueva para el usuario y devuelve sus tokens.
+pub async fn issue_tokens(
+    repos: &Repositories,
+    user_id: ObjectId,
+    role: String,
+    user_agent: Option<String>,
+) -> RepoResult<Tokens> {
+    let secret = generate_secret();
+    let now = DateTime::now();
+    let session = Session {
+        id: None,
+        user_id,
+        refresh_token_hash: hash_secret(&secret),
+        user_agent,
+        created_at: now,
+        last_used_at: now,
+        expires_at: expires_from_now(),
+        revoked_at: None,
+    };
+    let session_id = repos.sessions.insert(&session).await?;
+    Ok(Tokens {
+        token: auth::generate_token(user_id.to_hex(), role, session_id.to_hex()),
+        refresh_token: format!("{}.{}", session_id.to_hex(), secret),
+    })
+}
+
+/// Indica si la sesión existe, pertenece al usuario y no ha sido revocada ni ha caducado.
+pub async fn is_session_active(
+    repos: &Repositories,
+    session_id: ObjectId,
+    user_id: ObjectId,
+) -> bool {
+    repos
+        .sessions
+        .find_active(session_id, user_id)
+        .await
+        .ok()
+        .flatten()
+        .is_some()
+}
+
+/// Valida el refresh token y lo rota, devolviendo un token de acceso nuevo.
+pub async fn refresh(repos: &Repositories, refresh_token: &str) -> Result<Tokens, ApiError> {
+    let (session_id, secret) = match parse_refresh_token(refresh_token) {
+        Some(parsed) => parsed,
+        None => return Err(ApiError::unauthorized("Refresh token inválido")),
+    };
+    let session = match repos.sessions.find_by_id(session_id).await? {
+        Some(session) => session,
+        None => return Err(ApiError::unauthorized("Refresh token inválido")),
+    };
+    if session.revoked_at.is_some() || session.expires_at <= DateTime::now() {
+        return Err(ApiError::unauthorized("Sesión revocada o caducada"));
+    }
+    if session.refresh_token_hash != hash_secret(secret) {
+        // Un refresh token ya rotado se está reutilizando: se asume robado y se revoca la sesión
+        repos.sessions.revoke(session_id).await.ok();
+        write_log(&format!(
+            "POST /users/refresh - Reutilización de refresh token, sesión {} revocada",
+            session_id
+        ))
+        .ok();
+        return Err(ApiError::unauthorized("Refresh token inválido"));
+    }
+    let user = match repos.users.find_by_id(session.user_id).await? {
+        Some(user) => user,
+        None => return Err(ApiError::unauthorized("Usuario no encontrado")),
+    };
+
+    // Rotación: se exige el hash anterior para que dos peticiones simultáneas no roten el mismo token
+    let new_secret = generate_secret();
+    let rotated = repos
+        .sessions
+        .rotate(
+            session_id,
+            &session.refresh_token_hash,
+            &hash_secret(&new_secret),
+            expires_from_now(),
+        )
+        .await?;
+    if !rotated {
+        return Err(ApiError::unauthorized("Refresh token inválido"));
+    }
+    Ok(Tokens {
+        token: auth::generate_token(
+            session.user_id.to_hex(),
+            role_of(&user),
+            session_id.to_hex(),
+        ),
+        refresh_token: format!("{}.{}", session_id.to_hex(), new_secret),
+    })
+}
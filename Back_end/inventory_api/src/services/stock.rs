@@ -0,0 +1,117 @@
+use mongodb::bson::{oid::ObjectId, DateTime};
+use serde_json::json;
+
+use crate::entities::stock_movement::{MovementKind, StockMovement};
+use crate::error::ApiError;
+use crate::repository::{QuantityChange, Repositories};
+
+/// Movimiento pedido por el cliente: en `add` y `consume`, `quantity` es la cantidad que
+/// entra o sale; en `adjust`, la nueva cantidad total.
+pub struct MovementRequest {
+    pub kind: MovementKind,
+    pub quantity: f64,
+    pub reason: Option<String>,
+}
+
+/// Lee `{type, quantity, reason?}`. El motivo es obligatorio en los ajustes.
+pub fn movement_request(body: &serde_json::Value) -> Result<MovementRequest, ApiError> {
+    let kind = match body.get("type").and_then(|v| v.as_str()) {
+        Some("add") => MovementKind::Add,
+        Some("consume") => MovementKind::Consume,
+        Some("adjust") => MovementKind::Adjust,
+        _ => {
+            return Err(
+                ApiError::validation("'type' debe ser 'add', 'consume' o 'adjust'")
+                    .with_details(json!({"field": "type"})),
+            )
+        }
+    };
+    let quantity = match body.get("quantity").and_then(|v| v.as_f64()) {
+        Some(q) if q.is_finite() => q,
+        _ => {
+            return Err(ApiError::validation("'quantity' debe ser un número")
+                .with_details(json!({"field": "quantity"})))
+        }
+    };
+    if kind == MovementKind::Adjust && quantity < 0.0 {
+        return Err(ApiError::validation("La cantidad no puede ser negativa")
+            .with_details(json!({"field": "quantity"})));
+    }
+    if kind != MovementKind::Adjust && quantity <= 0.0 {
+        return Err(ApiError::validation("La cantidad debe ser mayor que 0")
+            .with_details(json!({"field": "quantity"})));
+    }
+    let reason = match body.get("reason") {
+        None | Some(serde_json::Value::Null) => None,
+        Some(serde_json::Value::String(reason)) if !reason.trim().is_empty() => {
+            Some(reason.trim().to_string())
+        }
+        Some(_) => {
+            return Err(ApiError::validation("Valor inválido para 'reason'")
+                .with_details(json!({"field": "reason"})))
+        }
+    };
+    if kind == MovementKind::Adjust && reason.is_none() {
+        return Err(ApiError::validation("Los ajustes necesitan un motivo")
+            .with_details(json!({"field": "reason"})));
+    }
+    Ok(MovementRequest {
+        kind,
+        quantity,
+        reason,
+    })
+}
+
+/// Aplica el movimiento a la cantidad del item y lo registra en la misma transacción.
+pub async fn record_movement(
+    repos: &Repositories,
+    item_id: ObjectId,
+    user_id: ObjectId,
+    request: MovementRequest,
+) -> Result<StockMovement, ApiError> {
+    let change = match request.kind {
+        MovementKind::Add => QuantityChange::Add(request.quantity),
+        MovementKind::Consume => QuantityChange::Add(-request.quantity),
+        MovementKind::Adjust => QuantityChange::Set(request.quantity),
+    };
+    let kind = request.kind;
+    // `delta` y `quantityAfter` los completa el repositorio con la cantidad real
+    let movement = StockMovement {
+        id: None,
+        item_id,
+        kind,
+        delta: 0.0,
+        quantity_after: 0.0,
+        reason: request.reason,
+        user_id,
+        created_at: DateTime::now(),
+    };
+    match repos.bulk.record_movement(change, &movement).await? {
+        Some(movement) => Ok(movement),
+        None if kind == MovementKind::Consume => Err(not_enough_stock(repos, item_id).await?),
+        None => Err(ApiError::not_found("Objeto no encontrado")),
+    }
+}
+
+/// Error de un consumo rechazado: el item no existe o no tiene cantidad suficiente.
+async fn not_enough_stock(repos: &Repositories, item_id: ObjectId) -> Result<ApiError, ApiError> {
+    Ok(match repos.items.find_by_id(item_id).await? {
+        Some(item) => ApiError::conflict("No hay cantidad suficiente")
+            .with_details(json!({"available": item.quantity.unwrap_or(0.0)})),
+        None => ApiError::not_found("Objeto no encontrado"),
+    })
+}
+
+/// Movimiento con el que se da de alta un item con cantidad.
+pub fn initial_movement(item_id: ObjectId, user_id: ObjectId, quantity: f64) -> StockMovement {
+    StockMovement {
+        id: None,
+        item_id,
+        kind: MovementKind::Add,
+        delta: quantity,
+        quantity_after: quantity,
+        reason: Some("Cantidad inicial".to_string()),
+        user_id,
+        created_at: DateTime::now(),
+    }
+}
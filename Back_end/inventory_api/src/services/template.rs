@@ -0,0 +1,113 @@
+use mongodb::bson::oid::ObjectId;
+use serde_json::{json, Value};
+
+use crate::entities::{item::Item, template::ItemTemplate};
+use crate::error::ApiError;
+use crate::repository::Repositories;
+use crate::services::attribute::{group_schema, normalize_values};
+use crate::services::category::check_category;
+
+/// Valida y guarda una plantilla del grupo. Los atributos obligatorios pueden faltar, ya
+/// que se completan al crear cada item.
+pub async fn create_template(
+    repos: &Repositories,
+    group_id: ObjectId,
+    body: &Value,
+) -> Result<ObjectId, ApiError> {
+    let mut body = body.clone();
+    if let Some(fields) = body.as_object_mut() {
+        fields.remove("_id");
+        fields.insert("groupId".to_string(), json!(group_id.to_hex()));
+    }
+    let mut template: ItemTemplate = serde_json::from_value(body).map_err(|e| {
+        ApiError::validation("Plantilla inválida").with_details(json!({"reason": e.to_string()}))
+    })?;
+    template.name = template.name.trim().to_string();
+    if template.name.is_empty() {
+        return Err(
+            ApiError::validation("'name' es requerido").with_details(json!({"field": "name"}))
+        );
+    }
+    if let Some(min) = template.min_quantity {
+        if !min.is_finite() || min < 0.0 {
+            return Err(ApiError::validation("'minQuantity' no puede ser negativo")
+                .with_details(json!({"field": "minQuantity"})));
+        }
+    }
+    template.unit = template
+        .unit
+        .map(|unit| unit.trim().to_string())
+        .filter(|unit| !unit.is_empty());
+    check_category(repos, group_id, template.category_id).await?;
+    let schema = group_schema(repos, group_id).await?;
+    let attributes = normalize_values(&schema, template.attributes.take())?;
+    template.attributes = Some(attributes).filter(|values| !values.is_empty());
+    Ok(repos.templates.insert(&template).await?)
+}
+
+/// Item del cuerpo de un `POST /items`. Con `templateId`, los campos de la plantilla son
+/// los valores por defecto: el cuerpo los sustituye y los atributos se combinan uno a uno.
+pub async fn instantiate(
+    repos: &Repositories,
+    body: &Value,
+) -> Result<(Item, Option<ItemTemplate>), ApiError> {
+    let mut body = body.clone();
+    let template_id = body
+        .as_object_mut()
+        .and_then(|fields| fields.remove("templateId"));
+    let template = match template_id {
+        None | Some(Value::Null) => None,
+        Some(value) => {
+            let template_id = match value.as_str().map(ObjectId::parse_str) {
+                Some(Ok(id)) => id,
+                _ => {
+                    return Err(ApiError::validation("'templateId' inválido")
+                        .with_details(json!({"field": "templateId"})))
+                }
+            };
+            match repos.templates.find_by_id(template_id).await? {
+                Some(template) => Some(template),
+                None => return Err(ApiError::not_found("Plantilla no encontrada")),
+            }
+        }
+    };
+    if let Some(template) = &template {
+        let mut merged = serde_json::to_value(template).unwrap_or_default();
+        if let (Some(defaults), Some(fields)) = (merged.as_object_mut(), body.as_object()) {
+            defaults.remove("_id");
+            defaults.remove("groupId");
+            for (field, value) in fields {
+                match (defaults.get_mut(field), value) {
+                    (Some(Value::Object(attributes)), Value::Object(values))
+                        if field == "attributes" =>
+                    {
+                        attributes.extend(values.clone());
+                    }
+                    _ => {
+                        defaults.insert(field.clone(), value.clone());
+                    }
+                }
+            }
+        }
+        body = merged;
+    }
+    let item: Item = serde_json::from_value(body).map_err(|e| {
+        ApiError::validation("Cuerpo de la petición inválido")
+            .with_details(json!({"reason": e.to_string()}))
+    })?;
+    Ok((item, template))
+}
+
+/// La plantilla tiene que ser del grupo de la zona en la que se crea el item.
+pub fn check_template_group(
+    template: Option<&ItemTemplate>,
+    group_id: ObjectId,
+) -> Result<(), ApiError> {
+    match template {
+        Some(template) if template.group_id != group_id => Err(ApiError::validation(
+            "La plantilla no pertenece al grupo de la zona",
+        )
+        .with_details(json!({"field": "templateId"}))),
+        _ => Ok(()),
+    }
+}
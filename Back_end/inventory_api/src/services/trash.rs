@@ -0,0 +1,270 @@
+use mongodb::bson::{oid::ObjectId, DateTime};
+
+use crate::entities::audit::AuditEntity;
+use crate::entities::trash::TrashEntry;
+use crate::entities::user_group::GroupRole;
+use crate::error::ApiError;
+use crate::log::write_log;
+use crate::middleware::auth::AuthUser;
+use crate::repository::{Repositories, TrashSet};
+use crate::services::audit as audit_service;
+use crate::services::item::remove_picture;
+use crate::services::zone::get_all_child_zone_ids;
+
+const PURGE_ROUTE: &str = "PURGE /trash";
+
+/// Entrada de papelera para un elemento, aún sin los contadores de lo borrado con él.
+pub fn trash_entry(
+    group_id: ObjectId,
+    entity_type: AuditEntity,
+    entity_id: ObjectId,
+    name: &str,
+    user_id: ObjectId,
+) -> TrashEntry {
+    TrashEntry {
+        id: None,
+        group_id,
+        entity_type,
+        entity_id,
+        name: name.to_string(),
+        parent_id: None,
+        user_id,
+        deleted_at: DateTime::now(),
+        properties: 0,
+        zones: 0,
+        items: 0,
+    }
+}
+
+/// Añade al conjunto las zonas indicadas y sus items.
+async fn add_zones(
+    repos: &Repositories,
+    set: &mut TrashSet,
+    zone_ids: Vec<ObjectId>,
+) -> Result<(), ApiError> {
+    for zone_id in &zone_ids {
+        for item in repos.items.find_by_zone(*zone_id).await? {
+            set.items.extend(item.id);
+        }
+    }
+    set.zones.extend(zone_ids);
+    Ok(())
+}
+
+/// Añade al conjunto la propiedad con todas sus zonas e items, también los que cuelgan
+/// directamente de ella.
+async fn add_property(
+    repos: &Repositories,
+    set: &mut TrashSet,
+    property_id: ObjectId,
+) -> Result<(), ApiError> {
+    for item in repos.items.find_by_zone(property_id).await? {
+        set.items.extend(item.id);
+    }
+    let zone_ids = repos
+        .zones
+        .find_by_property(property_id)
+        .await?
+        .into_iter()
+        .filter_map(|zone| zone.id)
+        .collect();
+    add_zones(repos, set, zone_ids).await?;
+    set.properties.push(property_id);
+    Ok(())
+}
+
+/// Mueve el elemento a la papelera junto con todo lo que contiene y devuelve la entrada.
+pub async fn move_to_trash(
+    repos: &Repositories,
+    user_id: ObjectId,
+    group_id: ObjectId,
+    entity_type: AuditEntity,
+    id: ObjectId,
+) -> Result<TrashEntry, ApiError> {
+    let mut set = TrashSet::default();
+    let (name, parent_id) = match entity_type {
+        AuditEntity::Group => {
+            let group = match repos.groups.find_by_id(id).await? {
+                Some(group) => group,
+                None => return Err(ApiError::not_found("Grupo no encontrado")),
+            };
+            for property in repos.properties.find_by_group(id, None).await? {
+                if let Some(property_id) = property.id {
+                    add_property(repos, &mut set, property_id).await?;
+                }
+            }
+            set.groups.push(id);
+            (group.name, None)
+        }
+        AuditEntity::Property => {
+            let property = match repos.properties.find_by_id(id).await? {
+                Some(property) => property,
+                None => return Err(ApiError::not_found("Propiedad no encontrada")),
+            };
+            add_property(repos, &mut set, id).await?;
+            (property.name, Some(property.group_id))
+        }
+        AuditEntity::Zone => {
+            let zone = match repos.zones.find_by_id(id).await? {
+                Some(zone) => zone,
+                None => return Err(ApiError::not_found("Zona no encontrada")),
+            };
+            let mut zone_ids = get_all_child_zone_ids(repos, &id).await;
+            zone_ids.push(id);
+            add_zones(repos, &mut set, zone_ids).await?;
+            (zone.name, zone.parent_zone_id.or(Some(zone.property_id)))
+        }
+        AuditEntity::Item => {
+            let item = match repos.items.find_by_id(id).await? {
+                Some(item) => item,
+                None => return Err(ApiError::not_found("Objeto no encontrado")),
+            };
+            set.items.push(id);
+            (item.name, Some(item.zone_id))
+        }
+    };
+    let mut entry = trash_entry(group_id, entity_type, id, &name, user_id);
+    entry.parent_id = parent_id;
+    entry.properties = set.properties.len() as u32;
+    entry.zones = set.zones.len() as u32;
+    entry.items = set.items.len() as u32;
+    entry.id = Some(repos.trash.trash(&entry, &set).await?);
+    Ok(entry)
+}
+
+/// Comprueba que el usuario tenga al menos el rol indicado en el grupo. No usa `authorize`
+/// porque el grupo puede estar él mismo en la papelera.
+pub async fn authorize_trash(
+    repos: &Repositories,
+    user: &AuthUser,
+    group_id: ObjectId,
+    required: GroupRole,
+    route: &str,
+) -> Result<(), ApiError> {
+    if user.is_admin() {
+        return Ok(());
+    }
+    match repos.user_groups.find(user.id, group_id).await? {
+        Some(ug) if ug.role >= required => Ok(()),
+        Some(_) => {
+            write_log(&format!(
+                "{} - Usuario {} sin permisos suficientes en el grupo {}",
+                route, user.id, group_id
+            ))
+            .ok();
+            Err(ApiError::forbidden(
+                "No tienes permisos suficientes en este grupo",
+            ))
+        }
+        None => {
+            write_log(&format!(
+                "{} - Usuario {} no pertenece al grupo {}",
+                route, user.id, group_id
+            ))
+            .ok();
+            Err(ApiError::forbidden("El Usuario no pertenece a este grupo"))
+        }
+    }
+}
+
+/// Entrada de la papelera con ese ID.
+pub async fn find_entry(repos: &Repositories, trash_id: ObjectId) -> Result<TrashEntry, ApiError> {
+    match repos.trash.find_by_id(trash_id).await? {
+        Some(entry) => Ok(entry),
+        None => Err(ApiError::not_found("Elemento no encontrado en la papelera")),
+    }
+}
+
+/// Comprueba que siga existiendo el elemento del que colgaba la entrada.
+async fn parent_exists(repos: &Repositories, entry: &TrashEntry) -> Result<bool, ApiError> {
+    let parent_id = match entry.parent_id {
+        Some(parent_id) => parent_id,
+        None => return Ok(true),
+    };
+    Ok(match entry.entity_type {
+        AuditEntity::Group => true,
+        AuditEntity::Property => repos.groups.find_by_id(parent_id).await?.is_some(),
+        AuditEntity::Item | AuditEntity::Zone => {
+            repos.zones.find_by_id(parent_id).await?.is_some()
+                || repos.properties.find_by_id(parent_id).await?.is_some()
+        }
+    })
+}
+
+/// Saca de la papelera la entrada con todo lo que se borró con ella.
+pub async fn restore(
+    repos: &Repositories,
+    user_id: ObjectId,
+    entry: &TrashEntry,
+) -> Result<(), ApiError> {
+    if !parent_exists(repos, entry).await? {
+        return Err(ApiError::conflict(
+            "El elemento que lo contenía está en la papelera: restáuralo primero",
+        ));
+    }
+    let trash_id = match entry.id {
+        Some(id) => id,
+        None => return Err(ApiError::internal("No hay ID")),
+    };
+    if !repos.trash.restore(trash_id).await? {
+        return Err(ApiError::not_found("Elemento no encontrado en la papelera"));
+    }
+    let id = entry.entity_id;
+    let (group_id, entity_type) = (entry.group_id, entry.entity_type);
+    match entity_type {
+        AuditEntity::Group => {
+            let after = repos.groups.find_by_id(id).await?;
+            audit_service::record_restore(repos, user_id, group_id, entity_type, id, after.as_ref())
+                .await
+        }
+        AuditEntity::Property => {
+            let after = repos.properties.find_by_id(id).await?;
+            audit_service::record_restore(repos, user_id, group_id, entity_type, id, after.as_ref())
+                .await
+        }
+        AuditEntity::Zone => {
+            let after = repos.zones.find_by_id(id).await?;
+            audit_service::record_restore(repos, user_id, group_id, entity_type, id, after.as_ref())
+                .await
+        }
+        AuditEntity::Item => {
+            let after = repos.items.find_by_id(id).await?;
+            audit_service::record_restore(repos, user_id, group_id, entity_type, id, after.as_ref())
+                .await
+        }
+    }
+    Ok(())
+}
+
+/// Elimina definitivamente la entrada, lo que se borró con ella y las imágenes de sus items.
+pub async fn purge(repos: &Repositories, trash_id: ObjectId) -> Result<(), ApiError> {
+    let entry = repos.trash.find_by_id(trash_id).await?;
+    for item in repos.trash.find_items(trash_id).await? {
+        if let Some(picture_url) = &item.picture_url {
+            remove_picture(picture_url, PURGE_ROUTE);
+        }
+    }
+    repos.trash.purge(trash_id).await?;
+    // Las categorías y plantillas de un grupo se van con él
+    if let Some(entry) = entry.filter(|entry| entry.entity_type == AuditEntity::Group) {
+        repos.categories.delete_by_group(entry.entity_id).await?;
+        repos.templates.delete_by_group(entry.entity_id).await?;
+    }
+    Ok(())
+}
+
+/// Elimina las entradas que llevan en la papelera más de `retention_days` días. Devuelve
+/// cuántas se han eliminado.
+pub async fn purge_expired(repos: &Repositories, retention_days: u32) -> Result<usize, ApiError> {
+    let before = DateTime::from_millis(
+        DateTime::now().timestamp_millis() - i64::from(retention_days) * 24 * 60 * 60 * 1000,
+    );
+    let mut purged = 0;
+    for entry in repos.trash.find_older_than(before).await? {
+        if let Some(trash_id) = entry.id {
+            purge(repos, trash_id).await?;
+            purged += 1;
+        }
+    }
+    Ok(purged)
+}
@@ -0,0 +1,483 @@
+use actix_web::web;
+use argon2::password_hash::{rand_core::OsRng, PasswordHash, SaltString};
+use argon2::{Algorithm, Argon2, Params, PasswordHasher, PasswordVerifier, Version};
+use mongodb::bson::oid::ObjectId;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+
+use crate::config;
+use crate::entities::user::User;
+use crate::error::ApiError;
+use crate::log::write_log;
+use crate::mail::{MailMessage, Mailer};
+use crate::middleware::auth::{
+    decode_action_token, fingerprint, generate_action_token, ActionPurpose,
+};
+use crate::repository::{Changes, Repositories};
+use crate::services::session::{issue_tokens, role_of, Tokens};
+use crate::services::user_group::delete_user_group;
+
+/// Parámetros de Argon2id de `auth.argon2`. Se validan al cargar la configuración.
+fn argon2_params() -> Params {
+    config::get().auth.argon2.params().unwrap_or_default()
+}
+
+fn argon2() -> Argon2<'static> {
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, argon2_params())
+}
+
+/// Expresión regular para validar direcciones de correo.
+static EMAIL_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^[^@\s]+@[^@\s]+\.[^@\s]+$").expect("Failed to create regex"));
+
+/// Longitud mínima de las contraseñas.
+const MIN_PASSWORD_LEN: usize = 8;
+
+/// Comprueba que la contraseña nueva sea lo bastante larga. `field` es el campo del cuerpo
+/// en el que llegó, para los detalles del error.
+fn validate_password(password: &str, field: &str) -> Result<(), ApiError> {
+    if password.trim().chars().count() < MIN_PASSWORD_LEN {
+        return Err(ApiError::validation(format!(
+            "La contraseña debe tener al menos {} caracteres",
+            MIN_PASSWORD_LEN
+        ))
+        .with_details(json!({"field": field})));
+    }
+    Ok(())
+}
+
+/// Envía al usuario el correo con el enlace de verificación.
+async fn send_verification_mail(mailer: &dyn Mailer, user_id: ObjectId, mail: &str) -> bool {
+    let token = generate_action_token(user_id.to_hex(), ActionPurpose::Verify, fingerprint(mail));
+    let message = MailMessage {
+        to: mail.to_string(),
+        subject: "Verifica tu correo electrónico".to_string(),
+        body: format!(
+            "Para verificar tu correo electrónico abre el siguiente enlace:\n\n{}/verify-email?token={}\n\nEl enlace caduca en 48 horas.",
+            config::get().mail.app_url,
+            token
+        ),
+    };
+    match mailer.send(&message).await {
+        Ok(_) => true,
+        Err(e) => {
+            write_log(&format!("MAIL - Error enviando verificación: {}", e)).ok();
+            false
+        }
+    }
+}
+
+/// Resultado de comprobar una contraseña contra el hash almacenado.
+#[derive(Debug, PartialEq, Eq)]
+enum PasswordCheck {
+    Invalid,
+    Valid,
+    /// La contraseña es correcta pero el hash es antiguo (SHA-256 o Argon2
+    /// con otros parámetros) y debe regenerarse.
+    NeedsRehash,
+}
+
+/// Hashes SHA-256 sin sal generados por versiones anteriores y por `DataBase/manage.py`.
+fn is_legacy_hash(stored: &str) -> bool {
+    stored.len() == 64 && stored.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+fn verify_legacy_hash(password: &str, stored: &str) -> bool {
+    let digest = format!("{:x}", Sha256::digest(password.as_bytes()));
+    // Comparación en tiempo constante
+    digest
+        .bytes()
+        .zip(stored.to_ascii_lowercase().bytes())
+        .fold(0u8, |acc, (a, b)| acc | (a ^ b))
+        == 0
+}
+
+fn check_password_sync(password: &str, stored: &str) -> PasswordCheck {
+    if is_legacy_hash(stored) {
+        return if verify_legacy_hash(password, stored) {
+            PasswordCheck::NeedsRehash
+        } else {
+            PasswordCheck::Invalid
+        };
+    }
+    let parsed = match PasswordHash::new(stored) {
+        Ok(parsed) => parsed,
+        Err(_) => return PasswordCheck::Invalid,
+    };
+    if argon2()
+        .verify_password(password.as_bytes(), &parsed)
+        .is_err()
+    {
+        return PasswordCheck::Invalid;
+    }
+    let up_to_date = parsed.algorithm == Algorithm::Argon2id.ident()
+        && Params::try_from(&parsed).is_ok_and(|p| {
+            let current = argon2_params();
+            p.m_cost() == current.m_cost()
+                && p.t_cost() == current.t_cost()
+                && p.p_cost() == current.p_cost()
+        });
+    if up_to_date {
+        PasswordCheck::Valid
+    } else {
+        PasswordCheck::NeedsRehash
+    }
+}
+
+/// Cifra la contraseña con Argon2id y sal aleatoria, en formato PHC.
+/// Se ejecuta en el pool de hilos bloqueantes para no frenar al worker.
+pub async fn hash_password(password: String) -> Result<String, ()> {
+    web::block(move || {
+        let salt = SaltString::generate(&mut OsRng);
+        argon2()
+            .hash_password(password.as_bytes(), &salt)
+            .map(|hash| hash.to_string())
+    })
+    .await
+    .map_err(|_| ())?
+    .map_err(|_| ())
+}
+
+/// Hash ficticio con los parámetros actuales. Cuando el correo no existe se comprueba la
+/// contraseña contra él para que el tiempo de respuesta no delate qué correos están
+/// registrados.
+static DUMMY_HASH: Lazy<String> = Lazy::new(|| {
+    let salt = SaltString::generate(&mut OsRng);
+    argon2()
+        .hash_password(b"contrasena-ficticia", &salt)
+        .map(|hash| hash.to_string())
+        .unwrap_or_default()
+});
+
+/// Comprueba la contraseña contra `stored` o, si no hay usuario, contra `DUMMY_HASH`.
+async fn check_password(password: String, stored: Option<String>) -> PasswordCheck {
+    web::block(move || match stored {
+        Some(stored) => check_password_sync(&password, &stored),
+        None => {
+            check_password_sync(&password, &DUMMY_HASH);
+            PasswordCheck::Invalid
+        }
+    })
+    .await
+    .unwrap_or(PasswordCheck::Invalid)
+}
+
+/// Inicia sesión con correo y contraseña. Los hashes antiguos se regeneran de forma
+/// transparente; si eso falla, el login sigue siendo válido.
+pub async fn login(
+    repos: &Repositories,
+    mail: &str,
+    password: &str,
+    user_agent: Option<String>,
+) -> Result<(Tokens, User), ApiError> {
+    let mut user = match repos.users.find_by_mail(mail).await? {
+        Some(user) => user,
+        None => {
+            check_password(password.to_string(), None).await;
+            return Err(ApiError::unauthorized("Usuario o contraseña erronea"));
+        }
+    };
+    let user_id = match user.id {
+        Some(id) => id,
+        None => return Err(ApiError::internal("No hay ID")),
+    };
+    match check_password(password.to_string(), Some(user.password_hash.clone())).await {
+        PasswordCheck::Invalid => {
+            return Err(ApiError::unauthorized("Usuario o contraseña erronea"));
+        }
+        PasswordCheck::Valid => {}
+        PasswordCheck::NeedsRehash => {
+            if let Ok(new_hash) = hash_password(password.to_string()).await {
+                match repos
+                    .users
+                    .replace_password_hash(user_id, &user.password_hash, &new_hash)
+                    .await
+                {
+                    Ok(_) => {
+                        user.password_hash = new_hash;
+                        write_log("POST /users/login - Hash de contraseña actualizado").ok();
+                    }
+                    Err(_) => {
+                        write_log("POST /users/login - Error actualizando hash").ok();
+                    }
+                }
+            }
+        }
+    }
+    let tokens = issue_tokens(repos, user_id, role_of(&user), user_agent).await?;
+    Ok((tokens, user))
+}
+
+/// Registra un usuario nuevo (nunca administrador ni verificado) y abre su primera sesión.
+pub async fn register(
+    repos: &Repositories,
+    mailer: &dyn Mailer,
+    mut user: User,
+    user_agent: Option<String>,
+) -> Result<(Tokens, User), ApiError> {
+    // Valida que el correo tenga el formato usuario@dominio.tld
+    if !EMAIL_REGEX.is_match(&user.mail) {
+        return Err(ApiError::validation("El correo no es válido"));
+    }
+    if repos.users.find_by_mail(&user.mail).await?.is_some() {
+        return Err(ApiError::conflict("El correo está en uso"));
+    }
+    validate_password(&user.password_hash, "passwordHash")?;
+    // Cifrar la contraseña antes de guardarla
+    user.password_hash = match hash_password(user.password_hash).await {
+        Ok(hash) => hash,
+        Err(_) => return Err(ApiError::internal("Error inesperado, vuelva a intentarlo")),
+    };
+    user.id = None;
+    user.admin = None;
+    user.verified = false;
+    let user_id = repos.users.insert(&user).await?;
+    user.id = Some(user_id);
+    // Un fallo al enviar el correo no impide el registro; se puede reenviar después
+    send_verification_mail(mailer, user_id, &user.mail).await;
+    let tokens = issue_tokens(repos, user_id, "user".to_string(), user_agent).await?;
+    Ok((tokens, user))
+}
+
+/// Convierte el cuerpo de un PATCH de usuario en cambios. `allow_admin` permite
+/// modificar el campo `admin` (solo para administradores).
+pub async fn user_changes(
+    body: &serde_json::Value,
+    allow_admin: bool,
+) -> Result<Changes, ApiError> {
+    let mut changes = Changes::new();
+
+    // Campo: name (solo si está presente con valor)
+    if let Some(value) = body.get("name") {
+        match value {
+            serde_json::Value::String(name) => changes.set("name", name.clone()),
+            serde_json::Value::Null => {
+                return Err(ApiError::validation("'name' no puede ser null")
+                    .with_details(json!({"field": "name"})));
+            }
+            _ => {
+                return Err(ApiError::validation("Valor inválido para 'name'")
+                    .with_details(json!({"field": "name"})));
+            }
+        };
+    }
+
+    // Campo: passwordHash (solo si está presente con valor)
+    if let Some(value) = body.get("passwordHash") {
+        match value {
+            serde_json::Value::String(pass) => {
+                validate_password(pass, "passwordHash")?;
+                let hashed = match hash_password(pass.clone()).await {
+                    Ok(hash) => hash,
+                    Err(_) => {
+                        return Err(ApiError::internal("Error inesperado, inténtelo nuevamente"))
+                    }
+                };
+                changes.set("passwordHash", hashed);
+            }
+            serde_json::Value::Null => {
+                return Err(ApiError::validation("'passwordHash' no puede ser null")
+                    .with_details(json!({"field": "passwordHash"})));
+            }
+            _ => {
+                return Err(ApiError::validation("Valor inválido para 'passwordHash'")
+                    .with_details(json!({"field": "passwordHash"})));
+            }
+        };
+    }
+
+    // Campo admin (solo admin puede modificar)
+    if allow_admin {
+        if let Some(value) = body.get("admin") {
+            match value {
+                serde_json::Value::Bool(b) => changes.set("admin", *b),
+                serde_json::Value::Null => changes.unset("admin"),
+                _ => {
+                    return Err(ApiError::validation("Valor inválido para 'admin'")
+                        .with_details(json!({"field": "admin"})));
+                }
+            };
+        }
+    }
+
+    // Validar que haya algo que actualizar
+    if changes.is_empty() {
+        return Err(ApiError::validation("No hay campos para actualizar"));
+    }
+    Ok(changes)
+}
+
+/// Aplica los cambios al usuario. Un cambio de contraseña cierra todas sus sesiones
+/// salvo `keep_session`.
+pub async fn update_user(
+    repos: &Repositories,
+    user_id: ObjectId,
+    changes: Changes,
+    keep_session: Option<ObjectId>,
+) -> Result<(), ApiError> {
+    let password_changed = changes.sets("passwordHash");
+    if !repos.users.update(user_id, changes).await? {
+        return Err(ApiError::not_found("Usuario no encontrado"));
+    }
+    if password_changed {
+        repos
+            .sessions
+            .revoke_all_for_user(user_id, keep_session)
+            .await
+            .ok();
+    }
+    Ok(())
+}
+
+/// Envía el enlace para restablecer la contraseña si el correo está registrado. Un fallo
+/// al enviar el correo solo se registra en el log: la respuesta tiene que ser la misma
+/// exista o no la cuenta.
+pub async fn forgot_password(
+    repos: &Repositories,
+    mailer: &dyn Mailer,
+    mail: &str,
+) -> Result<(), ApiError> {
+    let user = match repos.users.find_by_mail(mail).await? {
+        Some(user) => user,
+        None => {
+            write_log("POST /users/password/forgot - Correo no registrado").ok();
+            return Ok(());
+        }
+    };
+    let user_id = match user.id {
+        Some(id) => id,
+        None => return Err(ApiError::internal("No hay ID")),
+    };
+    let token = generate_action_token(
+        user_id.to_hex(),
+        ActionPurpose::Reset,
+        fingerprint(&user.password_hash),
+    );
+    let message = MailMessage {
+        to: user.mail.clone(),
+        subject: "Restablece tu contraseña".to_string(),
+        body: format!(
+            "Hemos recibido una solicitud para restablecer tu contraseña. Abre el siguiente enlace para elegir una nueva:\n\n{}/reset-password?token={}\n\nEl enlace caduca en 1 hora. Si no has sido tú, ignora este correo.",
+            config::get().mail.app_url,
+            token
+        ),
+    };
+    if let Err(e) = mailer.send(&message).await {
+        write_log(&format!(
+            "POST /users/password/forgot - Error enviando correo: {}",
+            e
+        ))
+        .ok();
+    }
+    Ok(())
+}
+
+/// Usuario al que pertenece un token de acción válido para la finalidad indicada.
+async fn user_from_action_token(
+    repos: &Repositories,
+    token: &str,
+    purpose: ActionPurpose,
+) -> Result<(User, String), ApiError> {
+    let claims = match decode_action_token(token, purpose) {
+        Some(claims) => claims,
+        None => return Err(ApiError::validation("Token inválido o expirado")),
+    };
+    let user_id = match ObjectId::parse_str(&claims.sub) {
+        Ok(id) => id,
+        Err(_) => return Err(ApiError::validation("Token inválido o expirado")),
+    };
+    match repos.users.find_by_id(user_id).await? {
+        Some(user) => Ok((user, claims.fp)),
+        None => Err(ApiError::validation("Token inválido o expirado")),
+    }
+}
+
+/// Cambia la contraseña con un token de restablecimiento y cierra todas las sesiones.
+pub async fn reset_password(
+    repos: &Repositories,
+    token: &str,
+    password: &str,
+) -> Result<(), ApiError> {
+    validate_password(password, "password")?;
+    let (user, fp) = user_from_action_token(repos, token, ActionPurpose::Reset).await?;
+    let user_id = match user.id {
+        Some(id) => id,
+        None => return Err(ApiError::internal("No hay ID")),
+    };
+    // La huella cambia en cuanto se modifica la contraseña, así que el token es de un solo uso
+    if fingerprint(&user.password_hash) != fp {
+        return Err(ApiError::validation("Token inválido o expirado"));
+    }
+    let new_hash = match hash_password(password.to_string()).await {
+        Ok(hash) => hash,
+        Err(_) => return Err(ApiError::internal("Error inesperado, inténtelo nuevamente")),
+    };
+    if !repos
+        .users
+        .replace_password_hash(user_id, &user.password_hash, &new_hash)
+        .await?
+    {
+        return Err(ApiError::validation("Token inválido o expirado"));
+    }
+    repos.sessions.revoke_all_for_user(user_id, None).await.ok();
+    Ok(())
+}
+
+/// Marca el correo como verificado con un token de verificación.
+pub async fn verify_email(repos: &Repositories, token: &str) -> Result<(), ApiError> {
+    let (user, fp) = user_from_action_token(repos, token, ActionPurpose::Verify).await?;
+    let user_id = match user.id {
+        Some(id) => id,
+        None => return Err(ApiError::internal("No hay ID")),
+    };
+    if fingerprint(&user.mail) != fp || user.verified {
+        return Err(ApiError::validation("Token inválido o expirado"));
+    }
+    if !repos.users.mark_verified(user_id).await? {
+        return Err(ApiError::validation("Token inválido o expirado"));
+    }
+    Ok(())
+}
+
+/// Reenvía el correo de verificación al usuario.
+pub async fn resend_verification(
+    repos: &Repositories,
+    mailer: &dyn Mailer,
+    user_id: ObjectId,
+) -> Result<(), ApiError> {
+    let user = match repos.users.find_by_id(user_id).await? {
+        Some(user) => user,
+        None => return Err(ApiError::not_found("Usuario no encontrado")),
+    };
+    if user.verified {
+        return Err(ApiError::conflict("El correo ya está verificado"));
+    }
+    if !send_verification_mail(mailer, user_id, &user.mail).await {
+        return Err(ApiError::internal(
+            "No se pudo enviar el correo, inténtelo nuevamente",
+        ));
+    }
+    Ok(())
+}
+
+/// Elimina el usuario junto con sus relaciones con grupos y sus sesiones.
+pub async fn delete_user(repos: &Repositories, user_id: ObjectId) -> Result<(), ApiError> {
+    for user_group in repos.user_groups.find_by_user(user_id).await? {
+        let id = match user_group.id {
+            Some(id) => id,
+            None => {
+                write_log("DELETE /users/{id} - Relación sin ID").ok();
+                return Err(ApiError::internal("No hay ID"));
+            }
+        };
+        // Si falla, detenemos la ejecución y devolvemos el error
+        delete_user_group(repos, id).await?;
+    }
+    repos.sessions.delete_by_user(user_id).await?;
+    if !repos.users.delete(user_id).await? {
+        return Err(ApiError::not_found("Usuario no encontrado"));
+    }
+    Ok(())
+}
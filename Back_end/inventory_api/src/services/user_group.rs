@@ -0,0 +1,121 @@
+use mongodb::bson::oid::ObjectId;
+use serde::Serialize;
+
+use crate::entities::{group::Group, user_group::GroupRole};
+use crate::error::ApiError;
+use crate::repository::Repositories;
+
+#[derive(Debug, Serialize)]
+pub struct GroupMember {
+    #[serde(rename = "userId")]
+    user_id: ObjectId,
+    name: String,
+    mail: String,
+    role: GroupRole,
+}
+
+/// Grupos del usuario. Un administrador ve todos los grupos.
+pub async fn groups_of_user(
+    repos: &Repositories,
+    user_id: ObjectId,
+    is_admin: bool,
+) -> Result<Vec<Group>, ApiError> {
+    if is_admin {
+        let groups = repos.groups.find_all().await?;
+        if groups.is_empty() {
+            return Err(ApiError::validation("No hay grupos en la base de datos"));
+        }
+        return Ok(groups);
+    }
+
+    let mut groups = Vec::new();
+    for user_group in repos.user_groups.find_by_user(user_id).await? {
+        if let Some(group) = repos.groups.find_by_id(user_group.group_id).await? {
+            groups.push(group);
+        }
+    }
+    if groups.is_empty() {
+        return Err(ApiError::validation(
+            "No hay grupos asociados a ese usuario",
+        ));
+    }
+    Ok(groups)
+}
+
+pub async fn delete_user_group(
+    repos: &Repositories,
+    user_group_id: ObjectId,
+) -> Result<(), ApiError> {
+    if !repos.user_groups.delete(user_group_id).await? {
+        return Err(ApiError::not_found("grupo usuario no encontrado"));
+    }
+    Ok(())
+}
+
+/// Miembros del grupo con su nombre, correo y rol.
+pub async fn members(
+    repos: &Repositories,
+    group_id: ObjectId,
+) -> Result<Vec<GroupMember>, ApiError> {
+    let mut members = Vec::new();
+    for user_group in repos.user_groups.find_by_group(group_id).await? {
+        if let Some(user) = repos.users.find_by_id(user_group.user_id).await? {
+            members.push(GroupMember {
+                user_id: user_group.user_id,
+                name: user.name,
+                mail: user.mail,
+                role: user_group.role,
+            });
+        }
+    }
+    Ok(members)
+}
+
+/// Cambia el rol de un miembro. El grupo debe conservar al menos un propietario.
+pub async fn set_member_role(
+    repos: &Repositories,
+    group_id: ObjectId,
+    user_id: ObjectId,
+    role: GroupRole,
+) -> Result<(), ApiError> {
+    let current = match repos.user_groups.find(user_id, group_id).await? {
+        Some(user_group) => user_group.role,
+        None => return Err(ApiError::not_found("El usuario no pertenece a este grupo")),
+    };
+    if current == GroupRole::Owner
+        && role != GroupRole::Owner
+        && repos.user_groups.count_owners(group_id).await? <= 1
+    {
+        return Err(ApiError::validation(
+            "El grupo debe tener al menos un propietario",
+        ));
+    }
+    if !repos.user_groups.set_role(group_id, user_id, role).await? {
+        return Err(ApiError::not_found("El usuario no pertenece a este grupo"));
+    }
+    Ok(())
+}
+
+/// Expulsa a un miembro del grupo. `actor_id` es quien realiza la acción y no puede
+/// expulsarse a sí mismo.
+pub async fn remove_member(
+    repos: &Repositories,
+    actor_id: ObjectId,
+    group_id: ObjectId,
+    user_id: ObjectId,
+) -> Result<(), ApiError> {
+    if actor_id == user_id {
+        return Err(ApiError::validation(
+            "Para salir del grupo usa la opción de abandonar",
+        ));
+    }
+    if !repos.user_groups.delete_member(group_id, user_id).await? {
+        return Err(ApiError::not_found("El usuario no pertenece a este grupo"));
+    }
+    if repos.groups.add_user_count(group_id, -1).await.is_err() {
+        return Err(ApiError::internal(
+            "Error al actualizar el contador de usuarios",
+        ));
+    }
+    Ok(())
+}
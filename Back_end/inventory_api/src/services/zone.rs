@@ -0,0 +1,253 @@
+use mongodb::bson::oid::ObjectId;
+use serde::Serialize;
+use serde_json::json;
+
+use crate::access::Resource;
+use crate::entities::{item::Item, zone::Zone};
+use crate::error::ApiError;
+use crate::pagination::{Cursor, Page, PageQuery};
+use crate::repository::{Changes, ItemWrite, Repositories};
+use crate::services::item::move_changes;
+
+/// Propiedad a la que pertenecerá una zona nueva y recurso del padre indicado en
+/// `parentZoneId`, que puede ser la propia propiedad o una zona.
+pub async fn resolve_parent(
+    repos: &Repositories,
+    parent_zone_id: ObjectId,
+) -> Result<(ObjectId, Resource), ApiError> {
+    if repos
+        .properties
+        .find_by_id(parent_zone_id)
+        .await
+        .ok()
+        .flatten()
+        .is_some()
+    {
+        // Si se encontró, se usa parent_zone_id como property_id
+        return Ok((parent_zone_id, Resource::Property(parent_zone_id)));
+    }
+    match repos.zones.find_by_id(parent_zone_id).await {
+        Ok(Some(zone)) => Ok((zone.property_id, Resource::Zone(parent_zone_id))),
+        _ => Err(ApiError::validation(
+            "parentZoneId no corresponde a propiedad ni zona válida",
+        )),
+    }
+}
+
+/// Hijo de una zona o propiedad, con su tipo en el campo `type`.
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Child {
+    Zone(Zone),
+    Item(Box<Item>),
+}
+
+/// Campos de las zonas por los que se puede filtrar; el resto de filtros son solo de items.
+const ZONE_FIELDS: &[&str] = &["name"];
+
+/// Zonas hijas visibles para `viewer` e items de la zona (o propiedad) `parent_id`, por
+/// páginas: primero todas las zonas y después los items. El cursor indica en cuál de las
+/// dos listas se va. Con filtros que solo tienen los items no se devuelven zonas.
+pub async fn children(
+    repos: &Repositories,
+    parent_id: ObjectId,
+    viewer: Option<ObjectId>,
+    query: &PageQuery,
+) -> Result<Page<Child>, ApiError> {
+    let zone_query = query.with_filters_on(ZONE_FIELDS);
+    let items_only = zone_query.filters.len() < query.filters.len();
+    let in_items = query
+        .after
+        .as_ref()
+        .is_some_and(|cursor| cursor.kind.as_deref() == Some("item"));
+
+    let zones = if items_only {
+        Page::new(Vec::new(), 0, &zone_query)
+    } else {
+        // Pasadas las zonas basta con contarlas
+        let zone_query = if in_items {
+            zone_query.starting_at(None, 0)
+        } else {
+            zone_query
+        };
+        match repos
+            .zones
+            .find_page_by_parent(parent_id, viewer, &zone_query)
+            .await
+        {
+            Ok(zones) => zones,
+            Err(_) => return Err(ApiError::internal("Error al obtener zonas")),
+        }
+    };
+    let zone_count = if in_items { 0 } else { zones.data.len() };
+    let remaining = query.limit - zone_count;
+    let item_after = match &query.after {
+        Some(cursor) if in_items => Some(Cursor {
+            kind: None,
+            ..cursor.clone()
+        }),
+        _ => None,
+    };
+    let item_query = if zones.next_cursor.is_some() {
+        query.starting_at(None, 0)
+    } else {
+        query.starting_at(item_after, remaining)
+    };
+    let items = match repos.items.find_page_by_zone(parent_id, &item_query).await {
+        Ok(items) => items,
+        Err(_) => return Err(ApiError::internal("Error al obtener ítems")),
+    };
+
+    let total = zones.total + items.total;
+    let mut data = Vec::new();
+    let next_cursor = if let Some(cursor) = zones.next_cursor {
+        data.extend(zones.data.into_iter().map(Child::Zone));
+        Some(Cursor {
+            kind: Some("zone".to_string()),
+            ..cursor
+        })
+    } else {
+        if !in_items {
+            data.extend(zones.data.into_iter().map(Child::Zone));
+        }
+        if remaining == 0 && items.total > 0 {
+            // Las zonas llenan la página justo: la siguiente empieza por los items
+            Some(Cursor {
+                kind: Some("item".to_string()),
+                ..Cursor::default()
+            })
+        } else {
+            data.extend(
+                items
+                    .data
+                    .into_iter()
+                    .map(|item| Child::Item(Box::new(item))),
+            );
+            items.next_cursor.map(|cursor| Cursor {
+                kind: Some("item".to_string()),
+                ..cursor
+            })
+        }
+    };
+    Ok(Page {
+        data,
+        next_cursor,
+        total,
+    })
+}
+
+/// Convierte el cuerpo de un PATCH de zona en cambios. Marcarla como privada la asigna
+/// a `user_id`.
+pub fn zone_changes(body: &serde_json::Value, user_id: ObjectId) -> Result<Changes, ApiError> {
+    let mut changes = Changes::new();
+
+    // Campo name
+    if let Some(value) = body.get("name") {
+        match value {
+            serde_json::Value::String(name) => changes.set("name", name.clone()),
+            _ => {
+                return Err(ApiError::validation("Valor inválido para 'name'")
+                    .with_details(json!({"field": "name"})));
+            }
+        }
+    }
+
+    // Campo private
+    if let Some(value) = body.get("private") {
+        match value {
+            serde_json::Value::Bool(true) => changes.set("userId", user_id),
+            serde_json::Value::Bool(false) => changes.unset("userId"),
+            _ => {
+                return Err(ApiError::validation("Valor inválido para 'private'")
+                    .with_details(json!({"field": "private"})));
+            }
+        }
+    }
+
+    if changes.is_empty() {
+        return Err(ApiError::validation("No hay campos para actualizar"));
+    }
+    Ok(changes)
+}
+
+pub async fn update_zone(
+    repos: &Repositories,
+    zone_id: ObjectId,
+    changes: Changes,
+) -> Result<(), ApiError> {
+    if !repos.zones.update(zone_id, changes).await? {
+        return Err(ApiError::not_found("Zona no encontrada"));
+    }
+    Ok(())
+}
+
+// Función auxiliar para obtener todos los IDs de zonas hijas recursivamente
+pub async fn get_all_child_zone_ids(repos: &Repositories, parent_id: &ObjectId) -> Vec<ObjectId> {
+    let mut all_ids = Vec::new();
+    let mut stack = vec![*parent_id];
+    while let Some(current_id) = stack.pop() {
+        // Buscar zonas hijas directas
+        if let Ok(children) = repos.zones.find_by_parent(current_id, None).await {
+            for child_id in children.into_iter().filter_map(|zone| zone.id) {
+                stack.push(child_id);
+                all_ids.push(child_id);
+            }
+        }
+    }
+    all_ids
+}
+
+/// Mueve la zona, con sus subzonas e items, bajo `target_id` (una propiedad u otra zona de
+/// la propiedad `property_id`). Si cambia de propiedad, se actualizan también sus subzonas;
+/// si cambia de grupo, sus items pierden la categoría y sus atributos se validan con los del
+/// grupo `target_group_id`. Si alguno no encaja, no se mueve nada.
+pub async fn move_zone(
+    repos: &Repositories,
+    zone_id: ObjectId,
+    target_id: ObjectId,
+    property_id: ObjectId,
+    group_id: ObjectId,
+    target_group_id: ObjectId,
+) -> Result<(), ApiError> {
+    let zone = match repos.zones.find_by_id(zone_id).await? {
+        Some(zone) => zone,
+        None => return Err(ApiError::not_found("Zona no encontrada")),
+    };
+    let descendants = get_all_child_zone_ids(repos, &zone_id).await;
+    // El destino no puede ser la propia zona ni una de sus subzonas
+    if target_id == zone_id || descendants.contains(&target_id) {
+        return Err(
+            ApiError::validation("Una zona no puede moverse dentro de sí misma")
+                .with_details(json!({"field": "parentZoneId"})),
+        );
+    }
+    // Los cambios de los items se preparan antes de mover nada
+    let mut writes = Vec::new();
+    if target_group_id != group_id {
+        for id in std::iter::once(zone_id).chain(descendants.iter().copied()) {
+            for item in repos.items.find_by_zone(id).await? {
+                let item_id = match item.id {
+                    Some(item_id) => item_id,
+                    None => continue,
+                };
+                let changes =
+                    move_changes(repos, &item, item.zone_id, group_id, target_group_id).await?;
+                writes.push(ItemWrite::Update {
+                    id: item_id,
+                    changes,
+                });
+            }
+        }
+    }
+    let mut changes = Changes::new();
+    changes.set("parentZoneId", target_id);
+    changes.set("propertyId", property_id);
+    update_zone(repos, zone_id, changes).await?;
+    if zone.property_id != property_id && !descendants.is_empty() {
+        repos.zones.set_property(&descendants, property_id).await?;
+    }
+    if !writes.is_empty() {
+        repos.bulk.write_items(&writes, false).await?;
+    }
+    Ok(())
+}
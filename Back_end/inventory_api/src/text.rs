@@ -0,0 +1,337 @@
+//! Análisis de texto de la búsqueda.
+//!
+//! MongoDB resuelve la búsqueda exacta con sus índices de texto; aquí están el equivalente
+//! para el backend en memoria, la búsqueda aproximada (con erratas) y el marcado de
+//! coincidencias de los resultados, que se calculan igual con los dos backends. Las
+//! palabras se comparan en minúsculas y sin acentos, como en los índices de MongoDB.
+
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+/// Peso de cada campo en los índices de texto.
+pub const NAME_WEIGHT: i32 = 10;
+pub const TAGS_WEIGHT: i32 = 5;
+pub const DESCRIPTION_WEIGHT: i32 = 1;
+
+/// Palabra de un texto, normalizada, con su posición en caracteres en el original.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Word {
+    pub start: usize,
+    pub end: usize,
+    pub text: String,
+}
+
+/// Texto en minúsculas y sin acentos ni otras marcas (`Cámara` pasa a ser `camara`).
+pub fn fold(text: &str) -> String {
+    text.nfd()
+        .filter(|c| !is_combining_mark(*c))
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Expresión regular que encuentra `text` sin distinguir acentos; las mayúsculas se
+/// ignoran con la opción `i`. Sirve para los filtros de MongoDB, que comparan el texto
+/// tal cual está guardado.
+pub fn accent_insensitive_pattern(text: &str) -> String {
+    const VARIANTS: &[(char, &str)] = &[
+        ('a', "aáàâäãå"),
+        ('e', "eéèêë"),
+        ('i', "iíìîï"),
+        ('o', "oóòôöõ"),
+        ('u', "uúùûü"),
+        ('n', "nñ"),
+        ('c', "cç"),
+    ];
+    fold(text)
+        .chars()
+        .map(|c| match VARIANTS.iter().find(|(base, _)| *base == c) {
+            Some((_, variants)) => format!("[{}]", variants),
+            None => regex::escape(&c.to_string()),
+        })
+        .collect()
+}
+
+/// Palabras (secuencias de letras y números) de un texto.
+pub fn words(text: &str) -> Vec<Word> {
+    let mut words = Vec::new();
+    let mut current: Option<Word> = None;
+    for (position, c) in text.chars().enumerate() {
+        if c.is_alphanumeric() {
+            let word = current.get_or_insert_with(|| Word {
+                start: position,
+                end: position,
+                text: String::new(),
+            });
+            word.text.push_str(&fold(&c.to_string()));
+            word.end = position + 1;
+        } else if is_combining_mark(c) {
+            // Acento ya separado de su letra: sigue siendo parte de la palabra
+            if let Some(word) = current.as_mut() {
+                word.end = position + 1;
+            }
+        } else if let Some(word) = current.take() {
+            words.push(word);
+        }
+    }
+    words.extend(current);
+    words
+}
+
+/// Términos distintos de una consulta. Basta con que aparezca uno de ellos.
+pub fn terms(query: &str) -> Vec<String> {
+    let mut terms: Vec<String> = Vec::new();
+    for word in words(query) {
+        if !terms.contains(&word.text) {
+            terms.push(word.text);
+        }
+    }
+    terms
+}
+
+/// Cómo se comparan los términos con las palabras.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Matching {
+    /// La palabra tiene que ser el término.
+    Exact,
+    /// Se admiten erratas según la longitud del término (ver [`max_typos`]).
+    Fuzzy,
+}
+
+/// Erratas que se admiten en un término: ninguna hasta 3 letras, una hasta 7 y dos a
+/// partir de ahí.
+pub fn max_typos(term: &str) -> usize {
+    match term.chars().count() {
+        0..=3 => 0,
+        4..=7 => 1,
+        _ => 2,
+    }
+}
+
+/// Expresión regular que encuentra, sin distinguir acentos, los textos con alguna palabra a
+/// [`max_typos`] erratas o menos del término (y algunos más). Sirve para descartar en la
+/// base de datos lo que no puede encajar antes de puntuar con [`Matching::Fuzzy`]. Con dos
+/// erratas, alguna de las dos mitades del término tiene como mucho una.
+pub fn typo_pattern(term: &str) -> String {
+    let letters: Vec<char> = fold(term).chars().collect();
+    let variants = match max_typos(term) {
+        0 => vec![letters_pattern(&letters)],
+        1 => one_typo(&letters),
+        _ => {
+            let (left, right) = letters.split_at(letters.len() / 2);
+            let mut variants = one_typo(left);
+            variants.extend(one_typo(right));
+            variants
+        }
+    };
+    variants.join("|")
+}
+
+fn letters_pattern(letters: &[char]) -> String {
+    letters
+        .iter()
+        .map(|c| accent_insensitive_pattern(&c.to_string()))
+        .collect()
+}
+
+/// Variantes de las letras con una errata: una cambiada, de menos, de más o dos seguidas
+/// intercambiadas. La letra cambiada cubre también el texto sin erratas.
+fn one_typo(letters: &[char]) -> Vec<String> {
+    let mut variants = Vec::new();
+    let mut add = |variant: String| {
+        if !variants.contains(&variant) {
+            variants.push(variant);
+        }
+    };
+    for i in 0..letters.len() {
+        let (before, after) = (letters_pattern(&letters[..i]), &letters[i + 1..]);
+        add(format!("{}.{}", before, letters_pattern(after)));
+        add(format!("{}{}", before, letters_pattern(after)));
+        if i > 0 {
+            add(format!("{}.{}", before, letters_pattern(&letters[i..])));
+        }
+        if let Some(next) = after.first() {
+            let swapped = [*next, letters[i]];
+            add(format!(
+                "{}{}{}",
+                before,
+                letters_pattern(&swapped),
+                letters_pattern(&after[1..])
+            ));
+        }
+    }
+    variants
+}
+
+impl Matching {
+    /// Parecido entre un término y una palabra, de 0 (no encaja) a 1 (iguales). Una errata
+    /// es una letra de más, de menos, cambiada o dos letras seguidas intercambiadas.
+    pub fn similarity(self, term: &str, word: &str) -> f64 {
+        if term == word {
+            return 1.0;
+        }
+        if self == Matching::Exact {
+            return 0.0;
+        }
+        let typos = strsim::osa_distance(term, word);
+        if typos > max_typos(term) {
+            return 0.0;
+        }
+        let longest = term.chars().count().max(word.chars().count());
+        1.0 - typos as f64 / longest as f64
+    }
+}
+
+/// Puntuación de un campo como la de MongoDB: cada aparición de un término suma el peso
+/// del campo, y más cuanto mayor es la parte del campo que ocupan sus apariciones. Con
+/// erratas, cada aparición suma en proporción a lo que se parece al término.
+pub fn field_score(text: &str, terms: &[String], weight: i32, matching: Matching) -> f64 {
+    let words = words(text);
+    let mut score = 0.0;
+    for term in terms {
+        let similarities: Vec<f64> = words
+            .iter()
+            .map(|word| matching.similarity(term, &word.text))
+            .filter(|similarity| *similarity > 0.0)
+            .collect();
+        if !similarities.is_empty() {
+            let count = similarities.len() as f64;
+            let coefficient = 0.5 * count / words.len() as f64 + 0.5;
+            score += weight as f64 * similarities.iter().sum::<f64>() * coefficient;
+        }
+    }
+    score
+}
+
+/// Tramos `[inicio, fin)`, en caracteres, de las palabras del texto que encajan con algún
+/// término.
+pub fn highlight(text: &str, terms: &[String], matching: Matching) -> Vec<(usize, usize)> {
+    words(text)
+        .into_iter()
+        .filter(|word| {
+            terms
+                .iter()
+                .any(|term| matching.similarity(term, &word.text) > 0.0)
+        })
+        .map(|word| (word.start, word.end))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use regex::RegexBuilder;
+
+    fn matches_pattern(term: &str, text: &str) -> bool {
+        RegexBuilder::new(&typo_pattern(term))
+            .case_insensitive(true)
+            .build()
+            .unwrap()
+            .is_match(text)
+    }
+
+    #[test]
+    fn fold_removes_case_and_accents() {
+        assert_eq!(fold("Cámara ÑANDÚ Pingüino"), "camara nandu pinguino");
+        // Acento como marca separada (NFD)
+        assert_eq!(fold("Ca\u{301}mara"), "camara");
+        assert_eq!(fold(""), "");
+        assert_eq!(terms("Caja, CAJA; cája roja"), vec!["caja", "roja"]);
+        assert!(terms(" ¿?¡! ").is_empty());
+    }
+
+    #[test]
+    fn typos_allowed_grow_with_the_term_length() {
+        assert_eq!(max_typos(""), 0);
+        assert_eq!(max_typos("sal"), 0);
+        assert_eq!(max_typos("caja"), 1);
+        assert_eq!(max_typos("tablero"), 1);
+        assert_eq!(max_typos("tableros"), 2);
+        // Cuentan las letras, no los bytes
+        assert_eq!(max_typos("ñañ"), 0);
+    }
+
+    #[test]
+    fn similarity_follows_the_osa_thresholds() {
+        let fuzzy = Matching::Fuzzy;
+        assert_eq!(fuzzy.similarity("sal", "sal"), 1.0);
+        assert_eq!(fuzzy.similarity("sal", "sol"), 0.0);
+        assert_eq!(fuzzy.similarity("caja", "cjaa"), 0.75);
+        assert_eq!(fuzzy.similarity("caja", "caj"), 0.75);
+        assert_eq!(fuzzy.similarity("caja", "cojo"), 0.0);
+        assert_eq!(fuzzy.similarity("tableros", "tabelro"), 0.75);
+        assert_eq!(fuzzy.similarity("tableros", "tbaelros"), 0.75);
+        assert_eq!(fuzzy.similarity("tableros", "tabla"), 0.0);
+        assert_eq!(Matching::Exact.similarity("caja", "caj"), 0.0);
+        assert_eq!(Matching::Exact.similarity("caja", "caja"), 1.0);
+    }
+
+    #[test]
+    fn field_score_weights_each_appearance() {
+        let terms = vec!["cable".to_string()];
+        assert_eq!(
+            field_score("Cable", &terms, NAME_WEIGHT, Matching::Exact),
+            10.0
+        );
+        assert_eq!(
+            field_score("Cable de red", &terms, 1, Matching::Exact),
+            1.0 * (0.5 / 3.0 + 0.5)
+        );
+        assert_eq!(
+            field_score("Router", &terms, NAME_WEIGHT, Matching::Exact),
+            0.0
+        );
+        assert_eq!(field_score("", &terms, NAME_WEIGHT, Matching::Fuzzy), 0.0);
+    }
+
+    #[test]
+    fn highlights_are_character_ranges_of_whole_words() {
+        let terms = vec!["camara".to_string(), "red".to_string()];
+        assert_eq!(
+            highlight("Cámara (red), redes", &terms, Matching::Exact),
+            vec![(0, 6), (8, 11)]
+        );
+        // La marca separada forma parte de la palabra marcada
+        assert_eq!(
+            highlight("la ca\u{301}mara", &terms, Matching::Exact),
+            vec![(3, 10)]
+        );
+        assert_eq!(highlight("Cámra", &terms, Matching::Fuzzy), vec![(0, 5)]);
+        assert!(highlight("Cámra", &terms, Matching::Exact).is_empty());
+        assert!(highlight("", &terms, Matching::Fuzzy).is_empty());
+    }
+
+    #[test]
+    fn typo_pattern_finds_every_word_within_the_allowed_typos() {
+        let cases = [
+            ("sal", vec!["Sal", "SAL"]),
+            ("cable", vec!["cable", "Cábel", "cabe", "cablle", "cazle"]),
+            (
+                "destornillador",
+                vec![
+                    "Destornillador",
+                    "destronilador",
+                    "dstornilladro",
+                    "DESTORNILLADO",
+                ],
+            ),
+        ];
+        for (term, words) in cases {
+            for word in words {
+                assert!(
+                    Matching::Fuzzy.similarity(term, &fold(word)) > 0.0,
+                    "{} / {}",
+                    term,
+                    word
+                );
+                assert!(matches_pattern(term, word), "{} / {}", term, word);
+            }
+        }
+    }
+
+    #[test]
+    fn typo_pattern_rejects_words_too_far_from_the_term() {
+        assert!(!matches_pattern("sal", "sol"));
+        assert!(!matches_pattern("cable", "Tostadora"));
+        assert!(!matches_pattern("cable", "caja"));
+        assert!(!matches_pattern("destornillador", "martillo percutor"));
+    }
+}
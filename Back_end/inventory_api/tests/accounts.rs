@@ -0,0 +1,156 @@
+mod common;
+
+use actix_web::http::StatusCode;
+use mongodb::bson::oid::ObjectId;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+
+use common::{call, init_app_on, init_app_with, register, relaxed_limits, repositories, Outbox};
+use inventory_api::repository::Changes;
+
+#[actix_web::test]
+async fn verification_link_marks_the_mail_as_verified_once() {
+    let outbox = Outbox::default();
+    let app = init_app_with(outbox.clone(), relaxed_limits()).await;
+    let ana = register(&app, "ana").await;
+    let (_, me) = call(&app, "GET", "/private/users/me/", Some(&ana.token), None).await;
+    assert_eq!(me["verified"], false);
+
+    let token = outbox.last_token("ana@example.com");
+    let verify = json!({"token": token});
+    let (status, _) = call(
+        &app,
+        "POST",
+        "/public/users/verify",
+        None,
+        Some(verify.clone()),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let (_, me) = call(&app, "GET", "/private/users/me/", Some(&ana.token), None).await;
+    assert_eq!(me["verified"], true);
+
+    let (status, _) = call(&app, "POST", "/public/users/verify", None, Some(verify)).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    let (status, _) = call(
+        &app,
+        "POST",
+        "/private/users/me/verify",
+        Some(&ana.token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    // Un token de restablecimiento no sirve para verificar
+    call(
+        &app,
+        "POST",
+        "/public/users/password/forgot",
+        None,
+        Some(json!({"mail": "ana@example.com"})),
+    )
+    .await;
+    let reset = outbox.last_token("ana@example.com");
+    let (status, _) = call(
+        &app,
+        "POST",
+        "/public/users/verify",
+        None,
+        Some(json!({"token": reset})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[actix_web::test]
+async fn legacy_password_hashes_are_upgraded_on_login() {
+    let repos = repositories().await;
+    let app = init_app_on(repos.clone(), Outbox::default(), relaxed_limits()).await;
+    let ana = register(&app, "ana").await;
+    let id = ObjectId::parse_str(&ana.id).unwrap();
+
+    // Hash SHA-256 sin sal como los de las versiones anteriores
+    let stored = repos.users.find_by_id(id).await.unwrap().unwrap();
+    let legacy = format!("{:x}", Sha256::digest("contraseña-segura".as_bytes()));
+    let replaced = repos
+        .users
+        .replace_password_hash(id, &stored.password_hash, &legacy)
+        .await
+        .unwrap();
+    assert!(replaced);
+
+    let (status, _) = call(
+        &app,
+        "POST",
+        "/public/users/login",
+        None,
+        Some(json!({"mail": "ana@example.com", "password": "incorrecta"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let stored = repos.users.find_by_id(id).await.unwrap().unwrap();
+    assert_eq!(stored.password_hash, legacy);
+
+    let (status, _) = call(
+        &app,
+        "POST",
+        "/public/users/login",
+        None,
+        Some(json!({"mail": "ana@example.com", "password": "contraseña-segura"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let stored = repos.users.find_by_id(id).await.unwrap().unwrap();
+    assert!(stored.password_hash.starts_with("$argon2id$"));
+
+    let (status, _) = call(
+        &app,
+        "POST",
+        "/public/users/login",
+        None,
+        Some(json!({"mail": "ana@example.com", "password": "contraseña-segura"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn mail_format_is_checked_before_looking_for_duplicates() {
+    let repos = repositories().await;
+    let app = init_app_on(repos.clone(), Outbox::default(), relaxed_limits()).await;
+    let ana = register(&app, "ana").await;
+    // Correo antiguo guardado sin validar
+    let mut changes = Changes::new();
+    changes.set("mail", "ana-sin-dominio");
+    let id = ObjectId::parse_str(&ana.id).unwrap();
+    assert!(repos.users.update(id, changes).await.unwrap());
+
+    let (status, _) = call(
+        &app,
+        "POST",
+        "/public/users/register",
+        None,
+        Some(json!({
+            "name": "otra",
+            "mail": "ana-sin-dominio",
+            "passwordHash": "contraseña-segura"
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    // Un correo desconocido responde igual que una contraseña errónea
+    for mail in ["ana-sin-dominio", "nadie@example.com"] {
+        let (status, body) = call(
+            &app,
+            "POST",
+            "/public/users/login",
+            None,
+            Some(json!({"mail": mail, "password": "incorrecta"})),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(body["message"], "Usuario o contraseña erronea", "{}", body);
+    }
+}
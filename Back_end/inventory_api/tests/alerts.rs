@@ -0,0 +1,236 @@
+mod common;
+
+use actix_web::http::StatusCode;
+use mongodb::bson::oid::ObjectId;
+use serde_json::{json, Value};
+
+use common::{
+    call, create, create_group, create_zone, init_app, init_app_on, of_type, oid, register,
+    relaxed_limits, repositories, Outbox,
+};
+use inventory_api::entities::item::Item;
+
+/// Nombres de los items de la respuesta de stock bajo, por zona.
+fn low_stock_names(alerts: &Value) -> Vec<(String, Vec<String>)> {
+    let mut result = Vec::new();
+    for property in alerts.as_array().unwrap() {
+        for zone in property["zones"].as_array().unwrap() {
+            let names = zone["items"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|item| item["name"].as_str().unwrap().to_string())
+                .collect();
+            result.push((zone["zone"]["name"].as_str().unwrap().to_string(), names));
+        }
+    }
+    result
+}
+
+#[actix_web::test]
+async fn low_stock_lists_items_under_threshold_by_zone() {
+    let app = init_app().await;
+    let owner = register(&app, "owner").await;
+    let stranger = register(&app, "stranger").await;
+    let (group_id, pantry) = create_zone(&app, &owner).await;
+    for item in [
+        json!({"name": "Arroz", "zoneId": pantry, "quantity": 1, "minQuantity": 2, "unit": "kg"}),
+        json!({"name": "Sal", "zoneId": pantry, "quantity": 5, "minQuantity": 2}),
+        json!({"name": "Azúcar", "zoneId": pantry, "minQuantity": 1}),
+        json!({"name": "Aceite", "zoneId": pantry, "quantity": 0}),
+    ] {
+        create(&app, &owner, "/private/items", item).await;
+    }
+    let (_, other_zone) = create_zone(&app, &stranger).await;
+    create(
+        &app,
+        &stranger,
+        "/private/items",
+        json!({"name": "Café", "zoneId": other_zone, "quantity": 0, "minQuantity": 1}),
+    )
+    .await;
+
+    let (status, alerts) = call(
+        &app,
+        "GET",
+        "/private/alerts/low-stock",
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        low_stock_names(&alerts),
+        vec![(
+            "Despensa".to_string(),
+            vec!["Arroz".to_string(), "Azúcar".to_string()]
+        )]
+    );
+    assert_eq!(oid(&alerts[0]["property"]["groupId"]), group_id);
+    let rice = &alerts[0]["zones"][0]["items"][0];
+    assert_eq!(rice["missing"], 1.0);
+    assert_eq!(rice["unit"], "kg");
+
+    // Al subir el mínimo de la sal pasa a la lista
+    let (_, zone) = call(
+        &app,
+        "GET",
+        &format!("/private/zones/parent/{}", pantry),
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    let salt = of_type(&zone, "item")
+        .into_iter()
+        .find(|item| item["name"] == "Sal")
+        .unwrap();
+    let (status, _) = call(
+        &app,
+        "PATCH",
+        &format!("/private/items/{}", oid(&salt["_id"])),
+        Some(&owner.token),
+        Some(json!({"minQuantity": 10})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let (_, alerts) = call(
+        &app,
+        "GET",
+        "/private/alerts/low-stock",
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    assert_eq!(alerts[0]["zones"][0]["items"].as_array().unwrap().len(), 3);
+}
+
+#[actix_web::test]
+async fn low_stock_skips_private_branches_of_other_members() {
+    let app = init_app().await;
+    let owner = register(&app, "owner").await;
+    let guest = register(&app, "guest").await;
+    let (group_id, pantry) = create_zone(&app, &owner).await;
+    let (_, group) = call(
+        &app,
+        "GET",
+        &format!("/private/groups/{}", group_id),
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    call(
+        &app,
+        "POST",
+        &format!(
+            "/private/groups/join/{}",
+            group["groupCode"].as_str().unwrap()
+        ),
+        Some(&guest.token),
+        None,
+    )
+    .await;
+    let private_zone = create(
+        &app,
+        &owner,
+        "/private/zones",
+        json!({"name": "Cajón", "parentZoneId": pantry, "private": true}),
+    )
+    .await;
+    let nested = create(
+        &app,
+        &owner,
+        "/private/zones",
+        json!({"name": "Caja", "parentZoneId": private_zone}),
+    )
+    .await;
+    create(
+        &app,
+        &owner,
+        "/private/items",
+        json!({"name": "Pilas", "zoneId": nested, "minQuantity": 4}),
+    )
+    .await;
+    create(
+        &app,
+        &owner,
+        "/private/items",
+        json!({"name": "Harina", "zoneId": pantry, "minQuantity": 1}),
+    )
+    .await;
+
+    let (_, alerts) = call(
+        &app,
+        "GET",
+        "/private/alerts/low-stock",
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    assert_eq!(low_stock_names(&alerts).len(), 2);
+
+    let (_, alerts) = call(
+        &app,
+        "GET",
+        "/private/alerts/low-stock",
+        Some(&guest.token),
+        None,
+    )
+    .await;
+    assert_eq!(
+        low_stock_names(&alerts),
+        vec![("Despensa".to_string(), vec!["Harina".to_string()])]
+    );
+}
+
+#[actix_web::test]
+async fn low_stock_includes_items_placed_directly_on_a_property() {
+    let repos = repositories().await;
+    let app = init_app_on(repos.clone(), Outbox::default(), relaxed_limits()).await;
+    let owner = register(&app, "owner").await;
+    let (group_id, _) = create_group(&app, &owner, "Casa").await;
+    let flat = create(
+        &app,
+        &owner,
+        "/private/properties",
+        json!({"name": "Piso", "groupId": group_id}),
+    )
+    .await;
+    // Datos antiguos: items cuyo `zoneId` es la propia propiedad
+    repos
+        .items
+        .insert(&Item {
+            id: None,
+            name: "Bombillas".to_string(),
+            description: None,
+            picture_url: None,
+            zone_id: ObjectId::parse_str(&flat).unwrap(),
+            tags: None,
+            quantity: Some(1.0),
+            unit: None,
+            min_quantity: Some(3.0),
+            expires_at: None,
+            purchased_at: None,
+            deleted_at: None,
+            trash_id: None,
+            category_id: None,
+            attributes: None,
+            loan_id: None,
+        })
+        .await
+        .unwrap();
+
+    let (status, alerts) = call(
+        &app,
+        "GET",
+        "/private/alerts/low-stock",
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(low_stock_names(&alerts).is_empty());
+    let items = alerts[0]["items"].as_array().unwrap();
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0]["name"], "Bombillas");
+    assert_eq!(items[0]["missing"], 2.0);
+}
@@ -0,0 +1,374 @@
+mod common;
+
+use actix_web::http::StatusCode;
+use serde_json::json;
+
+use common::{call, create, create_group, init_app, of_type, oid, register};
+
+#[actix_web::test]
+async fn private_routes_require_a_valid_session() {
+    let app = init_app().await;
+    let ana = register(&app, "ana").await;
+
+    let (status, _) = call(&app, "GET", "/private/users/me/", None, None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = call(&app, "GET", "/private/users/me/", Some("basura"), None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (status, me) = call(&app, "GET", "/private/users/me/", Some(&ana.token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(oid(&me["_id"]), ana.id);
+
+    let register_again = json!({
+        "name": "ana",
+        "mail": "ana@example.com",
+        "passwordHash": "otra-contraseña"
+    });
+    let (status, _) = call(
+        &app,
+        "POST",
+        "/public/users/register",
+        None,
+        Some(register_again),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    let (status, body) = call(
+        &app,
+        "POST",
+        "/public/users/login",
+        None,
+        Some(json!({"mail": "ana@example.com", "password": "contraseña-segura"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert!(body["token"].is_string());
+    let (status, _) = call(
+        &app,
+        "POST",
+        "/public/users/login",
+        None,
+        Some(json!({"mail": "ana@example.com", "password": "incorrecta"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[actix_web::test]
+async fn joining_by_code_grants_viewer_access_until_promoted() {
+    let app = init_app().await;
+    let owner = register(&app, "owner").await;
+    let guest = register(&app, "guest").await;
+    let (group_id, code) = create_group(&app, &owner, "Casa").await;
+    let group_uri = format!("/private/groups/{}", group_id);
+
+    let (status, _) = call(&app, "GET", &group_uri, Some(&guest.token), None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let join_uri = format!("/private/groups/join/{}", code);
+    let (status, body) = call(&app, "POST", &join_uri, Some(&guest.token), None).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let (status, _) = call(&app, "POST", &join_uri, Some(&guest.token), None).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    let (status, group) = call(&app, "GET", &group_uri, Some(&guest.token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(group["userCount"], 2);
+
+    let new_property = json!({"name": "Garaje", "groupId": group_id});
+    let (status, _) = call(
+        &app,
+        "POST",
+        "/private/properties",
+        Some(&guest.token),
+        Some(new_property.clone()),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // Solo el propietario puede cambiar roles
+    let member_uri = format!("/private/groups/{}/members/{}", group_id, guest.id);
+    let (status, _) = call(
+        &app,
+        "PATCH",
+        &member_uri,
+        Some(&guest.token),
+        Some(json!({"role": "editor"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, body) = call(
+        &app,
+        "PATCH",
+        &member_uri,
+        Some(&owner.token),
+        Some(json!({"role": "editor"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+
+    create(&app, &guest, "/private/properties", new_property).await;
+}
+
+#[actix_web::test]
+async fn private_properties_are_hidden_from_other_members() {
+    let app = init_app().await;
+    let owner = register(&app, "owner").await;
+    let guest = register(&app, "guest").await;
+    let (group_id, code) = create_group(&app, &owner, "Casa").await;
+    call(
+        &app,
+        "POST",
+        &format!("/private/groups/join/{}", code),
+        Some(&guest.token),
+        None,
+    )
+    .await;
+
+    let shared = create(
+        &app,
+        &owner,
+        "/private/properties",
+        json!({"name": "Piso", "groupId": group_id}),
+    )
+    .await;
+    let private = create(
+        &app,
+        &owner,
+        "/private/properties",
+        json!({"name": "Trastero", "groupId": group_id, "private": true}),
+    )
+    .await;
+
+    let list_uri = format!("/private/properties/group/{}", group_id);
+    let (status, properties) = call(&app, "GET", &list_uri, Some(&guest.token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let ids: Vec<String> = properties["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|p| oid(&p["_id"]))
+        .collect();
+    assert_eq!(ids, vec![shared]);
+
+    let private_uri = format!("/private/properties/{}", private);
+    let (status, _) = call(&app, "GET", &private_uri, Some(&guest.token), None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = call(&app, "GET", &private_uri, Some(&owner.token), None).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn deleting_zones_and_groups_cascades() {
+    let app = init_app().await;
+    let owner = register(&app, "owner").await;
+    let (group_id, _) = create_group(&app, &owner, "Casa").await;
+    let property = create(
+        &app,
+        &owner,
+        "/private/properties",
+        json!({"name": "Piso", "groupId": group_id}),
+    )
+    .await;
+    let kitchen = create(
+        &app,
+        &owner,
+        "/private/zones",
+        json!({"name": "Cocina", "parentZoneId": property}),
+    )
+    .await;
+    let pantry = create(
+        &app,
+        &owner,
+        "/private/zones",
+        json!({"name": "Despensa", "parentZoneId": kitchen}),
+    )
+    .await;
+    let rice = create(
+        &app,
+        &owner,
+        "/private/items",
+        json!({"name": "Arroz", "zoneId": pantry}),
+    )
+    .await;
+    let bedroom = create(
+        &app,
+        &owner,
+        "/private/zones",
+        json!({"name": "Dormitorio", "parentZoneId": property}),
+    )
+    .await;
+    let lamp = create(
+        &app,
+        &owner,
+        "/private/items",
+        json!({"name": "Lámpara", "zoneId": bedroom}),
+    )
+    .await;
+
+    let (status, children) = call(
+        &app,
+        "GET",
+        &format!("/private/zones/parent/{}", kitchen),
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(of_type(&children, "zone").len(), 1);
+
+    let (status, _) = call(
+        &app,
+        "DELETE",
+        &format!("/private/zones/{}", kitchen),
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    for uri in [
+        format!("/private/zones/{}", kitchen),
+        format!("/private/zones/{}", pantry),
+        format!("/private/items/{}", rice),
+    ] {
+        let (status, _) = call(&app, "GET", &uri, Some(&owner.token), None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND, "{}", uri);
+    }
+    let (status, _) = call(
+        &app,
+        "GET",
+        &format!("/private/items/{}", lamp),
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, _) = call(
+        &app,
+        "DELETE",
+        &format!("/private/groups/{}", group_id),
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    for uri in [
+        format!("/private/properties/{}", property),
+        format!("/private/zones/{}", bedroom),
+        format!("/private/items/{}", lamp),
+    ] {
+        let (status, _) = call(&app, "GET", &uri, Some(&owner.token), None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND, "{}", uri);
+    }
+}
+
+#[actix_web::test]
+async fn search_only_returns_visible_results() {
+    let app = init_app().await;
+    let ana = register(&app, "ana").await;
+    let bea = register(&app, "bea").await;
+    let (group_id, _) = create_group(&app, &ana, "Casa").await;
+    let property = create(
+        &app,
+        &ana,
+        "/private/properties",
+        json!({"name": "Piso", "groupId": group_id}),
+    )
+    .await;
+    let zone = create(
+        &app,
+        &ana,
+        "/private/zones",
+        json!({"name": "Salón", "parentZoneId": property}),
+    )
+    .await;
+    let drill = create(
+        &app,
+        &ana,
+        "/private/items",
+        json!({"name": "Taladro Percutor", "zoneId": zone}),
+    )
+    .await;
+
+    let (status, results) = call(
+        &app,
+        "GET",
+        "/private/search/taladro",
+        Some(&ana.token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let items = of_type(&results, "item");
+    assert_eq!(items.len(), 1);
+    assert_eq!(oid(&items[0]["_id"]), drill);
+
+    let (status, results) = call(
+        &app,
+        "GET",
+        "/private/search/taladro",
+        Some(&bea.token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(of_type(&results, "item").is_empty());
+
+    // Como miembro ve el objeto compartido, pero no los que están en zonas privadas de Ana
+    let (_, group) = call(
+        &app,
+        "GET",
+        &format!("/private/groups/{}", group_id),
+        Some(&ana.token),
+        None,
+    )
+    .await;
+    call(
+        &app,
+        "POST",
+        &format!(
+            "/private/groups/join/{}",
+            group["groupCode"].as_str().unwrap()
+        ),
+        Some(&bea.token),
+        None,
+    )
+    .await;
+    let private_zone = create(
+        &app,
+        &ana,
+        "/private/zones",
+        json!({"name": "Armario", "parentZoneId": property, "private": true}),
+    )
+    .await;
+    create(
+        &app,
+        &ana,
+        "/private/items",
+        json!({"name": "Taladro viejo", "zoneId": private_zone}),
+    )
+    .await;
+
+    let (_, results) = call(
+        &app,
+        "GET",
+        "/private/search/taladro",
+        Some(&ana.token),
+        None,
+    )
+    .await;
+    assert_eq!(of_type(&results, "item").len(), 2);
+    let (_, results) = call(
+        &app,
+        "GET",
+        "/private/search/taladro",
+        Some(&bea.token),
+        None,
+    )
+    .await;
+    let items = of_type(&results, "item");
+    assert_eq!(items.len(), 1);
+    assert_eq!(oid(&items[0]["_id"]), drill);
+}
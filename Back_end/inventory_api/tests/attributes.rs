@@ -0,0 +1,183 @@
+mod common;
+
+use actix_web::http::StatusCode;
+use serde_json::json;
+
+use common::{call, create, create_zone, init_app, of_type, register};
+
+#[actix_web::test]
+async fn item_attributes_are_validated_against_the_group_schema() {
+    let app = init_app().await;
+    let owner = register(&app, "owner").await;
+    let (group_id, pantry) = create_zone(&app, &owner).await;
+    let attributes_uri = format!("/private/groups/{}/attributes", group_id);
+
+    let (status, _) = call(
+        &app,
+        "PUT",
+        &attributes_uri,
+        Some(&owner.token),
+        Some(json!([{"name": "color", "type": "enum"}])),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    let (status, schema) = call(
+        &app,
+        "PUT",
+        &attributes_uri,
+        Some(&owner.token),
+        Some(json!([
+            {"name": "serial", "type": "text", "required": true},
+            {"name": "price", "type": "number"},
+            {"name": "warrantyEnd", "type": "date"},
+            {"name": "color", "type": "enum", "options": ["rojo", "negro"]}
+        ])),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", schema);
+    assert_eq!(schema.as_array().unwrap().len(), 4);
+
+    let (status, error) = call(
+        &app,
+        "POST",
+        "/private/items",
+        Some(&owner.token),
+        Some(json!({"name": "Taladro", "zoneId": pantry, "attributes": {"price": 80}})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(error["details"]["field"], "attributes.serial");
+    let (status, error) = call(
+        &app,
+        "POST",
+        "/private/items",
+        Some(&owner.token),
+        Some(json!({
+            "name": "Taladro",
+            "zoneId": pantry,
+            "attributes": {"serial": "SN-1", "price": "caro"}
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(error["details"]["field"], "attributes.price");
+
+    let drill = create(
+        &app,
+        &owner,
+        "/private/items",
+        json!({
+            "name": "Taladro",
+            "zoneId": pantry,
+            "attributes": {"serial": "SN-1", "price": 80, "warrantyEnd": "2027-05-01"}
+        }),
+    )
+    .await;
+    let item_uri = format!("/private/items/{}", drill);
+    let (_, item) = call(&app, "GET", &item_uri, Some(&owner.token), None).await;
+    assert_eq!(item["attributes"]["price"], 80.0);
+    assert_eq!(item["attributes"]["warrantyEnd"], "2027-05-01T00:00:00Z");
+
+    let (status, _) = call(
+        &app,
+        "PATCH",
+        &item_uri,
+        Some(&owner.token),
+        Some(json!({"attributes": {"color": "azul"}})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    let (status, _) = call(
+        &app,
+        "PATCH",
+        &item_uri,
+        Some(&owner.token),
+        Some(json!({"attributes": {"serial": null}})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    let (status, _) = call(
+        &app,
+        "PATCH",
+        &item_uri,
+        Some(&owner.token),
+        Some(json!({"attributes": {"color": "rojo", "price": null}})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let (_, item) = call(&app, "GET", &item_uri, Some(&owner.token), None).await;
+    assert_eq!(item["attributes"]["serial"], "SN-1");
+    assert_eq!(item["attributes"]["color"], "rojo");
+    assert!(item["attributes"].get("price").is_none());
+}
+
+#[actix_web::test]
+async fn search_filters_items_by_attribute_values() {
+    let app = init_app().await;
+    let owner = register(&app, "owner").await;
+    let (group_id, pantry) = create_zone(&app, &owner).await;
+    call(
+        &app,
+        "PUT",
+        &format!("/private/groups/{}/attributes", group_id),
+        Some(&owner.token),
+        Some(json!([
+            {"name": "price", "type": "number"},
+            {"name": "warrantyEnd", "type": "date"}
+        ])),
+    )
+    .await;
+    for (name, price, warranty) in [
+        ("Taladro", 80, "2027-05-01"),
+        ("Tostadora", 30, "2026-01-01"),
+        ("Televisor", 500, "2028-01-01"),
+    ] {
+        create(
+            &app,
+            &owner,
+            "/private/items",
+            json!({
+                "name": name,
+                "zoneId": pantry,
+                "attributes": {"price": price, "warrantyEnd": warranty}
+            }),
+        )
+        .await;
+    }
+
+    let (status, found) = call(
+        &app,
+        "GET",
+        "/private/search?attr.price.min=50&attr.warrantyEnd.max=2027-12-31",
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let items = of_type(&found, "item");
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0]["name"], "Taladro");
+    assert!(of_type(&found, "zone").is_empty());
+
+    let (_, found) = call(
+        &app,
+        "GET",
+        "/private/search?attr.price=30",
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    let items = of_type(&found, "item");
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0]["name"], "Tostadora");
+
+    let (status, _) = call(
+        &app,
+        "GET",
+        "/private/search?attr.price.between=1",
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+}
@@ -0,0 +1,313 @@
+mod common;
+
+use actix_http::Request;
+use actix_web::body::MessageBody;
+use actix_web::dev::{Service, ServiceResponse};
+use actix_web::http::StatusCode;
+use actix_web::test;
+use serde_json::json;
+use std::path::Path;
+
+use common::{call, create, create_zone, init_app, oid, register};
+
+#[actix_web::test]
+async fn item_changes_are_recorded_with_actor_and_diff() {
+    let app = init_app().await;
+    let owner = register(&app, "owner").await;
+    let (group_id, pantry) = create_zone(&app, &owner).await;
+    let fridge = create(
+        &app,
+        &owner,
+        "/private/zones",
+        json!({"name": "Nevera", "parentZoneId": pantry}),
+    )
+    .await;
+    let rice = create(
+        &app,
+        &owner,
+        "/private/items",
+        json!({"name": "Arroz", "zoneId": pantry, "description": "Redondo"}),
+    )
+    .await;
+    let item_uri = format!("/private/items/{}", rice);
+    call(
+        &app,
+        "PATCH",
+        &item_uri,
+        Some(&owner.token),
+        Some(json!({"name": "Arroz bomba"})),
+    )
+    .await;
+    call(
+        &app,
+        "POST",
+        &format!("{}/move", item_uri),
+        Some(&owner.token),
+        Some(json!({"zoneId": fridge})),
+    )
+    .await;
+    call(&app, "DELETE", &item_uri, Some(&owner.token), None).await;
+
+    // El historial de un item borrado sigue disponible para los propietarios
+    let (status, events) = call(
+        &app,
+        "GET",
+        &format!("/private/history/item/{}", rice),
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", events);
+    let events = events.as_array().unwrap();
+    let actions: Vec<&str> = events
+        .iter()
+        .map(|e| e["action"].as_str().unwrap())
+        .collect();
+    assert_eq!(actions, ["delete", "update", "update", "create"]);
+    assert!(events.iter().all(|e| oid(&e["userId"]) == owner.id));
+    assert_eq!(events[2]["before"], json!({"name": "Arroz"}));
+    assert_eq!(events[2]["after"], json!({"name": "Arroz bomba"}));
+    assert_eq!(oid(&events[1]["after"]["zoneId"]), fridge);
+    assert_eq!(events[0]["before"]["description"], "Redondo");
+    assert!(events[0].get("after").is_none());
+
+    let (status, events) = call(
+        &app,
+        "GET",
+        &format!("/private/groups/{}/history?limit=3", group_id),
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(events.as_array().unwrap().len(), 3);
+    let (status, events) = call(
+        &app,
+        "GET",
+        &format!("/private/groups/{}/history", group_id),
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let types: Vec<&str> = events
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| e["entityType"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        types,
+        ["item", "item", "item", "item", "zone", "zone", "property", "group"]
+    );
+}
+
+#[actix_web::test]
+async fn history_follows_the_access_rules_of_the_group() {
+    let app = init_app().await;
+    let owner = register(&app, "owner").await;
+    let guest = register(&app, "guest").await;
+    let (group_id, pantry) = create_zone(&app, &owner).await;
+    let (_, group) = call(
+        &app,
+        "GET",
+        &format!("/private/groups/{}", group_id),
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    let rice = create(
+        &app,
+        &owner,
+        "/private/items",
+        json!({"name": "Arroz", "zoneId": pantry}),
+    )
+    .await;
+    let history_uri = format!("/private/history/item/{}", rice);
+
+    let (status, _) = call(&app, "GET", &history_uri, Some(&guest.token), None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    call(
+        &app,
+        "POST",
+        &format!(
+            "/private/groups/join/{}",
+            group["groupCode"].as_str().unwrap()
+        ),
+        Some(&guest.token),
+        None,
+    )
+    .await;
+    let (status, events) = call(&app, "GET", &history_uri, Some(&guest.token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(events.as_array().unwrap().len(), 1);
+    let (status, _) = call(
+        &app,
+        "GET",
+        &format!("/private/groups/{}/history", group_id),
+        Some(&guest.token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    call(
+        &app,
+        "DELETE",
+        &format!("/private/items/{}", rice),
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    let (status, _) = call(&app, "GET", &history_uri, Some(&guest.token), None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, _) = call(
+        &app,
+        "GET",
+        "/private/history/widget/000000000000000000000000",
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[actix_web::test]
+async fn stock_movements_and_loans_are_recorded() {
+    let app = init_app().await;
+    let owner = register(&app, "owner").await;
+    let (_, pantry) = create_zone(&app, &owner).await;
+    let rice = create(
+        &app,
+        &owner,
+        "/private/items",
+        json!({"name": "Arroz", "zoneId": pantry, "quantity": 2}),
+    )
+    .await;
+    let item_uri = format!("/private/items/{}", rice);
+    let (status, _) = call(
+        &app,
+        "POST",
+        &format!("{}/movements", item_uri),
+        Some(&owner.token),
+        Some(json!({"type": "consume", "quantity": 0.5})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, loan) = call(
+        &app,
+        "POST",
+        &format!("{}/checkout", item_uri),
+        Some(&owner.token),
+        Some(json!({"borrowerName": "Vecino"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = call(
+        &app,
+        "POST",
+        &format!("{}/checkin", item_uri),
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (_, events) = call(
+        &app,
+        "GET",
+        &format!("/private/history/item/{}", rice),
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    let events = events.as_array().unwrap();
+    let actions: Vec<&str> = events
+        .iter()
+        .map(|e| e["action"].as_str().unwrap())
+        .collect();
+    assert_eq!(actions, ["update", "update", "update", "create"]);
+    // Devolución, préstamo y consumo, del más reciente al más antiguo
+    assert_eq!(oid(&events[0]["before"]["loanId"]), oid(&loan["_id"]));
+    assert!(events[0]["after"].get("loanId").is_none());
+    assert_eq!(oid(&events[1]["after"]["loanId"]), oid(&loan["_id"]));
+    assert_eq!(events[2]["before"]["quantity"], 2.0);
+    assert_eq!(events[2]["after"]["quantity"], 1.5);
+}
+
+/// Sube `data` como imagen del item con un formulario multipart.
+async fn upload_image<S, B>(app: &S, token: &str, item_id: &str, data: &[u8]) -> StatusCode
+where
+    S: Service<Request, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    B: MessageBody,
+{
+    let boundary = "frontera";
+    let mut body = format!(
+        "--{b}\r\nContent-Disposition: form-data; name=\"objectID\"\r\n\r\n{id}\r\n\
+         --{b}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"foto.png\"\r\n\
+         Content-Type: image/png\r\n\r\n",
+        b = boundary,
+        id = item_id
+    )
+    .into_bytes();
+    body.extend_from_slice(data);
+    body.extend_from_slice(format!("\r\n--{}--\r\n", boundary).as_bytes());
+    let req = test::TestRequest::post()
+        .uri("/private/image")
+        .insert_header(("Authorization", format!("Bearer {}", token)))
+        .insert_header((
+            "Content-Type",
+            format!("multipart/form-data; boundary={}", boundary),
+        ))
+        .set_payload(body)
+        .to_request();
+    match app.call(req).await {
+        Ok(res) => res.status(),
+        Err(e) => e.error_response().status(),
+    }
+}
+
+#[actix_web::test]
+async fn every_image_upload_is_recorded() {
+    let app = init_app().await;
+    let owner = register(&app, "owner").await;
+    let (_, pantry) = create_zone(&app, &owner).await;
+    let rice = create(
+        &app,
+        &owner,
+        "/private/items",
+        json!({"name": "Arroz", "zoneId": pantry}),
+    )
+    .await;
+    for data in [&b"primera"[..], &b"segunda"[..]] {
+        assert_eq!(
+            upload_image(&app, &owner.token, &rice, data).await,
+            StatusCode::OK
+        );
+    }
+    let file_name = format!("{}.png", rice);
+    let stored = std::fs::read(Path::new("images").join(&file_name)).unwrap();
+    std::fs::remove_file(Path::new("images").join(&file_name)).ok();
+    assert_eq!(stored, b"segunda");
+
+    let (_, events) = call(
+        &app,
+        "GET",
+        &format!("/private/history/item/{}", rice),
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    let events = events.as_array().unwrap();
+    let actions: Vec<&str> = events
+        .iter()
+        .map(|e| e["action"].as_str().unwrap())
+        .collect();
+    assert_eq!(actions, ["update", "update", "create"]);
+    assert_eq!(events[0]["before"]["pictureUrl"], file_name.as_str());
+    assert_eq!(events[0]["after"]["pictureUrl"], file_name.as_str());
+    assert!(events[1]["before"].get("pictureUrl").is_none());
+    assert_eq!(events[1]["after"]["pictureUrl"], file_name.as_str());
+}
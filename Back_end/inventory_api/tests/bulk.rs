@@ -0,0 +1,278 @@
+mod common;
+
+use actix_web::http::StatusCode;
+use serde_json::json;
+
+use common::{call, create, create_zone, init_app, of_type, oid, register};
+
+#[actix_web::test]
+async fn bulk_operations_report_a_result_for_each_operation() {
+    let app = init_app().await;
+    let owner = register(&app, "owner").await;
+    let (_, pantry) = create_zone(&app, &owner).await;
+    let fridge = create(
+        &app,
+        &owner,
+        "/private/zones",
+        json!({"name": "Nevera", "parentZoneId": pantry}),
+    )
+    .await;
+    let rice = create(
+        &app,
+        &owner,
+        "/private/items",
+        json!({"name": "Arroz", "zoneId": pantry}),
+    )
+    .await;
+    let salt = create(
+        &app,
+        &owner,
+        "/private/items",
+        json!({"name": "Sal", "zoneId": pantry}),
+    )
+    .await;
+    let oil = create(
+        &app,
+        &owner,
+        "/private/items",
+        json!({"name": "Aceite", "zoneId": pantry}),
+    )
+    .await;
+
+    let (status, body) = call(
+        &app,
+        "POST",
+        "/private/items/bulk",
+        Some(&owner.token),
+        Some(json!({"operations": [
+            {"op": "create", "item": {"name": "Leche", "zoneId": fridge, "quantity": 6.0}},
+            {"op": "patch", "id": rice, "changes": {"tags": ["cereales"]}},
+            {"op": "move", "id": oil, "zoneId": fridge},
+            {"op": "delete", "id": salt},
+            {"op": "patch", "id": "000000000000000000000000", "changes": {"name": "Nada"}},
+            {"op": "rename"},
+            {"op": "move", "id": rice, "zoneId": fridge}
+        ]})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["committed"], true);
+    let results = body["results"].as_array().unwrap();
+    let statuses: Vec<&str> = results
+        .iter()
+        .map(|r| r["status"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        statuses,
+        ["ok", "ok", "ok", "ok", "error", "error", "error"]
+    );
+    assert_eq!(results[4]["error"]["code"], "not_found");
+    assert_eq!(results[5]["error"]["code"], "validation_error");
+    // El mismo item no puede aparecer en dos operaciones
+    assert_eq!(results[6]["error"]["code"], "validation_error");
+    assert_eq!(results[6]["error"]["details"]["field"], "id");
+
+    let milk = oid(&results[0]["_id"]);
+    let (status, item) = call(
+        &app,
+        "GET",
+        &format!("/private/items/{}", milk),
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(item["quantity"], 6.0);
+    let (_, movements) = call(
+        &app,
+        "GET",
+        &format!("/private/items/{}/movements", milk),
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    assert_eq!(movements.as_array().unwrap().len(), 1);
+
+    let (_, item) = call(
+        &app,
+        "GET",
+        &format!("/private/items/{}", rice),
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    assert_eq!(oid(&item["zoneId"]), pantry);
+    assert_eq!(item["tags"], json!(["cereales"]));
+    let (_, item) = call(
+        &app,
+        "GET",
+        &format!("/private/items/{}", oil),
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    assert_eq!(oid(&item["zoneId"]), fridge);
+    let (status, _) = call(
+        &app,
+        "GET",
+        &format!("/private/items/{}", salt),
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn all_or_nothing_applies_nothing_when_an_operation_fails() {
+    let app = init_app().await;
+    let owner = register(&app, "owner").await;
+    let stranger = register(&app, "stranger").await;
+    let (_, pantry) = create_zone(&app, &owner).await;
+    let (_, other_zone) = create_zone(&app, &stranger).await;
+    let rice = create(
+        &app,
+        &owner,
+        "/private/items",
+        json!({"name": "Arroz", "zoneId": pantry}),
+    )
+    .await;
+    let salt = create(
+        &app,
+        &owner,
+        "/private/items",
+        json!({"name": "Sal", "zoneId": pantry}),
+    )
+    .await;
+
+    let (status, body) = call(
+        &app,
+        "POST",
+        "/private/items/bulk",
+        Some(&owner.token),
+        Some(json!({"allOrNothing": true, "operations": [
+            {"op": "create", "item": {"name": "Leche", "zoneId": pantry}},
+            {"op": "delete", "id": rice},
+            {"op": "move", "id": salt, "zoneId": other_zone}
+        ]})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["committed"], false);
+    let results = body["results"].as_array().unwrap();
+    assert_eq!(results[0]["status"], "skipped");
+    assert_eq!(results[1]["status"], "skipped");
+    assert_eq!(results[2]["status"], "error");
+    assert_eq!(results[2]["error"]["code"], "forbidden");
+
+    let (status, _) = call(
+        &app,
+        "GET",
+        &format!("/private/items/{}", rice),
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let (_, zone) = call(
+        &app,
+        "GET",
+        &format!("/private/zones/parent/{}", pantry),
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    assert_eq!(of_type(&zone, "item").len(), 2);
+
+    let (status, _) = call(
+        &app,
+        "POST",
+        "/private/items/bulk",
+        Some(&owner.token),
+        Some(json!({"operations": []})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[actix_web::test]
+async fn bulk_moves_validate_attributes_against_the_destination_group() {
+    let app = init_app().await;
+    let owner = register(&app, "owner").await;
+    let (home, pantry) = create_zone(&app, &owner).await;
+    let (workshop, bench) = create_zone(&app, &owner).await;
+    let (garage, shelf) = create_zone(&app, &owner).await;
+    for (group_id, schema) in [
+        (&home, json!([{"name": "serial", "type": "text"}])),
+        (&workshop, json!([{"name": "price", "type": "number"}])),
+        (
+            &garage,
+            json!([{"name": "serial", "type": "text", "required": true}]),
+        ),
+    ] {
+        let (status, _) = call(
+            &app,
+            "PUT",
+            &format!("/private/groups/{}/attributes", group_id),
+            Some(&owner.token),
+            Some(schema),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+    }
+    let drill = create(
+        &app,
+        &owner,
+        "/private/items",
+        json!({"name": "Taladro", "zoneId": pantry, "attributes": {"serial": "SN-1"}}),
+    )
+    .await;
+    let saw = create(
+        &app,
+        &owner,
+        "/private/items",
+        json!({"name": "Sierra", "zoneId": pantry}),
+    )
+    .await;
+
+    // El taller no tiene el atributo `serial` y el garaje lo exige
+    let (status, body) = call(
+        &app,
+        "POST",
+        "/private/items/bulk",
+        Some(&owner.token),
+        Some(json!({"operations": [
+            {"op": "move", "id": drill, "zoneId": bench},
+            {"op": "move", "id": saw, "zoneId": shelf},
+            {"op": "move", "id": drill, "zoneId": shelf}
+        ]})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let results = body["results"].as_array().unwrap();
+    assert_eq!(results[0]["status"], "error");
+    assert_eq!(results[0]["error"]["details"]["field"], "attributes.serial");
+    assert_eq!(results[1]["status"], "error");
+    assert_eq!(results[1]["error"]["details"]["field"], "attributes.serial");
+    assert_eq!(results[2]["status"], "ok");
+
+    let (_, item) = call(
+        &app,
+        "GET",
+        &format!("/private/items/{}", drill),
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    assert_eq!(oid(&item["zoneId"]), shelf);
+    assert_eq!(item["attributes"]["serial"], "SN-1");
+    let (_, item) = call(
+        &app,
+        "GET",
+        &format!("/private/items/{}", saw),
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    assert_eq!(oid(&item["zoneId"]), pantry);
+}
@@ -0,0 +1,339 @@
+//! Utilidades compartidas por los tests de integración.
+//!
+//! Por defecto los datos se guardan en memoria. Con `STORAGE_BACKEND=mongo` se usa el
+//! `mongod` de `MONGODB_URI` (`mongodb://localhost:27017` por defecto), que tiene que ser un
+//! replica set, con una base de datos nueva para cada test.
+// Cada fichero de tests usa solo una parte de estas utilidades
+#![allow(dead_code)]
+
+use actix_http::Request;
+use actix_web::body::MessageBody;
+use actix_web::dev::{Service, ServiceResponse};
+use actix_web::http::StatusCode;
+use actix_web::{test, App};
+use async_trait::async_trait;
+use mongodb::bson::oid::ObjectId;
+use serde_json::{json, Value};
+use std::env;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use inventory_api::configure_app;
+use inventory_api::mail::{MailError, MailMessage, Mailer};
+use inventory_api::middleware::rate_limit::{ClientIp, RateLimitConfig, RateLimiter};
+use inventory_api::repository::{mongo, Repositories};
+
+/// Guarda los correos enviados para que los tests puedan leerlos. Con `failing` todos
+/// los envíos fallan.
+#[derive(Clone, Default)]
+pub struct Outbox {
+    messages: Arc<Mutex<Vec<MailMessage>>>,
+    failing: bool,
+}
+
+impl Outbox {
+    pub fn failing() -> Self {
+        Self {
+            failing: true,
+            ..Self::default()
+        }
+    }
+
+    /// Correos enviados a `to`, del más antiguo al más reciente.
+    pub fn sent_to(&self, to: &str) -> Vec<MailMessage> {
+        let messages = self.messages.lock().unwrap();
+        messages.iter().filter(|m| m.to == to).cloned().collect()
+    }
+
+    /// Token del enlace (`?token=...`) del último correo enviado a `to`.
+    pub fn last_token(&self, to: &str) -> String {
+        let message = self
+            .sent_to(to)
+            .pop()
+            .unwrap_or_else(|| panic!("No se ha enviado ningún correo a {}", to));
+        let start = message
+            .body
+            .find("token=")
+            .unwrap_or_else(|| panic!("El correo no tiene enlace: {}", message.body))
+            + "token=".len();
+        message.body[start..]
+            .split_whitespace()
+            .next()
+            .unwrap()
+            .to_string()
+    }
+}
+
+#[async_trait]
+impl Mailer for Outbox {
+    async fn send(&self, message: &MailMessage) -> Result<(), MailError> {
+        if self.failing {
+            return Err(MailError("Servidor de correo caído".to_string()));
+        }
+        self.messages.lock().unwrap().push(message.clone());
+        Ok(())
+    }
+}
+
+/// Repositorios del backend elegido con `STORAGE_BACKEND`.
+pub async fn repositories() -> Repositories {
+    match env::var("STORAGE_BACKEND").as_deref() {
+        Ok("mongo") => {
+            let uri =
+                env::var("MONGODB_URI").unwrap_or_else(|_| "mongodb://localhost:27017".into());
+            let client = mongodb::Client::with_uri_str(&uri)
+                .await
+                .expect("No se pudo conectar con mongod");
+            let database = client.database(&format!("inventory_test_{}", ObjectId::new()));
+            mongo::check_transactions(&database)
+                .await
+                .expect("mongod tiene que ser un replica set");
+            mongo::ensure_indexes(&database)
+                .await
+                .expect("No se pudieron crear los índices");
+            Repositories::mongo(&database)
+        }
+        _ => Repositories::in_memory(),
+    }
+}
+
+/// Límites que no interfieren en los tests.
+pub fn relaxed_limits() -> RateLimitConfig {
+    RateLimitConfig {
+        window: Duration::from_secs(60),
+        max_requests: 1_000,
+        max_failures: 1_000,
+        base_lockout: Duration::from_secs(1),
+        max_lockout: Duration::from_secs(1),
+        lockout_decay: Duration::from_secs(1),
+        client_ip: ClientIp::Peer,
+    }
+}
+
+/// Monta la misma aplicación que `main`, con un limitador que no interfiere en los tests
+/// y sin enviar correos.
+pub async fn init_app(
+) -> impl Service<Request, Response = ServiceResponse<impl MessageBody>, Error = actix_web::Error> {
+    init_app_with(Outbox::default(), relaxed_limits()).await
+}
+
+/// Como `init_app`, con el buzón y los límites indicados.
+pub async fn init_app_with(
+    outbox: Outbox,
+    limits: RateLimitConfig,
+) -> impl Service<Request, Response = ServiceResponse<impl MessageBody>, Error = actix_web::Error> {
+    init_app_on(repositories().await, outbox, limits).await
+}
+
+/// Como `init_app_with`, sobre unos repositorios que el test puede consultar directamente.
+pub async fn init_app_on(
+    repos: Repositories,
+    outbox: Outbox,
+    limits: RateLimitConfig,
+) -> impl Service<Request, Response = ServiceResponse<impl MessageBody>, Error = actix_web::Error> {
+    let rate_limiter = RateLimiter::new(limits);
+    test::init_service(
+        App::new().configure(move |cfg| configure_app(cfg, repos, Arc::new(outbox), rate_limiter)),
+    )
+    .await
+}
+
+/// Hace una petición y devuelve el estado y el cuerpo (JSON o, si no lo es, texto).
+pub async fn call<S, B>(
+    app: &S,
+    method: &str,
+    uri: &str,
+    token: Option<&str>,
+    body: Option<Value>,
+) -> (StatusCode, Value)
+where
+    S: Service<Request, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    B: MessageBody,
+{
+    let mut req = match method {
+        "GET" => test::TestRequest::get(),
+        "POST" => test::TestRequest::post(),
+        "PATCH" => test::TestRequest::patch(),
+        "PUT" => test::TestRequest::put(),
+        "DELETE" => test::TestRequest::delete(),
+        _ => panic!("Método no soportado: {}", method),
+    }
+    .uri(uri);
+    if let Some(token) = token {
+        req = req.insert_header(("Authorization", format!("Bearer {}", token)));
+    }
+    if let Some(body) = body {
+        req = req.set_json(body);
+    }
+    let res = match app.call(req.to_request()).await {
+        Ok(res) => res,
+        Err(e) => {
+            let res = e.error_response();
+            let status = res.status();
+            let bytes = actix_web::body::to_bytes(res.into_body())
+                .await
+                .unwrap_or_default();
+            return (status, parse_body(&bytes));
+        }
+    };
+    let status = res.status();
+    let bytes = match actix_web::body::to_bytes(res.into_body()).await {
+        Ok(bytes) => bytes,
+        Err(_) => panic!("No se pudo leer el cuerpo de la respuesta"),
+    };
+    (status, parse_body(&bytes))
+}
+
+fn parse_body(bytes: &[u8]) -> Value {
+    serde_json::from_slice(bytes)
+        .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(bytes).into_owned()))
+}
+
+/// Identificador devuelto por la API (`{"$oid": "..."}`) como cadena.
+pub fn oid(value: &Value) -> String {
+    value["$oid"]
+        .as_str()
+        .unwrap_or_else(|| panic!("No es un ObjectId: {}", value))
+        .to_string()
+}
+
+/// Elementos de tipo `kind` de una página que mezcla varios tipos.
+pub fn of_type(page: &Value, kind: &str) -> Vec<Value> {
+    page["data"]
+        .as_array()
+        .unwrap_or_else(|| panic!("No es una página: {}", page))
+        .iter()
+        .filter(|row| row["type"] == kind)
+        .cloned()
+        .collect()
+}
+
+/// Nombres de los elementos de una página, en orden.
+pub fn names(page: &Value) -> Vec<&str> {
+    page["data"]
+        .as_array()
+        .unwrap_or_else(|| panic!("No es una página: {}", page))
+        .iter()
+        .map(|row| row["name"].as_str().unwrap())
+        .collect()
+}
+
+/// Recorre todas las páginas de un listado con `limit` elementos por página, siguiendo
+/// `nextCursor`, y devuelve los elementos de todas.
+pub async fn all_pages<S, B>(app: &S, user: &TestUser, uri: &str, limit: usize) -> Vec<Value>
+where
+    S: Service<Request, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    B: MessageBody,
+{
+    let separator = if uri.contains('?') { '&' } else { '?' };
+    let mut rows = Vec::new();
+    let mut after: Option<String> = None;
+    loop {
+        let mut page_uri = format!("{}{}limit={}", uri, separator, limit);
+        if let Some(after) = &after {
+            page_uri.push_str(&format!("&after={}", after));
+        }
+        let (status, page) = call(app, "GET", &page_uri, Some(&user.token), None).await;
+        assert_eq!(status, StatusCode::OK, "GET {}: {}", page_uri, page);
+        let data = page["data"].as_array().unwrap();
+        assert!(data.len() <= limit);
+        rows.extend(data.iter().cloned());
+        match page["nextCursor"].as_str() {
+            Some(cursor) => after = Some(cursor.to_string()),
+            None => return rows,
+        }
+    }
+}
+
+/// Usuario registrado con su token de acceso.
+pub struct TestUser {
+    pub id: String,
+    pub token: String,
+}
+
+pub async fn register<S, B>(app: &S, name: &str) -> TestUser
+where
+    S: Service<Request, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    B: MessageBody,
+{
+    let (status, body) = call(
+        app,
+        "POST",
+        "/public/users/register",
+        None,
+        Some(json!({
+            "name": name,
+            "mail": format!("{}@example.com", name),
+            "passwordHash": "contraseña-segura"
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "registro de {}: {}", name, body);
+    TestUser {
+        id: oid(&body["user"]["_id"]),
+        token: body["token"].as_str().unwrap().to_string(),
+    }
+}
+
+/// Crea un grupo y devuelve su ID y su código de invitación.
+pub async fn create_group<S, B>(app: &S, owner: &TestUser, name: &str) -> (String, String)
+where
+    S: Service<Request, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    B: MessageBody,
+{
+    let (status, body) = call(
+        app,
+        "POST",
+        "/private/groups",
+        Some(&owner.token),
+        Some(json!({"name": name})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "crear grupo: {}", body);
+    let group_id = oid(&body);
+    let (_, group) = call(
+        app,
+        "GET",
+        &format!("/private/groups/{}", group_id),
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    let code = group["groupCode"].as_str().unwrap().to_string();
+    (group_id, code)
+}
+
+/// Crea una propiedad, zona o item con `POST` y devuelve su ID.
+pub async fn create<S, B>(app: &S, user: &TestUser, uri: &str, body: Value) -> String
+where
+    S: Service<Request, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    B: MessageBody,
+{
+    let (status, res) = call(app, "POST", uri, Some(&user.token), Some(body)).await;
+    assert_eq!(status, StatusCode::OK, "POST {}: {}", uri, res);
+    oid(&res)
+}
+
+/// Crea un grupo con una propiedad y una zona y devuelve los IDs del grupo y de la zona.
+pub async fn create_zone<S, B>(app: &S, owner: &TestUser) -> (String, String)
+where
+    S: Service<Request, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    B: MessageBody,
+{
+    let (group_id, _) = create_group(app, owner, "Casa").await;
+    let property = create(
+        app,
+        owner,
+        "/private/properties",
+        json!({"name": "Piso", "groupId": group_id}),
+    )
+    .await;
+    let zone = create(
+        app,
+        owner,
+        "/private/zones",
+        json!({"name": "Despensa", "parentZoneId": property}),
+    )
+    .await;
+    (group_id, zone)
+}
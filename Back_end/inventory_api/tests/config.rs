@@ -0,0 +1,208 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use inventory_api::config::{Config, ConfigError, MailTransport, Profile, StorageBackend};
+use inventory_api::middleware::rate_limit::{ClientIp, RateLimitConfig};
+
+fn env_of(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+    let vars: HashMap<String, String> = vars
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+    move |name| vars.get(name).cloned()
+}
+
+#[test]
+fn defaults_are_valid_for_development() {
+    let config = Config::from_sources(None, env_of(&[("APP_PROFILE", "dev")])).unwrap();
+    assert_eq!(config.profile, Profile::Dev);
+    assert_eq!(config.server.port, 8000);
+    assert_eq!(config.database.name, "TFG");
+    assert_eq!(config.auth.access_token_ttl_secs, 15 * 60);
+    assert_eq!(config.mail.transport, MailTransport::Stdout);
+}
+
+#[test]
+fn missing_profile_is_checked_as_prod() {
+    // Sin perfil no se puede arrancar con el secreto de desarrollo
+    let result = Config::from_sources(None, env_of(&[]));
+    assert!(matches!(result, Err(ConfigError::Invalid(_))));
+
+    let secret = "x".repeat(32);
+    let config = Config::from_sources(None, env_of(&[("API_KEY", &secret)])).unwrap();
+    assert_eq!(config.profile, Profile::Prod);
+}
+
+#[test]
+fn auth_and_mail_settings_are_validated() {
+    let file = r#"
+        profile = "dev"
+
+        [auth]
+        refresh_token_ttl_days = 7
+
+        [auth.argon2]
+        iterations = 3
+
+        [mail]
+        transport = "smtp"
+        app_url = "https://inventario.example"
+    "#;
+    let env = env_of(&[("ACCESS_TOKEN_TTL_SECS", "600"), ("SMTP_PORT", "2525")]);
+    let config = Config::from_sources(Some(file), env).unwrap();
+    assert_eq!(config.auth.access_token_ttl_secs, 600);
+    assert_eq!(config.auth.refresh_token_ttl_days, 7);
+    assert_eq!(config.auth.argon2.iterations, 3);
+    assert_eq!(config.mail.transport, MailTransport::Smtp);
+    assert_eq!(config.mail.smtp_port, 2525);
+    assert_eq!(config.mail.app_url, "https://inventario.example");
+
+    let dev = ("APP_PROFILE", "dev");
+    let result = Config::from_sources(None, env_of(&[dev, ("ARGON2_ITERATIONS", "0")]));
+    assert!(matches!(result, Err(ConfigError::Invalid(_))));
+
+    let result = Config::from_sources(None, env_of(&[dev, ("APP_URL", "inventario.example")]));
+    assert!(matches!(result, Err(ConfigError::Invalid(_))));
+
+    let result = Config::from_sources(None, env_of(&[dev, ("MAIL_TRANSPORT", "paloma")]));
+    assert!(matches!(
+        result,
+        Err(ConfigError::Env {
+            var: "MAIL_TRANSPORT",
+            ..
+        })
+    ));
+
+    let result = Config::from_sources(
+        Some("profile = \"dev\"\n[mail]\nurl = \"x\"\n"),
+        env_of(&[]),
+    );
+    assert!(matches!(result, Err(ConfigError::Parse(_))));
+}
+
+#[test]
+fn environment_overrides_file() {
+    let file = r#"
+        profile = "dev"
+
+        [server]
+        host = "127.0.0.1"
+        port = 9000
+
+        [database]
+        name = "desde_fichero"
+    "#;
+    let env = env_of(&[
+        ("SERVER_PORT", "9100"),
+        (
+            "CORS_ALLOWED_ORIGINS",
+            "https://a.example, https://b.example",
+        ),
+        ("STORAGE_BACKEND", "memory"),
+    ]);
+    let config = Config::from_sources(Some(file), env).unwrap();
+    assert_eq!(config.server.host, "127.0.0.1");
+    assert_eq!(config.server.port, 9100);
+    assert_eq!(config.database.name, "desde_fichero");
+    assert_eq!(config.database.backend, StorageBackend::Memory);
+    assert_eq!(
+        config.cors.allowed_origins,
+        vec!["https://a.example", "https://b.example"]
+    );
+}
+
+#[test]
+fn prod_refuses_default_or_weak_secret() {
+    let result = Config::from_sources(None, env_of(&[("APP_PROFILE", "prod")]));
+    assert!(matches!(result, Err(ConfigError::Invalid(_))));
+
+    let result = Config::from_sources(
+        None,
+        env_of(&[("APP_PROFILE", "prod"), ("API_KEY", "corta")]),
+    );
+    assert!(matches!(result, Err(ConfigError::Invalid(_))));
+
+    let secret = "x".repeat(32);
+    let config = Config::from_sources(
+        None,
+        env_of(&[("APP_PROFILE", "prod"), ("API_KEY", &secret)]),
+    )
+    .unwrap();
+    assert_eq!(config.auth.api_key, secret);
+}
+
+#[test]
+fn invalid_values_are_rejected() {
+    let result = Config::from_sources(None, env_of(&[("SERVER_PORT", "ochenta")]));
+    assert!(matches!(
+        result,
+        Err(ConfigError::Env {
+            var: "SERVER_PORT",
+            ..
+        })
+    ));
+
+    let result = Config::from_sources(Some("[server]\nprot = 80\n"), env_of(&[]));
+    assert!(matches!(result, Err(ConfigError::Parse(_))));
+
+    let result = Config::from_sources(
+        None,
+        env_of(&[("APP_PROFILE", "dev"), ("MONGODB_URI", "localhost:27017")]),
+    );
+    assert!(matches!(result, Err(ConfigError::Invalid(_))));
+
+    let result = Config::from_sources(
+        Some("profile = \"dev\"\n[trash]\nretention_days = 0\n"),
+        env_of(&[]),
+    );
+    assert!(matches!(result, Err(ConfigError::Invalid(_))));
+
+    let result = Config::from_sources(None, env_of(&[("TRASH_RETENTION_DAYS", "un mes")]));
+    assert!(matches!(
+        result,
+        Err(ConfigError::Env {
+            var: "TRASH_RETENTION_DAYS",
+            ..
+        })
+    ));
+}
+
+#[test]
+fn rate_limit_settings_are_read_and_validated() {
+    let file = r#"
+        profile = "dev"
+
+        [rate_limit]
+        max_requests = 20
+        ip_header = "X-Real-IP"
+    "#;
+    let env = env_of(&[("RATE_LIMIT_LOCKOUT_SECS", "60")]);
+    let config = Config::from_sources(Some(file), env).unwrap();
+    assert_eq!(config.rate_limit.max_requests, 20);
+    assert_eq!(config.rate_limit.lockout_secs, 60);
+    assert_eq!(config.rate_limit.window_secs, 60);
+    assert_eq!(config.rate_limit.ip_header.as_deref(), Some("X-Real-IP"));
+
+    let limits = RateLimitConfig::from_config(&config.rate_limit);
+    assert_eq!(limits.base_lockout, Duration::from_secs(60));
+    assert_eq!(limits.client_ip, ClientIp::Header("X-Real-IP".to_string()));
+
+    let dev = ("APP_PROFILE", "dev");
+    let config = Config::from_sources(Some(file), env_of(&[("RATE_LIMIT_IP_HEADER", "")])).unwrap();
+    assert_eq!(config.rate_limit.ip_header, None);
+
+    let result = Config::from_sources(None, env_of(&[dev, ("RATE_LIMIT_MAX_FAILURES", "0")]));
+    assert!(matches!(result, Err(ConfigError::Invalid(_))));
+
+    let result = Config::from_sources(None, env_of(&[dev, ("RATE_LIMIT_MAX_LOCKOUT_SECS", "10")]));
+    assert!(matches!(result, Err(ConfigError::Invalid(_))));
+
+    let result = Config::from_sources(None, env_of(&[dev, ("RATE_LIMIT_WINDOW_SECS", "-1")]));
+    assert!(matches!(
+        result,
+        Err(ConfigError::Env {
+            var: "RATE_LIMIT_WINDOW_SECS",
+            ..
+        })
+    ));
+}
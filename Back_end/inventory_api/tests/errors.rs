@@ -0,0 +1,54 @@
+mod common;
+
+use actix_web::http::StatusCode;
+
+use common::{call, create_zone, init_app, register};
+
+#[actix_web::test]
+async fn malformed_path_segments_use_the_error_envelope() {
+    let app = init_app().await;
+    let ana = register(&app, "ana").await;
+
+    let (status, error) = call(
+        &app,
+        "GET",
+        "/private/zones/no-es-un-id",
+        Some(&ana.token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(error["code"], "validation_error");
+    assert!(error["message"].is_string());
+
+    // Tampoco con un segmento que no es UTF-8 válido ni en rutas con varios parámetros
+    for uri in ["/private/zones/%FF", "/private/history/item/no-es-un-id"] {
+        let (status, error) = call(&app, "GET", uri, Some(&ana.token), None).await;
+        assert_eq!(
+            status,
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "{}: {}",
+            uri,
+            error
+        );
+        assert_eq!(error["code"], "validation_error");
+    }
+}
+
+#[actix_web::test]
+async fn malformed_query_strings_use_the_error_envelope() {
+    let app = init_app().await;
+    let ana = register(&app, "ana").await;
+    let (group_id, _) = create_zone(&app, &ana).await;
+
+    let uri = format!(
+        "/private/alerts/expiring?groupId={}&days=7&days=30",
+        group_id
+    );
+    let (status, error) = call(&app, "GET", &uri, Some(&ana.token), None).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{}", error);
+    assert_eq!(error["code"], "validation_error");
+    assert!(error["details"]["reason"]
+        .as_str()
+        .is_some_and(|reason| reason.contains("days")));
+}
@@ -0,0 +1,263 @@
+mod common;
+
+use actix_web::http::StatusCode;
+use chrono::{Duration, SecondsFormat, Utc};
+use mongodb::bson::{oid::ObjectId, DateTime};
+use serde_json::json;
+
+use common::{
+    call, create, create_group, create_zone, init_app, init_app_on, register, relaxed_limits,
+    repositories, Outbox,
+};
+use inventory_api::entities::item::Item;
+
+fn in_days(days: i64) -> String {
+    in_hours(days * 24)
+}
+
+fn in_hours(hours: i64) -> String {
+    (Utc::now() + Duration::hours(hours)).to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+#[actix_web::test]
+async fn expiring_items_are_sorted_with_their_zone_path() {
+    let app = init_app().await;
+    let owner = register(&app, "owner").await;
+    let (_, pantry) = create_zone(&app, &owner).await;
+    let shelf = create(
+        &app,
+        &owner,
+        "/private/zones",
+        json!({"name": "Estante", "parentZoneId": pantry}),
+    )
+    .await;
+    create(
+        &app,
+        &owner,
+        "/private/items",
+        json!({"name": "Yogur", "zoneId": shelf, "expiresAt": in_days(2)}),
+    )
+    .await;
+    create(
+        &app,
+        &owner,
+        "/private/items",
+        json!({"name": "Leche", "zoneId": pantry, "expiresAt": in_hours(-30)}),
+    )
+    .await;
+    // Caducado hace menos de un día
+    create(
+        &app,
+        &owner,
+        "/private/items",
+        json!({"name": "Pan", "zoneId": pantry, "expiresAt": in_hours(-2)}),
+    )
+    .await;
+    create(
+        &app,
+        &owner,
+        "/private/items",
+        json!({"name": "Arroz", "zoneId": pantry, "expiresAt": in_days(60)}),
+    )
+    .await;
+    create(
+        &app,
+        &owner,
+        "/private/items",
+        json!({"name": "Pilas", "zoneId": pantry}),
+    )
+    .await;
+
+    let (status, items) = call(
+        &app,
+        "GET",
+        "/private/alerts/expiring",
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let items = items.as_array().unwrap();
+    assert_eq!(items.len(), 3);
+    assert_eq!(items[0]["name"], "Leche");
+    assert_eq!(items[0]["expired"], true);
+    assert_eq!(items[0]["daysLeft"], -2);
+    assert_eq!(items[1]["name"], "Pan");
+    assert_eq!(items[1]["expired"], true);
+    assert_eq!(items[1]["daysLeft"], -1);
+    assert_eq!(items[2]["name"], "Yogur");
+    assert_eq!(items[2]["expired"], false);
+    assert_eq!(items[2]["daysLeft"], 1);
+    assert!(items[2]["expiresAt"].is_string());
+    let path: Vec<&str> = items[2]["path"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|step| step["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(path, vec!["Piso", "Despensa", "Estante"]);
+
+    let (_, items) = call(
+        &app,
+        "GET",
+        "/private/alerts/expiring?days=90",
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    assert_eq!(items.as_array().unwrap().len(), 4);
+
+    let (status, _) = call(
+        &app,
+        "GET",
+        "/private/alerts/expiring?days=pronto",
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[actix_web::test]
+async fn item_dates_are_validated() {
+    let app = init_app().await;
+    let owner = register(&app, "owner").await;
+    let (_, pantry) = create_zone(&app, &owner).await;
+
+    let (status, body) = call(
+        &app,
+        "POST",
+        "/private/items",
+        Some(&owner.token),
+        Some(json!({"name": "Pan", "zoneId": pantry, "purchasedAt": in_days(3)})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(body["details"]["field"], "purchasedAt");
+
+    let (status, body) = call(
+        &app,
+        "POST",
+        "/private/items",
+        Some(&owner.token),
+        Some(json!({
+            "name": "Pan",
+            "zoneId": pantry,
+            "purchasedAt": in_days(-1),
+            "expiresAt": in_days(-2)
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(body["details"]["field"], "expiresAt");
+
+    let (status, _) = call(
+        &app,
+        "POST",
+        "/private/items",
+        Some(&owner.token),
+        Some(json!({"name": "Pan", "zoneId": pantry, "expiresAt": "mañana"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    let bread = create(
+        &app,
+        &owner,
+        "/private/items",
+        json!({"name": "Pan", "zoneId": pantry, "expiresAt": "2020-01-10"}),
+    )
+    .await;
+    let item_uri = format!("/private/items/{}", bread);
+
+    // La fecha de compra se compara con la caducidad ya guardada
+    let (status, _) = call(
+        &app,
+        "PATCH",
+        &item_uri,
+        Some(&owner.token),
+        Some(json!({"purchasedAt": "2020-02-01"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    let (status, _) = call(
+        &app,
+        "PATCH",
+        &item_uri,
+        Some(&owner.token),
+        Some(json!({"purchasedAt": "2020-01-01"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (_, item) = call(&app, "GET", &item_uri, Some(&owner.token), None).await;
+    assert_eq!(item["expiresAt"], "2020-01-10T00:00:00Z");
+    assert_eq!(item["purchasedAt"], "2020-01-01T00:00:00Z");
+
+    let (status, _) = call(
+        &app,
+        "PATCH",
+        &item_uri,
+        Some(&owner.token),
+        Some(json!({"expiresAt": null})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let (_, item) = call(&app, "GET", &item_uri, Some(&owner.token), None).await;
+    assert!(item.get("expiresAt").is_none());
+}
+
+#[actix_web::test]
+async fn items_placed_directly_on_a_property_expire_too() {
+    let repos = repositories().await;
+    let app = init_app_on(repos.clone(), Outbox::default(), relaxed_limits()).await;
+    let owner = register(&app, "owner").await;
+    let (group_id, _) = create_group(&app, &owner, "Casa").await;
+    let flat = create(
+        &app,
+        &owner,
+        "/private/properties",
+        json!({"name": "Piso", "groupId": group_id}),
+    )
+    .await;
+    // Datos antiguos: items cuyo `zoneId` es la propia propiedad
+    repos
+        .items
+        .insert(&Item {
+            id: None,
+            name: "Leche".to_string(),
+            description: None,
+            picture_url: None,
+            zone_id: ObjectId::parse_str(&flat).unwrap(),
+            tags: None,
+            quantity: None,
+            unit: None,
+            min_quantity: None,
+            expires_at: Some(DateTime::from_millis(
+                (Utc::now() + Duration::days(1)).timestamp_millis(),
+            )),
+            purchased_at: None,
+            deleted_at: None,
+            trash_id: None,
+            category_id: None,
+            attributes: None,
+            loan_id: None,
+        })
+        .await
+        .unwrap();
+
+    let (status, items) = call(
+        &app,
+        "GET",
+        "/private/alerts/expiring",
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let items = items.as_array().unwrap();
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0]["name"], "Leche");
+    assert_eq!(items[0]["path"].as_array().unwrap().len(), 1);
+    assert_eq!(items[0]["path"][0]["name"], "Piso");
+}
@@ -0,0 +1,269 @@
+mod common;
+
+use actix_web::http::StatusCode;
+use mongodb::bson::{doc, oid::ObjectId};
+use serde_json::json;
+
+use common::{
+    call, create, create_zone, init_app, init_app_on, names, register, relaxed_limits,
+    repositories, Outbox,
+};
+use inventory_api::entities::item::Item;
+use inventory_api::entities::search::SearchEntity;
+use inventory_api::repository::SearchQuery;
+use inventory_api::services::search::FUZZY_CANDIDATES;
+
+#[actix_web::test]
+async fn search_ignores_accents_and_tolerates_typos() {
+    let app = init_app().await;
+    let owner = register(&app, "owner").await;
+    let (_, pantry) = create_zone(&app, &owner).await;
+    create(
+        &app,
+        &owner,
+        "/private/zones",
+        json!({"name": "Garaje", "parentZoneId": pantry}),
+    )
+    .await;
+    for item in [
+        json!({"name": "Cámara de fotos", "zoneId": pantry}),
+        json!({"name": "Destornillador plano", "zoneId": pantry}),
+        json!({"name": "Martillo", "tags": ["herramienta"], "zoneId": pantry}),
+    ] {
+        create(&app, &owner, "/private/items", item).await;
+    }
+
+    let (status, found) = call(
+        &app,
+        "GET",
+        "/private/search?q=camara",
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(names(&found), vec!["Cámara de fotos"]);
+    assert_eq!(found["data"][0]["highlights"][0]["ranges"], json!([[0, 6]]));
+
+    let (_, found) = call(
+        &app,
+        "GET",
+        "/private/search?q=destornilador",
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    assert_eq!(names(&found), vec!["Destornillador plano"]);
+    assert_eq!(
+        found["data"][0]["highlights"],
+        json!([{"field": "name", "value": "Destornillador plano", "ranges": [[0, 14]]}])
+    );
+
+    let (_, found) = call(
+        &app,
+        "GET",
+        "/private/search?q=garage",
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    assert_eq!(names(&found), vec!["Garaje"]);
+    assert_eq!(found["data"][0]["type"], "zone");
+
+    let (_, found) = call(
+        &app,
+        "GET",
+        "/private/search?q=herramineta",
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    assert_eq!(names(&found), vec!["Martillo"]);
+    assert_eq!(found["data"][0]["highlights"][0]["field"], "tags.0");
+}
+
+#[actix_web::test]
+async fn fuzzy_results_are_ordered_by_similarity_and_can_be_disabled() {
+    let app = init_app().await;
+    let owner = register(&app, "owner").await;
+    let (_, pantry) = create_zone(&app, &owner).await;
+    for item in [
+        json!({"name": "Taladros", "zoneId": pantry}),
+        json!({"name": "Taladro", "zoneId": pantry}),
+        json!({"name": "Sol", "zoneId": pantry}),
+    ] {
+        create(&app, &owner, "/private/items", item).await;
+    }
+
+    let (status, found) = call(
+        &app,
+        "GET",
+        "/private/search?q=taladro&fuzzy=true",
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(names(&found), vec!["Taladro", "Taladros"]);
+    let data = found["data"].as_array().unwrap();
+    assert!(data[0]["score"].as_f64().unwrap() > data[1]["score"].as_f64().unwrap());
+
+    let (_, found) = call(
+        &app,
+        "GET",
+        "/private/search?q=taladro",
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    assert_eq!(names(&found), vec!["Taladro"]);
+
+    let (_, found) = call(
+        &app,
+        "GET",
+        "/private/search?q=taldro&fuzzy=false",
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    assert_eq!(found["total"], 0);
+
+    // Los términos cortos no admiten erratas
+    let (_, found) = call(
+        &app,
+        "GET",
+        "/private/search?q=sal",
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    assert_eq!(found["total"], 0);
+
+    let (status, error) = call(
+        &app,
+        "GET",
+        "/private/search?q=taladro&fuzzy=si",
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(error["details"]["field"], "fuzzy");
+}
+
+#[actix_web::test]
+async fn fuzzy_candidates_are_filtered_in_the_database() {
+    let repos = repositories().await;
+    let app = init_app_on(repos.clone(), Outbox::default(), relaxed_limits()).await;
+    let owner = register(&app, "owner").await;
+    let (group_id, pantry) = create_zone(&app, &owner).await;
+    for item in [
+        json!({"name": "Cable HDMI", "zoneId": pantry}),
+        json!({"name": "Caja", "tags": ["cables"], "zoneId": pantry}),
+        json!({"name": "Router", "description": "Con cabel de red", "zoneId": pantry}),
+        json!({"name": "Tostadora", "zoneId": pantry}),
+        json!({"name": "Tabla de cortar", "zoneId": pantry}),
+    ] {
+        create(&app, &owner, "/private/items", item).await;
+    }
+
+    // Solo llega lo que puede estar a una errata de "cable"
+    let mut query = SearchQuery {
+        group_ids: vec![ObjectId::parse_str(&group_id).unwrap()],
+        similar_to: vec!["cable".to_string()],
+        ..SearchQuery::default()
+    };
+    let found = repos.search.search(&query).await.unwrap();
+    let mut loaded: Vec<&str> = found
+        .iter()
+        .map(|found| match &found.entity {
+            SearchEntity::Item(item) => item.name.as_str(),
+            other => panic!("Solo hay items que encajen: {:?}", other),
+        })
+        .collect();
+    loaded.sort();
+    assert_eq!(loaded, vec!["Cable HDMI", "Caja", "Router"]);
+
+    query.limit = Some(2);
+    assert_eq!(repos.search.search(&query).await.unwrap().len(), 2);
+
+    let (status, found) = call(
+        &app,
+        "GET",
+        "/private/search?q=cabel&fuzzy=true",
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(names(&found), vec!["Cable HDMI", "Router"]);
+}
+
+#[actix_web::test]
+async fn fuzzy_candidates_are_filtered_before_the_cap_and_truncation_is_reported() {
+    let repos = repositories().await;
+    let app = init_app_on(repos.clone(), Outbox::default(), relaxed_limits()).await;
+    let owner = register(&app, "owner").await;
+    let (group_id, pantry) = create_zone(&app, &owner).await;
+    let (status, _) = call(
+        &app,
+        "PUT",
+        &format!("/private/groups/{}/attributes", group_id),
+        Some(&owner.token),
+        Some(json!([{"name": "color", "type": "text"}])),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let zone_id = ObjectId::parse_str(&pantry).unwrap();
+    let cable = |name: String, color: &str| Item {
+        id: None,
+        name,
+        description: None,
+        picture_url: None,
+        zone_id,
+        tags: None,
+        quantity: None,
+        unit: None,
+        min_quantity: None,
+        expires_at: None,
+        purchased_at: None,
+        deleted_at: None,
+        trash_id: None,
+        category_id: None,
+        attributes: Some(doc! {"color": color}),
+        loan_id: None,
+    };
+    for n in 0..FUZZY_CANDIDATES {
+        let item = cable(format!("Cable {}", n), "rojo");
+        repos.items.insert(&item).await.unwrap();
+    }
+    repos
+        .items
+        .insert(&cable("Cable azul".to_string(), "Azul"))
+        .await
+        .unwrap();
+
+    let (status, found) = call(
+        &app,
+        "GET",
+        "/private/search?q=cabel&fuzzy=true&attr.color=azul",
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(names(&found), vec!["Cable azul"]);
+    assert_eq!(found["total"], 1);
+    assert_eq!(found["truncated"], false);
+
+    let (_, found) = call(
+        &app,
+        "GET",
+        "/private/search?q=cabel&fuzzy=true&limit=1",
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    assert_eq!(found["total"], FUZZY_CANDIDATES);
+    assert_eq!(found["truncated"], true);
+}
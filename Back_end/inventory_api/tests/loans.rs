@@ -0,0 +1,247 @@
+mod common;
+
+use actix_web::http::StatusCode;
+use mongodb::bson::oid::ObjectId;
+use serde_json::json;
+
+use common::{
+    call, create, create_group, create_zone, init_app, init_app_on, oid, register, relaxed_limits,
+    repositories, Outbox,
+};
+use inventory_api::entities::item::Item;
+
+#[actix_web::test]
+async fn items_are_checked_out_to_members_and_checked_back_in() {
+    let app = init_app().await;
+    let owner = register(&app, "owner").await;
+    let member = register(&app, "member").await;
+    let (group_id, pantry) = create_zone(&app, &owner).await;
+    let (_, group) = call(
+        &app,
+        "GET",
+        &format!("/private/groups/{}", group_id),
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    call(
+        &app,
+        "POST",
+        &format!(
+            "/private/groups/join/{}",
+            group["groupCode"].as_str().unwrap()
+        ),
+        Some(&member.token),
+        None,
+    )
+    .await;
+    let drill = create(
+        &app,
+        &owner,
+        "/private/items",
+        json!({"name": "Taladro", "zoneId": pantry}),
+    )
+    .await;
+    let item_uri = format!("/private/items/{}", drill);
+
+    let (status, loan) = call(
+        &app,
+        "POST",
+        &format!("{}/checkout", item_uri),
+        Some(&owner.token),
+        Some(json!({"borrowerId": member.id, "dueAt": "2099-01-01", "notes": "Con brocas"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", loan);
+    assert_eq!(loan["borrowerName"], "member");
+    assert_eq!(loan["overdue"], false);
+    let (_, item) = call(&app, "GET", &item_uri, Some(&owner.token), None).await;
+    assert_eq!(oid(&item["loanId"]), oid(&loan["_id"]));
+    let (status, _) = call(
+        &app,
+        "POST",
+        &format!("{}/checkout", item_uri),
+        Some(&owner.token),
+        Some(json!({"borrowerName": "Vecino"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    let loans_uri = format!("/private/groups/{}/loans", group_id);
+    let (status, lent) = call(&app, "GET", &loans_uri, Some(&member.token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let lent = lent.as_array().unwrap();
+    assert_eq!(lent.len(), 1);
+    assert_eq!(lent[0]["item"]["name"], "Taladro");
+    assert_eq!(oid(&lent[0]["loan"]["borrowerId"]), member.id);
+
+    let (status, loan) = call(
+        &app,
+        "POST",
+        &format!("{}/checkin", item_uri),
+        Some(&owner.token),
+        Some(json!({"notes": "Sin batería"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", loan);
+    assert_eq!(oid(&loan["returnedBy"]), owner.id);
+    assert_eq!(loan["returnNotes"], "Sin batería");
+    let (status, _) = call(
+        &app,
+        "POST",
+        &format!("{}/checkin", item_uri),
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    let (_, lent) = call(&app, "GET", &loans_uri, Some(&owner.token), None).await;
+    assert!(lent.as_array().unwrap().is_empty());
+    let (status, history) = call(
+        &app,
+        "GET",
+        &format!("{}/loans", item_uri),
+        Some(&member.token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let history = history.as_array().unwrap();
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0]["notes"], "Con brocas");
+    assert!(history[0]["returnedAt"].is_string());
+}
+
+#[actix_web::test]
+async fn overdue_loans_to_external_people_are_listed() {
+    let app = init_app().await;
+    let owner = register(&app, "owner").await;
+    let stranger = register(&app, "stranger").await;
+    let (group_id, pantry) = create_zone(&app, &owner).await;
+    let ladder = create(
+        &app,
+        &owner,
+        "/private/items",
+        json!({"name": "Escalera", "zoneId": pantry}),
+    )
+    .await;
+    let saw = create(
+        &app,
+        &owner,
+        "/private/items",
+        json!({"name": "Sierra", "zoneId": pantry}),
+    )
+    .await;
+    let checkout = |id: &str| format!("/private/items/{}/checkout", id);
+
+    let (status, body) = call(
+        &app,
+        "POST",
+        &checkout(&ladder),
+        Some(&owner.token),
+        Some(json!({"borrowerId": stranger.id})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(body["details"]["field"], "borrowerId");
+    let (status, _) = call(
+        &app,
+        "POST",
+        &checkout(&ladder),
+        Some(&owner.token),
+        Some(json!({"borrowerId": owner.id, "borrowerName": "Vecino"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    for (id, due_at) in [(&ladder, "2020-05-01"), (&saw, "2099-05-01")] {
+        let (status, _) = call(
+            &app,
+            "POST",
+            &checkout(id),
+            Some(&owner.token),
+            Some(json!({"borrowerName": "Vecino del 3º", "dueAt": due_at})),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+    }
+    let loans_uri = format!("/private/groups/{}/loans", group_id);
+    let (_, lent) = call(&app, "GET", &loans_uri, Some(&owner.token), None).await;
+    let names: Vec<&str> = lent
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|l| l["item"]["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(names, ["Escalera", "Sierra"]);
+    let (status, overdue) = call(
+        &app,
+        "GET",
+        &format!("{}?overdue=true", loans_uri),
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let overdue = overdue.as_array().unwrap();
+    assert_eq!(overdue.len(), 1);
+    assert_eq!(overdue[0]["loan"]["borrowerName"], "Vecino del 3º");
+    assert_eq!(overdue[0]["loan"]["overdue"], true);
+    assert!(overdue[0]["loan"].get("borrowerId").is_none());
+
+    let (status, _) = call(&app, "GET", &loans_uri, Some(&stranger.token), None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
+#[actix_web::test]
+async fn items_placed_directly_on_a_property_are_listed_when_lent() {
+    let repos = repositories().await;
+    let app = init_app_on(repos.clone(), Outbox::default(), relaxed_limits()).await;
+    let owner = register(&app, "owner").await;
+    let (group_id, _) = create_group(&app, &owner, "Casa").await;
+    let flat = create(
+        &app,
+        &owner,
+        "/private/properties",
+        json!({"name": "Piso", "groupId": group_id}),
+    )
+    .await;
+    // Datos antiguos: items cuyo `zoneId` es la propia propiedad
+    let ladder = repos
+        .items
+        .insert(&Item {
+            id: None,
+            name: "Escalera".to_string(),
+            description: None,
+            picture_url: None,
+            zone_id: ObjectId::parse_str(&flat).unwrap(),
+            tags: None,
+            quantity: None,
+            unit: None,
+            min_quantity: None,
+            expires_at: None,
+            purchased_at: None,
+            deleted_at: None,
+            trash_id: None,
+            category_id: None,
+            attributes: None,
+            loan_id: None,
+        })
+        .await
+        .unwrap();
+    let (status, _) = call(
+        &app,
+        "POST",
+        &format!("/private/items/{}/checkout", ladder.to_hex()),
+        Some(&owner.token),
+        Some(json!({"borrowerName": "Vecino del 3º"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let loans_uri = format!("/private/groups/{}/loans", group_id);
+    let (_, lent) = call(&app, "GET", &loans_uri, Some(&owner.token), None).await;
+    let lent = lent.as_array().unwrap();
+    assert_eq!(lent.len(), 1);
+    assert_eq!(lent[0]["item"]["name"], "Escalera");
+}
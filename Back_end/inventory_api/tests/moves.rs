@@ -0,0 +1,427 @@
+mod common;
+
+use actix_web::http::StatusCode;
+use serde_json::json;
+
+use common::{call, create, create_group, create_zone, init_app, of_type, oid, register};
+
+#[actix_web::test]
+async fn moving_a_zone_carries_its_subtree_to_the_new_property() {
+    let app = init_app().await;
+    let owner = register(&app, "owner").await;
+    let (group_id, _) = create_group(&app, &owner, "Casa").await;
+    let flat = create(
+        &app,
+        &owner,
+        "/private/properties",
+        json!({"name": "Piso", "groupId": group_id}),
+    )
+    .await;
+    let cottage = create(
+        &app,
+        &owner,
+        "/private/properties",
+        json!({"name": "Pueblo", "groupId": group_id}),
+    )
+    .await;
+    let kitchen = create(
+        &app,
+        &owner,
+        "/private/zones",
+        json!({"name": "Cocina", "parentZoneId": flat}),
+    )
+    .await;
+    let pantry = create(
+        &app,
+        &owner,
+        "/private/zones",
+        json!({"name": "Despensa", "parentZoneId": kitchen}),
+    )
+    .await;
+    let shelf = create(
+        &app,
+        &owner,
+        "/private/zones",
+        json!({"name": "Estante", "parentZoneId": pantry}),
+    )
+    .await;
+    let rice = create(
+        &app,
+        &owner,
+        "/private/items",
+        json!({"name": "Arroz", "zoneId": shelf}),
+    )
+    .await;
+    let move_kitchen = format!("/private/zones/{}/move", kitchen);
+
+    // No se puede mover una zona dentro de sí misma ni de sus subzonas
+    for target in [&kitchen, &shelf] {
+        let (status, _) = call(
+            &app,
+            "POST",
+            &move_kitchen,
+            Some(&owner.token),
+            Some(json!({"parentZoneId": target})),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    let (status, body) = call(
+        &app,
+        "POST",
+        &move_kitchen,
+        Some(&owner.token),
+        Some(json!({"parentZoneId": cottage})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+
+    let (_, zone) = call(
+        &app,
+        "GET",
+        &format!("/private/zones/{}", kitchen),
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    assert_eq!(oid(&zone["parentZoneId"]), cottage);
+    for id in [&kitchen, &pantry, &shelf] {
+        let (_, zone) = call(
+            &app,
+            "GET",
+            &format!("/private/zones/{}", id),
+            Some(&owner.token),
+            None,
+        )
+        .await;
+        assert_eq!(oid(&zone["propertyId"]), cottage, "{}", zone["name"]);
+    }
+    let (_, item) = call(
+        &app,
+        "GET",
+        &format!("/private/items/{}", rice),
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    assert_eq!(oid(&item["zoneId"]), shelf);
+
+    // Y de vuelta, colgando de una zona del piso
+    let hall = create(
+        &app,
+        &owner,
+        "/private/zones",
+        json!({"name": "Entrada", "parentZoneId": flat}),
+    )
+    .await;
+    let (status, _) = call(
+        &app,
+        "POST",
+        &format!("/private/zones/{}/move", pantry),
+        Some(&owner.token),
+        Some(json!({"parentZoneId": hall})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let (_, zone) = call(
+        &app,
+        "GET",
+        &format!("/private/zones/{}", shelf),
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    assert_eq!(oid(&zone["propertyId"]), flat);
+}
+
+#[actix_web::test]
+async fn moving_items_requires_edit_rights_on_the_target() {
+    let app = init_app().await;
+    let owner = register(&app, "owner").await;
+    let friend = register(&app, "friend").await;
+    let (_, home) = create_zone(&app, &owner).await;
+    let (friend_group, friend_zone) = create_zone(&app, &friend).await;
+    let drill = create(
+        &app,
+        &owner,
+        "/private/items",
+        json!({"name": "Taladro", "zoneId": home}),
+    )
+    .await;
+    let move_drill = format!("/private/items/{}/move", drill);
+
+    // Sin pertenecer al grupo de destino
+    let (status, _) = call(
+        &app,
+        "POST",
+        &move_drill,
+        Some(&owner.token),
+        Some(json!({"zoneId": friend_zone})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // Como lector tampoco
+    let (_, group) = call(
+        &app,
+        "GET",
+        &format!("/private/groups/{}", friend_group),
+        Some(&friend.token),
+        None,
+    )
+    .await;
+    call(
+        &app,
+        "POST",
+        &format!(
+            "/private/groups/join/{}",
+            group["groupCode"].as_str().unwrap()
+        ),
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    let (status, _) = call(
+        &app,
+        "POST",
+        &move_drill,
+        Some(&owner.token),
+        Some(json!({"zoneId": friend_zone})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, _) = call(
+        &app,
+        "PATCH",
+        &format!("/private/groups/{}/members/{}", friend_group, owner.id),
+        Some(&friend.token),
+        Some(json!({"role": "editor"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = call(
+        &app,
+        "POST",
+        &move_drill,
+        Some(&owner.token),
+        Some(json!({"zoneId": friend_zone})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (_, children) = call(
+        &app,
+        "GET",
+        &format!("/private/zones/parent/{}", friend_zone),
+        Some(&friend.token),
+        None,
+    )
+    .await;
+    assert_eq!(oid(&of_type(&children, "item")[0]["_id"]), drill);
+}
+
+#[actix_web::test]
+async fn moving_items_to_another_group_drops_the_category_and_checks_attributes() {
+    let app = init_app().await;
+    let owner = register(&app, "owner").await;
+    let (home, pantry) = create_zone(&app, &owner).await;
+    let (workshop, bench) = create_zone(&app, &owner).await;
+    for (group_id, schema) in [
+        (&home, json!([{"name": "serial", "type": "text"}])),
+        (&workshop, json!([{"name": "serial", "type": "text"}])),
+    ] {
+        let (status, _) = call(
+            &app,
+            "PUT",
+            &format!("/private/groups/{}/attributes", group_id),
+            Some(&owner.token),
+            Some(schema),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+    }
+    let tools = create(
+        &app,
+        &owner,
+        &format!("/private/groups/{}/categories", home),
+        json!({"name": "Herramientas"}),
+    )
+    .await;
+    let mut items = Vec::new();
+    for name in ["Taladro", "Sierra", "Lijadora"] {
+        items.push(
+            create(
+                &app,
+                &owner,
+                "/private/items",
+                json!({
+                    "name": name,
+                    "zoneId": pantry,
+                    "categoryId": tools,
+                    "attributes": {"serial": "SN-1"}
+                }),
+            )
+            .await,
+        );
+    }
+
+    let (status, _) = call(
+        &app,
+        "POST",
+        &format!("/private/items/{}/move", items[0]),
+        Some(&owner.token),
+        Some(json!({"zoneId": bench})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, body) = call(
+        &app,
+        "POST",
+        "/private/items/bulk",
+        Some(&owner.token),
+        Some(json!({"operations": [{"op": "move", "id": items[1], "zoneId": bench}]})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["results"][0]["status"], "ok", "{}", body);
+    for item in &items[..2] {
+        let (_, item) = call(
+            &app,
+            "GET",
+            &format!("/private/items/{}", item),
+            Some(&owner.token),
+            None,
+        )
+        .await;
+        assert_eq!(oid(&item["zoneId"]), bench);
+        assert!(item.get("categoryId").is_none(), "{}", item);
+        assert_eq!(item["attributes"]["serial"], "SN-1");
+    }
+
+    // Si los atributos no encajan en el grupo de destino, el item no se mueve
+    let (status, _) = call(
+        &app,
+        "PUT",
+        &format!("/private/groups/{}/attributes", workshop),
+        Some(&owner.token),
+        Some(json!([{"name": "price", "type": "number"}])),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, body) = call(
+        &app,
+        "POST",
+        &format!("/private/items/{}/move", items[2]),
+        Some(&owner.token),
+        Some(json!({"zoneId": bench})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(body["details"]["field"], "attributes.serial");
+    let (_, item) = call(
+        &app,
+        "GET",
+        &format!("/private/items/{}", items[2]),
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    assert_eq!(oid(&item["zoneId"]), pantry);
+    assert_eq!(oid(&item["categoryId"]), tools);
+}
+
+#[actix_web::test]
+async fn moving_a_zone_to_another_group_updates_the_items_of_its_subtree() {
+    let app = init_app().await;
+    let owner = register(&app, "owner").await;
+    let (home, pantry) = create_zone(&app, &owner).await;
+    let (workshop, bench) = create_zone(&app, &owner).await;
+    let (status, _) = call(
+        &app,
+        "PUT",
+        &format!("/private/groups/{}/attributes", home),
+        Some(&owner.token),
+        Some(json!([{"name": "serial", "type": "text"}])),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let tools = create(
+        &app,
+        &owner,
+        &format!("/private/groups/{}/categories", home),
+        json!({"name": "Herramientas"}),
+    )
+    .await;
+    let shelf = create(
+        &app,
+        &owner,
+        "/private/zones",
+        json!({"name": "Balda", "parentZoneId": pantry}),
+    )
+    .await;
+    let drill = create(
+        &app,
+        &owner,
+        "/private/items",
+        json!({
+            "name": "Taladro",
+            "zoneId": shelf,
+            "categoryId": tools,
+            "attributes": {"serial": "SN-1"}
+        }),
+    )
+    .await;
+    let move_pantry = format!("/private/zones/{}/move", pantry);
+
+    // Sin el atributo en el grupo de destino no se mueve nada
+    let (status, body) = call(
+        &app,
+        "POST",
+        &move_pantry,
+        Some(&owner.token),
+        Some(json!({"parentZoneId": bench})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{}", body);
+    let (_, zone) = call(
+        &app,
+        "GET",
+        &format!("/private/zones/{}", pantry),
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    assert_ne!(oid(&zone["parentZoneId"]), bench);
+
+    let (status, _) = call(
+        &app,
+        "PUT",
+        &format!("/private/groups/{}/attributes", workshop),
+        Some(&owner.token),
+        Some(json!([{"name": "serial", "type": "text"}])),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = call(
+        &app,
+        "POST",
+        &move_pantry,
+        Some(&owner.token),
+        Some(json!({"parentZoneId": bench})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let (_, item) = call(
+        &app,
+        "GET",
+        &format!("/private/items/{}", drill),
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    assert_eq!(oid(&item["zoneId"]), shelf);
+    assert!(item.get("categoryId").is_none(), "{}", item);
+    assert_eq!(item["attributes"]["serial"], "SN-1");
+}
@@ -0,0 +1,122 @@
+mod common;
+
+use actix_web::http::StatusCode;
+use serde_json::json;
+
+use common::{all_pages, call, create, create_group, create_zone, init_app, names, register};
+
+#[actix_web::test]
+async fn children_are_paged_with_zones_before_items() {
+    let app = init_app().await;
+    let owner = register(&app, "owner").await;
+    let (_, pantry) = create_zone(&app, &owner).await;
+    for name in ["Estante B", "Estante A"] {
+        create(
+            &app,
+            &owner,
+            "/private/zones",
+            json!({"name": name, "parentZoneId": pantry}),
+        )
+        .await;
+    }
+    for name in ["Sal", "Arroz", "Harina"] {
+        create(
+            &app,
+            &owner,
+            "/private/items",
+            json!({"name": name, "zoneId": pantry}),
+        )
+        .await;
+    }
+
+    let seen: Vec<String> = all_pages(
+        &app,
+        &owner,
+        &format!("/private/zones/parent/{}", pantry),
+        2,
+    )
+    .await
+    .iter()
+    .map(|row| {
+        format!(
+            "{}:{}",
+            row["type"].as_str().unwrap(),
+            row["name"].as_str().unwrap()
+        )
+    })
+    .collect();
+    assert_eq!(
+        seen,
+        vec![
+            "zone:Estante A",
+            "zone:Estante B",
+            "item:Arroz",
+            "item:Harina",
+            "item:Sal"
+        ]
+    );
+
+    let (_, page) = call(
+        &app,
+        "GET",
+        &format!("/private/zones/parent/{}?sort=-name&name=a", pantry),
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    assert_eq!(
+        names(&page),
+        vec!["Estante B", "Estante A", "Sal", "Harina", "Arroz"]
+    );
+}
+
+#[actix_web::test]
+async fn listings_sort_filter_and_reject_bad_parameters() {
+    let app = init_app().await;
+    let owner = register(&app, "owner").await;
+    let (group_id, _) = create_group(&app, &owner, "Casa").await;
+    for name in ["Piso", "Garaje", "Pueblo"] {
+        create(
+            &app,
+            &owner,
+            "/private/properties",
+            json!({"name": name, "groupId": group_id}),
+        )
+        .await;
+    }
+    let uri = format!("/private/properties/group/{}", group_id);
+
+    let (status, page) = call(
+        &app,
+        "GET",
+        &format!("{}?sort=-name&name=p", uri),
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(names(&page), vec!["Pueblo", "Piso"]);
+    assert_eq!(page["total"], 2);
+    assert!(page["nextCursor"].is_null());
+
+    for query in [
+        "limit=0",
+        "limit=201",
+        "sort=color",
+        "after=no-es-un-cursor",
+    ] {
+        let (status, error) = call(
+            &app,
+            "GET",
+            &format!("{}?{}", uri, query),
+            Some(&owner.token),
+            None,
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{}", query);
+        assert!(error["details"]["field"].is_string());
+    }
+
+    let (status, _) = call(&app, "GET", "/private/groups", Some(&owner.token), None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
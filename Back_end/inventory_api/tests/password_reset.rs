@@ -0,0 +1,100 @@
+mod common;
+
+use actix_web::http::StatusCode;
+use serde_json::json;
+
+use common::{call, init_app_with, register, relaxed_limits, Outbox};
+
+#[actix_web::test]
+async fn reset_link_changes_the_password_once() {
+    let outbox = Outbox::default();
+    let app = init_app_with(outbox.clone(), relaxed_limits()).await;
+    register(&app, "ana").await;
+
+    let (status, _) = call(
+        &app,
+        "POST",
+        "/public/users/password/forgot",
+        None,
+        Some(json!({"mail": "ana@example.com"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let token = outbox.last_token("ana@example.com");
+
+    // La contraseña nueva tiene que cumplir la misma longitud mínima que en el registro
+    let (status, error) = call(
+        &app,
+        "POST",
+        "/public/users/password/reset",
+        None,
+        Some(json!({"token": token, "password": "corta"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(error["details"]["field"], "password");
+
+    let reset = json!({"token": token, "password": "contraseña-nueva"});
+    let (status, _) = call(
+        &app,
+        "POST",
+        "/public/users/password/reset",
+        None,
+        Some(reset.clone()),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = call(
+        &app,
+        "POST",
+        "/public/users/password/reset",
+        None,
+        Some(reset),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    let (status, _) = call(
+        &app,
+        "POST",
+        "/public/users/login",
+        None,
+        Some(json!({"mail": "ana@example.com", "password": "contraseña-nueva"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn forgot_password_does_not_reveal_which_accounts_exist() {
+    let app = init_app_with(Outbox::failing(), relaxed_limits()).await;
+    register(&app, "ana").await;
+
+    // Aunque el correo no se pueda enviar, la respuesta es la misma que sin cuenta
+    let mut responses = Vec::new();
+    for mail in ["ana@example.com", "nadie@example.com"] {
+        responses.push(
+            call(
+                &app,
+                "POST",
+                "/public/users/password/forgot",
+                None,
+                Some(json!({"mail": mail})),
+            )
+            .await,
+        );
+    }
+    assert_eq!(responses[0].0, StatusCode::OK);
+    assert_eq!(responses[0], responses[1]);
+
+    let (status, error) = call(
+        &app,
+        "POST",
+        "/public/users/register",
+        None,
+        Some(json!({"name": "Luis", "mail": "luis@example.com", "passwordHash": "1234"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(error["details"]["field"], "passwordHash");
+}
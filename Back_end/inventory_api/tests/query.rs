@@ -0,0 +1,97 @@
+mod common;
+
+use actix_http::Request;
+use actix_web::body::MessageBody;
+use actix_web::dev::{Service, ServiceResponse};
+use actix_web::http::StatusCode;
+use serde_json::{json, Value};
+
+use common::{call, create, create_zone, init_app, register, TestUser};
+
+/// Busca `q` (codificado para la URL) y devuelve el estado y los nombres encontrados.
+async fn search<S, B>(app: &S, user: &TestUser, q: &str) -> (StatusCode, Vec<String>, Value)
+where
+    S: Service<Request, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    B: MessageBody,
+{
+    let q = q
+        .replace(' ', "%20")
+        .replace('"', "%22")
+        .replace('<', "%3C")
+        .replace('>', "%3E");
+    let (status, found) = call(
+        app,
+        "GET",
+        &format!("/private/search?q={}&sort=name", q),
+        Some(&user.token),
+        None,
+    )
+    .await;
+    let names = found["data"]
+        .as_array()
+        .map(|data| {
+            data.iter()
+                .map(|row| row["name"].as_str().unwrap().to_string())
+                .collect()
+        })
+        .unwrap_or_default();
+    (status, names, found)
+}
+
+#[actix_web::test]
+async fn field_filters_phrases_and_date_ranges_narrow_the_search() {
+    let app = init_app().await;
+    let owner = register(&app, "owner").await;
+    let (_, pantry) = create_zone(&app, &owner).await;
+    let garage = create(
+        &app,
+        &owner,
+        "/private/zones",
+        json!({"name": "Garaje", "parentZoneId": pantry}),
+    )
+    .await;
+    for item in [
+        json!({"name": "Cable HDMI", "tags": ["cable"], "expiresAt": "2026-06-01", "zoneId": garage}),
+        json!({"name": "Cable USB", "tags": ["Cable"], "expiresAt": "2027-01-01", "zoneId": pantry}),
+        json!({"name": "Caja", "description": "Caja roja grande", "zoneId": garage}),
+        json!({"name": "Caja azul", "tags": ["roja"], "zoneId": garage}),
+    ] {
+        create(&app, &owner, "/private/items", item).await;
+    }
+
+    let (status, names, _) = search(&app, &owner, "tag:cable").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(names, vec!["Cable HDMI", "Cable USB"]);
+    let (_, names, _) = search(&app, &owner, "tag:cable zone:garaje").await;
+    assert_eq!(names, vec!["Cable HDMI"]);
+    let (_, names, _) = search(&app, &owner, "tag:cable expires:<2026-12-01").await;
+    assert_eq!(names, vec!["Cable HDMI"]);
+    let (_, names, _) = search(&app, &owner, "property:piso expires:2026-06-01").await;
+    assert_eq!(names, vec!["Cable HDMI"]);
+    let (_, names, _) = search(&app, &owner, "expires:2026-06-02..2027-01-01").await;
+    assert_eq!(names, vec!["Cable USB"]);
+    let (_, names, _) = search(&app, &owner, "\"caja roja\"").await;
+    assert_eq!(names, vec!["Caja"]);
+    let (_, names, _) = search(&app, &owner, "caja description:grande").await;
+    assert_eq!(names, vec!["Caja"]);
+}
+
+#[actix_web::test]
+async fn invalid_queries_are_rejected() {
+    let app = init_app().await;
+    let owner = register(&app, "owner").await;
+    create_zone(&app, &owner).await;
+
+    for q in [
+        "color:rojo",
+        "tag:",
+        "expires:ayer",
+        "purchased:>2026-13-01",
+        "expires:2026-12-01..2026-01-01",
+        "\"sin cerrar",
+    ] {
+        let (status, _, error) = search(&app, &owner, q).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{}", q);
+        assert_eq!(error["details"]["field"], "q", "{}", q);
+    }
+}
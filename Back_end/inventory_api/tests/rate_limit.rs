@@ -0,0 +1,116 @@
+mod common;
+
+use actix_web::dev::Service;
+use actix_web::http::StatusCode;
+use actix_web::test;
+use serde_json::json;
+use std::time::Duration;
+
+use common::{call, init_app_with, register, Outbox};
+use inventory_api::middleware::rate_limit::{ClientIp, RateLimitConfig};
+
+fn strict_limits(client_ip: ClientIp) -> RateLimitConfig {
+    RateLimitConfig {
+        window: Duration::from_secs(60),
+        max_requests: 2,
+        max_failures: 1_000,
+        base_lockout: Duration::from_secs(60),
+        max_lockout: Duration::from_secs(60),
+        lockout_decay: Duration::from_secs(60),
+        client_ip,
+    }
+}
+
+/// Intento de login desde `ip` (en `X-Real-IP`) con un correo distinto cada vez.
+fn login_from(ip: &str, attempt: usize) -> actix_http::Request {
+    test::TestRequest::post()
+        .uri("/public/users/login")
+        .insert_header(("X-Real-IP", ip))
+        .set_json(json!({
+            "mail": format!("nadie{}@example.com", attempt),
+            "password": "contraseña-segura"
+        }))
+        .to_request()
+}
+
+async fn status_of<S, B>(app: &S, req: actix_http::Request) -> StatusCode
+where
+    S: Service<
+        actix_http::Request,
+        Response = actix_web::dev::ServiceResponse<B>,
+        Error = actix_web::Error,
+    >,
+{
+    match app.call(req).await {
+        Ok(res) => res.status(),
+        Err(e) => e.error_response().status(),
+    }
+}
+
+#[actix_web::test]
+async fn behind_a_proxy_each_client_has_its_own_bucket() {
+    let app = init_app_with(
+        Outbox::default(),
+        strict_limits(ClientIp::Header("X-Real-IP".to_string())),
+    )
+    .await;
+
+    for attempt in 0..2 {
+        assert_ne!(
+            status_of(&app, login_from("10.0.0.1", attempt)).await,
+            StatusCode::TOO_MANY_REQUESTS
+        );
+    }
+    assert_eq!(
+        status_of(&app, login_from("10.0.0.1", 2)).await,
+        StatusCode::TOO_MANY_REQUESTS
+    );
+    // Otro cliente detrás del mismo proxy no queda bloqueado
+    assert_ne!(
+        status_of(&app, login_from("10.0.0.2", 3)).await,
+        StatusCode::TOO_MANY_REQUESTS
+    );
+}
+
+#[actix_web::test]
+async fn without_a_header_source_the_proxy_header_is_ignored() {
+    let app = init_app_with(Outbox::default(), strict_limits(ClientIp::Peer)).await;
+
+    for (attempt, ip) in ["10.0.0.1", "10.0.0.2"].into_iter().enumerate() {
+        assert_ne!(
+            status_of(&app, login_from(ip, attempt)).await,
+            StatusCode::TOO_MANY_REQUESTS
+        );
+    }
+    // Cambiar la cabecera no sirve para saltarse el límite
+    assert_eq!(
+        status_of(&app, login_from("10.0.0.3", 2)).await,
+        StatusCode::TOO_MANY_REQUESTS
+    );
+}
+
+#[actix_web::test]
+async fn repeated_failures_lock_the_account_even_with_the_right_password() {
+    let mut limits = strict_limits(ClientIp::Peer);
+    limits.max_requests = 1_000;
+    limits.max_failures = 2;
+    let app = init_app_with(Outbox::default(), limits).await;
+    register(&app, "ana").await;
+
+    let wrong = json!({"mail": "ana@example.com", "password": "incorrecta"});
+    for _ in 0..2 {
+        let (status, _) = call(
+            &app,
+            "POST",
+            "/public/users/login",
+            None,
+            Some(wrong.clone()),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+    let right = json!({"mail": "ana@example.com", "password": "contraseña-segura"});
+    let (status, error) = call(&app, "POST", "/public/users/login", None, Some(right)).await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(error["code"], "too_many_requests");
+}
@@ -0,0 +1,191 @@
+mod common;
+
+use actix_web::http::StatusCode;
+use serde_json::{json, Value};
+
+use common::{call, create, create_zone, init_app, names, oid, register};
+
+/// Tipo y nombre de cada paso de la ruta del resultado.
+fn breadcrumb(hit: &Value) -> Vec<(String, String)> {
+    hit["breadcrumb"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|crumb| {
+            (
+                crumb["type"].as_str().unwrap().to_string(),
+                crumb["name"].as_str().unwrap().to_string(),
+            )
+        })
+        .collect()
+}
+
+fn crumbs(path: &[(&str, &str)]) -> Vec<(String, String)> {
+    path.iter()
+        .map(|(kind, name)| (kind.to_string(), name.to_string()))
+        .collect()
+}
+
+#[actix_web::test]
+async fn search_within_a_subtree_returns_breadcrumbs() {
+    let app = init_app().await;
+    let owner = register(&app, "owner").await;
+    let (group, pantry) = create_zone(&app, &owner).await;
+    let shelf = create(
+        &app,
+        &owner,
+        "/private/zones",
+        json!({"name": "Estante", "parentZoneId": pantry}),
+    )
+    .await;
+    let garage = create(
+        &app,
+        &owner,
+        "/private/properties",
+        json!({"name": "Garaje", "groupId": group}),
+    )
+    .await;
+    let workshop = create(
+        &app,
+        &owner,
+        "/private/zones",
+        json!({"name": "Taller", "parentZoneId": garage}),
+    )
+    .await;
+    for item in [
+        json!({"name": "Cable A", "zoneId": shelf}),
+        json!({"name": "Cable B", "zoneId": pantry}),
+        json!({"name": "Cable C", "zoneId": workshop}),
+    ] {
+        create(&app, &owner, "/private/items", item).await;
+    }
+
+    let search =
+        |q: &str, within: &str| format!("/private/search?q={}&within={}&sort=name", q, within);
+    let (status, found) = call(
+        &app,
+        "GET",
+        &search("cable", &pantry),
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(names(&found), vec!["Cable A", "Cable B"]);
+    assert_eq!(
+        breadcrumb(&found["data"][0]),
+        crumbs(&[
+            ("group", "Casa"),
+            ("property", "Piso"),
+            ("zone", "Despensa"),
+            ("zone", "Estante")
+        ])
+    );
+    assert_eq!(oid(&found["data"][0]["breadcrumb"][3]["_id"]), shelf);
+
+    let (_, found) = call(
+        &app,
+        "GET",
+        &search("cable", &garage),
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    assert_eq!(names(&found), vec!["Cable C"]);
+    assert_eq!(
+        breadcrumb(&found["data"][0]),
+        crumbs(&[
+            ("group", "Casa"),
+            ("property", "Garaje"),
+            ("zone", "Taller")
+        ])
+    );
+
+    let (_, found) = call(
+        &app,
+        "GET",
+        &search("cable", &group),
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    assert_eq!(names(&found), vec!["Cable A", "Cable B", "Cable C"]);
+
+    // El elemento que encabeza la rama no se devuelve
+    let (_, found) = call(
+        &app,
+        "GET",
+        &search("despensa", &pantry),
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    assert_eq!(found["total"], 0);
+    let (_, found) = call(
+        &app,
+        "GET",
+        "/private/search?q=estante",
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    assert_eq!(names(&found), vec!["Estante"]);
+    assert_eq!(
+        breadcrumb(&found["data"][0]),
+        crumbs(&[
+            ("group", "Casa"),
+            ("property", "Piso"),
+            ("zone", "Despensa")
+        ])
+    );
+}
+
+#[actix_web::test]
+async fn search_within_requires_access_to_a_container() {
+    let app = init_app().await;
+    let owner = register(&app, "owner").await;
+    let stranger = register(&app, "stranger").await;
+    let (group, pantry) = create_zone(&app, &owner).await;
+    let item = create(
+        &app,
+        &owner,
+        "/private/items",
+        json!({"name": "Cable", "zoneId": pantry}),
+    )
+    .await;
+
+    for within in [&group, &pantry] {
+        let (status, _) = call(
+            &app,
+            "GET",
+            &format!("/private/search?q=cable&within={}", within),
+            Some(&stranger.token),
+            None,
+        )
+        .await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+    }
+
+    for within in [item.as_str(), "no-es-un-id"] {
+        let (status, error) = call(
+            &app,
+            "GET",
+            &format!("/private/search?q=cable&within={}", within),
+            Some(&owner.token),
+            None,
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{}", within);
+        assert_eq!(error["details"]["field"], "within");
+    }
+
+    let (status, _) = call(
+        &app,
+        "GET",
+        "/private/search?q=cable&within=000000000000000000000000",
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
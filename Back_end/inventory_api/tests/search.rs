@@ -0,0 +1,213 @@
+mod common;
+
+use actix_web::http::StatusCode;
+use serde_json::json;
+
+use common::{all_pages, call, create, create_zone, init_app, names, register};
+
+#[actix_web::test]
+async fn results_are_ranked_by_relevance_with_highlights() {
+    let app = init_app().await;
+    let owner = register(&app, "owner").await;
+    let (_, pantry) = create_zone(&app, &owner).await;
+    for item in [
+        json!({"name": "Router", "description": "Incluye cable de red", "zoneId": pantry}),
+        json!({"name": "Caja", "tags": ["cable"], "zoneId": pantry}),
+        json!({"name": "Cable HDMI", "zoneId": pantry}),
+        json!({"name": "Tostadora", "zoneId": pantry}),
+    ] {
+        create(&app, &owner, "/private/items", item).await;
+    }
+
+    let (status, found) = call(
+        &app,
+        "GET",
+        "/private/search/cable",
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(names(&found), vec!["Cable HDMI", "Caja", "Router"]);
+    assert_eq!(found["total"], 3);
+    let data = found["data"].as_array().unwrap();
+    assert!(data[0]["score"].as_f64().unwrap() > data[1]["score"].as_f64().unwrap());
+    assert!(data[1]["score"].as_f64().unwrap() > data[2]["score"].as_f64().unwrap());
+    assert_eq!(
+        data[0]["highlights"],
+        json!([{"field": "name", "value": "Cable HDMI", "ranges": [[0, 5]]}])
+    );
+    assert_eq!(data[1]["highlights"][0]["field"], "tags.0");
+    assert_eq!(
+        data[2]["highlights"],
+        json!([{"field": "description", "value": "Incluye cable de red", "ranges": [[8, 13]]}])
+    );
+}
+
+#[actix_web::test]
+async fn search_matches_any_term_within_the_callers_groups() {
+    let app = init_app().await;
+    let owner = register(&app, "owner").await;
+    let (_, pantry) = create_zone(&app, &owner).await;
+    let garage = create(
+        &app,
+        &owner,
+        "/private/zones",
+        json!({"name": "Garaje", "parentZoneId": pantry}),
+    )
+    .await;
+    create(
+        &app,
+        &owner,
+        "/private/items",
+        json!({"name": "Taladro", "zoneId": garage}),
+    )
+    .await;
+    create(
+        &app,
+        &owner,
+        "/private/items",
+        json!({"name": "Martillo", "zoneId": pantry}),
+    )
+    .await;
+    let stranger = register(&app, "stranger").await;
+    let (_, other_pantry) = create_zone(&app, &stranger).await;
+    create(
+        &app,
+        &stranger,
+        "/private/items",
+        json!({"name": "Martillo", "zoneId": other_pantry}),
+    )
+    .await;
+
+    let (_, found) = call(
+        &app,
+        "GET",
+        "/private/search?q=martillo%20taladro&sort=name",
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    assert_eq!(names(&found), vec!["Martillo", "Taladro"]);
+    let (_, found) = call(
+        &app,
+        "GET",
+        "/private/search/garaje?type=zone",
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    assert_eq!(names(&found), vec!["Garaje"]);
+    assert_eq!(found["data"][0]["type"], "zone");
+    let (status, found) = call(
+        &app,
+        "GET",
+        "/private/search/bicicleta",
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(found["total"], 0);
+}
+
+#[actix_web::test]
+async fn search_pages_follow_the_cursor_across_types() {
+    let app = init_app().await;
+    let owner = register(&app, "owner").await;
+    let (_, pantry) = create_zone(&app, &owner).await;
+    for name in ["Caja roja", "Caja azul", "Caja verde", "Cajón"] {
+        create(
+            &app,
+            &owner,
+            "/private/items",
+            json!({"name": name, "zoneId": pantry}),
+        )
+        .await;
+    }
+    create(
+        &app,
+        &owner,
+        "/private/zones",
+        json!({"name": "Caja fuerte", "parentZoneId": pantry}),
+    )
+    .await;
+
+    let (_, all) = call(
+        &app,
+        "GET",
+        "/private/search?q=caja&sort=name",
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    assert_eq!(all["total"], 4);
+    let paged = all_pages(&app, &owner, "/private/search?q=caja&sort=name", 1).await;
+    assert_eq!(&paged, all["data"].as_array().unwrap());
+    assert_eq!(
+        names(&all),
+        vec!["Caja azul", "Caja fuerte", "Caja roja", "Caja verde"]
+    );
+
+    let (_, zones) = call(
+        &app,
+        "GET",
+        "/private/search?q=caja&type=zone",
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    assert_eq!(zones["total"], 1);
+    assert_eq!(names(&zones), vec!["Caja fuerte"]);
+}
+
+/// Solo con `STORAGE_BACKEND=mongo`: el índice de texto en español, la unión de las cuatro
+/// colecciones y la paginación dentro de la agregación.
+#[actix_web::test]
+async fn mongo_search_uses_the_spanish_text_index_across_collections() {
+    if std::env::var("STORAGE_BACKEND").as_deref() != Ok("mongo") {
+        return;
+    }
+    let app = init_app().await;
+    let owner = register(&app, "owner").await;
+    let (_, pantry) = create_zone(&app, &owner).await;
+    for item in [
+        json!({"name": "Taladro percutor", "zoneId": pantry}),
+        json!({"name": "Brocas", "tags": ["taladros"], "zoneId": pantry}),
+        json!({"name": "Martillo", "description": "Para la casa", "zoneId": pantry}),
+        json!({"name": "Tostadora", "zoneId": pantry}),
+    ] {
+        create(&app, &owner, "/private/items", item).await;
+    }
+
+    // "casas", "despensas" y "taladros" se reducen a la misma raíz que los nombres
+    let uri = "/private/search?q=casas%20despensas%20taladros";
+    let (status, found) = call(&app, "GET", uri, Some(&owner.token), None).await;
+    assert_eq!(status, StatusCode::OK, "{}", found);
+    assert_eq!(found["total"], 5);
+    let mut kinds: Vec<&str> = found["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|row| row["type"].as_str().unwrap())
+        .collect();
+    kinds.sort();
+    assert_eq!(kinds, vec!["group", "item", "item", "item", "zone"]);
+    assert!(!names(&found).contains(&"Tostadora"));
+    for row in found["data"].as_array().unwrap() {
+        assert!(row["_id"]["$oid"].is_string(), "{}", row);
+        assert!(row["score"].as_f64().unwrap() > 0.0, "{}", row);
+        assert!(row.get("_type").is_none() && row.get("_groupId").is_none());
+        if row["type"] == "item" {
+            let crumbs: Vec<&str> = row["breadcrumb"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|crumb| crumb["name"].as_str().unwrap())
+                .collect();
+            assert_eq!(crumbs, vec!["Casa", "Piso", "Despensa"]);
+        }
+    }
+    let paged = all_pages(&app, &owner, &format!("{}&sort=-relevance", uri), 2).await;
+    assert_eq!(&paged, found["data"].as_array().unwrap());
+}
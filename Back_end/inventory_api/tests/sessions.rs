@@ -0,0 +1,74 @@
+mod common;
+
+use actix_web::http::StatusCode;
+use serde_json::json;
+
+use common::{call, init_app, register};
+
+#[actix_web::test]
+async fn refresh_tokens_rotate_and_reuse_revokes_the_session() {
+    let app = init_app().await;
+    register(&app, "ana").await;
+    let login = json!({"mail": "ana@example.com", "password": "contraseña-segura"});
+    let (status, session) = call(&app, "POST", "/public/users/login", None, Some(login)).await;
+    assert_eq!(status, StatusCode::OK);
+    let first = session["refreshToken"].as_str().unwrap().to_string();
+
+    let (status, rotated) = call(
+        &app,
+        "POST",
+        "/public/users/refresh",
+        None,
+        Some(json!({"refreshToken": first})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", rotated);
+    let second = rotated["refreshToken"].as_str().unwrap().to_string();
+    let token = rotated["token"].as_str().unwrap().to_string();
+    assert_ne!(first, second);
+    let (status, _) = call(&app, "GET", "/private/users/me/", Some(&token), None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // Volver a usar el token ya rotado revoca la sesión entera
+    let (status, _) = call(
+        &app,
+        "POST",
+        "/public/users/refresh",
+        None,
+        Some(json!({"refreshToken": first})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = call(
+        &app,
+        "POST",
+        "/public/users/refresh",
+        None,
+        Some(json!({"refreshToken": second})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = call(&app, "GET", "/private/users/me/", Some(&token), None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[actix_web::test]
+async fn malformed_refresh_tokens_are_rejected() {
+    let app = init_app().await;
+    let ana = register(&app, "ana").await;
+
+    for token in ["", "basura", &format!("{}.secreto", ana.id)] {
+        let (status, _) = call(
+            &app,
+            "POST",
+            "/public/users/refresh",
+            None,
+            Some(json!({"refreshToken": token})),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED, "{:?}", token);
+    }
+    let (status, error) = call(&app, "POST", "/public/users/refresh", None, Some(json!({}))).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(error["details"]["field"], "refreshToken");
+}
@@ -0,0 +1,159 @@
+mod common;
+
+use actix_web::http::StatusCode;
+use serde_json::json;
+
+use common::{call, create, create_zone, init_app, register};
+
+#[actix_web::test]
+async fn movements_update_quantity_and_are_logged() {
+    let app = init_app().await;
+    let owner = register(&app, "owner").await;
+    let (_, zone) = create_zone(&app, &owner).await;
+    let rice = create(
+        &app,
+        &owner,
+        "/private/items",
+        json!({"name": "Arroz", "zoneId": zone, "quantity": 2, "unit": "kg"}),
+    )
+    .await;
+    let movements_uri = format!("/private/items/{}/movements", rice);
+
+    let (status, body) = call(
+        &app,
+        "POST",
+        &movements_uri,
+        Some(&owner.token),
+        Some(json!({"type": "add", "quantity": 1.5})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["quantity"], 3.5);
+
+    let (status, body) = call(
+        &app,
+        "POST",
+        &movements_uri,
+        Some(&owner.token),
+        Some(json!({"type": "consume", "quantity": 0.5})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["quantity"], 3.0);
+    assert_eq!(body["movement"]["delta"], -0.5);
+
+    // No se puede consumir más de lo que hay
+    let (status, body) = call(
+        &app,
+        "POST",
+        &movements_uri,
+        Some(&owner.token),
+        Some(json!({"type": "consume", "quantity": 10})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(body["details"]["available"], 3.0, "{}", body);
+
+    // Los ajustes fijan el recuento y necesitan motivo
+    let (status, _) = call(
+        &app,
+        "POST",
+        &movements_uri,
+        Some(&owner.token),
+        Some(json!({"type": "adjust", "quantity": 1})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    let (status, body) = call(
+        &app,
+        "POST",
+        &movements_uri,
+        Some(&owner.token),
+        Some(json!({"type": "adjust", "quantity": 1, "reason": "Recuento"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["movement"]["delta"], -2.0);
+
+    let (_, item) = call(
+        &app,
+        "GET",
+        &format!("/private/items/{}", rice),
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    assert_eq!(item["quantity"], 1.0);
+    assert_eq!(item["unit"], "kg");
+
+    let (status, log) = call(&app, "GET", &movements_uri, Some(&owner.token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let kinds: Vec<&str> = log
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|m| m["type"].as_str().unwrap())
+        .collect();
+    assert_eq!(kinds, vec!["adjust", "consume", "add", "add"]);
+    assert_eq!(log[3]["reason"], "Cantidad inicial");
+    assert_eq!(log[0]["userId"]["$oid"], owner.id.as_str());
+
+    // La cantidad no se cambia con PATCH, solo con movimientos
+    let (status, _) = call(
+        &app,
+        "PATCH",
+        &format!("/private/items/{}", rice),
+        Some(&owner.token),
+        Some(json!({"quantity": 50})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[actix_web::test]
+async fn viewers_can_read_but_not_move_stock() {
+    let app = init_app().await;
+    let owner = register(&app, "owner").await;
+    let guest = register(&app, "guest").await;
+    let (group_id, zone) = create_zone(&app, &owner).await;
+    let (_, group) = call(
+        &app,
+        "GET",
+        &format!("/private/groups/{}", group_id),
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    call(
+        &app,
+        "POST",
+        &format!(
+            "/private/groups/join/{}",
+            group["groupCode"].as_str().unwrap()
+        ),
+        Some(&guest.token),
+        None,
+    )
+    .await;
+    let screws = create(
+        &app,
+        &owner,
+        "/private/items",
+        json!({"name": "Tornillos", "zoneId": zone, "quantity": 12}),
+    )
+    .await;
+    let movements_uri = format!("/private/items/{}/movements", screws);
+
+    let (status, _) = call(
+        &app,
+        "POST",
+        &movements_uri,
+        Some(&guest.token),
+        Some(json!({"type": "consume", "quantity": 1})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, log) = call(&app, "GET", &movements_uri, Some(&guest.token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(log.as_array().unwrap().len(), 1);
+}
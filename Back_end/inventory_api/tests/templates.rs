@@ -0,0 +1,186 @@
+mod common;
+
+use actix_web::http::StatusCode;
+use serde_json::json;
+
+use common::{call, create, create_zone, init_app, of_type, oid, register};
+
+#[actix_web::test]
+async fn items_are_created_from_group_templates() {
+    let app = init_app().await;
+    let owner = register(&app, "owner").await;
+    let (group_id, pantry) = create_zone(&app, &owner).await;
+    call(
+        &app,
+        "PUT",
+        &format!("/private/groups/{}/attributes", group_id),
+        Some(&owner.token),
+        Some(json!([
+            {"name": "serial", "type": "text", "required": true},
+            {"name": "length", "type": "number"}
+        ])),
+    )
+    .await;
+    let cables = create(
+        &app,
+        &owner,
+        &format!("/private/groups/{}/categories", group_id),
+        json!({"name": "Cables"}),
+    )
+    .await;
+    let template = create(
+        &app,
+        &owner,
+        &format!("/private/groups/{}/templates", group_id),
+        json!({
+            "name": "Cable HDMI",
+            "tags": ["hdmi", "cable"],
+            "categoryId": cables,
+            "attributes": {"length": 2}
+        }),
+    )
+    .await;
+
+    let (status, error) = call(
+        &app,
+        "POST",
+        "/private/items",
+        Some(&owner.token),
+        Some(json!({"templateId": template, "zoneId": pantry})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(error["details"]["field"], "attributes.serial");
+    let cable = create(
+        &app,
+        &owner,
+        "/private/items",
+        json!({"templateId": template, "zoneId": pantry, "attributes": {"serial": "HD-7"}}),
+    )
+    .await;
+    let (_, item) = call(
+        &app,
+        "GET",
+        &format!("/private/items/{}", cable),
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    assert_eq!(item["name"], "Cable HDMI");
+    assert_eq!(item["tags"], json!(["hdmi", "cable"]));
+    assert_eq!(oid(&item["categoryId"]), cables);
+    assert_eq!(item["attributes"]["length"], 2.0);
+    assert_eq!(item["attributes"]["serial"], "HD-7");
+
+    let other = register(&app, "other").await;
+    let (_, other_zone) = create_zone(&app, &other).await;
+    let (status, _) = call(
+        &app,
+        "POST",
+        "/private/items",
+        Some(&other.token),
+        Some(json!({"templateId": template, "zoneId": other_zone})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    let (status, _) = call(
+        &app,
+        "GET",
+        &format!("/private/templates/{}", template),
+        Some(&other.token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
+#[actix_web::test]
+async fn listings_and_search_filter_items_by_category() {
+    let app = init_app().await;
+    let owner = register(&app, "owner").await;
+    let (group_id, pantry) = create_zone(&app, &owner).await;
+    let categories_uri = format!("/private/groups/{}/categories", group_id);
+    let tools = create(
+        &app,
+        &owner,
+        &categories_uri,
+        json!({"name": "Herramientas"}),
+    )
+    .await;
+    let (status, _) = call(
+        &app,
+        "POST",
+        &categories_uri,
+        Some(&owner.token),
+        Some(json!({"name": "herramientas"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    let drill = create(
+        &app,
+        &owner,
+        "/private/items",
+        json!({"name": "Taladro", "zoneId": pantry, "categoryId": tools}),
+    )
+    .await;
+    let toaster = create(
+        &app,
+        &owner,
+        "/private/items",
+        json!({"name": "Tostadora", "zoneId": pantry}),
+    )
+    .await;
+    let (status, _) = call(
+        &app,
+        "PATCH",
+        &format!("/private/items/{}", toaster),
+        Some(&owner.token),
+        Some(json!({"categoryId": group_id})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    let (_, children) = call(
+        &app,
+        "GET",
+        &format!("/private/zones/parent/{}?categoryId={}", pantry, tools),
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    let items = of_type(&children, "item");
+    assert_eq!(items.len(), 1);
+    assert_eq!(oid(&items[0]["_id"]), drill);
+    let (_, found) = call(
+        &app,
+        "GET",
+        &format!("/private/search?categoryId={}", tools),
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    let items = of_type(&found, "item");
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0]["name"], "Taladro");
+
+    let (status, _) = call(
+        &app,
+        "DELETE",
+        &format!("/private/categories/{}", tools),
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let (_, item) = call(
+        &app,
+        "GET",
+        &format!("/private/items/{}", drill),
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    assert!(item.get("categoryId").is_none());
+    let (_, categories) = call(&app, "GET", &categories_uri, Some(&owner.token), None).await;
+    assert!(categories.as_array().unwrap().is_empty());
+}
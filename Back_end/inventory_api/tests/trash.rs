@@ -0,0 +1,232 @@
+mod common;
+
+use actix_web::http::StatusCode;
+use mongodb::bson::oid::ObjectId;
+use serde_json::json;
+
+use common::{
+    call, create, create_group, create_zone, init_app, init_app_on, oid, register, relaxed_limits,
+    repositories, Outbox,
+};
+use inventory_api::entities::item::Item;
+
+#[actix_web::test]
+async fn deleted_zone_goes_to_trash_and_is_restored_with_its_subtree() {
+    let app = init_app().await;
+    let owner = register(&app, "owner").await;
+    let guest = register(&app, "guest").await;
+    let (group_id, pantry) = create_zone(&app, &owner).await;
+    let fridge = create(
+        &app,
+        &owner,
+        "/private/zones",
+        json!({"name": "Nevera", "parentZoneId": pantry}),
+    )
+    .await;
+    let milk = create(
+        &app,
+        &owner,
+        "/private/items",
+        json!({"name": "Leche", "zoneId": fridge}),
+    )
+    .await;
+    let (status, _) = call(
+        &app,
+        "DELETE",
+        &format!("/private/zones/{}", pantry),
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = call(
+        &app,
+        "GET",
+        &format!("/private/items/{}", milk),
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let trash_uri = format!("/private/trash?groupId={}", group_id);
+    let (status, _) = call(&app, "GET", &trash_uri, Some(&guest.token), None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, entries) = call(&app, "GET", &trash_uri, Some(&owner.token), None).await;
+    assert_eq!(status, StatusCode::OK, "{}", entries);
+    let entries = entries.as_array().unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0]["entityType"], "zone");
+    assert_eq!(oid(&entries[0]["entityId"]), pantry);
+    assert_eq!(entries[0]["name"], "Despensa");
+    assert_eq!(entries[0]["zones"], 2);
+    assert_eq!(entries[0]["items"], 1);
+    assert!(entries[0]["purgeAt"].as_str().unwrap() > entries[0]["deletedAt"].as_str().unwrap());
+
+    let (status, _) = call(
+        &app,
+        "POST",
+        &format!("/private/trash/{}/restore", oid(&entries[0]["_id"])),
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, item) = call(
+        &app,
+        "GET",
+        &format!("/private/items/{}", milk),
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(oid(&item["zoneId"]), fridge);
+    let (_, entries) = call(&app, "GET", &trash_uri, Some(&owner.token), None).await;
+    assert!(entries.as_array().unwrap().is_empty());
+}
+
+#[actix_web::test]
+async fn items_wait_for_their_zone_and_purged_entries_are_gone() {
+    let app = init_app().await;
+    let owner = register(&app, "owner").await;
+    let (group_id, pantry) = create_zone(&app, &owner).await;
+    let rice = create(
+        &app,
+        &owner,
+        "/private/items",
+        json!({"name": "Arroz", "zoneId": pantry}),
+    )
+    .await;
+    for uri in [
+        format!("/private/items/{}", rice),
+        format!("/private/zones/{}", pantry),
+    ] {
+        let (status, _) = call(&app, "DELETE", &uri, Some(&owner.token), None).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+    let trash_uri = format!("/private/trash?groupId={}", group_id);
+    let (_, entries) = call(&app, "GET", &trash_uri, Some(&owner.token), None).await;
+    let entries = entries.as_array().unwrap();
+    // La más reciente primero
+    assert_eq!(entries[0]["entityType"], "zone");
+    assert_eq!(entries[0]["items"], 0);
+    assert_eq!(entries[1]["entityType"], "item");
+    let zone_entry = oid(&entries[0]["_id"]);
+    let item_entry = oid(&entries[1]["_id"]);
+
+    let restore_item = format!("/private/trash/{}/restore", item_entry);
+    let (status, body) = call(&app, "POST", &restore_item, Some(&owner.token), None).await;
+    assert_eq!(status, StatusCode::CONFLICT, "{}", body);
+
+    let (status, _) = call(
+        &app,
+        "DELETE",
+        &format!("/private/trash/{}", zone_entry),
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = call(
+        &app,
+        "POST",
+        &format!("/private/trash/{}/restore", zone_entry),
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = call(&app, "POST", &restore_item, Some(&owner.token), None).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    let (_, entries) = call(&app, "GET", &trash_uri, Some(&owner.token), None).await;
+    assert_eq!(entries.as_array().unwrap().len(), 1);
+}
+
+#[actix_web::test]
+async fn items_placed_directly_on_a_property_go_with_it() {
+    let repos = repositories().await;
+    let app = init_app_on(repos.clone(), Outbox::default(), relaxed_limits()).await;
+    let owner = register(&app, "owner").await;
+    let (group_id, _) = create_group(&app, &owner, "Casa").await;
+    let flat = create(
+        &app,
+        &owner,
+        "/private/properties",
+        json!({"name": "Piso", "groupId": group_id}),
+    )
+    .await;
+    // Datos antiguos: items cuyo `zoneId` es la propia propiedad
+    let property_id = ObjectId::parse_str(&flat).unwrap();
+    let item_id = repos
+        .items
+        .insert(&Item {
+            id: None,
+            name: "Escalera".to_string(),
+            description: None,
+            picture_url: None,
+            zone_id: property_id,
+            tags: None,
+            quantity: None,
+            unit: None,
+            min_quantity: None,
+            expires_at: None,
+            purchased_at: None,
+            deleted_at: None,
+            trash_id: None,
+            category_id: None,
+            attributes: None,
+            loan_id: None,
+        })
+        .await
+        .unwrap();
+
+    let (status, _) = call(
+        &app,
+        "DELETE",
+        &format!("/private/properties/{}", flat),
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(repos.items.find_by_id(item_id).await.unwrap().is_none());
+    let trash_uri = format!("/private/trash?groupId={}", group_id);
+    let (_, entries) = call(&app, "GET", &trash_uri, Some(&owner.token), None).await;
+    assert_eq!(entries[0]["entityType"], "property");
+    assert_eq!(entries[0]["items"], 1);
+
+    let (status, _) = call(
+        &app,
+        "POST",
+        &format!("/private/trash/{}/restore", oid(&entries[0]["_id"])),
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(repos.items.find_by_id(item_id).await.unwrap().is_some());
+
+    // Borrado por separado, vuelve a su propiedad
+    let (status, _) = call(
+        &app,
+        "DELETE",
+        &format!("/private/items/{}", item_id.to_hex()),
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let (_, entries) = call(&app, "GET", &trash_uri, Some(&owner.token), None).await;
+    assert_eq!(entries[0]["entityType"], "item");
+    let (status, body) = call(
+        &app,
+        "POST",
+        &format!("/private/trash/{}/restore", oid(&entries[0]["_id"])),
+        Some(&owner.token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert!(repos.items.find_by_id(item_id).await.unwrap().is_some());
+}
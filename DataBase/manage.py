@@ -12,7 +12,8 @@ client = MongoClient(MONGO_URI)
 db = client[DB_NAME]
 usuarios = db.get_collection("users")
 
-# Función de hashing: SHA256 de la cadena dada
+# Función de hashing: SHA256 de la cadena dada.
+# La API lo acepta como hash heredado y lo migra a Argon2id en el primer login.
 def hash_password(text: str) -> str:
     return hashlib.sha256(text.encode()).hexdigest()
 
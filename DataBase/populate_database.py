@@ -84,8 +84,9 @@ def assign_users_to_groups(users, groups):
     assignments = []
     for group in groups:
         members = random.sample(users, k=random.randint(2, len(users)))
-        for user in members:
-            ug = {"groupId": group["_id"], "userId": user["_id"]}
+        for i, user in enumerate(members):
+            role = "owner" if i == 0 else random.choice(["editor", "viewer"])
+            ug = {"groupId": group["_id"], "userId": user["_id"], "role": role}
             db.userGroup.insert_one(ug)
             assignments.append(ug)
         db.groups.update_one({"_id": group["_id"]}, {"$set": {"userCount": len(members)}})
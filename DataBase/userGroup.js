@@ -14,6 +14,10 @@ db.createCollection("userGroup", {
         userId: {
           bsonType: "objectId",
           description: "Referencia al usuario"
+        },
+        role: {
+          enum: ["owner", "editor", "viewer"],
+          description: "Rol del usuario en el grupo"
         }
       }
     }